@@ -2,18 +2,23 @@
 
 A [Sequence] is the fundamental data type in XPath. It is a series of zero or more [Item]s.
 
-An [Item] is a [Node], Function or atomic [Value].
+An [Item] is a [Node], [Function], [Map], [Array] or atomic [Value].
 
 [Node]s are defined as a trait.
 */
 
 use crate::item;
-use crate::output::OutputDefinition;
+use crate::output::{OutputDefinition, OutputMethod};
 use crate::qname::QualifiedName;
-use crate::value::{Operator, Value};
+use crate::transform::callable::Function;
+use crate::transform::types::derives_from;
+use crate::value::{json_string, Operator, Value};
 use crate::xdmerror::{Error, ErrorKind};
 use crate::xmldecl::XMLDecl;
+use rust_decimal::Decimal;
 use std::cmp::Ordering;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
 use std::fmt;
 use std::fmt::Formatter;
 use std::rc::Rc;
@@ -63,21 +68,44 @@ impl<N: Node> SequenceTrait<N> for Sequence<N> {
         }
         r
     }
-    /// Renders the Sequence as XML
+    /// Renders the Sequence according to the output definition.
+    /// The item-separator, if any, is inserted between items.
+    /// The byte order mark, if any, is emitted once at the start of the serialization.
     fn to_xml_with_options(&self, od: &OutputDefinition) -> String {
+        if od.get_method() == OutputMethod::Json {
+            return self.to_json();
+        }
+        let sep = od.get_item_separator();
         let mut r = String::new();
-        for i in self {
-            r.push_str(i.to_xml_with_options(od).as_str())
+        let mut item_od = od.clone();
+        if od.get_byte_order_mark() && od.get_method() != OutputMethod::Text {
+            r.push('\u{feff}');
+            item_od.set_byte_order_mark(false)
+        }
+        for (c, i) in self.iter().enumerate() {
+            if c > 0 {
+                if let Some(s) = &sep {
+                    r.push_str(s.as_str())
+                }
+            }
+            r.push_str(i.to_xml_with_options(&item_od).as_str())
         }
         r
     }
-    /// Renders the Sequence as JSON
+    /// Renders the Sequence as JSON.
+    /// A singleton sequence is rendered as its item, a longer sequence as a JSON array.
     fn to_json(&self) -> String {
-        let mut r = String::new();
-        for i in self {
-            r.push_str(i.to_json().as_str())
+        match self.len() {
+            0 => String::new(),
+            1 => self[0].to_json(),
+            _ => format!(
+                "[{}]",
+                self.iter()
+                    .map(|i| i.to_json())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ),
         }
-        r
     }
     /// Push a document's [Node] on to the [Sequence]. This clones the node.
     fn push_node(&mut self, n: &N) {
@@ -178,19 +206,259 @@ impl fmt::Display for NodeType {
     }
 }
 
-/// An Item in a [Sequence]. Can be a node, function or [Value].
-///
-/// Functions are not yet implemented.
+/// An Item in a [Sequence]. Can be a node, function, map, array or [Value].
 #[derive(Clone)]
 pub enum Item<N: Node> {
     /// A [Node] in the source document.
     Node(N),
 
-    /// Functions are not yet supported
-    Function,
+    /// A function item. Functions are immutable, so they are shared.
+    Function(Rc<Function<N>>),
 
     /// A scalar value. These are in an Rc since they are frequently shared.
     Value(Rc<Value>),
+
+    /// An XDM map. Maps are immutable, so they are shared.
+    Map(Rc<Map<N>>),
+
+    /// An XDM array. Arrays are immutable, so they are shared.
+    Array(Rc<Array<N>>),
+}
+
+/// An XDM map: a set of entries, each a key/value pair.
+/// The key is an atomic [Value] and the value is a [Sequence].
+///
+/// Entries are kept in insertion order, so that serialisation is predictable.
+/// Keys are compared using the "same key" rules of XPath 3.1 section 17.1.1;
+/// i.e. numeric keys are compared by their value, regardless of their type.
+/// Entries are found using an index on the normalised key, so lookup is constant time.
+#[derive(Clone)]
+pub struct Map<N: Node> {
+    entries: Vec<(Rc<Value>, Sequence<N>)>,
+    index: HashMap<MapKey, usize>,
+}
+
+impl<N: Node> Map<N> {
+    /// Create an empty map.
+    pub fn new() -> Self {
+        Map {
+            entries: vec![],
+            index: HashMap::new(),
+        }
+    }
+    /// The number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    /// Is the map empty?
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// Find the value for the given key.
+    pub fn get(&self, k: &Value) -> Option<&Sequence<N>> {
+        self.index
+            .get(&MapKey::from(k))
+            .map(|i| &self.entries[*i].1)
+    }
+    /// Does the map have an entry for the given key?
+    pub fn contains(&self, k: &Value) -> bool {
+        self.index.contains_key(&MapKey::from(k))
+    }
+    /// Add an entry to the map. If the key is already present then its value is replaced.
+    pub fn insert(&mut self, k: Rc<Value>, v: Sequence<N>) {
+        match self.index.entry(MapKey::from(&*k)) {
+            Entry::Occupied(e) => self.entries[*e.get()].1 = v,
+            Entry::Vacant(e) => {
+                e.insert(self.entries.len());
+                self.entries.push((k, v))
+            }
+        }
+    }
+    /// Remove the entry for the given key, if it is present.
+    pub fn remove(&mut self, k: &Value) {
+        if let Some(i) = self.index.remove(&MapKey::from(k)) {
+            self.entries.remove(i);
+            self.index
+                .values_mut()
+                .filter(|j| **j > i)
+                .for_each(|j| *j -= 1)
+        }
+    }
+    /// The keys of the map.
+    pub fn keys(&self) -> Vec<Rc<Value>> {
+        self.entries.iter().map(|(k, _)| k.clone()).collect()
+    }
+    /// Iterate over the entries of the map.
+    pub fn iter(&self) -> impl Iterator<Item = &(Rc<Value>, Sequence<N>)> {
+        self.entries.iter()
+    }
+    /// Serialise the map as a JSON object.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{{}}}",
+            self.entries
+                .iter()
+                .map(|(k, v)| format!("{}:{}", json_string(k.to_string().as_str()), member_json(v)))
+                .collect::<Vec<String>>()
+                .join(",")
+        )
+    }
+}
+
+/// A map key, normalised so that keys that are the same key (XPath 3.1 17.1.1) are equal.
+/// Keys are distinguished by their type family as well as their value:
+/// numeric values are compared by their exact value, with all NaNs being the same key;
+/// strings, URIs and untyped values are compared as strings;
+/// all other values are the same key only when they have the same type and lexical form.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum MapKey {
+    Decimal(Decimal),
+    Double(u64),
+    Boolean(bool),
+    String(String),
+    Other(&'static str, String),
+}
+
+impl From<&Value> for MapKey {
+    fn from(v: &Value) -> Self {
+        match v {
+            Value::Boolean(b) => MapKey::Boolean(*b),
+            Value::Decimal(d) => MapKey::Decimal(d.normalize()),
+            Value::Double(_) | Value::Float(_) => {
+                let d = v.to_double();
+                if d.is_nan() {
+                    MapKey::Double(f64::NAN.to_bits())
+                } else if d == 0.0 {
+                    MapKey::Decimal(Decimal::ZERO)
+                } else {
+                    // A double that has an exact decimal value is the same key as that decimal
+                    Decimal::from_str_exact(d.to_string().as_str())
+                        .map_or(MapKey::Double(d.to_bits()), |e| {
+                            MapKey::Decimal(e.normalize())
+                        })
+                }
+            }
+            _ if v.is_numeric() => Decimal::from_str_exact(v.to_string().as_str())
+                .map_or(MapKey::Double(v.to_double().to_bits()), |e| {
+                    MapKey::Decimal(e.normalize())
+                }),
+            Value::AnyURI(_) => MapKey::String(v.to_string()),
+            _ if derives_from(v, &Value::from("")) => MapKey::String(v.to_string()),
+            _ => MapKey::Other(v.value_type(), v.to_string()),
+        }
+    }
+}
+
+impl<N: Node> Default for Map<N> {
+    fn default() -> Self {
+        Map::new()
+    }
+}
+
+impl<N: Node> FromIterator<(Rc<Value>, Sequence<N>)> for Map<N> {
+    fn from_iter<I: IntoIterator<Item = (Rc<Value>, Sequence<N>)>>(iter: I) -> Self {
+        let mut m = Map::new();
+        iter.into_iter().for_each(|(k, v)| m.insert(k, v));
+        m
+    }
+}
+
+/// Determine whether two map keys are the same key (XPath 3.1 17.1.1).
+pub(crate) fn same_key(a: &Value, b: &Value) -> bool {
+    MapKey::from(a) == MapKey::from(b)
+}
+
+/// An XDM array: an ordered list of members, each of which is a [Sequence].
+///
+/// Note that XPath array positions start at 1, so [Array::get] uses 1-based indexing.
+#[derive(Clone)]
+pub struct Array<N: Node>(Vec<Sequence<N>>);
+
+impl<N: Node> Array<N> {
+    /// Create an empty array.
+    pub fn new() -> Self {
+        Array(vec![])
+    }
+    /// The number of members in the array.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    /// Is the array empty?
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// Get the member at the given position. The first member is at position 1.
+    /// Returns error FOAY0001 if the position is out of bounds.
+    pub fn get(&self, i: i64) -> Result<&Sequence<N>, Error> {
+        if i < 1 || i as usize > self.0.len() {
+            Err(Error::new_with_code(
+                ErrorKind::Unknown,
+                format!("array index {} out of bounds", i),
+                Some(QualifiedName::new(None, None, "FOAY0001")),
+            ))
+        } else {
+            Ok(&self.0[i as usize - 1])
+        }
+    }
+    /// Add a member to the end of the array.
+    pub fn push(&mut self, m: Sequence<N>) {
+        self.0.push(m)
+    }
+    /// The members of the array.
+    pub fn members(&self) -> &Vec<Sequence<N>> {
+        &self.0
+    }
+    /// Iterate over the members of the array.
+    pub fn iter(&self) -> impl Iterator<Item = &Sequence<N>> {
+        self.0.iter()
+    }
+    /// Serialise the array as a JSON array.
+    pub fn to_json(&self) -> String {
+        format!(
+            "[{}]",
+            self.0
+                .iter()
+                .map(|m| member_json(m))
+                .collect::<Vec<String>>()
+                .join(",")
+        )
+    }
+}
+
+impl<N: Node> Default for Array<N> {
+    fn default() -> Self {
+        Array::new()
+    }
+}
+
+impl<N: Node> From<Vec<Sequence<N>>> for Array<N> {
+    fn from(v: Vec<Sequence<N>>) -> Self {
+        Array(v)
+    }
+}
+
+impl<N: Node> FromIterator<Sequence<N>> for Array<N> {
+    fn from_iter<I: IntoIterator<Item = Sequence<N>>>(iter: I) -> Self {
+        Array(iter.into_iter().collect())
+    }
+}
+
+/// Serialise the value of a map entry or array member as JSON.
+/// An empty sequence is null, a singleton is its item, otherwise a JSON array.
+/// Nodes are serialised as XML and written as a JSON string.
+fn member_json<N: Node>(s: &Sequence<N>) -> String {
+    let item_json = |i: &Item<N>| match i {
+        Item::Node(n) => json_string(n.to_xml().as_str()),
+        _ => i.to_json(),
+    };
+    match s.len() {
+        0 => String::from("null"),
+        1 => item_json(&s[0]),
+        _ => format!(
+            "[{}]",
+            s.iter().map(item_json).collect::<Vec<String>>().join(",")
+        ),
+    }
 }
 
 impl<N: item::Node> fmt::Display for Item<N> {
@@ -198,8 +466,9 @@ impl<N: item::Node> fmt::Display for Item<N> {
         // Gives the string value of an item. All items have a string value.
         let result = match self {
             Item::Node(n) => n.to_string(),
-            Item::Function => "".to_string(),
+            Item::Function(_) => "".to_string(),
             Item::Value(v) => v.to_string(),
+            Item::Map(_) | Item::Array(_) => "".to_string(),
         };
         f.write_str(result.as_str())
     }
@@ -210,24 +479,30 @@ impl<N: Node> Item<N> {
     pub fn to_xml(&self) -> String {
         match self {
             Item::Node(n) => n.to_xml(),
-            Item::Function => "".to_string(),
+            Item::Function(_) => "".to_string(),
             Item::Value(v) => v.to_string(),
+            Item::Map(m) => m.to_json(),
+            Item::Array(a) => a.to_json(),
         }
     }
     /// Serialize as XML, with options
     pub fn to_xml_with_options(&self, od: &OutputDefinition) -> String {
         match self {
             Item::Node(n) => n.to_xml_with_options(od),
-            Item::Function => "".to_string(),
-            Item::Value(v) => v.to_string(),
+            Item::Function(_) => "".to_string(),
+            Item::Value(v) => od.map_characters(v.to_string().as_str()),
+            Item::Map(m) => m.to_json(),
+            Item::Array(a) => a.to_json(),
         }
     }
     /// Serialize as JSON
     pub fn to_json(&self) -> String {
         match self {
             Item::Node(n) => n.to_json(),
-            Item::Function => "".to_string(),
-            Item::Value(v) => v.to_string(),
+            Item::Function(_) => "".to_string(),
+            Item::Value(v) => v.to_json(),
+            Item::Map(m) => m.to_json(),
+            Item::Array(a) => a.to_json(),
         }
     }
 
@@ -236,8 +511,9 @@ impl<N: Node> Item<N> {
     pub fn to_bool(&self) -> bool {
         match self {
             Item::Node(..) => true,
-            Item::Function => false,
+            Item::Function(_) => false,
             Item::Value(v) => v.to_bool(),
+            Item::Map(_) | Item::Array(_) => false,
         }
     }
 
@@ -248,7 +524,7 @@ impl<N: Node> Item<N> {
                 ErrorKind::TypeError,
                 String::from("type error: item is a node"),
             )),
-            Item::Function => Result::Err(Error::new(
+            Item::Function(_) => Result::Err(Error::new(
                 ErrorKind::TypeError,
                 String::from("type error: item is a function"),
             )),
@@ -256,6 +532,10 @@ impl<N: Node> Item<N> {
                 Ok(i) => Ok(i),
                 Err(e) => Result::Err(e),
             },
+            Item::Map(_) | Item::Array(_) => Result::Err(Error::new(
+                ErrorKind::TypeError,
+                String::from("type error: item is a map or array"),
+            )),
         }
     }
 
@@ -263,8 +543,9 @@ impl<N: Node> Item<N> {
     pub fn to_double(&self) -> f64 {
         match self {
             Item::Node(..) => f64::NAN,
-            Item::Function => f64::NAN,
+            Item::Function(_) => f64::NAN,
             Item::Value(v) => v.to_double(),
+            Item::Map(_) | Item::Array(_) => f64::NAN,
         }
     }
 
@@ -326,8 +607,10 @@ impl<N: Node> Item<N> {
     pub fn item_type(&self) -> &'static str {
         match self {
             Item::Node(..) => "Node",
-            Item::Function => "Function",
+            Item::Function(_) => "Function",
             Item::Value(v) => v.value_type(),
+            Item::Map(_) => "Map",
+            Item::Array(_) => "Array",
         }
     }
     /// Make a shallow copy of an item.
@@ -335,6 +618,7 @@ impl<N: Node> Item<N> {
     pub fn shallow_copy(&self) -> Result<Self, Error> {
         match self {
             Item::Value(v) => Ok(Item::Value(v.clone())),
+            Item::Map(_) | Item::Array(_) => Ok(self.clone()),
             Item::Node(n) => Ok(Item::Node(n.shallow_copy()?)),
             _ => Result::Err(Error::new(
                 ErrorKind::NotImplemented,
@@ -346,6 +630,7 @@ impl<N: Node> Item<N> {
     pub fn deep_copy(&self) -> Result<Self, Error> {
         match self {
             Item::Value(v) => Ok(Item::Value(v.clone())),
+            Item::Map(_) | Item::Array(_) => Ok(self.clone()),
             Item::Node(n) => Ok(Item::Node(n.deep_copy()?)),
             _ => Result::Err(Error::new(
                 ErrorKind::NotImplemented,
@@ -367,12 +652,19 @@ impl<N: Node> fmt::Debug for Item<N> {
                       //                    n.name()
                 )
             }
-            Item::Function => {
-                write!(f, "function type item")
-            }
+            Item::Function(g) => match g.name() {
+                Some(n) => write!(f, "function type item ({}#{})", n, g.arity()),
+                None => write!(f, "function type item (anonymous#{})", g.arity()),
+            },
             Item::Value(v) => {
                 write!(f, "value type item ({})", v)
             }
+            Item::Map(m) => {
+                write!(f, "map type item ({} entries)", m.len())
+            }
+            Item::Array(a) => {
+                write!(f, "array type item ({} members)", a.len())
+            }
         }
     }
 }
@@ -478,6 +770,16 @@ pub trait Node: Clone + PartialEq + fmt::Debug {
     fn new_element(&self, qn: Rc<QualifiedName>) -> Result<Self, Error>;
     /// Create a new text-type node in the same document tree. The new node is not attached to the tree.
     fn new_text(&self, v: Rc<Value>) -> Result<Self, Error>;
+    /// Create a new text-type node in the same document tree, for which output escaping is disabled.
+    /// When the node is serialised its value is written as is, without escaping markup characters.
+    /// The default implementation ignores disable-output-escaping and creates an ordinary text node.
+    fn new_unescaped_text(&self, v: Rc<Value>) -> Result<Self, Error> {
+        self.new_text(v)
+    }
+    /// Check if output escaping is disabled for a text node. See [Node::new_unescaped_text].
+    fn disables_output_escaping(&self) -> bool {
+        false
+    }
     /// Create a new attribute-type node in the same document tree. The new node is not attached to the tree.
     fn new_attribute(&self, qn: Rc<QualifiedName>, v: Rc<Value>) -> Result<Self, Error>;
     /// Create a new comment-type node in the same document tree. The new node is not attached to the tree.
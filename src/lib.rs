@@ -47,6 +47,8 @@ NB, the library has not been extensively tested.
 
 One aim of the library is to be usable in a WASM environment. To allow that, the library must not have dependencies on file and network I/O, since that is provided by the host browser environment. Where external resources, i.e. URLs, are required the application must provide a closure. In particular, closures must be provided for stylesheet inclusion and importing, as well as for messages.
 
+Similarly, the library does not write result documents. Secondary result documents, created by xsl:result-document, are collected by the [Context] and the application is responsible for serialising and storing them.
+
 ## Plan
 
 1. Complete the XPath 1.0 implementation. (Done!)
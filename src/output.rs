@@ -1,15 +1,71 @@
 /*! How to serialise a tree structure.
+
+The serialization parameters are defined by XSLT and XQuery Serialization 3.1.
+An [OutputDefinition] only describes the serialisation. It is up to the application to encode, and store, the result.
 */
 
 use crate::qname::QualifiedName;
 use core::fmt;
+use std::collections::HashMap;
+
+/// The serialization method. See XSLT and XQuery Serialization 3.1 section 3.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputMethod {
+    Xml,
+    Html,
+    Xhtml,
+    Text,
+    Json,
+    Adaptive,
+}
+
+impl TryFrom<&str> for OutputMethod {
+    type Error = String;
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s.trim() {
+            "xml" => Ok(OutputMethod::Xml),
+            "html" => Ok(OutputMethod::Html),
+            "xhtml" => Ok(OutputMethod::Xhtml),
+            "text" => Ok(OutputMethod::Text),
+            "json" => Ok(OutputMethod::Json),
+            "adaptive" => Ok(OutputMethod::Adaptive),
+            _ => Err(format!("unknown output method \"{}\"", s)),
+        }
+    }
+}
+
+impl fmt::Display for OutputMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            OutputMethod::Xml => "xml",
+            OutputMethod::Html => "html",
+            OutputMethod::Xhtml => "xhtml",
+            OutputMethod::Text => "text",
+            OutputMethod::Json => "json",
+            OutputMethod::Adaptive => "adaptive",
+        })
+    }
+}
 
 /// An output definition. See XSLT v3.0 26 Serialization
 #[derive(Clone, Debug)]
 pub struct OutputDefinition {
     name: Option<QualifiedName>, // TODO: EQName
+    method: OutputMethod,
     indent: bool,
-    // TODO: all the other myriad output parameters
+    omit_xml_declaration: bool,
+    standalone: Option<bool>,
+    doctype_public: Option<String>,
+    doctype_system: Option<String>,
+    cdata_section_elements: Vec<QualifiedName>,
+    suppress_indentation: Vec<QualifiedName>,
+    encoding: String,
+    byte_order_mark: bool,
+    character_map: HashMap<char, String>,
+    item_separator: Option<String>,
+    html_version: Option<f64>,
+    escape_uri_attributes: bool,
+    include_content_type: bool,
 }
 
 impl Default for OutputDefinition {
@@ -22,7 +78,21 @@ impl OutputDefinition {
     pub fn new() -> OutputDefinition {
         OutputDefinition {
             name: None,
+            method: OutputMethod::Xml,
             indent: false,
+            omit_xml_declaration: false,
+            standalone: None,
+            doctype_public: None,
+            doctype_system: None,
+            cdata_section_elements: vec![],
+            suppress_indentation: vec![],
+            encoding: String::from("UTF-8"),
+            byte_order_mark: false,
+            character_map: HashMap::new(),
+            item_separator: None,
+            html_version: None,
+            escape_uri_attributes: true,
+            include_content_type: true,
         }
     }
     pub fn get_name(&self) -> Option<QualifiedName> {
@@ -38,19 +108,128 @@ impl OutputDefinition {
             }
         }
     }
+    pub fn get_method(&self) -> OutputMethod {
+        self.method
+    }
+    pub fn set_method(&mut self, m: OutputMethod) {
+        self.method = m;
+    }
     pub fn get_indent(&self) -> bool {
         self.indent
     }
     pub fn set_indent(&mut self, ind: bool) {
         self.indent = ind;
     }
+    /// Whether to omit the XML declaration. This only has an effect when a document node is serialised.
+    pub fn get_omit_xml_declaration(&self) -> bool {
+        self.omit_xml_declaration
+    }
+    pub fn set_omit_xml_declaration(&mut self, o: bool) {
+        self.omit_xml_declaration = o;
+    }
+    /// The value of the standalone pseudo-attribute. If None, then the pseudo-attribute is omitted.
+    pub fn get_standalone(&self) -> Option<bool> {
+        self.standalone
+    }
+    pub fn set_standalone(&mut self, s: Option<bool>) {
+        self.standalone = s;
+    }
+    pub fn get_doctype_public(&self) -> Option<String> {
+        self.doctype_public.clone()
+    }
+    pub fn set_doctype_public(&mut self, d: Option<String>) {
+        self.doctype_public = d;
+    }
+    pub fn get_doctype_system(&self) -> Option<String> {
+        self.doctype_system.clone()
+    }
+    pub fn set_doctype_system(&mut self, d: Option<String>) {
+        self.doctype_system = d;
+    }
+    /// The elements whose text content is serialised as CDATA sections.
+    pub fn get_cdata_section_elements(&self) -> &Vec<QualifiedName> {
+        &self.cdata_section_elements
+    }
+    pub fn set_cdata_section_elements(&mut self, v: Vec<QualifiedName>) {
+        self.cdata_section_elements = v;
+    }
+    /// The elements whose content is not indented, even when indentation is enabled.
+    pub fn get_suppress_indentation(&self) -> &Vec<QualifiedName> {
+        &self.suppress_indentation
+    }
+    pub fn set_suppress_indentation(&mut self, v: Vec<QualifiedName>) {
+        self.suppress_indentation = v;
+    }
+    /// The character encoding. This is only reported in the XML declaration; the serialiser always produces a Rust string.
+    pub fn get_encoding(&self) -> String {
+        self.encoding.clone()
+    }
+    pub fn set_encoding(&mut self, e: String) {
+        self.encoding = e;
+    }
+    pub fn get_byte_order_mark(&self) -> bool {
+        self.byte_order_mark
+    }
+    pub fn set_byte_order_mark(&mut self, b: bool) {
+        self.byte_order_mark = b;
+    }
+    /// The character map, i.e. the combination of the character maps named by use-character-maps.
+    /// A mapped character is replaced by its string, which is not escaped.
+    pub fn get_character_map(&self) -> &HashMap<char, String> {
+        &self.character_map
+    }
+    pub fn set_character_map(&mut self, m: HashMap<char, String>) {
+        self.character_map = m;
+    }
+    /// Apply the character map to a string.
+    pub fn map_characters(&self, s: &str) -> String {
+        if self.character_map.is_empty() {
+            s.to_string()
+        } else {
+            s.chars().fold(String::new(), |mut acc, c| {
+                match self.character_map.get(&c) {
+                    Some(m) => acc.push_str(m),
+                    None => acc.push(c),
+                }
+                acc
+            })
+        }
+    }
+    /// The string that separates items in a sequence.
+    pub fn get_item_separator(&self) -> Option<String> {
+        self.item_separator.clone()
+    }
+    pub fn set_item_separator(&mut self, s: Option<String>) {
+        self.item_separator = s;
+    }
+    /// The version of HTML produced by the html method. If None, then HTML5 is produced.
+    pub fn get_html_version(&self) -> Option<f64> {
+        self.html_version
+    }
+    pub fn set_html_version(&mut self, v: Option<f64>) {
+        self.html_version = v;
+    }
+    /// Whether the html method escapes non-ASCII characters in URI attributes, such as href and src.
+    pub fn get_escape_uri_attributes(&self) -> bool {
+        self.escape_uri_attributes
+    }
+    pub fn set_escape_uri_attributes(&mut self, e: bool) {
+        self.escape_uri_attributes = e;
+    }
+    /// Whether the html method adds a meta element, giving the character encoding, to the head element.
+    pub fn get_include_content_type(&self) -> bool {
+        self.include_content_type
+    }
+    pub fn set_include_content_type(&mut self, i: bool) {
+        self.include_content_type = i;
+    }
 }
 impl fmt::Display for OutputDefinition {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.indent {
-            f.write_str("indent output")
+            write!(f, "{} method, indent output", self.method)
         } else {
-            f.write_str("do not indent output")
+            write!(f, "{} method, do not indent output", self.method)
         }
     }
 }
@@ -6,14 +6,13 @@ See XSL Transformations v3.0 5.6.1.
 
 use crate::item::{Item, Node};
 use crate::parser::combinators::alt::alt2;
-use crate::parser::combinators::many::{many0, many1};
+use crate::parser::combinators::many::many0;
 use crate::parser::combinators::map::map;
 use crate::parser::{ParseError, ParseInput, ParserState};
 use crate::value::Value;
 use crate::xdmerror::{Error, ErrorKind};
 use std::rc::Rc;
 //use crate::parser::combinators::debug::inspect;
-use crate::parser::combinators::support::none_of;
 use crate::parser::xpath::expr;
 use crate::transform::Transform;
 
@@ -60,46 +59,111 @@ fn avt_expr<N: Node>(input: ParseInput<N>) -> Result<(ParseInput<N>, Transform<N
 
 fn avt<'a, N: Node + 'a>(
 ) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
-    Box::new(map(
-        many0(alt2(
-            map(many1(none_of("{")), |v| {
-                Transform::Literal(Item::Value(Rc::new(Value::from(
-                    v.iter().collect::<String>(),
-                ))))
-            }),
-            braced_expr(),
-        )),
-        |mut v| {
-            if v.len() == 1 {
-                v.pop().unwrap()
+    Box::new(map(many0(alt2(literal_text(), braced_expr())), |mut v| {
+        if v.len() == 1 {
+            v.pop().unwrap()
+        } else {
+            Transform::SequenceItems(v)
+        }
+    }))
+}
+
+/// Literal text in the AVT. A doubled brace is an escape for a single brace.
+/// The text ends at an expression, or at a single close brace, which is an error.
+fn literal_text<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
+    Box::new(move |(input, state)| {
+        let mut text = String::new();
+        let mut rest = input;
+        loop {
+            if let Some(r) = rest.strip_prefix("{{") {
+                text.push('{');
+                rest = r
+            } else if let Some(r) = rest.strip_prefix("}}") {
+                text.push('}');
+                rest = r
             } else {
-                Transform::SequenceItems(v)
+                match rest.chars().next() {
+                    Some(c) if c != '{' && c != '}' => {
+                        text.push(c);
+                        rest = &rest[c.len_utf8()..]
+                    }
+                    _ => break,
+                }
             }
-        },
-    ))
+        }
+        if text.is_empty() {
+            Err(ParseError::Combinator)
+        } else {
+            Ok((
+                (rest, state),
+                Transform::Literal(Item::Value(Rc::new(Value::from(text)))),
+            ))
+        }
+    })
 }
 
-/// A XPath expression in the AVT. Braces do not nest.
+/// A XPath expression in the AVT.
+/// The expression ends at the close brace that follows it, so braces within the expression,
+/// such as in a map constructor or an inline function body, are part of the expression.
 fn braced_expr<'a, N: Node + 'a>(
 ) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
-    // Can't use combinator directly, since the close brace will be unexpected.
-    // Instead, extract the string up to the close brace, then feed that to the combinator.
-    //    Box::new(map(
-    //        tuple3(
-    //            inspect("brace-open", tag("{")),
-    //            inspect("expr",expr()),
-    //            inspect("brace-close", tag("}")),
-    //        ),
-    //        |(_, e, _)| e
-    //    ))
-    Box::new(move |(input, state)| match input.get(0..1) {
-        Some("{") => match input.find('}') {
-            None => Err(ParseError::Combinator),
-            Some(ind) => match expr()((input.get(1..ind).unwrap(), state.clone())) {
-                Ok((_, result)) => Ok(((input.get(ind..).map_or("", |r| r), state), result)),
-                Err(e) => Err(e),
-            },
-        },
-        _ => Err(ParseError::Combinator),
+    Box::new(move |(input, state)| match input.strip_prefix('{') {
+        Some(rest) => {
+            let ((rest1, state1), result) = expr()((rest, state))?;
+            match rest1.trim_start().strip_prefix('}') {
+                Some(rest2) => Ok(((rest2, state1), result)),
+                None => Err(ParseError::Combinator),
+            }
+        }
+        None => Err(ParseError::Combinator),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::SequenceTrait;
+    use crate::transform::context::{Context, StaticContextBuilder};
+    use crate::trees::smite::RNode;
+
+    fn evaluate(avt: &str) -> String {
+        let t = parse::<RNode>(avt, None).expect("failed to parse AVT");
+        let mut stctxt = StaticContextBuilder::new()
+            .message(|_| Ok(()))
+            .fetcher(|_| Err(Error::new(ErrorKind::NotImplemented, "not implemented")))
+            .parser(|_| Err(Error::new(ErrorKind::NotImplemented, "not implemented")))
+            .build();
+        Context::new()
+            .dispatch(&mut stctxt, &t)
+            .expect("failed to evaluate AVT")
+            .to_string()
+    }
+
+    #[test]
+    fn avt_literal() {
+        assert_eq!(evaluate("abc"), "abc");
+    }
+
+    #[test]
+    fn avt_expression() {
+        assert_eq!(evaluate("{1}"), "1");
+        // The closing brace is not part of the following text
+        assert_eq!(evaluate("a{1}b"), "a1b");
+        assert_eq!(evaluate("a{1}b{'c'}d"), "a1bcd");
+    }
+
+    #[test]
+    fn avt_escaped_braces() {
+        assert_eq!(evaluate("{{a}}"), "{a}");
+        assert_eq!(evaluate("\\d{{4}}-{1}"), "\\d{4}-1");
+        assert!(parse::<RNode>("a}b", None).is_err());
+    }
+
+    #[test]
+    fn avt_nested_braces() {
+        assert_eq!(evaluate("a{map{'k':1}?k}b"), "a1b");
+        assert_eq!(evaluate("{function($x){$x + 1}(2)}-{{}}"), "3-{}");
+        assert!(parse::<RNode>("{1", None).is_err());
+    }
+}
@@ -37,9 +37,12 @@ pub(crate) fn comparison_expr<'a, N: Node + 'a>(
                     "=" | "!=" | "<" | "<=" | ">" | ">=" => {
                         Transform::GeneralComparison(Operator::from(b), Box::new(v), Box::new(t))
                     }
-                    "eq" | "ne" | "lt" | "le" | "gt" | "ge" | "is" | "<<" | ">>" => {
+                    "eq" | "ne" | "lt" | "le" | "gt" | "ge" => {
                         Transform::ValueComparison(Operator::from(b), Box::new(v), Box::new(t))
                     }
+                    "is" | "<<" | ">>" => {
+                        Transform::NodeComparison(Operator::from(b), Box::new(v), Box::new(t))
+                    }
                     _ => Transform::Empty, // error
                 }
             }
@@ -1,36 +1,77 @@
 //! General productions for XPath expressions.
 
 use crate::item::Node;
-use crate::parser::combinators::alt::{alt2, alt5};
+use crate::parser::combinators::alt::{alt2, alt3, alt4};
+use crate::parser::combinators::many::many0;
 use crate::parser::combinators::map::map;
+use crate::parser::combinators::pair::pair;
 use crate::parser::{ParseError, ParseInput};
 //use crate::parser::combinators::debug::inspect;
 use crate::parser::combinators::delimited::delimited;
 use crate::parser::combinators::tag::tag;
 use crate::parser::xpath::context::context_item;
 use crate::parser::xpath::expr_wrapper;
-use crate::parser::xpath::functions::function_call;
+use crate::parser::xpath::functions::{
+    argumentlist, function_call, inline_function_expr, named_function_ref,
+};
 use crate::parser::xpath::literals::literal;
+use crate::parser::xpath::maps::{array_constructor, lookup, map_constructor, unary_lookup};
 use crate::parser::xpath::variables::variable_reference;
 use crate::transform::Transform;
 
 // PostfixExpr ::= PrimaryExpr (Predicate | ArgumentList | Lookup)*
-// TODO: predicates, arg list, lookup
+// TODO: predicates
 pub(crate) fn postfix_expr<'a, N: Node + 'a>(
 ) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
-    Box::new(primary_expr::<N>())
+    Box::new(map(
+        pair(
+            primary_expr::<N>(),
+            many0(alt2(
+                map(lookup::<N>(), Postfix::Lookup),
+                map(argumentlist::<N>(), Postfix::Arguments),
+            )),
+        ),
+        |(p, l)| {
+            l.into_iter().fold(p, |e, q| match q {
+                Postfix::Lookup(k) => Transform::Lookup(Box::new(e), k.map(Box::new)),
+                Postfix::Arguments(a) => {
+                    if a.iter().all(|b| b.is_some()) {
+                        Transform::DynamicCall(Box::new(e), a.into_iter().flatten().collect())
+                    } else {
+                        Transform::PartialApplication(Box::new(e), a)
+                    }
+                }
+            })
+        },
+    ))
+}
+
+enum Postfix<N: Node> {
+    Lookup(Option<Transform<N>>),
+    Arguments(Vec<Option<Transform<N>>>),
 }
 
 // PrimaryExpr ::= Literal | VarRef | ParenthesizedExpr | ContextItemExpr | FunctionCall | FunctionItemExpr | MapConstructor | ArrayConstructor | UnaryLookup
-// TODO: finish this parser
+// FunctionItemExpr ::= NamedFunctionRef | InlineFunctionExpr
 fn primary_expr<'a, N: Node + 'a>(
 ) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
-    Box::new(alt5(
-        literal::<N>(),
-        parenthesized_expr::<N>(),
-        function_call::<N>(),
-        variable_reference::<N>(),
-        context_item::<N>(),
+    Box::new(alt3(
+        alt4(
+            literal::<N>(),
+            parenthesized_expr::<N>(),
+            map_constructor::<N>(),
+            array_constructor::<N>(),
+        ),
+        alt3(
+            inline_function_expr::<N>(),
+            named_function_ref::<N>(),
+            function_call::<N>(),
+        ),
+        alt3(
+            variable_reference::<N>(),
+            context_item::<N>(),
+            unary_lookup::<N>(),
+        ),
     ))
 }
 
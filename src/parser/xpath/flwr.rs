@@ -1,6 +1,7 @@
 //! XPath FLWR expressions.
 
 use crate::item::Node;
+use crate::parser::combinators::alt::alt2;
 use crate::parser::combinators::list::separated_list1;
 use crate::parser::combinators::map::{map, map_with_state};
 use crate::parser::combinators::pair::pair;
@@ -9,9 +10,10 @@ use crate::parser::combinators::tuple::{tuple10, tuple3, tuple5, tuple6};
 use crate::parser::combinators::whitespace::xpwhitespace;
 //use crate::parser::combinators::debug::inspect;
 use crate::parser::xpath::nodetests::qualname_test;
-use crate::parser::xpath::support::get_nt_localname;
+use crate::parser::xpath::support::get_nt_varname;
 use crate::parser::xpath::{expr_single_wrapper, expr_wrapper};
 use crate::parser::{ParseError, ParseInput};
+use crate::transform::types::Quantifier;
 use crate::transform::{in_scope_namespaces, Transform};
 
 // IfExpr ::= 'if' '(' Expr ')' 'then' ExprSingle 'else' ExprSingle
@@ -70,7 +72,7 @@ fn simple_for_clause<'a, N: Node + 'a>() -> Box<
             xpwhitespace(),
             separated_list1(
                 map(tuple3(xpwhitespace(), tag(","), xpwhitespace()), |_| ()),
-                map(
+                map_with_state(
                     tuple6(
                         tag("$"),
                         qualname_test(),
@@ -79,7 +81,12 @@ fn simple_for_clause<'a, N: Node + 'a>() -> Box<
                         xpwhitespace(),
                         expr_single_wrapper::<N>(true),
                     ),
-                    |(_, qn, _, _, _, e)| (get_nt_localname(&qn), e),
+                    |(_, qn, _, _, _, e), state| {
+                        (
+                            get_nt_varname(&qn, &in_scope_namespaces(state.cur.clone())),
+                            e,
+                        )
+                    },
                 ),
             ),
         ),
@@ -87,6 +94,42 @@ fn simple_for_clause<'a, N: Node + 'a>() -> Box<
     ))
 }
 
+// QuantifiedExpr ::= ('some' | 'every') '$' VarName 'in' ExprSingle (',' '$' VarName 'in' ExprSingle)* 'satisfies' ExprSingle
+pub(crate) fn quantified_expr<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
+    Box::new(map(
+        tuple5(
+            alt2(
+                map(tag("some"), |_| Quantifier::Some),
+                map(tag("every"), |_| Quantifier::Every),
+            ),
+            xpwhitespace(),
+            separated_list1(
+                map(tuple3(xpwhitespace(), tag(","), xpwhitespace()), |_| ()),
+                map_with_state(
+                    tuple6(
+                        tag("$"),
+                        qualname_test(),
+                        xpwhitespace(),
+                        tag("in"),
+                        xpwhitespace(),
+                        expr_single_wrapper::<N>(true),
+                    ),
+                    |(_, qn, _, _, _, e), state| {
+                        (
+                            get_nt_varname(&qn, &in_scope_namespaces(state.cur.clone())),
+                            e,
+                        )
+                    },
+                ),
+            ),
+            tuple3(xpwhitespace(), tag("satisfies"), xpwhitespace()),
+            expr_single_wrapper::<N>(true),
+        ),
+        |(q, _, v, _, t)| Transform::Quantified(q, v, Box::new(t)),
+    ))
+}
+
 // LetExpr ::= SimpleLetClause 'return' ExprSingle
 pub(crate) fn let_expr<'a, N: Node + 'a>(
 ) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
@@ -135,7 +178,7 @@ fn simple_let_clause<'a, N: Node + 'a>() -> Box<
             xpwhitespace(),
             separated_list1(
                 map(tuple3(xpwhitespace(), tag(","), xpwhitespace()), |_| ()),
-                map(
+                map_with_state(
                     tuple6(
                         tag("$"),
                         qualname_test(),
@@ -144,7 +187,12 @@ fn simple_let_clause<'a, N: Node + 'a>() -> Box<
                         xpwhitespace(),
                         expr_single_wrapper::<N>(true),
                     ),
-                    |(_, qn, _, _, _, e)| (get_nt_localname(&qn), e),
+                    |(_, qn, _, _, _, e), state| {
+                        (
+                            get_nt_varname(&qn, &in_scope_namespaces(state.cur.clone())),
+                            e,
+                        )
+                    },
                 ),
             ),
         ),
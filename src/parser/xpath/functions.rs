@@ -1,62 +1,94 @@
 //! Functions for functions.
 
 use crate::item::Node;
-use crate::parser::combinators::alt::alt2;
+use crate::parser::combinators::alt::{alt2, alt3};
 use crate::parser::combinators::list::separated_list0;
+use crate::parser::combinators::many::many0;
 use crate::parser::combinators::map::{map, map_with_state};
 use crate::parser::combinators::opt::opt;
 use crate::parser::combinators::pair::pair;
+use crate::parser::combinators::support::digit1;
 use crate::parser::combinators::tag::tag;
-use crate::parser::combinators::tuple::{tuple3, tuple6};
+use crate::parser::combinators::tuple::{tuple10, tuple3, tuple4, tuple5};
 use crate::parser::combinators::whitespace::xpwhitespace;
 use std::rc::Rc;
 //use crate::parser::combinators::debug::inspect;
-use crate::parser::xml::qname::qualname;
-use crate::parser::xpath::expr_single_wrapper;
 use crate::parser::xpath::expressions::parenthesized_expr;
+use crate::parser::xpath::maps::{array_function, map_function};
 use crate::parser::xpath::nodetests::qualname_test;
 use crate::parser::xpath::numbers::unary_expr;
+use crate::parser::xpath::support::get_nt_qualname;
+use crate::parser::xpath::types::{sequencetype_expr, XSD_NAMESPACE};
+use crate::parser::xpath::variables::variable_reference;
+use crate::parser::xpath::{expr_single_wrapper, expr_wrapper};
 use crate::parser::{ParseError, ParseInput};
 use crate::qname::QualifiedName;
 use crate::transform::callable::ActualParameters;
+use crate::transform::datetime::Component;
+use crate::transform::types::{atomic_type, SequenceType};
 use crate::transform::{in_scope_namespaces, NameTest, NodeTest, Transform, WildcardOrName};
+use crate::value::Value;
 use crate::xdmerror::ErrorKind;
 
 // ArrowExpr ::= UnaryExpr ( '=>' ArrowFunctionSpecifier ArgumentList)*
+// The value of the left-hand expression becomes the first argument of the function call.
 pub(crate) fn arrow_expr<'a, N: Node + 'a>(
 ) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
-    Box::new(map(
+    Box::new(map_with_state(
         pair(
             unary_expr::<N>(),
-            opt(tuple6(
+            many0(tuple5(
                 xpwhitespace(),
                 tag("=>"),
                 xpwhitespace(),
                 arrowfunctionspecifier::<N>(),
-                xpwhitespace(),
-                opt(argumentlist::<N>()),
+                pair(xpwhitespace(), argumentlist::<N>()),
             )),
         ),
-        |(v, o)| {
-            if o.is_none() {
-                v
-            } else {
-                Transform::NotImplemented("arrow_expr".to_string())
-            }
+        |(v, o), state| {
+            o.into_iter().fold(v, |acc, (_, _, _, f, (_, mut a))| {
+                a.insert(0, Some(acc));
+                match f {
+                    ArrowSpecifier::Name(qn) => {
+                        if a.iter().all(|b| b.is_some()) {
+                            function_transform(
+                                qn,
+                                a.into_iter().flatten().collect(),
+                                state.cur.clone(),
+                            )
+                        } else {
+                            match named_function(qn, a.len(), state.cur.clone()) {
+                                Transform::Error(k, m) => Transform::Error(k, m),
+                                g => Transform::PartialApplication(Box::new(g), a),
+                            }
+                        }
+                    }
+                    ArrowSpecifier::Dynamic(g) => {
+                        if a.iter().all(|b| b.is_some()) {
+                            Transform::DynamicCall(Box::new(g), a.into_iter().flatten().collect())
+                        } else {
+                            Transform::PartialApplication(Box::new(g), a)
+                        }
+                    }
+                }
+            })
         },
     ))
 }
 
+// The function to call in an arrow expression: either a named function or an expression that evaluates to a function item.
+enum ArrowSpecifier<N: Node> {
+    Name(NodeTest),
+    Dynamic(Transform<N>),
+}
+
 // ArrowFunctionSpecifier ::= EQName | VarRef | ParenthesizedExpr
-// TODO: finish this parser with EQName and VarRef
 fn arrowfunctionspecifier<'a, N: Node + 'a>(
-) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
-    Box::new(map(
-        alt2(
-            map(qualname(), |_| ()),
-            map(parenthesized_expr::<N>(), |_| ()),
-        ),
-        |_| Transform::NotImplemented("arrowfunctionspecifier".to_string()),
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, ArrowSpecifier<N>), ParseError> + 'a> {
+    Box::new(alt3(
+        map(qualname_test(), ArrowSpecifier::Name),
+        map(variable_reference::<N>(), ArrowSpecifier::Dynamic),
+        map(parenthesized_expr::<N>(), ArrowSpecifier::Dynamic),
     ))
 }
 
@@ -65,491 +97,1370 @@ pub(crate) fn function_call<'a, N: Node + 'a>(
 ) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
     Box::new(map_with_state(
         pair(qualname_test(), argumentlist::<N>()),
-        |(qn, mut a), state| match qn {
-            NodeTest::Name(NameTest {
-                name: Some(WildcardOrName::Name(ref localpart)),
-                ns: None,
-                prefix: None,
-            }) => match localpart.to_string().as_str() {
-                "current" => Transform::CurrentItem,
-                "position" => Transform::Position,
-                "last" => Transform::Last,
-                "count" => {
-                    if a.is_empty() {
-                        Transform::Count(Box::new(Transform::Empty))
-                    } else if a.len() == 1 {
-                        Transform::Count(Box::new(a.pop().unwrap()))
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+        |(qn, a), state| {
+            if a.iter().all(|b| b.is_some()) {
+                function_transform(qn, a.into_iter().flatten().collect(), state.cur.clone())
+            } else {
+                // Partial function application
+                match named_function(qn, a.len(), state.cur.clone()) {
+                    Transform::Error(k, m) => Transform::Error(k, m),
+                    f => Transform::PartialApplication(Box::new(f), a),
                 }
-                "local-name" => {
-                    if a.is_empty() {
-                        Transform::LocalName(None)
-                    } else if a.len() == 1 {
-                        Transform::LocalName(Some(Box::new(a.pop().unwrap())))
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+            }
+        },
+    ))
+}
+
+/// Map a call to a named function to its [Transform].
+fn function_transform<N: Node>(
+    qn: NodeTest,
+    mut a: Vec<Transform<N>>,
+    cur: Option<N>,
+) -> Transform<N> {
+    match qn {
+        NodeTest::Name(NameTest {
+            name: Some(WildcardOrName::Name(ref localpart)),
+            ns: None,
+            prefix: None,
+        }) => match localpart.to_string().as_str() {
+            "current" => Transform::CurrentItem,
+            "position" => Transform::Position,
+            "last" => Transform::Last,
+            "count" => {
+                if a.is_empty() {
+                    Transform::Count(Box::new(Transform::Empty))
+                } else if a.len() == 1 {
+                    Transform::Count(Box::new(a.pop().unwrap()))
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
                 }
-                "name" => {
-                    if a.is_empty() {
-                        Transform::Name(None)
-                    } else if a.len() == 1 {
-                        Transform::Name(Some(Box::new(a.pop().unwrap())))
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+            }
+            "local-name" => {
+                if a.is_empty() {
+                    Transform::LocalName(None)
+                } else if a.len() == 1 {
+                    Transform::LocalName(Some(Box::new(a.pop().unwrap())))
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
                 }
-                "string" => {
-                    if a.len() == 1 {
-                        Transform::String(Box::new(a.pop().unwrap()))
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+            }
+            "name" => {
+                if a.is_empty() {
+                    Transform::Name(None)
+                } else if a.len() == 1 {
+                    Transform::Name(Some(Box::new(a.pop().unwrap())))
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
                 }
-                "concat" => Transform::Concat(a),
-                "starts-with" => {
-                    if a.len() == 2 {
-                        let b = a.pop().unwrap();
-                        let c = a.pop().unwrap();
-                        Transform::StartsWith(Box::new(c), Box::new(b))
-                    } else {
-                        // Incorrect arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("incorrect arguments"))
-                    }
+            }
+            "string" => {
+                if a.len() == 1 {
+                    Transform::String(Box::new(a.pop().unwrap()))
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
                 }
-                "contains" => {
-                    if a.len() == 2 {
-                        let b = a.pop().unwrap();
-                        let c = a.pop().unwrap();
-                        Transform::Contains(Box::new(c), Box::new(b))
-                    } else {
-                        // Incorrect arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("incorrect arguments"))
-                    }
+            }
+            "concat" => Transform::Concat(a),
+            "starts-with" => {
+                if a.len() == 2 {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::StartsWith(Box::new(c), Box::new(b))
+                } else {
+                    // Incorrect arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("incorrect arguments"))
                 }
-                "substring" => {
-                    if a.len() == 2 {
-                        let b = a.pop().unwrap();
-                        let c = a.pop().unwrap();
-                        Transform::Substring(Box::new(c), Box::new(b), None)
-                    } else if a.len() == 3 {
-                        let b = a.pop().unwrap();
-                        let c = a.pop().unwrap();
-                        let d = a.pop().unwrap();
-                        Transform::Substring(Box::new(d), Box::new(c), Some(Box::new(b)))
-                    } else {
-                        // Wrong number of arguments
-                        Transform::Error(
-                            ErrorKind::ParseError,
-                            String::from("wrong number of arguments"),
-                        )
-                    }
+            }
+            "contains" => {
+                if a.len() == 2 {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::Contains(Box::new(c), Box::new(b))
+                } else {
+                    // Incorrect arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("incorrect arguments"))
                 }
-                "substring-before" => {
-                    if a.len() == 2 {
-                        let b = a.pop().unwrap();
-                        let c = a.pop().unwrap();
-                        Transform::SubstringBefore(Box::new(c), Box::new(b))
-                    } else {
-                        // Incorrect arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("incorrect arguments"))
-                    }
+            }
+            "substring" => {
+                if a.len() == 2 {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::Substring(Box::new(c), Box::new(b), None)
+                } else if a.len() == 3 {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    let d = a.pop().unwrap();
+                    Transform::Substring(Box::new(d), Box::new(c), Some(Box::new(b)))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
                 }
-                "substring-after" => {
-                    if a.len() == 2 {
-                        let b = a.pop().unwrap();
-                        let c = a.pop().unwrap();
-                        Transform::SubstringAfter(Box::new(c), Box::new(b))
-                    } else {
-                        // Incorrect arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("incorrect arguments"))
-                    }
+            }
+            "substring-before" => {
+                if a.len() == 2 {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::SubstringBefore(Box::new(c), Box::new(b))
+                } else {
+                    // Incorrect arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("incorrect arguments"))
                 }
-                "normalize-space" => {
-                    if a.is_empty() {
-                        Transform::NormalizeSpace(None)
-                    } else if a.len() == 1 {
-                        Transform::NormalizeSpace(Some(Box::new(a.pop().unwrap())))
-                    } else {
-                        // Wrong number of arguments
-                        Transform::Error(
-                            ErrorKind::ParseError,
-                            String::from("wrong number of arguments"),
-                        )
-                    }
+            }
+            "substring-after" => {
+                if a.len() == 2 {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::SubstringAfter(Box::new(c), Box::new(b))
+                } else {
+                    // Incorrect arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("incorrect arguments"))
                 }
-                "translate" => {
-                    if a.len() == 3 {
-                        let b = a.pop().unwrap();
-                        let c = a.pop().unwrap();
-                        let d = a.pop().unwrap();
-                        Transform::Translate(Box::new(d), Box::new(c), Box::new(b))
-                    } else {
-                        // Wrong number of arguments
-                        Transform::Error(
-                            ErrorKind::ParseError,
-                            String::from("wrong number of arguments"),
-                        )
-                    }
+            }
+            "normalize-space" => {
+                if a.is_empty() {
+                    Transform::NormalizeSpace(None)
+                } else if a.len() == 1 {
+                    Transform::NormalizeSpace(Some(Box::new(a.pop().unwrap())))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
                 }
-                "generate-id" => {
-                    if a.is_empty() {
-                        Transform::GenerateId(None)
-                    } else if a.len() == 1 {
-                        Transform::GenerateId(Some(Box::new(a.pop().unwrap())))
-                    } else {
-                        // Wrong number of arguments
-                        Transform::Error(
-                            ErrorKind::ParseError,
-                            String::from("wrong number of arguments"),
-                        )
-                    }
+            }
+            "translate" => {
+                if a.len() == 3 {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    let d = a.pop().unwrap();
+                    Transform::Translate(Box::new(d), Box::new(c), Box::new(b))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
                 }
-                "boolean" => {
-                    if a.len() == 1 {
-                        Transform::Boolean(Box::new(a.pop().unwrap()))
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+            }
+            "upper-case" => {
+                if a.len() == 1 {
+                    Transform::UpperCase(Box::new(a.pop().unwrap()))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
                 }
-                "not" => {
-                    if a.len() == 1 {
-                        Transform::Not(Box::new(a.pop().unwrap()))
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+            }
+            "lower-case" => {
+                if a.len() == 1 {
+                    Transform::LowerCase(Box::new(a.pop().unwrap()))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
                 }
-                "true" => {
-                    if a.is_empty() {
-                        Transform::True
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+            }
+            "string-join" => match a.len() {
+                1 => Transform::StringJoin(Box::new(a.pop().unwrap()), None),
+                2 => {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::StringJoin(Box::new(c), Some(Box::new(b)))
                 }
-                "false" => {
-                    if a.is_empty() {
-                        Transform::False
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            "string-length" => match a.len() {
+                0 => Transform::StringLength(None),
+                1 => Transform::StringLength(Some(Box::new(a.pop().unwrap()))),
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            "tokenize" => match a.len() {
+                1 => Transform::Tokenize(Box::new(a.pop().unwrap()), None, None),
+                2 => {
+                    let p = a.pop().unwrap();
+                    let s = a.pop().unwrap();
+                    Transform::Tokenize(Box::new(s), Some(Box::new(p)), None)
                 }
-                "number" => {
-                    if a.len() == 1 {
-                        Transform::Number(Box::new(a.pop().unwrap()))
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+                3 => {
+                    let f = a.pop().unwrap();
+                    let p = a.pop().unwrap();
+                    let s = a.pop().unwrap();
+                    Transform::Tokenize(Box::new(s), Some(Box::new(p)), Some(Box::new(f)))
                 }
-                "sum" => {
-                    if a.len() == 1 {
-                        Transform::Sum(Box::new(a.pop().unwrap()))
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            "matches" => match a.len() {
+                2 => {
+                    let p = a.pop().unwrap();
+                    let s = a.pop().unwrap();
+                    Transform::Matches(Box::new(s), Box::new(p), None)
                 }
-                "avg" => {
-                    if a.len() == 0 {
-                        Transform::Empty
-                    } else if a.len() == 1 {
-                        Transform::Avg(Box::new(a.pop().unwrap()))
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+                3 => {
+                    let f = a.pop().unwrap();
+                    let p = a.pop().unwrap();
+                    let s = a.pop().unwrap();
+                    Transform::Matches(Box::new(s), Box::new(p), Some(Box::new(f)))
                 }
-                "min" => {
-                    if a.len() == 0 {
-                        Transform::Empty
-                    } else if a.len() == 1 {
-                        Transform::Min(Box::new(a.pop().unwrap()))
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            "replace" => match a.len() {
+                3 => {
+                    let r = a.pop().unwrap();
+                    let p = a.pop().unwrap();
+                    let s = a.pop().unwrap();
+                    Transform::Replace(Box::new(s), Box::new(p), Box::new(r), None)
                 }
-                "max" => {
-                    if a.len() == 0 {
-                        Transform::Empty
-                    } else if a.len() == 1 {
-                        Transform::Max(Box::new(a.pop().unwrap()))
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+                4 => {
+                    let f = a.pop().unwrap();
+                    let r = a.pop().unwrap();
+                    let p = a.pop().unwrap();
+                    let s = a.pop().unwrap();
+                    Transform::Replace(Box::new(s), Box::new(p), Box::new(r), Some(Box::new(f)))
                 }
-                "floor" => {
-                    if a.len() == 1 {
-                        Transform::Floor(Box::new(a.pop().unwrap()))
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            "analyze-string" => match a.len() {
+                2 => {
+                    let p = a.pop().unwrap();
+                    let s = a.pop().unwrap();
+                    Transform::AnalyzeString(Box::new(s), Box::new(p), None)
                 }
-                "ceiling" => {
-                    if a.len() == 1 {
-                        Transform::Ceiling(Box::new(a.pop().unwrap()))
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+                3 => {
+                    let f = a.pop().unwrap();
+                    let p = a.pop().unwrap();
+                    let s = a.pop().unwrap();
+                    Transform::AnalyzeString(Box::new(s), Box::new(p), Some(Box::new(f)))
                 }
-                "round" => {
-                    if a.len() == 1 {
-                        let b = a.pop().unwrap();
-                        Transform::Round(Box::new(b), None)
-                    } else if a.len() == 2 {
-                        let b = a.pop().unwrap();
-                        let c = a.pop().unwrap();
-                        Transform::Round(Box::new(c), Some(Box::new(b)))
-                    } else {
-                        // Wrong number of arguments
-                        Transform::Error(
-                            ErrorKind::ParseError,
-                            String::from("wrong number of arguments"),
-                        )
-                    }
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            "codepoints-to-string" => {
+                if a.len() == 1 {
+                    Transform::CodepointsToString(Box::new(a.pop().unwrap()))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
                 }
-                "current-date-time" => {
-                    if a.is_empty() {
-                        Transform::CurrentDateTime
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+            }
+            "string-to-codepoints" => {
+                if a.len() == 1 {
+                    Transform::StringToCodepoints(Box::new(a.pop().unwrap()))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
                 }
-                "current-date" => {
-                    if a.is_empty() {
-                        Transform::CurrentDate
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+            }
+            "compare" => match a.len() {
+                2 => {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::Compare(Box::new(c), Box::new(b), None)
                 }
-                "current-time" => {
-                    if a.is_empty() {
-                        Transform::CurrentTime
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+                3 => {
+                    let col = a.pop().unwrap();
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::Compare(Box::new(c), Box::new(b), Some(Box::new(col)))
                 }
-                "format-date-time" => {
-                    if a.len() == 2 {
-                        let b = a.pop().unwrap();
-                        let c = a.pop().unwrap();
-                        Transform::FormatDateTime(Box::new(c), Box::new(b), None, None, None)
-                    } else if a.len() == 5 {
-                        let b = a.pop().unwrap();
-                        let c = a.pop().unwrap();
-                        let d = a.pop().unwrap();
-                        let e = a.pop().unwrap();
-                        let f = a.pop().unwrap();
-                        Transform::FormatDateTime(
-                            Box::new(f),
-                            Box::new(e),
-                            Some(Box::new(d)),
-                            Some(Box::new(c)),
-                            Some(Box::new(b)),
-                        )
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            "normalize-unicode" => match a.len() {
+                1 => Transform::NormalizeUnicode(Box::new(a.pop().unwrap()), None),
+                2 => {
+                    let f = a.pop().unwrap();
+                    let s = a.pop().unwrap();
+                    Transform::NormalizeUnicode(Box::new(s), Some(Box::new(f)))
                 }
-                "format-date" => {
-                    if a.len() == 2 {
-                        let b = a.pop().unwrap();
-                        let c = a.pop().unwrap();
-                        Transform::FormatDate(Box::new(c), Box::new(b), None, None, None)
-                    } else if a.len() == 5 {
-                        let b = a.pop().unwrap();
-                        let c = a.pop().unwrap();
-                        let d = a.pop().unwrap();
-                        let e = a.pop().unwrap();
-                        let f = a.pop().unwrap();
-                        Transform::FormatDate(
-                            Box::new(f),
-                            Box::new(e),
-                            Some(Box::new(d)),
-                            Some(Box::new(c)),
-                            Some(Box::new(b)),
-                        )
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            "encode-for-uri" => {
+                if a.len() == 1 {
+                    Transform::EncodeForUri(Box::new(a.pop().unwrap()))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
                 }
-                "format-time" => {
-                    if a.len() == 2 {
-                        let b = a.pop().unwrap();
-                        let c = a.pop().unwrap();
-                        Transform::FormatTime(Box::new(c), Box::new(b), None, None, None)
-                    } else if a.len() == 5 {
-                        let b = a.pop().unwrap();
-                        let c = a.pop().unwrap();
-                        let d = a.pop().unwrap();
-                        let e = a.pop().unwrap();
-                        let f = a.pop().unwrap();
-                        Transform::FormatTime(
-                            Box::new(f),
-                            Box::new(e),
-                            Some(Box::new(d)),
-                            Some(Box::new(c)),
-                            Some(Box::new(b)),
-                        )
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+            }
+            "escape-html-uri" => {
+                if a.len() == 1 {
+                    Transform::EscapeHtmlUri(Box::new(a.pop().unwrap()))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
                 }
-                "format-number" => {
-                    if a.is_empty() || a.len() == 1 {
-                        // Too few arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too few arguments"))
-                    } else if a.len() == 2 {
-                        let b = a.pop().unwrap();
-                        let c = a.pop().unwrap();
-                        Transform::FormatNumber(Box::new(c), Box::new(b), None)
-                    } else if a.len() == 3 {
-                        let b = a.pop().unwrap();
-                        let c = a.pop().unwrap();
-                        let d = a.pop().unwrap();
-                        Transform::FormatNumber(Box::new(d), Box::new(c), Some(Box::new(b)))
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+            }
+            "iri-to-uri" => {
+                if a.len() == 1 {
+                    Transform::IriToUri(Box::new(a.pop().unwrap()))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
                 }
-                "current-group" => {
-                    if a.is_empty() {
-                        Transform::CurrentGroup
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+            }
+            "empty" => {
+                if a.len() == 1 {
+                    Transform::FnEmpty(Box::new(a.pop().unwrap()))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
                 }
-                "current-grouping-key" => {
-                    if a.is_empty() {
-                        Transform::CurrentGroupingKey
-                    } else {
-                        // Too many arguments
-                        Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
-                    }
+            }
+            "exists" => {
+                if a.len() == 1 {
+                    Transform::Exists(Box::new(a.pop().unwrap()))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
                 }
-                "key" => {
-                    if a.len() == 2 {
-                        let m = a.pop().unwrap();
-                        let name = a.pop().unwrap();
-                        Transform::Key(
-                            Box::new(name),
-                            Box::new(m),
-                            None,
-                            in_scope_namespaces(state.cur.clone()),
-                        )
-                    } else if a.len() == 3 {
-                        let u = a.pop().unwrap();
-                        let m = a.pop().unwrap();
-                        let name = a.pop().unwrap();
-                        Transform::Key(
-                            Box::new(name),
-                            Box::new(m),
-                            Some(Box::new(u)),
-                            in_scope_namespaces(state.cur.clone()),
-                        )
-                    } else {
-                        // Wrong # arguments
-                        Transform::Error(
-                            ErrorKind::ParseError,
-                            String::from("wrong number of arguments"),
-                        )
-                    }
+            }
+            "distinct-values" => match a.len() {
+                1 => Transform::DistinctValues(Box::new(a.pop().unwrap()), None),
+                2 => {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::DistinctValues(Box::new(c), Some(Box::new(b)))
                 }
-                "system-property" => {
-                    if a.len() == 1 {
-                        let p = a.pop().unwrap();
-                        Transform::SystemProperty(
-                            Box::new(p),
-                            in_scope_namespaces(state.cur.clone()),
-                        )
-                    } else {
-                        // Wrong # arguments
-                        Transform::Error(
-                            ErrorKind::ParseError,
-                            String::from("wrong number of arguments"),
-                        )
-                    }
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            "index-of" => match a.len() {
+                2 => {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::IndexOf(Box::new(c), Box::new(b), None)
                 }
-                "available-system-properties" => {
-                    if a.is_empty() {
-                        Transform::AvailableSystemProperties
-                    } else {
-                        // Wrong # arguments
-                        Transform::Error(
-                            ErrorKind::ParseError,
-                            String::from("wrong number of arguments"),
-                        )
-                    }
+                3 => {
+                    let d = a.pop().unwrap();
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::IndexOf(Box::new(c), Box::new(b), Some(Box::new(d)))
                 }
-                "document" => match a.len() {
-                    0 => Transform::Document(Box::new(Transform::Empty), None),
-                    1 => {
-                        let u = a.pop().unwrap();
-                        Transform::Document(Box::new(u), None)
-                    }
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            "insert-before" => {
+                if a.len() == 3 {
+                    let i = a.pop().unwrap();
+                    let p = a.pop().unwrap();
+                    let s = a.pop().unwrap();
+                    Transform::InsertBefore(Box::new(s), Box::new(p), Box::new(i))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "remove" => {
+                if a.len() == 2 {
+                    let p = a.pop().unwrap();
+                    let s = a.pop().unwrap();
+                    Transform::Remove(Box::new(s), Box::new(p))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "reverse" => {
+                if a.len() == 1 {
+                    Transform::Reverse(Box::new(a.pop().unwrap()))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "subsequence" => match a.len() {
+                2 => {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::Subsequence(Box::new(c), Box::new(b), None)
+                }
+                3 => {
+                    let d = a.pop().unwrap();
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::Subsequence(Box::new(c), Box::new(b), Some(Box::new(d)))
+                }
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            "unordered" => {
+                if a.len() == 1 {
+                    // The order of the result is implementation-dependent, so the sequence is unchanged
+                    a.pop().unwrap()
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "head" => {
+                if a.len() == 1 {
+                    Transform::Head(Box::new(a.pop().unwrap()))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "tail" => {
+                if a.len() == 1 {
+                    Transform::Tail(Box::new(a.pop().unwrap()))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "zero-or-one" => {
+                if a.len() == 1 {
+                    Transform::ZeroOrOne(Box::new(a.pop().unwrap()))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "one-or-more" => {
+                if a.len() == 1 {
+                    Transform::OneOrMore(Box::new(a.pop().unwrap()))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "exactly-one" => {
+                if a.len() == 1 {
+                    Transform::ExactlyOne(Box::new(a.pop().unwrap()))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "deep-equal" => match a.len() {
+                2 => {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::DeepEqual(Box::new(c), Box::new(b), None)
+                }
+                3 => {
+                    let d = a.pop().unwrap();
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::DeepEqual(Box::new(c), Box::new(b), Some(Box::new(d)))
+                }
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            "innermost" => {
+                if a.len() == 1 {
+                    Transform::Innermost(Box::new(a.pop().unwrap()))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "outermost" => {
+                if a.len() == 1 {
+                    Transform::Outermost(Box::new(a.pop().unwrap()))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "root" => match a.len() {
+                0 => Transform::Root,
+                1 => Transform::FnRoot(Box::new(a.pop().unwrap())),
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            "path" => match a.len() {
+                0 => Transform::Path(None),
+                1 => Transform::Path(Some(Box::new(a.pop().unwrap()))),
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            "has-children" => match a.len() {
+                0 => Transform::HasChildren(None),
+                1 => Transform::HasChildren(Some(Box::new(a.pop().unwrap()))),
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            "namespace-uri" => match a.len() {
+                0 => Transform::NamespaceUri(None),
+                1 => Transform::NamespaceUri(Some(Box::new(a.pop().unwrap()))),
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            "lang" => match a.len() {
+                1 => Transform::Lang(Box::new(a.pop().unwrap()), None),
+                2 => {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::Lang(Box::new(c), Some(Box::new(b)))
+                }
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            "id" => match a.len() {
+                1 => Transform::Id(Box::new(a.pop().unwrap()), None),
+                2 => {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::Id(Box::new(c), Some(Box::new(b)))
+                }
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            "idref" => match a.len() {
+                1 => Transform::Idref(Box::new(a.pop().unwrap()), None),
+                2 => {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::Idref(Box::new(c), Some(Box::new(b)))
+                }
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            "generate-id" => {
+                if a.is_empty() {
+                    Transform::GenerateId(None)
+                } else if a.len() == 1 {
+                    Transform::GenerateId(Some(Box::new(a.pop().unwrap())))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "boolean" => {
+                if a.len() == 1 {
+                    Transform::Boolean(Box::new(a.pop().unwrap()))
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "not" => {
+                if a.len() == 1 {
+                    Transform::Not(Box::new(a.pop().unwrap()))
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "true" => {
+                if a.is_empty() {
+                    Transform::True
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "false" => {
+                if a.is_empty() {
+                    Transform::False
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "number" => {
+                if a.len() == 1 {
+                    Transform::Number(Box::new(a.pop().unwrap()))
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "sum" => {
+                if a.len() == 1 {
+                    Transform::Sum(Box::new(a.pop().unwrap()))
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "avg" => {
+                if a.len() == 0 {
+                    Transform::Empty
+                } else if a.len() == 1 {
+                    Transform::Avg(Box::new(a.pop().unwrap()))
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "min" => {
+                if a.len() == 0 {
+                    Transform::Empty
+                } else if a.len() == 1 {
+                    Transform::Min(Box::new(a.pop().unwrap()))
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "max" => {
+                if a.len() == 0 {
+                    Transform::Empty
+                } else if a.len() == 1 {
+                    Transform::Max(Box::new(a.pop().unwrap()))
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "floor" => {
+                if a.len() == 1 {
+                    Transform::Floor(Box::new(a.pop().unwrap()))
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "ceiling" => {
+                if a.len() == 1 {
+                    Transform::Ceiling(Box::new(a.pop().unwrap()))
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "round" => {
+                if a.len() == 1 {
+                    let b = a.pop().unwrap();
+                    Transform::Round(Box::new(b), None)
+                } else if a.len() == 2 {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::Round(Box::new(c), Some(Box::new(b)))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "current-date-time" | "current-dateTime" => {
+                if a.is_empty() {
+                    Transform::CurrentDateTime
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "current-date" => {
+                if a.is_empty() {
+                    Transform::CurrentDate
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "current-time" => {
+                if a.is_empty() {
+                    Transform::CurrentTime
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "format-date-time" | "format-dateTime" => {
+                if a.len() == 2 {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::FormatDateTime(Box::new(c), Box::new(b), None, None, None)
+                } else if a.len() == 5 {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    let d = a.pop().unwrap();
+                    let e = a.pop().unwrap();
+                    let f = a.pop().unwrap();
+                    Transform::FormatDateTime(
+                        Box::new(f),
+                        Box::new(e),
+                        Some(Box::new(d)),
+                        Some(Box::new(c)),
+                        Some(Box::new(b)),
+                    )
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "format-date" => {
+                if a.len() == 2 {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::FormatDate(Box::new(c), Box::new(b), None, None, None)
+                } else if a.len() == 5 {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    let d = a.pop().unwrap();
+                    let e = a.pop().unwrap();
+                    let f = a.pop().unwrap();
+                    Transform::FormatDate(
+                        Box::new(f),
+                        Box::new(e),
+                        Some(Box::new(d)),
+                        Some(Box::new(c)),
+                        Some(Box::new(b)),
+                    )
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "format-time" => {
+                if a.len() == 2 {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::FormatTime(Box::new(c), Box::new(b), None, None, None)
+                } else if a.len() == 5 {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    let d = a.pop().unwrap();
+                    let e = a.pop().unwrap();
+                    let f = a.pop().unwrap();
+                    Transform::FormatTime(
+                        Box::new(f),
+                        Box::new(e),
+                        Some(Box::new(d)),
+                        Some(Box::new(c)),
+                        Some(Box::new(b)),
+                    )
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "format-number" => {
+                if a.is_empty() || a.len() == 1 {
+                    // Too few arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too few arguments"))
+                } else if a.len() == 2 {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::FormatNumber(
+                        Box::new(c),
+                        Box::new(b),
+                        None,
+                        in_scope_namespaces(cur.clone()),
+                    )
+                } else if a.len() == 3 {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    let d = a.pop().unwrap();
+                    Transform::FormatNumber(
+                        Box::new(d),
+                        Box::new(c),
+                        Some(Box::new(b)),
+                        in_scope_namespaces(cur.clone()),
+                    )
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "error" => {
+                if a.len() > 3 {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                } else {
+                    let mut args = a.into_iter().map(Box::new);
+                    Transform::RaiseError(args.next(), args.next(), args.next())
+                }
+            }
+            "QName" => {
+                if a.len() == 2 {
+                    let b = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    Transform::QName(Box::new(c), Box::new(b))
+                } else {
+                    // Wrong number of arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("incorrect arguments"))
+                }
+            }
+            "current-group" => {
+                if a.is_empty() {
+                    Transform::CurrentGroup
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "current-grouping-key" => {
+                if a.is_empty() {
+                    Transform::CurrentGroupingKey
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "current-merge-group" => match a.len() {
+                0 => Transform::CurrentMergeGroup(None),
+                1 => Transform::CurrentMergeGroup(Some(Box::new(a.pop().unwrap()))),
+                _ => Transform::Error(ErrorKind::ParseError, String::from("too many arguments")),
+            },
+            "current-merge-key" => {
+                if a.is_empty() {
+                    Transform::CurrentMergeKey
+                } else {
+                    // Too many arguments
+                    Transform::Error(ErrorKind::ParseError, String::from("too many arguments"))
+                }
+            }
+            "regex-group" => {
+                if a.len() == 1 {
+                    Transform::RegexGroup(Box::new(a.pop().unwrap()))
+                } else {
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "key" => {
+                if a.len() == 2 {
+                    let m = a.pop().unwrap();
+                    let name = a.pop().unwrap();
+                    Transform::Key(
+                        Box::new(name),
+                        Box::new(m),
+                        None,
+                        in_scope_namespaces(cur.clone()),
+                    )
+                } else if a.len() == 3 {
+                    let u = a.pop().unwrap();
+                    let m = a.pop().unwrap();
+                    let name = a.pop().unwrap();
+                    Transform::Key(
+                        Box::new(name),
+                        Box::new(m),
+                        Some(Box::new(u)),
+                        in_scope_namespaces(cur.clone()),
+                    )
+                } else {
+                    // Wrong # arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "accumulator-before" => {
+                if a.len() == 1 {
+                    let name = a.pop().unwrap();
+                    Transform::AccumulatorBefore(Box::new(name), in_scope_namespaces(cur.clone()))
+                } else {
+                    // Wrong # arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "accumulator-after" => {
+                if a.len() == 1 {
+                    let name = a.pop().unwrap();
+                    Transform::AccumulatorAfter(Box::new(name), in_scope_namespaces(cur.clone()))
+                } else {
+                    // Wrong # arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "system-property" => {
+                if a.len() == 1 {
+                    let p = a.pop().unwrap();
+                    Transform::SystemProperty(Box::new(p), in_scope_namespaces(cur.clone()))
+                } else {
+                    // Wrong # arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "available-system-properties" => {
+                if a.is_empty() {
+                    Transform::AvailableSystemProperties
+                } else {
+                    // Wrong # arguments
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "document" => match a.len() {
+                0 => Transform::Document(Box::new(Transform::Empty), None),
+                1 => {
+                    let u = a.pop().unwrap();
+                    Transform::Document(Box::new(u), None)
+                }
+                2 => {
+                    let b = a.pop().unwrap();
+                    let u = a.pop().unwrap();
+                    Transform::Document(Box::new(u), Some(Box::new(b)))
+                }
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            "for-each" => {
+                if a.len() == 2 {
+                    let f = a.pop().unwrap();
+                    let s = a.pop().unwrap();
+                    Transform::FnForEach(Box::new(s), Box::new(f))
+                } else {
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "filter" => {
+                if a.len() == 2 {
+                    let f = a.pop().unwrap();
+                    let s = a.pop().unwrap();
+                    Transform::FnFilter(Box::new(s), Box::new(f))
+                } else {
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "fold-left" => {
+                if a.len() == 3 {
+                    let f = a.pop().unwrap();
+                    let z = a.pop().unwrap();
+                    let s = a.pop().unwrap();
+                    Transform::FoldLeft(Box::new(s), Box::new(z), Box::new(f))
+                } else {
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "fold-right" => {
+                if a.len() == 3 {
+                    let f = a.pop().unwrap();
+                    let z = a.pop().unwrap();
+                    let s = a.pop().unwrap();
+                    Transform::FoldRight(Box::new(s), Box::new(z), Box::new(f))
+                } else {
+                    Transform::Error(
+                        ErrorKind::ParseError,
+                        String::from("wrong number of arguments"),
+                    )
+                }
+            }
+            "adjust-dateTime-to-timezone"
+            | "adjust-date-to-timezone"
+            | "adjust-time-to-timezone" => {
+                let t = match localpart.to_string().as_str() {
+                    "adjust-time-to-timezone" => atomic_type("time"),
+                    "adjust-date-to-timezone" => atomic_type("date"),
+                    _ => atomic_type("dateTime"),
+                }
+                .unwrap();
+                match a.len() {
+                    1 => Transform::AdjustToTimezone(t, Box::new(a.pop().unwrap()), None),
                     2 => {
-                        let b = a.pop().unwrap();
-                        let u = a.pop().unwrap();
-                        Transform::Document(Box::new(u), Some(Box::new(b)))
+                        let z = a.pop().unwrap();
+                        let v = a.pop().unwrap();
+                        Transform::AdjustToTimezone(t, Box::new(v), Some(Box::new(z)))
                     }
                     _ => Transform::Error(
                         ErrorKind::ParseError,
                         String::from("wrong number of arguments"),
                     ),
-                },
+                }
+            }
+            n if component_function(n).is_some() => match (component_function(n), a.len()) {
+                (Some((c, t)), 1) => Transform::DateTimeComponent(c, t, Box::new(a.pop().unwrap())),
                 _ => Transform::Error(
                     ErrorKind::ParseError,
-                    format!("undefined function \"{}\"", qn),
-                ), // TODO: user-defined functions
+                    String::from("wrong number of arguments"),
+                ),
             },
-            NodeTest::Name(NameTest {
-                name: Some(WildcardOrName::Name(localpart)),
-                ns: Some(WildcardOrName::Name(nsuri)),
-                prefix: p,
-            }) => Transform::Invoke(
-                Rc::new(QualifiedName::new_from_values(Some(nsuri), p, localpart)),
-                ActualParameters::Positional(a),
-                in_scope_namespaces(state.cur.clone()),
-            ),
-            NodeTest::Name(NameTest {
-                name: Some(WildcardOrName::Name(localpart)),
-                ns: None,
-                prefix: p,
-            }) => Transform::Invoke(
-                Rc::new(QualifiedName::new_from_values(None, p, localpart)),
-                ActualParameters::Positional(a),
-                in_scope_namespaces(state.cur.clone()),
+            "sort" => match a.len() {
+                1 => Transform::FnSort(Box::new(a.pop().unwrap()), None, None),
+                2 => {
+                    let c = a.pop().unwrap();
+                    let s = a.pop().unwrap();
+                    Transform::FnSort(Box::new(s), Some(Box::new(c)), None)
+                }
+                3 => {
+                    let k = a.pop().unwrap();
+                    let c = a.pop().unwrap();
+                    let s = a.pop().unwrap();
+                    Transform::FnSort(Box::new(s), Some(Box::new(c)), Some(Box::new(k)))
+                }
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    String::from("wrong number of arguments"),
+                ),
+            },
+            _ => Transform::Error(
+                ErrorKind::ParseError,
+                format!("undefined function \"{}\"", qn),
+            ), // TODO: user-defined functions
+        },
+        NodeTest::Name(NameTest {
+            name: Some(WildcardOrName::Name(localpart)),
+            ns,
+            prefix,
+        }) if !matches!(ns, Some(WildcardOrName::Wildcard)) => {
+            // Built-in functions are recognised by their namespace URI, not by the prefix used
+            match function_namespace(&ns, &prefix, &cur).as_deref() {
+                Some(XPATH_FUNCTIONS_NS) => function_transform(
+                    NodeTest::Name(NameTest {
+                        name: Some(WildcardOrName::Name(localpart)),
+                        ns: None,
+                        prefix: None,
+                    }),
+                    a,
+                    cur,
+                ),
+                Some(XSD_NAMESPACE) => constructor_function(localpart.to_string().as_str(), a),
+                Some(MAP_FUNCTIONS_NS) => map_function(localpart.to_string().as_str(), a),
+                Some(ARRAY_FUNCTIONS_NS) => array_function(localpart.to_string().as_str(), a),
+                _ => Transform::Invoke(
+                    Rc::new(QualifiedName::new_from_values(
+                        match ns {
+                            Some(WildcardOrName::Name(nsuri)) => Some(nsuri),
+                            _ => None,
+                        },
+                        prefix,
+                        localpart,
+                    )),
+                    ActualParameters::Positional(a),
+                    in_scope_namespaces(cur.clone()),
+                ),
+            }
+        }
+        _ => Transform::Error(ErrorKind::Unknown, format!("unknown function \"{}\"", qn)),
+    }
+}
+
+const XPATH_FUNCTIONS_NS: &str = "http://www.w3.org/2005/xpath-functions";
+const MAP_FUNCTIONS_NS: &str = "http://www.w3.org/2005/xpath-functions/map";
+const ARRAY_FUNCTIONS_NS: &str = "http://www.w3.org/2005/xpath-functions/array";
+
+// The namespace URI of a function name. A prefix is resolved using the in-scope namespaces,
+// falling back to the prefixes that are statically known in XPath.
+fn function_namespace<N: Node>(
+    ns: &Option<WildcardOrName>,
+    prefix: &Option<Rc<Value>>,
+    cur: &Option<N>,
+) -> Option<String> {
+    match (ns, prefix) {
+        (Some(WildcardOrName::Name(nsuri)), _) => Some(nsuri.to_string()),
+        (_, Some(p)) => in_scope_namespaces(cur.clone())
+            .get(&Some(p.clone()))
+            .map(|nsuri| nsuri.to_string())
+            .or_else(|| {
+                match p.to_string().as_str() {
+                    "fn" => Some(XPATH_FUNCTIONS_NS),
+                    "xs" => Some(XSD_NAMESPACE),
+                    "map" => Some(MAP_FUNCTIONS_NS),
+                    "array" => Some(ARRAY_FUNCTIONS_NS),
+                    _ => None,
+                }
+                .map(String::from)
+            }),
+        _ => None,
+    }
+}
+
+// A constructor function for a built-in atomic type, such as xs:NCName('x').
+// This is equivalent to casting the argument to the type, where the argument may be the empty sequence.
+fn constructor_function<N: Node>(name: &str, mut a: Vec<Transform<N>>) -> Transform<N> {
+    match (atomic_type(name), a.len()) {
+        (Some(Value::AnyAtomicType | Value::Numeric | Value::NOTATION), _) | (None, _) => {
+            Transform::Error(
+                ErrorKind::ParseError,
+                format!("no constructor function for type \"xs:{}\"", name),
+            )
+        }
+        (Some(t), 1) => Transform::Cast(Box::new(a.pop().unwrap()), t, true),
+        _ => Transform::Error(
+            ErrorKind::ParseError,
+            String::from("wrong number of arguments"),
+        ),
+    }
+}
+
+// The component extraction functions, such as year-from-date.
+// Gives the component and the type of the argument.
+fn component_function(name: &str) -> Option<(Component, Value)> {
+    let (c, t) = match name {
+        "year-from-dateTime" => (Component::Years, "dateTime"),
+        "month-from-dateTime" => (Component::Months, "dateTime"),
+        "day-from-dateTime" => (Component::Days, "dateTime"),
+        "hours-from-dateTime" => (Component::Hours, "dateTime"),
+        "minutes-from-dateTime" => (Component::Minutes, "dateTime"),
+        "seconds-from-dateTime" => (Component::Seconds, "dateTime"),
+        "timezone-from-dateTime" => (Component::Timezone, "dateTime"),
+        "year-from-date" => (Component::Years, "date"),
+        "month-from-date" => (Component::Months, "date"),
+        "day-from-date" => (Component::Days, "date"),
+        "timezone-from-date" => (Component::Timezone, "date"),
+        "hours-from-time" => (Component::Hours, "time"),
+        "minutes-from-time" => (Component::Minutes, "time"),
+        "seconds-from-time" => (Component::Seconds, "time"),
+        "timezone-from-time" => (Component::Timezone, "time"),
+        "years-from-duration" => (Component::Years, "duration"),
+        "months-from-duration" => (Component::Months, "duration"),
+        "days-from-duration" => (Component::Days, "duration"),
+        "hours-from-duration" => (Component::Hours, "duration"),
+        "minutes-from-duration" => (Component::Minutes, "duration"),
+        "seconds-from-duration" => (Component::Seconds, "duration"),
+        _ => return None,
+    };
+    atomic_type(t).map(|v| (c, v))
+}
+
+// NamedFunctionRef ::= EQName "#" IntegerLiteral
+pub(crate) fn named_function_ref<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
+    Box::new(map_with_state(
+        tuple3(qualname_test(), tag("#"), digit1()),
+        |(qn, _, n), state| match n.parse::<usize>() {
+            Ok(arity) => named_function(qn, arity, state.cur.clone()),
+            Err(_) => Transform::Error(ErrorKind::ParseError, String::from("invalid arity")),
+        },
+    ))
+}
+
+/// Create a function item for a named function.
+/// The body of the function is a call to the named function, with the parameters as its arguments.
+fn named_function<N: Node>(qn: NodeTest, arity: usize, cur: Option<N>) -> Transform<N> {
+    let name = match &qn {
+        NodeTest::Name(NameTest {
+            name: Some(WildcardOrName::Name(localpart)),
+            ns,
+            prefix,
+        }) => QualifiedName::new_from_values(
+            function_namespace(ns, prefix, &cur).map(|nsuri| Rc::new(Value::from(nsuri))),
+            prefix.clone(),
+            localpart.clone(),
+        ),
+        _ => return Transform::Error(ErrorKind::Unknown, format!("unknown function \"{}\"", qn)),
+    };
+    // Parameter names are not valid XPath variable names, so they cannot clash with other variables
+    let params: Vec<(QualifiedName, Option<SequenceType>)> = (1..=arity)
+        .map(|i| (QualifiedName::new(None, None, format!("#{}", i)), None))
+        .collect();
+    let ns = in_scope_namespaces(cur.clone());
+    let args = params
+        .iter()
+        .map(|(p, _)| Transform::VariableReference(p.to_string(), ns.clone()))
+        .collect();
+    match function_transform(qn, args, cur) {
+        Transform::Error(k, m) => Transform::Error(k, m),
+        body => Transform::InlineFunction(Some(Rc::new(name)), params, Box::new(body)),
+    }
+}
+
+// InlineFunctionExpr ::= Annotation* "function" "(" ParamList? ")" ("as" SequenceType)? FunctionBody
+// TODO: annotations.
+pub(crate) fn inline_function_expr<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
+    Box::new(map(
+        tuple10(
+            tag("function"),
+            xpwhitespace(),
+            tag("("),
+            xpwhitespace(),
+            separated_list0(
+                map(tuple3(xpwhitespace(), tag(","), xpwhitespace()), |_| ()),
+                map_with_state(
+                    tuple3(
+                        tag("$"),
+                        qualname_test(),
+                        opt(pair(xpwhitespace(), type_declaration::<N>())),
+                    ),
+                    |(_, qn, t), state| {
+                        (
+                            get_nt_qualname(&qn, &in_scope_namespaces(state.cur.clone())),
+                            t.map(|(_, t)| t),
+                        )
+                    },
+                ),
             ),
-            _ => Transform::Error(ErrorKind::Unknown, format!("unknown function \"{}\"", qn)),
+            xpwhitespace(),
+            tag(")"),
+            pair(xpwhitespace(), opt(type_declaration::<N>())),
+            function_body::<N>(),
+            xpwhitespace(),
+        ),
+        |(_, _, _, _, p, _, _, (_, r), b, _)| {
+            // The result is converted to the declared type, as for a function call
+            let b = match r {
+                Some(t) => Transform::Convert(Box::new(b), t, String::from("XPTY0004"), false),
+                None => b,
+            };
+            Transform::InlineFunction(None, p, Box::new(b))
         },
     ))
 }
 
+// TypeDeclaration ::= "as" SequenceType
+fn type_declaration<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, SequenceType), ParseError> + 'a> {
+    Box::new(map(
+        tuple4(
+            tag("as"),
+            xpwhitespace(),
+            sequencetype_expr::<N>(),
+            xpwhitespace(),
+        ),
+        |(_, _, t, _)| t,
+    ))
+}
+
+// FunctionBody ::= EnclosedExpr
+// EnclosedExpr ::= "{" Expr? "}"
+fn function_body<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
+    Box::new(map(
+        tuple3(
+            tag("{"),
+            alt2(
+                map(
+                    tuple3(xpwhitespace(), expr_wrapper::<N>(true), xpwhitespace()),
+                    |(_, e, _)| e,
+                ),
+                map(xpwhitespace(), |_| Transform::Empty),
+            ),
+            tag("}"),
+        ),
+        |(_, b, _)| b,
+    ))
+}
+
 // ArgumentList ::= '(' (Argument (',' Argument)*)? ')'
-// TODO: finish this parser with actual arguments
-fn argumentlist<'a, N: Node + 'a>(
-) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Vec<Transform<N>>), ParseError> + 'a> {
+// An argument placeholder is None.
+pub(crate) fn argumentlist<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Vec<Option<Transform<N>>>), ParseError> + 'a>
+{
     Box::new(map(
         tuple3(
             tag("("),
@@ -564,8 +1475,11 @@ fn argumentlist<'a, N: Node + 'a>(
 }
 
 // Argument ::= ExprSingle | ArgumentPlaceHolder
-// TODO: ArgumentPlaceHolder
+// ArgumentPlaceHolder ::= "?"
 fn argument<'a, N: Node + 'a>(
-) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
-    Box::new(expr_single_wrapper::<N>(true))
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Option<Transform<N>>), ParseError> + 'a> {
+    Box::new(alt2(
+        map(expr_single_wrapper::<N>(true), Some),
+        map(tag("?"), |_| None),
+    ))
 }
@@ -0,0 +1,322 @@
+//! Functions for maps and arrays.
+
+use rust_decimal::Decimal;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use crate::item::{Item, Node};
+use crate::parser::combinators::alt::{alt2, alt4};
+use crate::parser::combinators::list::separated_list0;
+use crate::parser::combinators::map::map;
+use crate::parser::combinators::pair::pair;
+use crate::parser::combinators::support::digit1;
+use crate::parser::combinators::tag::tag;
+use crate::parser::combinators::tuple::{tuple3, tuple5, tuple6};
+use crate::parser::combinators::whitespace::xpwhitespace;
+use crate::parser::xml::qname::ncname;
+use crate::parser::xpath::expressions::parenthesized_expr;
+use crate::parser::xpath::{expr_single_wrapper, expr_wrapper};
+use crate::parser::{ParseError, ParseInput};
+use crate::transform::Transform;
+use crate::value::Value;
+use crate::xdmerror::ErrorKind;
+
+// MapConstructor ::= "map" "{" (MapConstructorEntry ("," MapConstructorEntry)*)? "}"
+pub(crate) fn map_constructor<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
+    Box::new(map(
+        tuple6(
+            tag("map"),
+            xpwhitespace(),
+            tag("{"),
+            xpwhitespace(),
+            separated_list0(
+                map(tuple3(xpwhitespace(), tag(","), xpwhitespace()), |_| ()),
+                map_constructor_entry::<N>(),
+            ),
+            pair(xpwhitespace(), tag("}")),
+        ),
+        |(_, _, _, _, e, _)| Transform::MapConstructor(e),
+    ))
+}
+
+// MapConstructorEntry ::= MapKeyExpr ":" MapValueExpr
+fn map_constructor_entry<'a, N: Node + 'a>() -> Box<
+    dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, (Transform<N>, Transform<N>)), ParseError> + 'a,
+> {
+    Box::new(map(
+        tuple3(
+            expr_single_wrapper::<N>(true),
+            tuple3(xpwhitespace(), tag(":"), xpwhitespace()),
+            expr_single_wrapper::<N>(true),
+        ),
+        |(k, _, v)| (k, v),
+    ))
+}
+
+// ArrayConstructor ::= SquareArrayConstructor | CurlyArrayConstructor
+pub(crate) fn array_constructor<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
+    Box::new(alt2(
+        square_array_constructor::<N>(),
+        curly_array_constructor::<N>(),
+    ))
+}
+
+// SquareArrayConstructor ::= "[" (ExprSingle ("," ExprSingle)*)? "]"
+fn square_array_constructor<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
+    Box::new(map(
+        tuple5(
+            tag("["),
+            xpwhitespace(),
+            separated_list0(
+                map(tuple3(xpwhitespace(), tag(","), xpwhitespace()), |_| ()),
+                expr_single_wrapper::<N>(true),
+            ),
+            xpwhitespace(),
+            tag("]"),
+        ),
+        |(_, _, m, _, _)| Transform::SquareArrayConstructor(m),
+    ))
+}
+
+// CurlyArrayConstructor ::= "array" EnclosedExpr
+// EnclosedExpr ::= "{" Expr? "}"
+fn curly_array_constructor<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
+    Box::new(map(
+        tuple5(
+            tag("array"),
+            xpwhitespace(),
+            tag("{"),
+            alt2(
+                map(
+                    tuple3(xpwhitespace(), expr_wrapper::<N>(true), xpwhitespace()),
+                    |(_, e, _)| e,
+                ),
+                map(xpwhitespace(), |_| Transform::Empty),
+            ),
+            tag("}"),
+        ),
+        |(_, _, _, e, _)| Transform::CurlyArrayConstructor(Box::new(e)),
+    ))
+}
+
+// UnaryLookup ::= "?" KeySpecifier
+pub(crate) fn unary_lookup<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
+    Box::new(map(lookup::<N>(), |k| {
+        Transform::Lookup(Box::new(Transform::ContextItem), k.map(Box::new))
+    }))
+}
+
+// Lookup ::= "?" KeySpecifier
+// The result is the key specifier; None is the wildcard.
+pub(crate) fn lookup<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Option<Transform<N>>), ParseError> + 'a> {
+    Box::new(map(
+        tuple3(xpwhitespace(), tag("?"), key_specifier::<N>()),
+        |(_, _, k)| k,
+    ))
+}
+
+// KeySpecifier ::= NCName | IntegerLiteral | ParenthesizedExpr | "*"
+fn key_specifier<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Option<Transform<N>>), ParseError> + 'a> {
+    Box::new(alt4(
+        map(ncname(), |n| {
+            Some(Transform::Literal(Item::Value(Rc::new(Value::from(n)))))
+        }),
+        // An integer that is too large for an xs:integer is kept as an xs:decimal.
+        // It may still be a map key, but it is never the position of an array member.
+        map(digit1(), |d: String| {
+            Some(match (d.parse::<i64>(), Decimal::from_str(&d)) {
+                (Ok(i), _) => Transform::Literal(Item::Value(Rc::new(Value::Integer(i)))),
+                (_, Ok(dec)) => Transform::Literal(Item::Value(Rc::new(Value::Decimal(dec)))),
+                _ => Transform::Error(
+                    ErrorKind::ParseError,
+                    format!("key specifier {} is too large", d),
+                ),
+            })
+        }),
+        map(parenthesized_expr::<N>(), Some),
+        map(tag("*"), |_| None),
+    ))
+}
+
+fn wrong_arguments<N: Node>() -> Transform<N> {
+    Transform::Error(
+        ErrorKind::ParseError,
+        String::from("wrong number of arguments"),
+    )
+}
+
+/// Map a call to a function in the map namespace to its [Transform].
+pub(crate) fn map_function<N: Node>(name: &str, mut a: Vec<Transform<N>>) -> Transform<N> {
+    match (name, a.len()) {
+        ("merge", 1) => Transform::MapMerge(Box::new(a.pop().unwrap()), None),
+        ("merge", 2) => {
+            let o = a.pop().unwrap();
+            let m = a.pop().unwrap();
+            Transform::MapMerge(Box::new(m), Some(Box::new(o)))
+        }
+        ("size", 1) => Transform::MapSize(Box::new(a.pop().unwrap())),
+        ("keys", 1) => Transform::MapKeys(Box::new(a.pop().unwrap())),
+        ("contains", 2) => {
+            let k = a.pop().unwrap();
+            let m = a.pop().unwrap();
+            Transform::MapContains(Box::new(m), Box::new(k))
+        }
+        ("get", 2) => {
+            let k = a.pop().unwrap();
+            let m = a.pop().unwrap();
+            Transform::MapGet(Box::new(m), Box::new(k))
+        }
+        ("find", 2) => {
+            let k = a.pop().unwrap();
+            let i = a.pop().unwrap();
+            Transform::MapFind(Box::new(i), Box::new(k))
+        }
+        ("put", 3) => {
+            let v = a.pop().unwrap();
+            let k = a.pop().unwrap();
+            let m = a.pop().unwrap();
+            Transform::MapPut(Box::new(m), Box::new(k), Box::new(v))
+        }
+        ("entry", 2) => {
+            let v = a.pop().unwrap();
+            let k = a.pop().unwrap();
+            Transform::MapEntry(Box::new(k), Box::new(v))
+        }
+        ("remove", 2) => {
+            let k = a.pop().unwrap();
+            let m = a.pop().unwrap();
+            Transform::MapRemove(Box::new(m), Box::new(k))
+        }
+        ("for-each", 2) => {
+            let f = a.pop().unwrap();
+            let m = a.pop().unwrap();
+            Transform::MapForEach(Box::new(m), Box::new(f))
+        }
+        ("merge", _)
+        | ("size", _)
+        | ("keys", _)
+        | ("contains", _)
+        | ("get", _)
+        | ("find", _)
+        | ("put", _)
+        | ("entry", _)
+        | ("remove", _)
+        | ("for-each", _) => wrong_arguments(),
+        _ => Transform::Error(
+            ErrorKind::ParseError,
+            format!("undefined function \"map:{}\"", name),
+        ),
+    }
+}
+
+/// Map a call to a function in the array namespace to its [Transform].
+pub(crate) fn array_function<N: Node>(name: &str, mut a: Vec<Transform<N>>) -> Transform<N> {
+    match (name, a.len()) {
+        ("size", 1) => Transform::ArraySize(Box::new(a.pop().unwrap())),
+        ("get", 2) => {
+            let p = a.pop().unwrap();
+            let r = a.pop().unwrap();
+            Transform::ArrayGet(Box::new(r), Box::new(p))
+        }
+        ("put", 3) => {
+            let m = a.pop().unwrap();
+            let p = a.pop().unwrap();
+            let r = a.pop().unwrap();
+            Transform::ArrayPut(Box::new(r), Box::new(p), Box::new(m))
+        }
+        ("append", 2) => {
+            let m = a.pop().unwrap();
+            let r = a.pop().unwrap();
+            Transform::ArrayAppend(Box::new(r), Box::new(m))
+        }
+        ("subarray", 2) => {
+            let s = a.pop().unwrap();
+            let r = a.pop().unwrap();
+            Transform::ArraySubarray(Box::new(r), Box::new(s), None)
+        }
+        ("subarray", 3) => {
+            let l = a.pop().unwrap();
+            let s = a.pop().unwrap();
+            let r = a.pop().unwrap();
+            Transform::ArraySubarray(Box::new(r), Box::new(s), Some(Box::new(l)))
+        }
+        ("remove", 2) => {
+            let p = a.pop().unwrap();
+            let r = a.pop().unwrap();
+            Transform::ArrayRemove(Box::new(r), Box::new(p))
+        }
+        ("insert-before", 3) => {
+            let m = a.pop().unwrap();
+            let p = a.pop().unwrap();
+            let r = a.pop().unwrap();
+            Transform::ArrayInsertBefore(Box::new(r), Box::new(p), Box::new(m))
+        }
+        ("head", 1) => Transform::ArrayHead(Box::new(a.pop().unwrap())),
+        ("tail", 1) => Transform::ArrayTail(Box::new(a.pop().unwrap())),
+        ("reverse", 1) => Transform::ArrayReverse(Box::new(a.pop().unwrap())),
+        ("join", 1) => Transform::ArrayJoin(Box::new(a.pop().unwrap())),
+        ("flatten", 1) => Transform::ArrayFlatten(Box::new(a.pop().unwrap())),
+        ("for-each", 2) => {
+            let f = a.pop().unwrap();
+            let r = a.pop().unwrap();
+            Transform::ArrayForEach(Box::new(r), Box::new(f))
+        }
+        ("filter", 2) => {
+            let f = a.pop().unwrap();
+            let r = a.pop().unwrap();
+            Transform::ArrayFilter(Box::new(r), Box::new(f))
+        }
+        ("fold-left", 3) => {
+            let f = a.pop().unwrap();
+            let z = a.pop().unwrap();
+            let r = a.pop().unwrap();
+            Transform::ArrayFoldLeft(Box::new(r), Box::new(z), Box::new(f))
+        }
+        ("fold-right", 3) => {
+            let f = a.pop().unwrap();
+            let z = a.pop().unwrap();
+            let r = a.pop().unwrap();
+            Transform::ArrayFoldRight(Box::new(r), Box::new(z), Box::new(f))
+        }
+        ("sort", 1) => Transform::ArraySort(Box::new(a.pop().unwrap()), None, None),
+        ("sort", 2) => {
+            let c = a.pop().unwrap();
+            let r = a.pop().unwrap();
+            Transform::ArraySort(Box::new(r), Some(Box::new(c)), None)
+        }
+        ("sort", 3) => {
+            let k = a.pop().unwrap();
+            let c = a.pop().unwrap();
+            let r = a.pop().unwrap();
+            Transform::ArraySort(Box::new(r), Some(Box::new(c)), Some(Box::new(k)))
+        }
+        ("size", _)
+        | ("get", _)
+        | ("put", _)
+        | ("append", _)
+        | ("subarray", _)
+        | ("remove", _)
+        | ("insert-before", _)
+        | ("head", _)
+        | ("tail", _)
+        | ("reverse", _)
+        | ("join", _)
+        | ("flatten", _)
+        | ("for-each", _)
+        | ("filter", _)
+        | ("fold-left", _)
+        | ("fold-right", _)
+        | ("sort", _) => wrong_arguments(),
+        _ => Transform::Error(
+            ErrorKind::ParseError,
+            format!("undefined function \"array:{}\"", name),
+        ),
+    }
+}
@@ -51,6 +51,7 @@ mod flwr;
 mod functions;
 pub(crate) mod literals;
 mod logic;
+mod maps;
 mod nodes;
 pub(crate) mod nodetests;
 mod numbers;
@@ -60,19 +61,22 @@ pub(crate) mod support;
 mod types;
 pub(crate) mod variables;
 
-use crate::parser::combinators::alt::alt4;
+use crate::parser::combinators::alt::alt5;
 use crate::parser::combinators::list::separated_list1;
 use crate::parser::combinators::map::map;
 use crate::parser::combinators::tag::tag;
 use crate::parser::combinators::tuple::tuple3;
 use crate::parser::combinators::whitespace::xpwhitespace;
 //use crate::parser::combinators::debug::inspect;
-use crate::parser::xpath::flwr::{for_expr, if_expr, let_expr};
+use crate::parser::xpath::flwr::{for_expr, if_expr, let_expr, quantified_expr};
 use crate::parser::xpath::logic::or_expr;
 use crate::parser::xpath::support::noop;
+use crate::parser::xpath::types::sequencetype_expr;
 use crate::parser::{ParseError, ParseInput, ParserState};
 
 use crate::item::Node;
+use crate::qname::QualifiedName;
+use crate::transform::types::SequenceType;
 use crate::transform::Transform;
 use crate::xdmerror::{Error, ErrorKind};
 
@@ -111,6 +115,19 @@ pub fn parse<N: Node>(input: &str, n: Option<N>) -> Result<Transform<N>, Error>
     }
 }
 
+/// Parse a SequenceType, such as the value of an "as" attribute in an XSL stylesheet. The optional [Node] is used to resolve XML Namespaces.
+pub fn parse_sequence_type<N: Node>(input: &str, n: Option<N>) -> Result<SequenceType, Error> {
+    let state = ParserState::new(None, n, None);
+    match sequencetype_expr::<N>()((input.trim(), state)) {
+        Ok((("", _), t)) => Ok(t),
+        _ => Err(Error::new_with_code(
+            ErrorKind::ParseError,
+            format!("invalid sequence type \"{}\"", input),
+            Some(QualifiedName::new(None, None, "XPST0003")),
+        )),
+    }
+}
+
 fn xpath_expr<N: Node>(input: ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> {
     match expr::<N>()(input) {
         Err(err) => Err(err),
@@ -161,7 +178,13 @@ pub(crate) fn expr_wrapper<N: Node>(
 // ExprSingle ::= ForExpr | LetExpr | QuantifiedExpr | IfExpr | OrExpr
 fn expr_single<'a, N: Node + 'a>(
 ) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
-    Box::new(alt4(let_expr(), for_expr(), if_expr(), or_expr()))
+    Box::new(alt5(
+        let_expr(),
+        for_expr(),
+        quantified_expr(),
+        if_expr(),
+        or_expr(),
+    ))
 }
 
 pub(crate) fn expr_single_wrapper<N: Node>(
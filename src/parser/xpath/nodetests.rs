@@ -1,7 +1,7 @@
 //! Functions that produce tests for nodes.
 
 use crate::item::Node;
-use crate::parser::combinators::alt::{alt2, alt5};
+use crate::parser::combinators::alt::{alt2, alt3, alt5};
 use crate::parser::combinators::map::map;
 use crate::parser::combinators::opt::opt;
 use crate::parser::combinators::tag::tag;
@@ -10,12 +10,27 @@ use crate::parser::{ParseError, ParseInput};
 use crate::transform::{KindTest, NameTest, NodeTest, WildcardOrName};
 use std::rc::Rc;
 //use crate::parser::combinators::debug::inspect;
-use crate::parser::xml::qname::{ncname, qualname};
+use crate::parser::xml::qname::{ncname, qualname, uriqualname};
 use crate::value::Value;
 
 pub(crate) fn qualname_test<'a, N: Node + 'a>(
 ) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, NodeTest), ParseError> + 'a> {
-    Box::new(alt2(prefixed_name(), unprefixed_name()))
+    Box::new(alt3(
+        uri_qualified_name(),
+        prefixed_name(),
+        unprefixed_name(),
+    ))
+}
+// URIQualifiedName ::= "Q" "{" [^{}]* "}" NCName
+fn uri_qualified_name<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, NodeTest), ParseError> + 'a> {
+    Box::new(map(uriqualname(), |qn| {
+        NodeTest::Name(NameTest {
+            ns: qn.namespace_uri().map(WildcardOrName::Name),
+            prefix: None,
+            name: Some(WildcardOrName::Name(qn.localname())),
+        })
+    }))
 }
 fn unprefixed_name<'a, N: Node + 'a>(
 ) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, NodeTest), ParseError> + 'a> {
@@ -158,7 +173,6 @@ fn any_kind_test<'a, N: Node + 'a>(
 }
 
 // NameTest ::= EQName | Wildcard
-// TODO: allow EQName rather than QName
 fn nametest<'a, N: Node + 'a>(
 ) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, NodeTest), ParseError> + 'a> {
     Box::new(alt2(qualname_test(), wildcard()))
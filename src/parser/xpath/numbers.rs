@@ -132,17 +132,34 @@ pub(crate) fn unary_expr<'a, N: Node + 'a>(
     ))
 }
 
-// ValueExpr (SBox<dyneMapExpr) ::= PathExpr ('!' PathExpr)*
+// ValueExpr (SimpleMapExpr) ::= PathExpr ('!' PathExpr)*
+// Each item selected by the left-hand expression becomes the context item for the right-hand expression.
 fn value_expr<'a, N: Node + 'a>(
 ) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
     Box::new(map(
-        pair(path_expr::<N>(), many0(tuple2(tag("!"), path_expr::<N>()))),
+        pair(
+            path_expr::<N>(),
+            many0(tuple2(
+                tuple3(xpwhitespace(), simple_map_operator(), xpwhitespace()),
+                path_expr::<N>(),
+            )),
+        ),
         |(u, v)| {
-            if v.is_empty() {
-                u
-            } else {
-                Transform::NotImplemented("value_expr".to_string())
-            }
+            v.into_iter().fold(u, |acc, (_, e)| {
+                Transform::ForEach(None, Box::new(acc), Box::new(e), vec![])
+            })
         },
     ))
 }
+
+// The '!' operator, but not the '!=' operator
+fn simple_map_operator<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, ()), ParseError> + 'a> {
+    Box::new(move |(input, state): ParseInput<N>| {
+        if input.starts_with('!') && !input.starts_with("!=") {
+            Ok(((&input[1..], state), ()))
+        } else {
+            Err(ParseError::Combinator)
+        }
+    })
+}
@@ -1,8 +1,10 @@
 //! Supporting functions.
 
 use crate::item::Node;
+use crate::namespace::NamespaceMap;
 use crate::parser::{ParseError, ParseInput};
-use crate::transform::{NameTest, NodeTest, Transform, WildcardOrName};
+use crate::qname::QualifiedName;
+use crate::transform::{variable_name, NameTest, NodeTest, Transform, WildcardOrName};
 
 pub(crate) fn get_nt_localname(nt: &NodeTest) -> String {
     match nt {
@@ -15,6 +17,27 @@ pub(crate) fn get_nt_localname(nt: &NodeTest) -> String {
     }
 }
 
+// A prefixed variable name is resolved to its expanded name (e.g. $err:code).
+// If the prefix is not declared then the name is left as is, and so will not match any variable.
+pub(crate) fn get_nt_varname(nt: &NodeTest, namespaces: &NamespaceMap) -> String {
+    variable_name(&get_nt_qualname(nt, namespaces))
+}
+
+// The name of a variable binding, resolved in the same way as a variable reference.
+pub(crate) fn get_nt_qualname(nt: &NodeTest, namespaces: &NamespaceMap) -> QualifiedName {
+    match nt {
+        NodeTest::Name(NameTest {
+            name: Some(WildcardOrName::Name(localpart)),
+            ns: None,
+            prefix: Some(p),
+        }) => namespaces.get(&Some(p.clone())).map_or_else(
+            || QualifiedName::new(None, None, format!("{}:{}", p, localpart)),
+            |ns| QualifiedName::new(Some(ns.to_string()), None, localpart.to_string()),
+        ),
+        _ => QualifiedName::new(None, None, get_nt_localname(nt)),
+    }
+}
+
 pub(crate) fn noop<N: Node>(
 ) -> impl Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> {
     move |_| Err(ParseError::Combinator)
@@ -1,16 +1,22 @@
 //! Functions that manipulate type information
 
 use crate::item::Node;
+use crate::parser::combinators::alt::{alt2, alt3};
+use crate::parser::combinators::list::separated_list0;
 use crate::parser::combinators::map::map;
 use crate::parser::combinators::opt::opt;
 use crate::parser::combinators::pair::pair;
 use crate::parser::combinators::tag::tag;
-use crate::parser::combinators::tuple::tuple6;
+use crate::parser::combinators::tuple::{tuple2, tuple3, tuple4, tuple5, tuple6};
 use crate::parser::combinators::whitespace::xpwhitespace;
 use crate::parser::xpath::functions::arrow_expr;
-use crate::parser::xpath::nodetests::qualname_test;
+use crate::parser::xpath::nodetests::{kindtest, qualname_test};
 use crate::parser::{ParseError, ParseInput};
-use crate::transform::Transform;
+use crate::transform::types::{atomic_type, ItemType, Occurrence, SequenceType};
+use crate::transform::{in_scope_namespaces, NodeTest, Transform, WildcardOrName};
+use crate::value::Value;
+
+pub(crate) const XSD_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema";
 
 // InstanceOfExpr ::= TreatExpr ( 'instance' 'of' SequenceType)?
 pub(crate) fn instanceof_expr<'a, N: Node + 'a>(
@@ -27,23 +33,209 @@ pub(crate) fn instanceof_expr<'a, N: Node + 'a>(
                 sequencetype_expr::<N>(),
             )),
         ),
-        |(v, o)| {
-            if o.is_none() {
-                v
-            } else {
-                Transform::NotImplemented("instanceof_expr".to_string())
-            }
+        |(v, o)| match o {
+            Some((_, _, _, _, _, t)) => Transform::InstanceOf(Box::new(v), t),
+            None => v,
         },
     ))
 }
 
 // SequenceType ::= ( 'empty-sequence' '(' ')' | (ItemType OccurrenceIndicator?)
-// TODO: implement this parser fully
-fn sequencetype_expr<'a, N: Node + 'a>(
-) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
-    Box::new(map(tag("empty-sequence()"), |_| {
-        Transform::NotImplemented("sequencetype_expr".to_string())
-    }))
+pub(crate) fn sequencetype_expr<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, SequenceType), ParseError> + 'a> {
+    Box::new(alt2(
+        map(
+            tuple5(
+                tag("empty-sequence"),
+                xpwhitespace(),
+                tag("("),
+                xpwhitespace(),
+                tag(")"),
+            ),
+            |_| SequenceType::Empty,
+        ),
+        map(
+            pair(item_type::<N>(), opt(occurrence_indicator())),
+            |(t, o)| SequenceType::Items(t, o.unwrap_or(Occurrence::ExactlyOne)),
+        ),
+    ))
+}
+
+// The sequence type parser is recursive, via function, map and array tests, so must be constructed lazily.
+fn sequencetype_wrapper<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, SequenceType), ParseError> + 'a> {
+    Box::new(move |input| sequencetype_expr::<N>()(input))
+}
+
+// OccurrenceIndicator ::= '?' | '*' | '+'
+fn occurrence_indicator<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Occurrence), ParseError> + 'a> {
+    Box::new(alt3(
+        map(tag("?"), |_| Occurrence::ZeroOrOne),
+        map(tag("*"), |_| Occurrence::ZeroOrMore),
+        map(tag("+"), |_| Occurrence::OneOrMore),
+    ))
+}
+
+// ItemType ::= KindTest | ('item' '(' ')') | FunctionTest | MapTest | ArrayTest | AtomicOrUnionType | ParenthesizedItemType
+fn item_type<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, ItemType), ParseError> + 'a> {
+    Box::new(alt3(
+        alt2(
+            map(kindtest(), ItemType::Node),
+            map(
+                tuple5(
+                    tag("item"),
+                    xpwhitespace(),
+                    tag("("),
+                    xpwhitespace(),
+                    tag(")"),
+                ),
+                |_| ItemType::Any,
+            ),
+        ),
+        alt3(
+            map(function_test::<N>(), |_| ItemType::Function),
+            map(map_test::<N>(), |_| ItemType::Map),
+            map(array_test::<N>(), |_| ItemType::Array),
+        ),
+        alt2(
+            map(atomic_type_name::<N>(), ItemType::Atomic),
+            map(
+                tuple5(
+                    tag("("),
+                    xpwhitespace(),
+                    item_type_wrapper::<N>(),
+                    xpwhitespace(),
+                    tag(")"),
+                ),
+                |(_, _, t, _, _)| t,
+            ),
+        ),
+    ))
+}
+
+// ParenthesizedItemType is recursive, so the parser must be constructed lazily.
+fn item_type_wrapper<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, ItemType), ParseError> + 'a> {
+    Box::new(move |input| item_type::<N>()(input))
+}
+
+// FunctionTest ::= AnyFunctionTest | TypedFunctionTest
+// AnyFunctionTest ::= 'function' '(' '*' ')'
+// TypedFunctionTest ::= 'function' '(' (SequenceType (',' SequenceType)*)? ')' 'as' SequenceType
+// The function signature is parsed, but is not used.
+fn function_test<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, ()), ParseError> + 'a> {
+    Box::new(map(
+        tuple4(
+            tag("function"),
+            xpwhitespace(),
+            tag("("),
+            alt2(
+                map(
+                    tuple4(xpwhitespace(), tag("*"), xpwhitespace(), tag(")")),
+                    |_| (),
+                ),
+                map(
+                    tuple6(
+                        xpwhitespace(),
+                        separated_list0(
+                            map(tuple3(xpwhitespace(), tag(","), xpwhitespace()), |_| ()),
+                            sequencetype_wrapper::<N>(),
+                        ),
+                        xpwhitespace(),
+                        tag(")"),
+                        tuple3(xpwhitespace(), tag("as"), xpwhitespace()),
+                        sequencetype_wrapper::<N>(),
+                    ),
+                    |_| (),
+                ),
+            ),
+        ),
+        |_| (),
+    ))
+}
+
+// MapTest ::= AnyMapTest | TypedMapTest
+// AnyMapTest ::= 'map' '(' '*' ')'
+// TypedMapTest ::= 'map' '(' AtomicOrUnionType ',' SequenceType ')'
+// The types of the keys and values are parsed, but are not used.
+fn map_test<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, ()), ParseError> + 'a> {
+    Box::new(map(
+        tuple6(
+            tag("map"),
+            xpwhitespace(),
+            tag("("),
+            xpwhitespace(),
+            alt2(
+                map(tag("*"), |_| ()),
+                map(
+                    tuple5(
+                        atomic_type_name::<N>(),
+                        xpwhitespace(),
+                        tag(","),
+                        xpwhitespace(),
+                        sequencetype_wrapper::<N>(),
+                    ),
+                    |_| (),
+                ),
+            ),
+            tuple2(xpwhitespace(), tag(")")),
+        ),
+        |_| (),
+    ))
+}
+
+// ArrayTest ::= AnyArrayTest | TypedArrayTest
+// AnyArrayTest ::= 'array' '(' '*' ')'
+// TypedArrayTest ::= 'array' '(' SequenceType ')'
+// The type of the members is parsed, but is not used.
+fn array_test<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, ()), ParseError> + 'a> {
+    Box::new(map(
+        tuple6(
+            tag("array"),
+            xpwhitespace(),
+            tag("("),
+            xpwhitespace(),
+            alt2(
+                map(tag("*"), |_| ()),
+                map(sequencetype_wrapper::<N>(), |_| ()),
+            ),
+            tuple2(xpwhitespace(), tag(")")),
+        ),
+        |_| (),
+    ))
+}
+
+// AtomicOrUnionType ::= EQName
+// Only the built-in types of XML Schema are recognised. The prefix must be "xs" or be bound to the XML Schema namespace.
+fn atomic_type_name<'a, N: Node + 'a>(
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Value), ParseError> + 'a> {
+    Box::new(move |input| match qualname_test()(input) {
+        Ok(((rest, state), NodeTest::Name(nt))) => {
+            let xsd = match &nt.prefix {
+                Some(p) => {
+                    p.to_string() == "xs"
+                        || in_scope_namespaces(state.cur.clone())
+                            .get(&Some(p.clone()))
+                            .is_some_and(|ns| ns.to_string() == XSD_NAMESPACE)
+                }
+                None => false,
+            };
+            match (xsd, &nt.name) {
+                (true, Some(WildcardOrName::Name(n))) => match atomic_type(&n.to_string()) {
+                    Some(v) => Ok(((rest, state), v)),
+                    None => Err(ParseError::Combinator),
+                },
+                _ => Err(ParseError::Combinator),
+            }
+        }
+        Ok(_) => Err(ParseError::Combinator),
+        Err(e) => Err(e),
+    })
 }
 
 // TreatExpr ::= CastableExpr ( 'treat' 'as' SequenceType)?
@@ -61,12 +253,9 @@ fn treat_expr<'a, N: Node + 'a>(
                 sequencetype_expr::<N>(),
             )),
         ),
-        |(v, o)| {
-            if o.is_none() {
-                v
-            } else {
-                Transform::NotImplemented("treat_expr".to_string())
-            }
+        |(v, o)| match o {
+            Some((_, _, _, _, _, t)) => Transform::Treat(Box::new(v), t),
+            None => v,
         },
     ))
 }
@@ -86,12 +275,9 @@ fn castable_expr<'a, N: Node + 'a>(
                 singletype_expr::<N>(),
             )),
         ),
-        |(v, o)| {
-            if o.is_none() {
-                v
-            } else {
-                Transform::NotImplemented("castable_expr".to_string())
-            }
+        |(v, o)| match o {
+            Some((_, _, _, _, _, (t, e))) => Transform::Castable(Box::new(v), t, e),
+            None => v,
         },
     ))
 }
@@ -108,12 +294,13 @@ fn castable_expr<'a, N: Node + 'a>(
 // LocalPart ::= NCName
 // NCName ::= Name - (Char* ':' Char*)
 // Char ::= #x9 | #xA |#xD | [#x20-#xD7FF] | [#xE000-#xFFFD | [#x10000-#x10FFFF]
-// TODO: implement this parser fully
+// The result is the target type and whether the empty sequence is allowed.
 fn singletype_expr<'a, N: Node + 'a>(
-) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, Transform<N>), ParseError> + 'a> {
-    Box::new(map(pair(qualname_test(), tag("?")), |_| {
-        Transform::NotImplemented("singletype_expr".to_string())
-    }))
+) -> Box<dyn Fn(ParseInput<N>) -> Result<(ParseInput<N>, (Value, bool)), ParseError> + 'a> {
+    Box::new(map(
+        pair(atomic_type_name::<N>(), opt(tag("?"))),
+        |(t, e)| (t, e.is_some()),
+    ))
 }
 
 // CastExpr ::= ArrowExpr ( 'cast' 'as' SingleType)?
@@ -131,12 +318,9 @@ fn cast_expr<'a, N: Node + 'a>(
                 singletype_expr::<N>(),
             )),
         ),
-        |(v, o)| {
-            if o.is_none() {
-                v
-            } else {
-                Transform::NotImplemented("cast_expr".to_string())
-            }
+        |(v, o)| match o {
+            Some((_, _, _, _, _, (t, e))) => Transform::Cast(Box::new(v), t, e),
+            None => v,
         },
     ))
 }
@@ -6,7 +6,7 @@ use crate::parser::combinators::pair::pair;
 use crate::parser::combinators::tag::tag;
 //use crate::parser::combinators::debug::inspect;
 use crate::parser::xpath::nodetests::qualname_test;
-use crate::parser::xpath::support::get_nt_localname;
+use crate::parser::xpath::support::get_nt_varname;
 use crate::parser::{ParseError, ParseInput};
 use crate::transform::{in_scope_namespaces, Transform};
 
@@ -16,10 +16,8 @@ pub(crate) fn variable_reference<'a, N: Node + 'a>(
     Box::new(map_with_state(
         pair(tag("$"), qualname_test()),
         |(_, qn), state| {
-            Transform::VariableReference(
-                get_nt_localname(&qn),
-                in_scope_namespaces(state.cur.clone()),
-            )
+            let namespaces = in_scope_namespaces(state.cur.clone());
+            Transform::VariableReference(get_nt_varname(&qn, &namespaces), namespaces)
         },
     ))
 }
@@ -0,0 +1,259 @@
+//! # Accumulators
+//! An accumulator computes a value for every node in a document, by visiting the nodes in document order.
+//! See XSLT 18.2.
+//!
+//! The values of an accumulator are computed the first time it is used with a document,
+//! and are then retained for the rest of the transformation.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::rc::Rc;
+use url::Url;
+
+use crate::item::{Item, Node, NodeType, Sequence};
+use crate::namespace::NamespaceMap;
+use crate::qname::QualifiedName;
+use crate::transform::context::{Context, ContextBuilder, StaticContext};
+use crate::transform::types::{function_conversion, SequenceType};
+use crate::transform::Transform;
+use crate::xdmerror::{Error, ErrorKind};
+use crate::{Pattern, SequenceTrait};
+
+/// When an accumulator rule applies to a node: before (start) or after (end) its descendants are visited.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Phase {
+    #[default]
+    Start,
+    End,
+}
+
+impl TryFrom<&str> for Phase {
+    type Error = String;
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s.trim() {
+            "start" => Ok(Phase::Start),
+            "end" => Ok(Phase::End),
+            _ => Err(format!("unknown phase \"{}\"", s)),
+        }
+    }
+}
+
+/// A rule that computes a new value for an accumulator when a node matches its pattern.
+/// The current value of the accumulator is available in the variable $value.
+#[derive(Clone)]
+pub struct AccumulatorRule<N: Node> {
+    pub(crate) pattern: Pattern<N>,
+    pub(crate) phase: Phase,
+    pub(crate) select: Transform<N>,
+}
+
+impl<N: Node> AccumulatorRule<N> {
+    pub fn new(pattern: Pattern<N>, phase: Phase, select: Transform<N>) -> Self {
+        AccumulatorRule {
+            pattern,
+            phase,
+            select,
+        }
+    }
+}
+
+impl<N: Node> std::fmt::Debug for AccumulatorRule<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "accumulator rule match \"{:?}\" phase {:?}",
+            self.pattern, self.phase
+        )
+    }
+}
+
+/// An accumulator declaration.
+/// When a node matches more than one rule for the same phase, the last of those rules is used.
+#[derive(Clone, Debug)]
+pub struct Accumulator<N: Node> {
+    pub(crate) initial_value: Transform<N>,
+    pub(crate) as_type: Option<SequenceType>,
+    pub(crate) rules: Vec<AccumulatorRule<N>>,
+}
+
+impl<N: Node> Accumulator<N> {
+    pub fn new(
+        initial_value: Transform<N>,
+        as_type: Option<SequenceType>,
+        rules: Vec<AccumulatorRule<N>>,
+    ) -> Self {
+        Accumulator {
+            initial_value,
+            as_type,
+            rules,
+        }
+    }
+    fn convert(&self, s: Sequence<N>) -> Result<Sequence<N>, Error> {
+        match &self.as_type {
+            Some(t) => function_conversion(s, t, "XTTE0570"),
+            None => Ok(s),
+        }
+    }
+}
+
+/// The computed values of accumulators, indexed by the package and name of the accumulator and the identifier of the node.
+/// Each entry is the value before and after the descendants of the node are visited.
+/// The entry for a document node is None while the values for that document are being computed.
+pub(crate) type AccumulatorValues<N> =
+    HashMap<(Option<Rc<str>>, QualifiedName, String), Option<(Sequence<N>, Sequence<N>)>>;
+
+/// Implements the accumulator-before and accumulator-after functions.
+pub(crate) fn accumulator_value<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    name: &Transform<N>,
+    ns: &Rc<NamespaceMap>,
+    after: bool,
+) -> Result<Sequence<N>, Error> {
+    let s = ctxt.dispatch(stctxt, name)?.to_string();
+    let qn =
+        QualifiedName::try_from((s.as_str(), ns.clone())).map_err(|_| unknown_accumulator(&s))?;
+    // Only the accumulators of the package whose code is being evaluated are used
+    let package = ctxt.package.clone();
+    let acc = ctxt
+        .accumulators
+        .get(&(package.clone(), qn.clone()))
+        .ok_or_else(|| unknown_accumulator(&qn.to_string()))?;
+    let n = match ctxt.cur.get(ctxt.i) {
+        Some(Item::Node(n)) => n.clone(),
+        _ => {
+            return Err(Error::new_with_code(
+                ErrorKind::TypeError,
+                "context item is not a node",
+                Some(QualifiedName::new(None, None, "XTTE3360")),
+            ))
+        }
+    };
+    let doc = n.owner_document();
+    let computed = ctxt
+        .accumulator_values
+        .borrow()
+        .get(&(package.clone(), qn.clone(), doc.get_id()))
+        .map(|v| v.is_some());
+    match computed {
+        Some(true) => {}
+        Some(false) => {
+            return Err(Error::new_with_code(
+                ErrorKind::DynamicAbsent,
+                format!("accumulator \"{}\" depends on its own value", qn),
+                Some(QualifiedName::new(None, None, "XTDE3400")),
+            ))
+        }
+        None => {
+            ctxt.accumulator_values
+                .borrow_mut()
+                .insert((package.clone(), qn.clone(), doc.get_id()), None);
+            let mut values = HashMap::new();
+            let result = ContextBuilder::from(ctxt)
+                .context(vec![Item::Node(doc.clone())])
+                .index(0)
+                .build()
+                .dispatch(stctxt, &acc.initial_value)
+                .and_then(|initial| acc.convert(initial))
+                .and_then(|initial| visit(ctxt, stctxt, acc, &doc, initial, &mut values));
+            let mut av = ctxt.accumulator_values.borrow_mut();
+            if let Err(e) = result {
+                av.remove(&(package.clone(), qn.clone(), doc.get_id()));
+                return Err(e);
+            }
+            av.extend(
+                values
+                    .into_iter()
+                    .map(|(id, v)| ((package.clone(), qn.clone(), id), Some(v))),
+            );
+        }
+    }
+    match ctxt
+        .accumulator_values
+        .borrow()
+        .get(&(package.clone(), qn.clone(), n.get_id()))
+    {
+        Some(Some((b, a))) => Ok(if after { a.clone() } else { b.clone() }),
+        _ => Err(Error::new_with_code(
+            ErrorKind::DynamicAbsent,
+            format!("accumulator \"{}\" does not apply to the context node", qn),
+            Some(QualifiedName::new(None, None, "XTDE3350")),
+        )),
+    }
+}
+
+fn unknown_accumulator(name: &str) -> Error {
+    Error::new_with_code(
+        ErrorKind::DynamicAbsent,
+        format!("no accumulator named \"{}\"", name),
+        Some(QualifiedName::new(None, None, "XTDE3340")),
+    )
+}
+
+/// Compute the values of an accumulator for a node and its descendants, returning the value after the node has been visited.
+/// Attributes are visited after their element and before its children.
+fn visit<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    acc: &Accumulator<N>,
+    n: &N,
+    value: Sequence<N>,
+    values: &mut HashMap<String, (Sequence<N>, Sequence<N>)>,
+) -> Result<Sequence<N>, Error> {
+    let before = apply_rule(ctxt, stctxt, acc, n, Phase::Start, value)?;
+    let mut v = before.clone();
+    if n.node_type() == NodeType::Element {
+        for a in n.attribute_iter() {
+            v = visit(ctxt, stctxt, acc, &a, v, values)?
+        }
+    }
+    for c in n.child_iter() {
+        v = visit(ctxt, stctxt, acc, &c, v, values)?
+    }
+    let after = apply_rule(ctxt, stctxt, acc, n, Phase::End, v)?;
+    values.insert(n.get_id(), (before, after.clone()));
+    Ok(after)
+}
+
+/// Apply the last matching rule for the phase. If no rule matches then the value is unchanged.
+fn apply_rule<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    acc: &Accumulator<N>,
+    n: &N,
+    phase: Phase,
+    value: Sequence<N>,
+) -> Result<Sequence<N>, Error> {
+    let i = Item::Node(n.clone());
+    match acc
+        .rules
+        .iter()
+        .rev()
+        .find(|r| r.phase == phase && r.pattern.matches(ctxt, stctxt, &i))
+    {
+        Some(r) => acc.convert(
+            ContextBuilder::from(ctxt)
+                .context(vec![i])
+                .index(0)
+                .variable(String::from("value"), value)
+                .build()
+                .dispatch(stctxt, &r.select)?,
+        ),
+        None => Ok(value),
+    }
+}
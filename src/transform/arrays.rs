@@ -0,0 +1,445 @@
+//! These functions are for features defined in XPath 3.1 and XPath Functions 3.1 for arrays.
+
+use std::rc::Rc;
+use url::Url;
+
+use crate::item::{Array, Item, Node, Sequence, SequenceTrait};
+use crate::qname::QualifiedName;
+use crate::transform::callable::{call_item, single_function};
+use crate::transform::context::{Context, StaticContext};
+use crate::transform::hof::{predicate, sort_members};
+use crate::transform::Transform;
+use crate::value::Value;
+use crate::xdmerror::{Error, ErrorKind};
+
+/// Evaluate a transformation that must produce a single array.
+pub(crate) fn single_array<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    t: &Transform<N>,
+) -> Result<Rc<Array<N>>, Error> {
+    let a = ctxt.dispatch(stctxt, t)?;
+    match a.as_slice() {
+        [Item::Array(a)] => Ok(a.clone()),
+        _ => Err(Error::new_with_code(
+            ErrorKind::TypeError,
+            "not a singleton array",
+            Some(QualifiedName::new(None, None, "XPTY0004")),
+        )),
+    }
+}
+
+/// Check that a position is within the bounds of an array, or one past the end if `end` is true.
+fn check_position<N: Node>(a: &Array<N>, p: i64, end: bool) -> Result<usize, Error> {
+    let limit = if end { a.len() + 1 } else { a.len() };
+    if p < 1 || p as usize > limit {
+        Err(Error::new_with_code(
+            ErrorKind::Unknown,
+            format!("array index {} out of bounds", p),
+            Some(QualifiedName::new(None, None, "FOAY0001")),
+        ))
+    } else {
+        Ok(p as usize - 1)
+    }
+}
+
+/// XPath square array constructor. Each transformation produces one member of the array.
+pub(crate) fn square_array_constructor<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    m: &[Transform<N>],
+) -> Result<Sequence<N>, Error> {
+    let a = m
+        .iter()
+        .map(|t| ctxt.dispatch(stctxt, t))
+        .collect::<Result<Array<N>, Error>>()?;
+    Ok(vec![Item::Array(Rc::new(a))])
+}
+
+/// XPath curly array constructor. Each item in the resulting sequence becomes a member of the array.
+pub(crate) fn curly_array_constructor<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    t: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let a = ctxt
+        .dispatch(stctxt, t)?
+        .into_iter()
+        .map(|i| vec![i])
+        .collect::<Array<N>>();
+    Ok(vec![Item::Array(Rc::new(a))])
+}
+
+/// XPath array:size function.
+pub(crate) fn array_size<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    a: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    Ok(vec![Item::Value(Rc::new(Value::from(
+        single_array(ctxt, stctxt, a)?.len() as i64,
+    )))])
+}
+
+/// XPath array:get function.
+pub(crate) fn array_get<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    a: &Transform<N>,
+    p: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let arr = single_array(ctxt, stctxt, a)?;
+    let pos = ctxt.dispatch(stctxt, p)?.to_int()?;
+    Ok(arr.get(pos)?.clone())
+}
+
+/// XPath array:put function.
+pub(crate) fn array_put<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    a: &Transform<N>,
+    p: &Transform<N>,
+    m: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let arr = single_array(ctxt, stctxt, a)?;
+    let pos = check_position(&arr, ctxt.dispatch(stctxt, p)?.to_int()?, false)?;
+    let mut members = arr.members().clone();
+    members[pos] = ctxt.dispatch(stctxt, m)?;
+    Ok(vec![Item::Array(Rc::new(Array::from(members)))])
+}
+
+/// XPath array:append function.
+pub(crate) fn array_append<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    a: &Transform<N>,
+    m: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let mut arr = (*single_array(ctxt, stctxt, a)?).clone();
+    arr.push(ctxt.dispatch(stctxt, m)?);
+    Ok(vec![Item::Array(Rc::new(arr))])
+}
+
+/// XPath array:subarray function.
+pub(crate) fn array_subarray<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    a: &Transform<N>,
+    s: &Transform<N>,
+    l: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let arr = single_array(ctxt, stctxt, a)?;
+    let start = check_position(&arr, ctxt.dispatch(stctxt, s)?.to_int()?, true)?;
+    let len = match l {
+        Some(t) => {
+            let len = ctxt.dispatch(stctxt, t)?.to_int()?;
+            if len < 0 {
+                return Err(Error::new_with_code(
+                    ErrorKind::Unknown,
+                    "negative array length",
+                    Some(QualifiedName::new(None, None, "FOAY0002")),
+                ));
+            }
+            check_position(&arr, (start + len as usize + 1) as i64, true)?;
+            len as usize
+        }
+        None => arr.len() - start,
+    };
+    Ok(vec![Item::Array(Rc::new(Array::from(
+        arr.members()[start..start + len].to_vec(),
+    )))])
+}
+
+/// XPath array:remove function. The positions to remove are given as a sequence.
+pub(crate) fn array_remove<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    a: &Transform<N>,
+    p: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let arr = single_array(ctxt, stctxt, a)?;
+    let mut positions = vec![];
+    for i in ctxt.dispatch(stctxt, p)? {
+        positions.push(check_position(&arr, i.to_int()?, false)?)
+    }
+    Ok(vec![Item::Array(Rc::new(
+        arr.iter()
+            .enumerate()
+            .filter(|(i, _)| !positions.contains(i))
+            .map(|(_, m)| m.clone())
+            .collect(),
+    ))])
+}
+
+/// XPath array:insert-before function.
+pub(crate) fn array_insert_before<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    a: &Transform<N>,
+    p: &Transform<N>,
+    m: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let arr = single_array(ctxt, stctxt, a)?;
+    let pos = check_position(&arr, ctxt.dispatch(stctxt, p)?.to_int()?, true)?;
+    let mut members = arr.members().clone();
+    members.insert(pos, ctxt.dispatch(stctxt, m)?);
+    Ok(vec![Item::Array(Rc::new(Array::from(members)))])
+}
+
+/// XPath array:head function.
+pub(crate) fn array_head<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    a: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    Ok(single_array(ctxt, stctxt, a)?.get(1)?.clone())
+}
+
+/// XPath array:tail function.
+pub(crate) fn array_tail<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    a: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let arr = single_array(ctxt, stctxt, a)?;
+    check_position(&arr, 1, false)?;
+    Ok(vec![Item::Array(Rc::new(Array::from(
+        arr.members()[1..].to_vec(),
+    )))])
+}
+
+/// XPath array:reverse function.
+pub(crate) fn array_reverse<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    a: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    Ok(vec![Item::Array(Rc::new(
+        single_array(ctxt, stctxt, a)?
+            .members()
+            .iter()
+            .rev()
+            .cloned()
+            .collect(),
+    ))])
+}
+
+/// XPath array:join function.
+pub(crate) fn array_join<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    a: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let mut result = Array::new();
+    for i in ctxt.dispatch(stctxt, a)? {
+        if let Item::Array(arr) = i {
+            arr.iter().for_each(|m| result.push(m.clone()))
+        } else {
+            return Err(Error::new_with_code(
+                ErrorKind::TypeError,
+                "not an array",
+                Some(QualifiedName::new(None, None, "XPTY0004")),
+            ));
+        }
+    }
+    Ok(vec![Item::Array(Rc::new(result))])
+}
+
+/// XPath array:flatten function.
+pub(crate) fn array_flatten<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    a: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let mut result = vec![];
+    flatten_aux(ctxt.dispatch(stctxt, a)?, &mut result);
+    Ok(result)
+}
+fn flatten_aux<N: Node>(s: Sequence<N>, result: &mut Sequence<N>) {
+    s.into_iter().for_each(|i| match i {
+        Item::Array(a) => a.iter().for_each(|m| flatten_aux(m.clone(), result)),
+        _ => result.push(i),
+    })
+}
+
+/// XPath array:for-each function.
+pub(crate) fn array_for_each<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    a: &Transform<N>,
+    f: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let array = single_array(ctxt, stctxt, a)?;
+    let func = single_function(ctxt, stctxt, f)?;
+    let result = array
+        .iter()
+        .map(|m| call_item(ctxt, stctxt, &func, vec![m.clone()]))
+        .collect::<Result<Array<N>, Error>>()?;
+    Ok(vec![Item::Array(Rc::new(result))])
+}
+
+/// XPath array:filter function.
+pub(crate) fn array_filter<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    a: &Transform<N>,
+    f: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let array = single_array(ctxt, stctxt, a)?;
+    let func = single_function(ctxt, stctxt, f)?;
+    let mut result = Array::new();
+    for m in array.iter() {
+        if predicate(ctxt, stctxt, &func, m.clone())? {
+            result.push(m.clone())
+        }
+    }
+    Ok(vec![Item::Array(Rc::new(result))])
+}
+
+/// XPath array:fold-left function.
+pub(crate) fn array_fold_left<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    a: &Transform<N>,
+    z: &Transform<N>,
+    f: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let array = single_array(ctxt, stctxt, a)?;
+    let zero = ctxt.dispatch(stctxt, z)?;
+    let func = single_function(ctxt, stctxt, f)?;
+    let result = array.iter().try_fold(zero, |acc, m| {
+        call_item(ctxt, stctxt, &func, vec![acc, m.clone()])
+    });
+    result
+}
+
+/// XPath array:fold-right function.
+pub(crate) fn array_fold_right<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    a: &Transform<N>,
+    z: &Transform<N>,
+    f: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let array = single_array(ctxt, stctxt, a)?;
+    let zero = ctxt.dispatch(stctxt, z)?;
+    let func = single_function(ctxt, stctxt, f)?;
+    let result = array.members().iter().rev().try_fold(zero, |acc, m| {
+        call_item(ctxt, stctxt, &func, vec![m.clone(), acc])
+    });
+    result
+}
+
+/// XPath array:sort function.
+/// Only the Unicode codepoint collation is supported, so the collation argument is ignored.
+pub(crate) fn array_sort<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    a: &Transform<N>,
+    _c: &Option<Box<Transform<N>>>,
+    k: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let array = single_array(ctxt, stctxt, a)?;
+    let sorted = sort_members(ctxt, stctxt, array.members().clone(), k)?;
+    Ok(vec![Item::Array(Rc::new(Array::from(sorted)))])
+}
@@ -3,11 +3,37 @@
 use std::rc::Rc;
 use url::Url;
 
-use crate::item::{Item, Node, Sequence, SequenceTrait};
+use crate::item::{Item, Node, Sequence};
+use crate::qname::QualifiedName;
 use crate::transform::context::{Context, StaticContext};
 use crate::transform::Transform;
 use crate::value::Value;
-use crate::xdmerror::Error;
+use crate::xdmerror::{Error, ErrorKind};
+
+/// Determine the effective boolean value of a sequence (XPath 2.4.3).
+/// Unlike [crate::item::SequenceTrait::to_bool], sequences that have no effective boolean value,
+/// such as a map, an array or several atomic values, raise FORG0006.
+pub(crate) fn effective_boolean_value<N: Node>(s: &Sequence<N>) -> Result<bool, Error> {
+    match s.first() {
+        None => Ok(false),
+        Some(Item::Node(..)) => Ok(true),
+        Some(Item::Value(v)) if s.len() == 1 => Ok(v.to_bool()),
+        Some(i) => Err(Error::new_with_code(
+            ErrorKind::TypeError,
+            match i {
+                Item::Map(_) => String::from("a map does not have an effective boolean value"),
+                Item::Array(_) => String::from("an array does not have an effective boolean value"),
+                Item::Function(_) => {
+                    String::from("a function does not have an effective boolean value")
+                }
+                _ => String::from(
+                    "a sequence of more than one atomic value does not have an effective boolean value",
+                ),
+            },
+            Some(QualifiedName::new(None, None, "FORG0006")),
+        )),
+    }
+}
 
 /// XPath boolean function.
 pub fn boolean<
@@ -21,7 +47,7 @@ pub fn boolean<
     b: &Transform<N>,
 ) -> Result<Sequence<N>, Error> {
     Ok(vec![Item::Value(Rc::new(Value::Boolean(
-        ctxt.dispatch(stctxt, b)?.to_bool(),
+        effective_boolean_value(&ctxt.dispatch(stctxt, b)?)?,
     )))])
 }
 
@@ -37,7 +63,7 @@ pub fn not<
     n: &Transform<N>,
 ) -> Result<Sequence<N>, Error> {
     Ok(vec![Item::Value(Rc::new(Value::Boolean(
-        !ctxt.dispatch(stctxt, n)?.to_bool(),
+        !effective_boolean_value(&ctxt.dispatch(stctxt, n)?)?,
     )))])
 }
 
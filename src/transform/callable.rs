@@ -2,35 +2,86 @@
 //! Sequence constructors that are invoked by stylesheet code, such as named templates and functions.
 //! The difference between them is that named templates have named parameters,
 //! whereas functions have positional parameters.
+//!
+//! A [Function] is a function item, i.e. a function that is a value in a [Sequence].
+//! Function items are created by inline function expressions, named function references and partial function application.
+//!
+//! Tunnel parameters are passed implicitly through template invocations, see XSLT 10.1.3.
+//! They are not passed to functions.
 
-// TODO: tunneling parameters
-
-use crate::item::Node;
+use crate::item::{Item, Node, SequenceTrait};
 use crate::qname::QualifiedName;
-use crate::transform::context::StaticContext;
-use crate::transform::{NamespaceMap, Transform};
+use crate::transform::context::{hidden_component, StaticContext};
+use crate::transform::maps::atomize_key;
+use crate::transform::types::{function_conversion, SequenceType};
+use crate::transform::{variable_name, NamespaceMap, Transform};
 use crate::{Context, Error, ErrorKind, Sequence};
 use std::collections::HashMap;
+use std::rc::Rc;
 use url::Url;
 
 #[derive(Clone, Debug)]
 pub struct Callable<N: Node> {
     pub(crate) body: Transform<N>,
     pub(crate) parameters: FormalParameters<N>,
-    // TODO: return type
+    pub(crate) return_type: Option<SequenceType>,
+    // The package that declares the callable. Its body is evaluated as code of that package. See XSLT 3.5.
+    pub(crate) package: Option<Rc<str>>,
 }
 
 impl<N: Node> Callable<N> {
     pub fn new(body: Transform<N>, parameters: FormalParameters<N>) -> Self {
-        Callable { body, parameters }
+        Callable {
+            body,
+            parameters,
+            return_type: None,
+            package: None,
+        }
+    }
+    /// Create a callable whose result is converted to the given type.
+    pub fn new_with_type(
+        body: Transform<N>,
+        parameters: FormalParameters<N>,
+        return_type: Option<SequenceType>,
+    ) -> Self {
+        Callable {
+            body,
+            parameters,
+            return_type,
+            package: None,
+        }
+    }
+    /// The error code for a value that does not match the declared type of a parameter or of the result.
+    /// See XSLT 9.9 and 10.3.
+    fn type_error_code(&self, result: bool) -> &'static str {
+        match (&self.parameters, result) {
+            (FormalParameters::Named(_), false) => "XTTE0590",
+            (FormalParameters::Named(_), true) => "XTTE0505",
+            (FormalParameters::Positional(_), false) => "XPTY0004",
+            (FormalParameters::Positional(_), true) => "XTTE0780",
+        }
+    }
+    /// Apply the function conversion rules to the result of the callable.
+    fn result(&self, s: Sequence<N>) -> Result<Sequence<N>, Error> {
+        match &self.return_type {
+            Some(t) => function_conversion(s, t, self.type_error_code(true)),
+            None => Ok(s),
+        }
+    }
+    /// Apply the function conversion rules to the value of a parameter.
+    fn argument(&self, s: Sequence<N>, t: &Option<SequenceType>) -> Result<Sequence<N>, Error> {
+        match t {
+            Some(t) => function_conversion(s, t, self.type_error_code(false)),
+            None => Ok(s),
+        }
     }
 }
 
-// TODO: parameter type ("as" attribute)
+/// Parameters with a declared type are converted to that type using the function conversion rules.
 #[derive(Clone, Debug)]
 pub enum FormalParameters<N: Node> {
-    Named(Vec<(QualifiedName, Option<Transform<N>>)>), // parameter name, default value
-    Positional(Vec<QualifiedName>),
+    Named(Vec<(QualifiedName, Option<Transform<N>>, Option<SequenceType>)>), // parameter name, default value, type
+    Positional(Vec<(QualifiedName, Option<SequenceType>)>),                  // parameter name, type
 }
 #[derive(Clone, Debug)]
 pub enum ActualParameters<N: Node> {
@@ -61,11 +112,18 @@ pub(crate) fn invoke<
             |r| Ok(r.clone()),
         )
     })?;
-    match ctxt.callables.get(&qnr) {
+    let Some(key) = ctxt.callable_key(&qnr) else {
+        return Err(match a {
+            ActualParameters::Named(_) => hidden_component("template", qnr, "XTSE3050"),
+            ActualParameters::Positional(_) => hidden_component("function", qnr, "XPST0017"),
+        });
+    };
+    match ctxt.callables.get(&key) {
         Some(t) => {
             match &t.parameters {
                 FormalParameters::Named(v) => {
                     let mut newctxt = ctxt.clone();
+                    newctxt.package = t.package.clone();
                     // Put the actual parameters in a HashMap for easy access
                     let mut actuals = HashMap::new();
                     if let ActualParameters::Named(av) = a {
@@ -77,35 +135,36 @@ pub(crate) fn invoke<
                         return Err(Error::new(ErrorKind::TypeError, "argument mismatch"));
                     }
                     // Match each actual parameter to a formal parameter by name
-                    v.iter().try_for_each(|(name, dflt)| {
-                        match actuals.get(name) {
-                            Some(val) => {
-                                newctxt.var_push(name.to_string(), val.clone());
-                                Ok(())
-                            }
-                            None => {
-                                // Use default value
-                                if let Some(d) = dflt {
-                                    newctxt.var_push(name.to_string(), ctxt.dispatch(stctxt, d)?)
-                                } else {
-                                    newctxt.var_push(name.to_string(), vec![])
-                                }
-                                Ok(())
-                            }
-                        }
+                    v.iter().try_for_each(|(name, dflt, st)| {
+                        let val = match actuals.remove(name) {
+                            Some(val) => val,
+                            // Use default value
+                            None => match dflt {
+                                Some(d) => ctxt.dispatch(stctxt, d)?,
+                                None => vec![],
+                            },
+                        };
+                        newctxt.var_push(variable_name(name), t.argument(val, st)?);
+                        Ok::<(), Error>(())
                     })?;
-                    newctxt.dispatch(stctxt, &t.body)
+                    t.result(newctxt.dispatch(stctxt, &t.body)?)
                 }
                 FormalParameters::Positional(v) => {
                     if let ActualParameters::Positional(av) = a {
                         // Make sure number of parameters are equal, then set up variables by position
                         if v.len() == av.len() {
                             let mut newctxt = ctxt.clone();
-                            v.iter().zip(av.iter()).try_for_each(|(qn, t)| {
-                                newctxt.var_push(qn.to_string(), ctxt.dispatch(stctxt, t)?);
-                                Ok(())
+                            newctxt.package = t.package.clone();
+                            // Tunnel parameters are not passed to functions
+                            newctxt.tunnel_params.clear();
+                            v.iter().zip(av.iter()).try_for_each(|((qn, st), a)| {
+                                newctxt.var_push(
+                                    variable_name(qn),
+                                    t.argument(ctxt.dispatch(stctxt, a)?, st)?,
+                                );
+                                Ok::<(), Error>(())
                             })?;
-                            newctxt.dispatch(stctxt, &t.body)
+                            t.result(newctxt.dispatch(stctxt, &t.body)?)
                         } else {
                             Err(Error::new(ErrorKind::TypeError, "argument mismatch"))
                         }
@@ -121,3 +180,273 @@ pub(crate) fn invoke<
         )),
     }
 }
+
+/// Evaluate a transformation with additional tunnel parameters, such as an xsl:apply-templates or xsl:call-template instruction.
+/// The new parameters replace any tunnel parameters with the same name.
+pub(crate) fn tunnel<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    params: &[(String, Transform<N>)],
+    body: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let mut newctxt = ctxt.clone();
+    params.iter().try_for_each(|(name, t)| {
+        newctxt
+            .tunnel_params
+            .insert(name.clone(), ctxt.dispatch(stctxt, t)?);
+        Ok::<(), Error>(())
+    })?;
+    newctxt.dispatch(stctxt, body)
+}
+
+/// The value of a tunnel parameter declared by a template.
+/// If the parameter was not supplied then its default value is used.
+pub(crate) fn tunnel_parameter<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    name: &String,
+    dflt: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    match ctxt.tunnel_params.get(name) {
+        Some(v) => Ok(v.clone()),
+        None => ctxt.dispatch(stctxt, dflt),
+    }
+}
+
+/// A function item.
+#[derive(Clone, Debug)]
+pub enum Function<N: Node> {
+    /// An inline function, or a reference to a named function.
+    /// The closure is the set of variables that were in scope when the function item was created.
+    Inline {
+        name: Option<Rc<QualifiedName>>,
+        callable: Callable<N>,
+        closure: HashMap<String, Vec<Sequence<N>>>,
+    },
+    /// A partially applied function. Placeholders for the remaining arguments are None.
+    Partial(Rc<Function<N>>, Vec<Option<Sequence<N>>>),
+}
+
+impl<N: Node> Function<N> {
+    /// The number of arguments the function accepts.
+    pub fn arity(&self) -> usize {
+        match self {
+            Function::Inline { callable, .. } => match &callable.parameters {
+                FormalParameters::Named(v) => v.len(),
+                FormalParameters::Positional(v) => v.len(),
+            },
+            Function::Partial(_, a) => a.iter().filter(|b| b.is_none()).count(),
+        }
+    }
+    /// The name of the function. Anonymous functions, including partially applied functions, have no name.
+    pub fn name(&self) -> Option<Rc<QualifiedName>> {
+        match self {
+            Function::Inline { name, .. } => name.clone(),
+            Function::Partial(_, _) => None,
+        }
+    }
+}
+
+/// Create a function item. The function closes over the variables currently in scope.
+pub(crate) fn function_item<N: Node>(
+    ctxt: &Context<N>,
+    name: &Option<Rc<QualifiedName>>,
+    params: &[(QualifiedName, Option<SequenceType>)],
+    body: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    Ok(vec![Item::Function(Rc::new(Function::Inline {
+        name: name.clone(),
+        callable: Callable {
+            package: ctxt.package.clone(),
+            ..Callable::new(body.clone(), FormalParameters::Positional(params.to_vec()))
+        },
+        closure: ctxt.vars.clone(),
+    }))])
+}
+
+/// Evaluate a transformation that must produce a single function item.
+/// Maps and arrays are also functions.
+pub(crate) fn single_function<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    f: &Transform<N>,
+) -> Result<Item<N>, Error> {
+    let s = ctxt.dispatch(stctxt, f)?;
+    match s.len() {
+        1 => match &s[0] {
+            Item::Function(_) | Item::Map(_) | Item::Array(_) => Ok(s[0].clone()),
+            _ => Err(not_a_function()),
+        },
+        _ => Err(not_a_function()),
+    }
+}
+
+fn not_a_function() -> Error {
+    Error::new_with_code(
+        ErrorKind::TypeError,
+        "not a function item",
+        Some(QualifiedName::new(None, None, "XPTY0004")),
+    )
+}
+
+/// Call a function item (including maps and arrays) with the given arguments.
+pub(crate) fn call_item<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    i: &Item<N>,
+    mut args: Vec<Sequence<N>>,
+) -> Result<Sequence<N>, Error> {
+    match i {
+        Item::Function(f) => call_function(ctxt, stctxt, f, args),
+        Item::Map(m) if args.len() == 1 => {
+            let k = args.pop().unwrap();
+            if k.len() == 1 {
+                Ok(m.get(&*atomize_key(&k[0])?).cloned().unwrap_or_default())
+            } else {
+                Err(not_a_function())
+            }
+        }
+        Item::Array(a) if args.len() == 1 => {
+            let p = args.pop().unwrap();
+            a.get(p.to_int()?).cloned()
+        }
+        Item::Map(_) | Item::Array(_) => Err(arity_mismatch()),
+        _ => Err(not_a_function()),
+    }
+}
+
+fn arity_mismatch() -> Error {
+    Error::new_with_code(
+        ErrorKind::TypeError,
+        "wrong number of arguments in function call",
+        Some(QualifiedName::new(None, None, "XPTY0004")),
+    )
+}
+
+/// Call a function item with the given arguments.
+pub(crate) fn call_function<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    f: &Function<N>,
+    args: Vec<Sequence<N>>,
+) -> Result<Sequence<N>, Error> {
+    if args.len() != f.arity() {
+        return Err(arity_mismatch());
+    }
+    match f {
+        Function::Inline {
+            callable, closure, ..
+        } => {
+            let mut newctxt = ctxt.clone();
+            newctxt.vars = closure.clone();
+            newctxt.package = callable.package.clone();
+            newctxt.tunnel_params.clear();
+            if let FormalParameters::Positional(v) = &callable.parameters {
+                v.iter().zip(args).try_for_each(|((qn, st), a)| {
+                    newctxt.var_push(variable_name(qn), callable.argument(a, st)?);
+                    Ok::<(), Error>(())
+                })?;
+            }
+            callable.result(newctxt.dispatch(stctxt, &callable.body)?)
+        }
+        Function::Partial(g, bound) => {
+            let mut a = args.into_iter();
+            let all = bound
+                .iter()
+                .map(|b| b.clone().unwrap_or_else(|| a.next().unwrap()))
+                .collect();
+            call_function(ctxt, stctxt, g, all)
+        }
+    }
+}
+
+/// Evaluate a dynamic function call.
+pub(crate) fn dynamic_call<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    f: &Transform<N>,
+    a: &[Transform<N>],
+) -> Result<Sequence<N>, Error> {
+    let i = single_function(ctxt, stctxt, f)?;
+    let args = a
+        .iter()
+        .map(|t| ctxt.dispatch(stctxt, t))
+        .collect::<Result<Vec<Sequence<N>>, Error>>()?;
+    call_item(ctxt, stctxt, &i, args)
+}
+
+/// Partially apply a function. The supplied arguments are evaluated now,
+/// the placeholders become the parameters of the resulting function item.
+pub(crate) fn partial_application<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    f: &Transform<N>,
+    a: &[Option<Transform<N>>],
+) -> Result<Sequence<N>, Error> {
+    let g = match single_function(ctxt, stctxt, f)? {
+        Item::Function(g) => g,
+        i => {
+            // Maps and arrays are functions of one argument
+            Rc::new(Function::Inline {
+                name: None,
+                callable: Callable::new(
+                    Transform::DynamicCall(
+                        Box::new(Transform::Literal(i)),
+                        vec![Transform::VariableReference(
+                            String::from("#1"),
+                            Rc::new(NamespaceMap::new()),
+                        )],
+                    ),
+                    FormalParameters::Positional(vec![(
+                        QualifiedName::new(None, None, "#1"),
+                        None,
+                    )]),
+                ),
+                closure: HashMap::new(),
+            })
+        }
+    };
+    if a.len() != g.arity() {
+        return Err(arity_mismatch());
+    }
+    let bound = a
+        .iter()
+        .map(|t| t.as_ref().map(|u| ctxt.dispatch(stctxt, u)).transpose())
+        .collect::<Result<Vec<Option<Sequence<N>>>, Error>>()?;
+    Ok(vec![Item::Function(Rc::new(Function::Partial(g, bound)))])
+}
@@ -1,8 +1,9 @@
 //! These functions construct nodes, possibly destined for the result document.
 
 use crate::item::{Node, NodeType, Sequence, SequenceTrait};
+use crate::output::OutputDefinition;
 use crate::qname::QualifiedName;
-use crate::transform::context::{Context, StaticContext};
+use crate::transform::context::{Context, ContextBuilder, ResultDocument, StaticContext};
 use crate::transform::Transform;
 use crate::value::Value;
 use crate::xdmerror::{Error, ErrorKind};
@@ -119,7 +120,7 @@ pub(crate) fn element<
 
 /// Creates a new text node.
 /// The transform is evaluated to create the value of the text node.
-/// Special characters are escaped, unless disabled.
+/// If output escaping is disabled then the text node is not escaped when it is serialised.
 pub(crate) fn literal_text<
     N: Node,
     F: FnMut(&str) -> Result<(), Error>,
@@ -138,21 +139,12 @@ pub(crate) fn literal_text<
         ));
     }
 
-    let v = ctxt.dispatch(stctxt, t)?.to_string();
+    let rd = ctxt.rd.clone().unwrap();
+    let v = Rc::new(Value::from(ctxt.dispatch(stctxt, t)?.to_string()));
     if *b {
-        Ok(vec![Item::Node(
-            ctxt.rd.clone().unwrap().new_text(Rc::new(Value::from(v)))?,
-        )])
+        Ok(vec![Item::Node(rd.new_unescaped_text(v)?)])
     } else {
-        Ok(vec![Item::Node(
-            ctxt.rd.clone().unwrap().new_text(Rc::new(Value::from(
-                v.replace('&', "&amp;")
-                    .replace('>', "&gt;")
-                    .replace('<', "&lt;")
-                    .replace('\'', "&apos;")
-                    .replace('\"', "&quot;"),
-            )))?,
-        )])
+        Ok(vec![Item::Node(rd.new_text(v)?)])
     }
 }
 
@@ -184,6 +176,116 @@ pub(crate) fn literal_attribute<
     Ok(vec![Item::Node(a)])
 }
 
+/// Creates a singleton sequence with a new document node, i.e. a temporary tree.
+/// The transform is evaluated to create the content of the document.
+/// Nodes constructed by the transform are owned by the new document.
+pub(crate) fn document_node<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    c: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let mut d = N::new_document();
+    let content = ContextBuilder::from(ctxt)
+        .result_document(d.clone())
+        .build()
+        .dispatch(stctxt, c)?;
+    add_document_content(&mut d, content)?;
+    Ok(vec![Item::Node(d)])
+}
+
+// Add the content of a document node.
+// Adjacent atomic values are joined, with a space separator, to make a single text node.
+fn add_document_content<N: Node>(d: &mut N, content: Sequence<N>) -> Result<(), Error> {
+    let mut text: Option<String> = None;
+    for i in content {
+        match i {
+            Item::Node(n) => {
+                if let Some(t) = text.take() {
+                    d.push(d.new_text(Rc::new(Value::from(t)))?)?
+                }
+                match n.node_type() {
+                    NodeType::Document => {
+                        n.child_iter().try_for_each(|c| d.push(c.deep_copy()?))?
+                    }
+                    NodeType::Attribute | NodeType::Namespace => {
+                        return Err(Error::new_with_code(
+                            ErrorKind::TypeError,
+                            "a document node cannot have an attribute or namespace node",
+                            Some(QualifiedName::new(None, None, "XTDE0420")),
+                        ))
+                    }
+                    _ => d.push(n.deep_copy()?)?,
+                }
+            }
+            _ => {
+                let t = text.get_or_insert_with(String::new);
+                if !t.is_empty() {
+                    t.push(' ')
+                }
+                t.push_str(i.to_string().as_str())
+            }
+        }
+    }
+    if let Some(t) = text {
+        d.push(d.new_text(Rc::new(Value::from(t)))?)?
+    }
+    Ok(())
+}
+
+/// Creates a secondary result document, which is added to the result documents of the transformation.
+/// If the href is empty then the content is added to the primary result instead.
+/// The transform is evaluated to create the content of the document.
+pub(crate) fn result_document<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    href: &Transform<N>,
+    od: &OutputDefinition,
+    c: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let h = ctxt.dispatch(stctxt, href)?.to_string();
+    if h.is_empty() {
+        return ctxt.dispatch(stctxt, c);
+    }
+    let href = match &ctxt.base_url {
+        Some(b) => b.join(h.as_str()).map_or(h, |u| u.to_string()),
+        None => h,
+    };
+    if ctxt
+        .result_documents
+        .borrow()
+        .iter()
+        .any(|r| r.href == href)
+    {
+        return Err(Error::new_with_code(
+            ErrorKind::DynamicAbsent,
+            format!("result document \"{}\" has already been created", href),
+            Some(QualifiedName::new(None, None, "XTDE1490")),
+        ));
+    }
+    let mut d = N::new_document();
+    let content = ContextBuilder::from(ctxt)
+        .result_document(d.clone())
+        .build()
+        .dispatch(stctxt, c)?;
+    add_document_content(&mut d, content)?;
+    ctxt.result_documents.borrow_mut().push(ResultDocument {
+        href,
+        document: d,
+        output: od.clone(),
+    });
+    Ok(vec![])
+}
+
 /// Creates a singleton sequence with a new comment node.
 /// The transform is evaluated to create the value of the comment.
 pub(crate) fn literal_comment<
@@ -10,31 +10,48 @@ A [Context] is used to evaluate a [Transform]. The evaluate method matches the c
 
  */
 
-use crate::item::{Node, Sequence};
+use crate::item::{Node, NodeType, Sequence};
 use crate::output::OutputDefinition;
 #[allow(unused_imports)]
 use crate::pattern::Pattern;
 use crate::qname::QualifiedName;
+use crate::transform::accumulators::{accumulator_value, Accumulator, AccumulatorValues};
+use crate::transform::arrays::*;
 use crate::transform::booleans::*;
-use crate::transform::callable::{invoke, Callable};
+use crate::transform::callable::{
+    dynamic_call, function_item, invoke, partial_application, tunnel, tunnel_parameter, Callable,
+};
 use crate::transform::construct::*;
 use crate::transform::controlflow::*;
 use crate::transform::datetime::*;
 use crate::transform::functions::*;
 use crate::transform::grouping::*;
+use crate::transform::hof::*;
 use crate::transform::keys::{key, populate_key_values};
 use crate::transform::logic::*;
+use crate::transform::maps::*;
 use crate::transform::misc::*;
 use crate::transform::navigate::*;
 use crate::transform::numbers::*;
+use crate::transform::sequences::*;
 use crate::transform::strings::*;
-use crate::transform::template::{apply_imports, apply_templates, next_match, Template};
-use crate::transform::variables::{declare_variable, reference_variable};
+use crate::transform::template::{
+    apply_imports, apply_templates, next_match, pass_parameters, template_parameter,
+    with_parameters, Mode, ModeKey, OnNoMatch, Template, Typed,
+};
+use crate::transform::types::{cast_as, castable_as, convert, instance_of, quantified, treat_as};
+use crate::transform::variables::{
+    declare_variable, missing_parameter, reference_variable, GlobalParameter, GlobalValues,
+};
 use crate::transform::Transform;
 use crate::xdmerror::Error;
 use crate::{ErrorKind, Item, SequenceTrait, Value};
+use chrono::{DateTime, FixedOffset, Local, Offset, Utc};
+use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::hash::Hash;
 use std::rc::Rc;
 use url::Url;
 
@@ -55,22 +72,66 @@ pub struct Context<N: Node> {
     // Built-in templates have no priority and no document order
     pub(crate) templates: Vec<Rc<Template<N>>>,
     pub(crate) current_templates: Vec<Rc<Template<N>>>,
+    // Mode declarations. Each package has its own unnamed mode.
+    pub(crate) modes: HashMap<ModeKey, Mode>,
+    // The mode used to begin the transformation
+    pub(crate) default_mode: Option<Rc<QualifiedName>>,
+    // The mode of the template rule being evaluated. See XSLT 6.6.1.
+    pub(crate) current_mode: Option<Rc<QualifiedName>>,
+    // The package whose code is being evaluated. The unnamed mode, and the components that are hidden,
+    // are those of this package. See XSLT 3.5.
+    pub(crate) package: Option<Rc<str>>,
+    // The components of used packages that cannot be referenced by the code of each package. See XSLT 3.5.3.
+    pub(crate) hidden: Rc<HashMap<Option<Rc<str>>, Hidden>>,
+    // The state of the innermost xsl:iterate. This is shared by all contexts derived from the one used to evaluate the body.
+    pub(crate) iteration: Rc<RefCell<Iteration<N>>>,
+    // The error object given to the error function, for use by xsl:catch. This is shared by all contexts derived from this one.
+    pub(crate) error_object: Rc<RefCell<Option<Sequence<N>>>>,
     // Named templates and functions
-    pub(crate) callables: HashMap<QualifiedName, Callable<N>>,
+    pub(crate) callables: HashMap<ComponentKey<QualifiedName>, Callable<N>>,
     // Variables, with scoping
     pub(crate) vars: HashMap<String, Vec<Sequence<N>>>,
+    // Tunnel parameters are passed on by template invocations. See XSLT 10.1.3.
+    pub(crate) tunnel_params: HashMap<String, Sequence<N>>,
+    // The non-tunnel parameters supplied to the template rule being evaluated.
+    pub(crate) template_params: HashMap<String, Sequence<N>>,
+    // Global variable declarations, and the package that declares each of them.
+    // Their values are shared by all contexts derived from this one.
+    pub(crate) globals: HashMap<ComponentKey<String>, (Transform<N>, Option<Rc<str>>)>,
+    pub(crate) global_values: Rc<RefCell<GlobalValues<N>>>,
+    // Stylesheet parameters. These are global variables whose values are supplied by the application.
+    pub(crate) global_params: HashMap<ComponentKey<String>, GlobalParameter>,
+    pub(crate) params: HashMap<QualifiedName, Sequence<N>>,
+    // Secondary result documents. These are shared by all contexts derived from this one.
+    pub(crate) result_documents: Rc<RefCell<Vec<ResultDocument<N>>>>,
     // Grouping
     pub(crate) current_grouping_key: Option<Rc<Value>>,
     pub(crate) current_group: Sequence<N>,
+    // Merging. The current merge group is the items from each merge source. Outside of a merge action there is no merge key.
+    pub(crate) current_merge_group: Vec<(Option<String>, Sequence<N>)>,
+    pub(crate) current_merge_key: Option<Sequence<N>>,
+    // The captured groups of the current matching substring in xsl:analyze-string. Group 0 is the whole substring.
+    pub(crate) regex_groups: Vec<String>,
+    // Keys, accumulators and decimal formats are not components, so each package has its own,
+    // and the code of a package uses only those of that package. They are identified by the package that declares them as well as by name.
     // Keys
     // The declaration of a key. Keys are named, and each key can have multiple definitions.
     // Each definition is the pattern that matches nodes and the expression that computes the key value.
-    pub(crate) keys: HashMap<String, Vec<(Pattern<N>, Transform<N>)>>,
+    pub(crate) keys: HashMap<(Option<Rc<str>>, String), Vec<(Pattern<N>, Transform<N>)>>,
     // The calculated values of keys.
-    pub(crate) key_values: HashMap<String, HashMap<String, Vec<N>>>,
+    pub(crate) key_values: HashMap<(Option<Rc<str>>, String), HashMap<String, Vec<N>>>,
+    // Accumulator declarations, and their values. The values are shared by all contexts derived from this one.
+    pub(crate) accumulators: HashMap<(Option<Rc<str>>, QualifiedName), Accumulator<N>>,
+    pub(crate) accumulator_values: Rc<RefCell<AccumulatorValues<N>>>,
+    // Decimal formats for format-number. The unnamed decimal format has no name.
+    pub(crate) decimal_formats: HashMap<(Option<Rc<str>>, Option<QualifiedName>), DecimalFormat>,
     // Output control
     pub(crate) od: OutputDefinition,
     pub(crate) base_url: Option<Url>,
+    // Date and time. If the implicit timezone is not set then the local timezone is used.
+    // If the current dateTime is not set then the system clock is used.
+    pub(crate) implicit_timezone: Option<FixedOffset>,
+    pub(crate) current_date_time: Option<DateTime<FixedOffset>>,
     // Namespace resolution. If any transforms contain a QName that needs to be resolved to an EQName,
     // then these prefix -> URI mappings are used. These are usually derived from the stylesheet document.
     //pub(crate) namespaces: Vec<HashMap<Option<String>, String>>,
@@ -86,14 +147,36 @@ impl<N: Node> Context<N> {
             rd: None,
             templates: vec![],
             current_templates: vec![],
+            modes: HashMap::new(),
+            default_mode: None,
+            current_mode: None,
+            package: None,
+            hidden: Rc::new(HashMap::new()),
+            iteration: Rc::new(RefCell::new(Iteration::Continue)),
+            error_object: Rc::new(RefCell::new(None)),
             callables: HashMap::new(),
             vars: HashMap::new(),
+            tunnel_params: HashMap::new(),
+            template_params: HashMap::new(),
+            globals: HashMap::new(),
+            global_values: Rc::new(RefCell::new(GlobalValues::new())),
+            global_params: HashMap::new(),
+            params: HashMap::new(),
+            result_documents: Rc::new(RefCell::new(vec![])),
             current_grouping_key: None,
             current_group: Sequence::new(),
+            current_merge_group: vec![],
+            current_merge_key: None,
+            regex_groups: vec![],
             keys: HashMap::new(),
             key_values: HashMap::new(),
+            accumulators: HashMap::new(),
+            accumulator_values: Rc::new(RefCell::new(HashMap::new())),
+            decimal_formats: HashMap::new(),
             od: OutputDefinition::new(),
             base_url: None,
+            implicit_timezone: None,
+            current_date_time: None,
         }
     }
     /// Sets the context item.
@@ -109,8 +192,50 @@ impl<N: Node> Context<N> {
     pub fn result_document(&mut self, rd: N) {
         self.rd = Some(rd);
     }
-    /// Declare a key
+    /// The secondary result documents created by the most recent evaluation of the transformation.
+    /// The application is responsible for serialising, and storing, each document.
+    pub fn result_documents(&self) -> Vec<ResultDocument<N>> {
+        self.result_documents.borrow().clone()
+    }
+    /// How the primary result document is to be serialised, as given by the stylesheet's xsl:output declaration.
+    pub fn output_definition(&self) -> &OutputDefinition {
+        &self.od
+    }
+    /// Find a named decimal format by its expanded name.
+    /// Only the decimal formats of the package whose code is being evaluated are found.
+    pub fn decimal_format(&self, name: &QualifiedName) -> Option<DecimalFormat> {
+        self.decimal_formats
+            .get(&(self.package.clone(), Some(name.clone())))
+            .cloned()
+    }
+    // The unnamed decimal format of the package whose code is being evaluated.
+    pub(crate) fn default_decimal_format(&self) -> DecimalFormat {
+        self.decimal_formats
+            .get(&(self.package.clone(), None))
+            .cloned()
+            .unwrap_or_default()
+    }
+    /// Supply the value of a stylesheet parameter.
+    /// The value is converted to the type given by the parameter's declaration when the parameter is evaluated.
+    pub fn parameter(&mut self, qn: QualifiedName, v: Sequence<N>) {
+        self.params.insert(qn, v);
+    }
+    /// The implicit timezone. This is the timezone of date and time values that do not have an explicit timezone.
+    /// Unless it has been set, this is the local timezone.
+    pub fn implicit_timezone(&self) -> FixedOffset {
+        self.implicit_timezone
+            .unwrap_or_else(|| Local::now().offset().fix())
+    }
+    /// The current dateTime, in the implicit timezone.
+    /// Unless it has been set, this is given by the system clock.
+    pub fn current_date_time(&self) -> DateTime<FixedOffset> {
+        self.current_date_time
+            .unwrap_or_else(|| Utc::now().fixed_offset())
+            .with_timezone(&self.implicit_timezone())
+    }
+    /// Declare a key. The key belongs to the package whose code is evaluated in the context.
     pub fn declare_key(&mut self, name: String, m: Pattern<N>, u: Transform<N>) {
+        let name = (self.package.clone(), name);
         if let Some(v) = self.keys.get_mut(&name) {
             v.push((m, u))
         } else {
@@ -136,7 +261,7 @@ impl<N: Node> Context<N> {
         populate_key_values(self, stctxt, sd)
     }
     pub fn dump_key_values(&self) {
-        self.key_values.iter().for_each(|(k, v)| {
+        self.key_values.iter().for_each(|((_, k), v)| {
             println!("key \"{}\":", k);
             v.iter()
                 .for_each(|(kk, vv)| println!("\tvalue \"{}\" {} nodes", kk, vv.len()))
@@ -170,9 +295,51 @@ impl<N: Node> Context<N> {
         })
     }
 
+    // The components of used packages that cannot be referenced by the code being evaluated.
+    pub(crate) fn hidden(&self) -> Option<&Hidden> {
+        self.hidden.get(&self.package)
+    }
+    // The named template or function that the code being evaluated references by the given name.
+    // None if it is hidden from that code.
+    pub(crate) fn callable_key(&self, qn: &QualifiedName) -> Option<ComponentKey<QualifiedName>> {
+        component_key(
+            qn,
+            self.hidden().map(|h| (&h.private_callables, &h.callables)),
+        )
+    }
+    // The global variable that the code being evaluated references by the given name.
+    // None if it is hidden from that code.
+    pub(crate) fn variable_key(&self, name: &String) -> Option<ComponentKey<String>> {
+        component_key(
+            name,
+            self.hidden().map(|h| (&h.private_variables, &h.variables)),
+        )
+    }
+    // The mode that the code of the given package references by the given name.
+    // None if it is hidden from that code.
+    fn mode_key(
+        &self,
+        m: &Option<Rc<QualifiedName>>,
+        package: &Option<Rc<str>>,
+    ) -> Option<ModeKey> {
+        match ModeKey::new(m, package) {
+            ModeKey::Named(n) => component_key(
+                &n,
+                self.hidden
+                    .get(package)
+                    .map(|h| (&h.private_modes, &h.modes)),
+            )
+            .map(|k| match k {
+                ComponentKey::Visible(n) => ModeKey::Named(n),
+                ComponentKey::Private(p, n) => ModeKey::Private(p, n),
+            }),
+            k => Some(k),
+        }
+    }
+
     /// Callable components: named templates and user-defined functions
     pub fn callable_push(&mut self, qn: QualifiedName, c: Callable<N>) {
-        self.callables.insert(qn, c);
+        self.callables.insert(ComponentKey::Visible(qn), c);
     }
 
     /// Returns the Base URL.
@@ -232,6 +399,20 @@ impl<N: Node> Context<N> {
         &self,
         stctxt: &mut StaticContext<N, F, G, H>,
     ) -> Result<Sequence<N>, Error> {
+        // Every required stylesheet parameter must have a value
+        if let Some(p) = self
+            .global_params
+            .values()
+            .find(|p| p.required && !self.params.contains_key(&p.name))
+        {
+            return Err(missing_parameter(&p.name));
+        }
+        // This is a new transformation, so global variables must be evaluated again
+        self.global_values
+            .borrow_mut()
+            .reset(self.cur.get(self.i).cloned());
+        self.result_documents.borrow_mut().clear();
+        self.accumulator_values.borrow_mut().clear();
         if self.cur.is_empty() {
             Ok(Sequence::new())
         } else {
@@ -246,13 +427,20 @@ impl<N: Node> Context<N> {
                     // There may be 0, 1, or more matching templates.
                     // If there are more than one with the same priority and import level,
                     // then take the one with the higher document order.
-                    let templates = self.find_templates(stctxt, i, &None)?;
+                    let templates = self.find_templates(stctxt, i, &self.default_mode)?;
+                    let mut run = |t: &Template<N>| {
+                        ContextBuilder::from(self)
+                            .current_mode(self.default_mode.clone())
+                            .package(t.package.clone())
+                            .build()
+                            .dispatch(stctxt, &t.body)
+                    };
                     match templates.len() {
                         0 => Err(Error::new(
                             ErrorKind::DynamicAbsent,
                             String::from("no matching template"),
                         )),
-                        1 => self.dispatch(stctxt, &templates[0].body),
+                        1 => run(&templates[0]),
                         _ => {
                             if templates[0].priority == templates[1].priority
                                 && templates[0].import.len() == templates[1].import.len()
@@ -270,9 +458,9 @@ impl<N: Node> Context<N> {
                                         b.document_order.map_or(Ordering::Less, |u| v.cmp(&u))
                                     })
                                 });
-                                self.dispatch(stctxt, &candidates.last().unwrap().body)
+                                run(candidates.last().unwrap())
                             } else {
-                                self.dispatch(stctxt, &templates[0].body)
+                                run(&templates[0])
                             }
                         }
                     }
@@ -292,21 +480,80 @@ impl<N: Node> Context<N> {
         i: &Item<N>,
         m: &Option<Rc<QualifiedName>>,
     ) -> Result<Vec<Rc<Template<N>>>, Error> {
-        let mut candidates =
-            self.templates
-                .iter()
-                .filter(|t| t.mode == *m)
-                .try_fold(vec![], |mut cand, t| {
-                    let e = t.pattern.matches(self, stctxt, i);
-                    if e {
-                        cand.push(t.clone())
-                    }
-                    Ok(cand)
-                })?;
+        let key = self.mode_key(m, &self.package).ok_or_else(|| {
+            hidden_component(
+                "mode",
+                m.as_ref().map_or(String::new(), |n| n.to_string()),
+                "XTSE3050",
+            )
+        })?;
+        let mode = self.modes.get(&key).cloned().unwrap_or_default();
+        if mode.typed != Typed::No && mode.typed != Typed::Unspecified {
+            if let Item::Node(n) = i {
+                if matches!(n.node_type(), NodeType::Element | NodeType::Attribute) {
+                    return Err(Error::new_with_code(
+                        ErrorKind::TypeError,
+                        format!("untyped node processed in typed mode \"{:?}\"", m),
+                        Some(QualifiedName::new(None, None, "XTTE3100")),
+                    ));
+                }
+            }
+        }
+        let mut candidates = self
+            .templates
+            .iter()
+            .filter(|t| self.mode_key(&t.mode, &t.package).as_ref() == Some(&key))
+            .try_fold(vec![], |mut cand, t| {
+                let e = t.pattern.matches(self, stctxt, i);
+                if e {
+                    cand.push(t.clone())
+                }
+                Ok(cand)
+            })?;
+        // Find the template(s) with the lowest priority.
+        candidates.sort_unstable_by(|a, b| (*a).cmp(b));
+        // Built-in templates have no priority, so they sort after the stylesheet's template rules
+        if candidates.first().map_or(true, |t| t.priority.is_none()) {
+            if mode.on_no_match == OnNoMatch::Fail {
+                return Err(Error::new_with_code(
+                    ErrorKind::DynamicAbsent,
+                    format!("no matching template for item {:?} in mode \"{:?}\"", i, m),
+                    Some(QualifiedName::new(None, None, "XTDE0555")),
+                ));
+            }
+            if mode.warning_on_no_match {
+                if let Some(f) = &mut stctxt.message {
+                    f(
+                        format!("no matching template for item {:?} in mode \"{:?}\"", i, m)
+                            .as_str(),
+                    )?
+                }
+            }
+        } else if candidates.len() > 1
+            && candidates[1].priority == candidates[0].priority
+            && candidates[1].import.len() == candidates[0].import.len()
+        {
+            if mode.on_multiple_match_fail {
+                return Err(Error::new_with_code(
+                    ErrorKind::DynamicAbsent,
+                    format!(
+                        "more than one template matches item {:?} in mode \"{:?}\"",
+                        i, m
+                    ),
+                    Some(QualifiedName::new(None, None, "XTDE0540")),
+                ));
+            }
+            if mode.warning_on_multiple_match {
+                if let Some(f) = &mut stctxt.message {
+                    f(format!(
+                        "more than one template matches item {:?} in mode \"{:?}\"",
+                        i, m
+                    )
+                    .as_str())?
+                }
+            }
+        }
         if !candidates.is_empty() {
-            // Find the template(s) with the lowest priority.
-
-            candidates.sort_unstable_by(|a, b| (*a).cmp(b));
             Ok(candidates)
         } else {
             Err(Error::new(
@@ -372,6 +619,8 @@ impl<N: Node> Context<N> {
             Transform::LiteralText(t, b) => literal_text(self, stctxt, t, b),
             Transform::LiteralAttribute(qn, t) => literal_attribute(self, stctxt, qn, t),
             Transform::LiteralComment(t) => literal_comment(self, stctxt, t),
+            Transform::DocumentNode(t) => document_node(self, stctxt, t),
+            Transform::ResultDocument(h, od, t) => result_document(self, stctxt, h, od, t),
             Transform::LiteralProcessingInstruction(n, t) => {
                 literal_processing_instruction(self, stctxt, n, t)
             }
@@ -384,19 +633,38 @@ impl<N: Node> Context<N> {
             Transform::Union(b) => union(self, stctxt, b),
             Transform::GeneralComparison(o, l, r) => general_comparison(self, stctxt, o, l, r),
             Transform::ValueComparison(o, l, r) => value_comparison(self, stctxt, o, l, r),
+            Transform::NodeComparison(o, l, r) => node_comparison(self, stctxt, o, l, r),
             Transform::Concat(v) => tr_concat(self, stctxt, v),
             Transform::Range(s, e) => tr_range(self, stctxt, s, e),
             Transform::Arithmetic(v) => arithmetic(self, stctxt, v),
             Transform::Loop(v, b) => tr_loop(self, stctxt, v, b),
             Transform::Switch(c, o) => switch(self, stctxt, c, o),
+            Transform::Quantified(q, v, t) => quantified(self, stctxt, q, v, t),
+            Transform::InstanceOf(s, t) => instance_of(self, stctxt, s, t),
+            Transform::Convert(s, t, c, u) => convert(self, stctxt, s, t, c, *u),
+            Transform::Treat(s, t) => treat_as(self, stctxt, s, t),
+            Transform::Cast(s, t, e) => cast_as(self, stctxt, s, t, *e),
+            Transform::Castable(s, t, e) => castable_as(self, stctxt, s, t, *e),
             Transform::ForEach(g, s, b, o) => for_each(self, stctxt, g, s, b, o),
-            Transform::ApplyTemplates(s, m, o) => apply_templates(self, stctxt, s, m, o),
+            Transform::Merge(s, a) => merge(self, stctxt, s, a),
+            Transform::Iterate(s, p, b, c) => iterate(self, stctxt, s, p, b, c),
+            Transform::Try(b, c, r) => try_catch(self, stctxt, b, c, *r),
+            Transform::RaiseError(c, d, o) => error(self, stctxt, c, d, o),
+            Transform::QName(u, q) => qname(self, stctxt, u, q),
+            Transform::Break(v) => break_iteration(self, stctxt, v),
+            Transform::NextIteration(p) => next_iteration(self, stctxt, p),
+            Transform::ApplyTemplates(s, m, o) => {
+                apply_templates(self, stctxt, s, m, o, &HashMap::new())
+            }
+            Transform::ApplyTemplatesCurrentMode(s, o) => {
+                apply_templates(self, stctxt, s, &self.current_mode, o, &HashMap::new())
+            }
             Transform::ApplyImports => apply_imports(self, stctxt),
             Transform::NextMatch => next_match(self, stctxt),
             Transform::VariableDeclaration(n, v, f, _) => {
                 declare_variable(self, stctxt, n.clone(), v, f)
             }
-            Transform::VariableReference(n, _) => reference_variable(self, n),
+            Transform::VariableReference(n, _) => reference_variable(self, stctxt, n),
             Transform::Position => position(self),
             Transform::Last => last(self),
             Transform::Count(s) => tr_count(self, stctxt, s),
@@ -411,6 +679,48 @@ impl<N: Node> Context<N> {
             Transform::SubstringAfter(s, t) => substring_after(self, stctxt, s, t),
             Transform::NormalizeSpace(s) => normalize_space(self, stctxt, s),
             Transform::Translate(s, m, t) => translate(self, stctxt, s, m, t),
+            Transform::UpperCase(s) => upper_case(self, stctxt, s),
+            Transform::LowerCase(s) => lower_case(self, stctxt, s),
+            Transform::StringJoin(s, t) => string_join(self, stctxt, s, t),
+            Transform::StringLength(s) => string_length(self, stctxt, s),
+            Transform::Tokenize(s, p, f) => tokenize(self, stctxt, s, p, f),
+            Transform::Matches(s, p, f) => matches(self, stctxt, s, p, f),
+            Transform::Replace(s, p, r, f) => replace(self, stctxt, s, p, r, f),
+            Transform::AnalyzeString(s, p, f) => analyze_string(self, stctxt, s, p, f),
+            Transform::AnalyzeStringInstruction(s, p, f, m, n) => {
+                analyze_string_instruction(self, stctxt, s, p, f, m, n)
+            }
+            Transform::RegexGroup(n) => regex_group(self, stctxt, n),
+            Transform::CodepointsToString(s) => codepoints_to_string(self, stctxt, s),
+            Transform::StringToCodepoints(s) => string_to_codepoints(self, stctxt, s),
+            Transform::Compare(s, t, c) => compare(self, stctxt, s, t, c),
+            Transform::NormalizeUnicode(s, f) => normalize_unicode(self, stctxt, s, f),
+            Transform::EncodeForUri(s) => encode_for_uri(self, stctxt, s),
+            Transform::EscapeHtmlUri(s) => escape_html_uri(self, stctxt, s),
+            Transform::IriToUri(s) => iri_to_uri(self, stctxt, s),
+            Transform::FnEmpty(s) => fn_empty(self, stctxt, s),
+            Transform::Exists(s) => exists(self, stctxt, s),
+            Transform::DistinctValues(s, c) => distinct_values(self, stctxt, s, c),
+            Transform::IndexOf(s, t, c) => index_of(self, stctxt, s, t, c),
+            Transform::InsertBefore(s, p, i) => insert_before(self, stctxt, s, p, i),
+            Transform::Remove(s, p) => remove(self, stctxt, s, p),
+            Transform::Reverse(s) => reverse(self, stctxt, s),
+            Transform::Subsequence(s, p, l) => subsequence(self, stctxt, s, p, l),
+            Transform::Head(s) => head(self, stctxt, s),
+            Transform::Tail(s) => tail(self, stctxt, s),
+            Transform::ZeroOrOne(s) => zero_or_one(self, stctxt, s),
+            Transform::OneOrMore(s) => one_or_more(self, stctxt, s),
+            Transform::ExactlyOne(s) => exactly_one(self, stctxt, s),
+            Transform::DeepEqual(s, t, c) => deep_equal(self, stctxt, s, t, c),
+            Transform::Innermost(s) => innermost(self, stctxt, s),
+            Transform::Outermost(s) => outermost(self, stctxt, s),
+            Transform::FnRoot(s) => fn_root(self, stctxt, s),
+            Transform::Path(s) => path(self, stctxt, s),
+            Transform::HasChildren(s) => has_children(self, stctxt, s),
+            Transform::NamespaceUri(s) => namespace_uri(self, stctxt, s),
+            Transform::Lang(l, s) => lang(self, stctxt, l, s),
+            Transform::Id(s, t) => id(self, stctxt, s, t),
+            Transform::Idref(s, t) => idref(self, stctxt, s, t),
             Transform::GenerateId(s) => generate_id(self, stctxt, s),
             Transform::Boolean(b) => boolean(self, stctxt, b),
             Transform::Not(b) => not(self, stctxt, b),
@@ -426,7 +736,11 @@ impl<N: Node> Context<N> {
             Transform::Round(n, p) => round(self, stctxt, n, p),
             Transform::CurrentGroup => current_group(self),
             Transform::CurrentGroupingKey => current_grouping_key(self),
+            Transform::CurrentMergeGroup(n) => current_merge_group(self, stctxt, n),
+            Transform::CurrentMergeKey => current_merge_key(self),
             Transform::CurrentDateTime => current_date_time(self),
+            Transform::DateTimeComponent(c, t, v) => date_time_component(self, stctxt, c, t, v),
+            Transform::AdjustToTimezone(t, v, z) => adjust_to_timezone(self, stctxt, t, v, z),
             Transform::CurrentDate => current_date(self),
             Transform::CurrentTime => current_time(self),
             Transform::FormatDateTime(t, p, l, c, q) => {
@@ -434,8 +748,9 @@ impl<N: Node> Context<N> {
             }
             Transform::FormatDate(t, p, l, c, q) => format_date(self, stctxt, t, p, l, c, q),
             Transform::FormatTime(t, p, l, c, q) => format_time(self, stctxt, t, p, l, c, q),
-            Transform::FormatNumber(v, p, d) => format_number(self, stctxt, v, p, d),
+            Transform::FormatNumber(v, p, d, ns) => format_number(self, stctxt, v, p, d, ns),
             Transform::FormatInteger(i, s) => format_integer(self, stctxt, i, s),
+            Transform::NumberToString(i, s, nf) => number_to_string(self, stctxt, i, s, nf),
             Transform::GenerateIntegers(start_at, select, n) => {
                 generate_integers(self, stctxt, start_at, select, n)
             }
@@ -443,7 +758,53 @@ impl<N: Node> Context<N> {
             Transform::SystemProperty(p, ns) => system_property(self, stctxt, p, ns),
             Transform::AvailableSystemProperties => available_system_properties(),
             Transform::Document(uris, base) => document(self, stctxt, uris, base),
+            Transform::MapConstructor(e) => map_constructor(self, stctxt, e),
+            Transform::SquareArrayConstructor(m) => square_array_constructor(self, stctxt, m),
+            Transform::CurlyArrayConstructor(m) => curly_array_constructor(self, stctxt, m),
+            Transform::Lookup(e, k) => lookup(self, stctxt, e, k),
+            Transform::InlineFunction(n, p, b) => function_item(self, n, p, b),
+            Transform::DynamicCall(g, a) => dynamic_call(self, stctxt, g, a),
+            Transform::PartialApplication(g, a) => partial_application(self, stctxt, g, a),
+            Transform::FnForEach(s, g) => fn_for_each(self, stctxt, s, g),
+            Transform::FnFilter(s, g) => fn_filter(self, stctxt, s, g),
+            Transform::FoldLeft(s, z, g) => fold_left(self, stctxt, s, z, g),
+            Transform::FoldRight(s, z, g) => fold_right(self, stctxt, s, z, g),
+            Transform::FnSort(s, c, k) => fn_sort(self, stctxt, s, c, k),
+            Transform::MapMerge(m, o) => map_merge(self, stctxt, m, o),
+            Transform::MapSize(m) => map_size(self, stctxt, m),
+            Transform::MapKeys(m) => map_keys(self, stctxt, m),
+            Transform::MapContains(m, k) => map_contains(self, stctxt, m, k),
+            Transform::MapGet(m, k) => map_get(self, stctxt, m, k),
+            Transform::MapFind(i, k) => map_find(self, stctxt, i, k),
+            Transform::MapPut(m, k, v) => map_put(self, stctxt, m, k, v),
+            Transform::MapEntry(k, v) => map_entry(self, stctxt, k, v),
+            Transform::MapRemove(m, k) => map_remove(self, stctxt, m, k),
+            Transform::MapForEach(m, g) => map_for_each(self, stctxt, m, g),
+            Transform::ArraySize(a) => array_size(self, stctxt, a),
+            Transform::ArrayGet(a, p) => array_get(self, stctxt, a, p),
+            Transform::ArrayPut(a, p, m) => array_put(self, stctxt, a, p, m),
+            Transform::ArrayAppend(a, m) => array_append(self, stctxt, a, m),
+            Transform::ArraySubarray(a, s, l) => array_subarray(self, stctxt, a, s, l),
+            Transform::ArrayRemove(a, p) => array_remove(self, stctxt, a, p),
+            Transform::ArrayInsertBefore(a, p, m) => array_insert_before(self, stctxt, a, p, m),
+            Transform::ArrayHead(a) => array_head(self, stctxt, a),
+            Transform::ArrayTail(a) => array_tail(self, stctxt, a),
+            Transform::ArrayReverse(a) => array_reverse(self, stctxt, a),
+            Transform::ArrayJoin(a) => array_join(self, stctxt, a),
+            Transform::ArrayFlatten(a) => array_flatten(self, stctxt, a),
+            Transform::ArrayForEach(a, g) => array_for_each(self, stctxt, a, g),
+            Transform::ArrayFilter(a, g) => array_filter(self, stctxt, a, g),
+            Transform::ArrayFoldLeft(a, z, g) => array_fold_left(self, stctxt, a, z, g),
+            Transform::ArrayFoldRight(a, z, g) => array_fold_right(self, stctxt, a, z, g),
+            Transform::ArraySort(a, c, k) => array_sort(self, stctxt, a, c, k),
             Transform::Invoke(qn, a, ns) => invoke(self, stctxt, qn, a, ns),
+            Transform::AccumulatorBefore(s, ns) => accumulator_value(self, stctxt, s, ns, false),
+            Transform::AccumulatorAfter(s, ns) => accumulator_value(self, stctxt, s, ns, true),
+            Transform::Tunnel(p, b) => tunnel(self, stctxt, p, b),
+            Transform::TunnelParameter(n, d) => tunnel_parameter(self, stctxt, n, d),
+            Transform::WithParameters(p, b) => with_parameters(self, stctxt, p, b),
+            Transform::PassParameters(b) => pass_parameters(self, stctxt, b),
+            Transform::TemplateParameter(n, d) => template_parameter(self, stctxt, n, d),
             Transform::Message(b, s, e, t) => message(self, stctxt, b, s, e, t),
             Transform::Error(k, m) => tr_error(self, k, m),
             Transform::NotImplemented(s) => not_implemented(self, s),
@@ -465,18 +826,95 @@ impl<N: Node> From<Sequence<N>> for Context<N> {
             rd: None,
             templates: vec![],
             current_templates: vec![],
+            modes: HashMap::new(),
+            default_mode: None,
+            current_mode: None,
+            package: None,
+            hidden: Rc::new(HashMap::new()),
+            iteration: Rc::new(RefCell::new(Iteration::Continue)),
+            error_object: Rc::new(RefCell::new(None)),
             callables: HashMap::new(),
             vars: HashMap::new(),
+            tunnel_params: HashMap::new(),
+            template_params: HashMap::new(),
+            globals: HashMap::new(),
+            global_values: Rc::new(RefCell::new(GlobalValues::new())),
+            global_params: HashMap::new(),
+            params: HashMap::new(),
+            result_documents: Rc::new(RefCell::new(vec![])),
             keys: HashMap::new(),
             key_values: HashMap::new(),
+            accumulators: HashMap::new(),
+            accumulator_values: Rc::new(RefCell::new(HashMap::new())),
+            decimal_formats: HashMap::new(),
             current_grouping_key: None,
             current_group: Sequence::new(),
+            current_merge_group: vec![],
+            current_merge_key: None,
+            regex_groups: vec![],
             od: OutputDefinition::new(),
             base_url: None,
+            implicit_timezone: None,
+            current_date_time: None,
         }
     }
 }
 
+/// The components of used packages that the code of a package cannot reference, because they are hidden in that package.
+/// Also the components that the code of the package references by name, but that are private to one of the packages it uses,
+/// since they are hidden in a package that uses it. See XSLT 3.5.3.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Hidden {
+    pub(crate) callables: HashSet<QualifiedName>,
+    pub(crate) variables: HashSet<String>,
+    pub(crate) modes: HashSet<QualifiedName>,
+    pub(crate) private_callables: HashMap<QualifiedName, Option<Rc<str>>>,
+    pub(crate) private_variables: HashMap<String, Option<Rc<str>>>,
+    pub(crate) private_modes: HashMap<QualifiedName, Option<Rc<str>>>,
+}
+
+/// Identifies a named template, function or global variable. See XSLT 3.5.
+/// Visible components share a single namespace. A component of a used package that is hidden in the using package
+/// is private to the used package, so it is identified by the name of that package as well.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum ComponentKey<T> {
+    Visible(T),
+    Private(Option<Rc<str>>, T),
+}
+
+// The component that the code of a package references by the given name,
+// given the names of the components that are private to a used package, and the names of those that are hidden from that code.
+fn component_key<T: Clone + Eq + Hash>(
+    name: &T,
+    hidden: Option<(&HashMap<T, Option<Rc<str>>>, &HashSet<T>)>,
+) -> Option<ComponentKey<T>> {
+    match hidden.map(|(private, h)| (private.get(name), h.contains(name))) {
+        Some((Some(p), _)) => Some(ComponentKey::Private(p.clone(), name.clone())),
+        Some((None, true)) => None,
+        _ => Some(ComponentKey::Visible(name.clone())),
+    }
+}
+
+/// The error for a reference to a component of a used package that is hidden.
+pub(crate) fn hidden_component(kind: &str, name: impl Display, code: &str) -> Error {
+    Error::new_with_code(
+        ErrorKind::StaticAbsent,
+        format!("{} \"{}\" is not visible in this package", kind, name),
+        Some(QualifiedName::new(None, None, code)),
+    )
+}
+
+/// A secondary result document, created by the transformation.
+#[derive(Clone, Debug)]
+pub struct ResultDocument<N: Node> {
+    /// The absolute URI of the document, if the context has a base URL. Otherwise this is the URI as given by the transformation.
+    pub href: String,
+    /// The content of the document.
+    pub document: N,
+    /// How the document is to be serialised.
+    pub output: OutputDefinition,
+}
+
 /// Builder for a [Context]
 pub struct ContextBuilder<N: Node>(Context<N>);
 
@@ -508,6 +946,28 @@ impl<N: Node> ContextBuilder<N> {
         self.0.vars = v;
         self
     }
+    /// Declare global variables, and the package that declares each of them.
+    /// A global variable is evaluated when it is first referenced.
+    pub fn global_variables(mut self, g: HashMap<String, (Transform<N>, Option<Rc<str>>)>) -> Self {
+        self.0.globals = g
+            .into_iter()
+            .map(|(n, v)| (ComponentKey::Visible(n), v))
+            .collect();
+        self
+    }
+    /// Declare stylesheet parameters. The default value of a parameter is given by the global variable of the same name.
+    pub(crate) fn global_parameters(
+        mut self,
+        p: HashMap<ComponentKey<String>, GlobalParameter>,
+    ) -> Self {
+        self.0.global_params = p;
+        self
+    }
+    /// Supply the value of a stylesheet parameter.
+    pub fn parameter(mut self, qn: QualifiedName, v: Sequence<N>) -> Self {
+        self.0.params.insert(qn, v);
+        self
+    }
     pub fn result_document(mut self, rd: N) -> Self {
         self.0.rd = Some(rd);
         self
@@ -526,6 +986,38 @@ impl<N: Node> ContextBuilder<N> {
         self.0.current_templates = c;
         self
     }
+    /// Declare a mode.
+    pub fn mode(mut self, name: ModeKey, m: Mode) -> Self {
+        self.0.modes.insert(name, m);
+        self
+    }
+    /// The mode in which the transformation begins.
+    pub fn default_mode(mut self, m: Option<Rc<QualifiedName>>) -> Self {
+        self.0.default_mode = m;
+        self
+    }
+    pub fn current_mode(mut self, m: Option<Rc<QualifiedName>>) -> Self {
+        self.0.current_mode = m;
+        self
+    }
+    pub(crate) fn template_params(mut self, p: HashMap<String, Sequence<N>>) -> Self {
+        self.0.template_params = p;
+        self
+    }
+    /// The package whose code is evaluated in the context.
+    pub(crate) fn package(mut self, p: Option<Rc<str>>) -> Self {
+        self.0.package = p;
+        self
+    }
+    /// The components of used packages that are hidden from the code of each package.
+    pub(crate) fn hidden(mut self, h: HashMap<Option<Rc<str>>, Hidden>) -> Self {
+        self.0.hidden = Rc::new(h);
+        self
+    }
+    pub(crate) fn iteration(mut self, i: Rc<RefCell<Iteration<N>>>) -> Self {
+        self.0.iteration = i;
+        self
+    }
     pub fn current_group(mut self, c: Sequence<N>) -> Self {
         self.0.current_group = c;
         self
@@ -534,6 +1026,33 @@ impl<N: Node> ContextBuilder<N> {
         self.0.current_grouping_key = Some(k);
         self
     }
+    pub fn current_merge_group(mut self, g: Vec<(Option<String>, Sequence<N>)>) -> Self {
+        self.0.current_merge_group = g;
+        self
+    }
+    pub fn current_merge_key(mut self, k: Sequence<N>) -> Self {
+        self.0.current_merge_key = Some(k);
+        self
+    }
+    pub fn regex_groups(mut self, g: Vec<String>) -> Self {
+        self.0.regex_groups = g;
+        self
+    }
+    /// Declare an accumulator. It belongs to the package that has been set for the context.
+    pub fn accumulator(mut self, name: QualifiedName, a: Accumulator<N>) -> Self {
+        self.0
+            .accumulators
+            .insert((self.0.package.clone(), name), a);
+        self
+    }
+    /// Declare a decimal format. If the name is None then this is the unnamed, i.e. default, decimal format.
+    /// It belongs to the package that has been set for the context.
+    pub fn decimal_format(mut self, name: Option<QualifiedName>, df: DecimalFormat) -> Self {
+        self.0
+            .decimal_formats
+            .insert((self.0.package.clone(), name), df);
+        self
+    }
     pub fn output_definition(mut self, od: OutputDefinition) -> Self {
         self.0.od = od;
         self
@@ -543,7 +1062,16 @@ impl<N: Node> ContextBuilder<N> {
         self
     }
     pub fn callable(mut self, qn: QualifiedName, c: Callable<N>) -> Self {
-        self.0.callables.insert(qn, c);
+        self.0.callables.insert(ComponentKey::Visible(qn), c);
+        self
+    }
+    pub fn implicit_timezone(mut self, tz: FixedOffset) -> Self {
+        self.0.implicit_timezone = Some(tz);
+        self
+    }
+    /// Fix the value of the current dateTime, for example to give reproducible results.
+    pub fn current_date_time(mut self, dt: DateTime<FixedOffset>) -> Self {
+        self.0.current_date_time = Some(dt);
         self
     }
     pub fn build(self) -> Context<N> {
@@ -1,12 +1,19 @@
 //! These functions are for features that control program flow.
 
+use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::rc::Rc;
 use url::Url;
 
-use crate::item::{Node, Sequence, SequenceTrait};
+use crate::item::{Item, Node, Sequence, SequenceTrait};
+use crate::qname::QualifiedName;
+use crate::transform::booleans::effective_boolean_value;
 use crate::transform::context::{Context, ContextBuilder, StaticContext};
-use crate::transform::{do_sort, Grouping, Order, Transform};
+use crate::transform::{
+    compare_sort_keys, do_sort, sort_key_values, variable_name, Grouping, MergeSource, NameTest,
+    Order, Transform, WildcardOrName,
+};
 use crate::value::{Operator, Value};
 use crate::xdmerror::{Error, ErrorKind};
 
@@ -41,6 +48,191 @@ pub(crate) fn tr_loop<
     Ok(result)
 }
 
+/// How an iteration of xsl:iterate ends. This is set by xsl:break and xsl:next-iteration.
+#[derive(Clone, Debug)]
+pub(crate) enum Iteration<N: Node> {
+    /// Continue with the next item, with unchanged parameters
+    Continue,
+    /// Stop iterating, without evaluating xsl:on-completion
+    Break,
+    /// Continue with the next item, with new values for the given parameters
+    Next(Vec<(String, Sequence<N>)>),
+}
+
+/// Evaluate the body of an xsl:iterate instruction for each item in a sequence. See XSLT 7.1.
+/// Each parameter is in scope for the body and the on-completion instruction.
+/// This is evaluated as a loop, so the depth of evaluation does not grow with the number of items.
+pub(crate) fn iterate<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    params: &[(String, Transform<N>)],
+    body: &Transform<N>,
+    on_completion: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let mut values = params
+        .iter()
+        .map(|(name, v)| Ok((name.clone(), ctxt.dispatch(stctxt, v)?)))
+        .collect::<Result<Vec<(String, Sequence<N>)>, Error>>()?;
+    let state = Rc::new(RefCell::new(Iteration::Continue));
+    let mut result: Sequence<N> = Vec::new();
+    for i in ctxt.dispatch(stctxt, s)? {
+        let mut v = values
+            .iter()
+            .fold(ContextBuilder::from(ctxt), |b, (name, v)| {
+                b.variable(name.clone(), v.clone())
+            })
+            .context(vec![i.clone()])
+            .previous_context(Some(i))
+            .iteration(state.clone())
+            .build()
+            .dispatch(stctxt, body)?;
+        result.append(&mut v);
+        match state.replace(Iteration::Continue) {
+            Iteration::Continue => {}
+            Iteration::Break => return Ok(result),
+            Iteration::Next(n) => n.into_iter().for_each(|(name, v)| {
+                if let Some(p) = values.iter_mut().find(|(pn, _)| *pn == name) {
+                    p.1 = v
+                }
+            }),
+        }
+    }
+    // The context item is absent when evaluating xsl:on-completion
+    let mut c = values
+        .iter()
+        .fold(ContextBuilder::from(ctxt), |b, (name, v)| {
+            b.variable(name.clone(), v.clone())
+        })
+        .context(vec![])
+        .build()
+        .dispatch(stctxt, on_completion)?;
+    result.append(&mut c);
+    Ok(result)
+}
+
+/// Stop the enclosing xsl:iterate. The result is the value of the xsl:break instruction.
+pub(crate) fn break_iteration<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    v: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let result = ctxt.dispatch(stctxt, v)?;
+    *ctxt.iteration.borrow_mut() = Iteration::Break;
+    Ok(result)
+}
+
+/// Continue the enclosing xsl:iterate with new values for its parameters.
+pub(crate) fn next_iteration<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    params: &[(String, Transform<N>)],
+) -> Result<Sequence<N>, Error> {
+    let values = params
+        .iter()
+        .map(|(name, v)| Ok((name.clone(), ctxt.dispatch(stctxt, v)?)))
+        .collect::<Result<Vec<(String, Sequence<N>)>, Error>>()?;
+    *ctxt.iteration.borrow_mut() = Iteration::Next(values);
+    Ok(vec![])
+}
+
+/// The namespace of the errors defined by XPath, XQuery and XSLT.
+const ERR_NS: &str = "http://www.w3.org/2005/xqt-errors";
+
+/// Evaluate a sequence constructor, recovering from a dynamic error. See XSLT 8.3.
+/// If an error occurs then the first catch clause that matches the error code is evaluated.
+/// If rollback is true then any secondary result documents created by the try clause are discarded.
+pub(crate) fn try_catch<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    body: &Transform<N>,
+    catches: &[(Vec<NameTest>, Transform<N>)],
+    rollback: bool,
+) -> Result<Sequence<N>, Error> {
+    let rd = ctxt.result_documents.borrow().len();
+    ctxt.error_object.replace(None);
+    match ctxt.dispatch(stctxt, body) {
+        Ok(r) => Ok(r),
+        Err(e) => match catches
+            .iter()
+            .find(|(tests, _)| tests.iter().any(|t| error_matches(t, &e)))
+        {
+            Some((_, c)) => {
+                if rollback {
+                    ctxt.result_documents.borrow_mut().truncate(rd)
+                }
+                let value = ctxt.error_object.replace(None).unwrap_or_default();
+                let string = |s: &str| vec![Item::Value(Rc::new(Value::from(s)))];
+                // The error variables are in the error namespace, so they do not shadow the stylesheet's variables.
+                let err =
+                    |n: &str| variable_name(&QualifiedName::new(Some(ERR_NS.to_string()), None, n));
+                ContextBuilder::from(ctxt)
+                    .variable(
+                        err("code"),
+                        e.code
+                            .clone()
+                            .map_or(vec![], |qn| vec![Item::Value(Rc::new(Value::QName(qn)))]),
+                    )
+                    .variable(err("description"), string(&e.message))
+                    .variable(err("value"), value)
+                    .variable(err("module"), vec![])
+                    .variable(err("line-number"), vec![])
+                    .variable(err("column-number"), vec![])
+                    .variable(err("additional"), vec![])
+                    .build()
+                    .dispatch(stctxt, c)
+            }
+            None => Err(e),
+        },
+    }
+}
+
+// Does an error match a name test from the errors attribute of xsl:catch?
+// An error code that has no namespace is in the standard error namespace,
+// but it also matches a name test that has no namespace.
+fn error_matches(t: &NameTest, e: &Error) -> bool {
+    let Some(code) = &e.code else {
+        return matches!(
+            (&t.ns, &t.name),
+            (
+                Some(WildcardOrName::Wildcard),
+                Some(WildcardOrName::Wildcard)
+            )
+        );
+    };
+    let ns_match = match (&t.ns, code.namespace_uri_to_string()) {
+        (Some(WildcardOrName::Wildcard), _) => true,
+        (None, c) => c.is_none(),
+        (Some(WildcardOrName::Name(u)), c) => u.to_string() == c.unwrap_or(ERR_NS.to_string()),
+    };
+    ns_match
+        && match &t.name {
+            Some(WildcardOrName::Wildcard) => true,
+            Some(WildcardOrName::Name(l)) => l.to_string() == code.localname_to_string(),
+            None => false,
+        }
+}
+
 /// Choose a sequence to return.
 pub(crate) fn switch<
     N: Node,
@@ -56,7 +248,7 @@ pub(crate) fn switch<
     let mut candidate = ctxt.dispatch(stctxt, o)?;
     for (t, w) in v {
         let r = ctxt.dispatch(stctxt, t)?;
-        if r.to_bool() {
+        if effective_boolean_value(&r)? {
             candidate = ctxt.dispatch(stctxt, w)?;
             break;
         }
@@ -329,3 +521,87 @@ pub fn group_ending_with<
         String::from("not implemented"),
     ))
 }
+
+/// Merge the input sequences of the merge sources, then evaluate the merge action for each group of items that have the same merge key values.
+/// The items in a group are in the order of the merge sources. The first item of the group is the context item for the merge action.
+/// An input sequence that is not in merge key order is an error, unless the merge source sorts its inputs before merging.
+pub(crate) fn merge<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    sources: &[MergeSource<N>],
+    action: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    // Each entry is the index of the merge source, the item and its merge key values
+    let mut entries = vec![];
+    for (i, source) in sources.iter().enumerate() {
+        let inputs = match &source.for_each {
+            Some(f) => ctxt
+                .dispatch(stctxt, f)?
+                .into_iter()
+                .map(|a| ContextBuilder::from(ctxt).context(vec![a]).index(0).build())
+                .collect(),
+            None => vec![ctxt.clone()],
+        };
+        for c in inputs {
+            let mut input = c
+                .dispatch(stctxt, &source.select)?
+                .into_iter()
+                .map(|item| {
+                    let k = sort_key_values(ctxt, stctxt, &item, &source.keys)?;
+                    Ok((i, item, k))
+                })
+                .collect::<Result<Vec<(usize, Item<N>, Vec<Option<Rc<Value>>>)>, Error>>()?;
+            if source.sort_before_merge {
+                input.sort_by(|a, b| compare_sort_keys(&a.2, &b.2, &source.keys));
+            } else if input
+                .windows(2)
+                .any(|w| compare_sort_keys(&w[0].2, &w[1].2, &source.keys) == Ordering::Greater)
+            {
+                return Err(Error::new_with_code(
+                    ErrorKind::DynamicAbsent,
+                    "merge input is not in merge key order",
+                    Some(QualifiedName::new(None, None, "XTDE2220")),
+                ));
+            }
+            entries.append(&mut input);
+        }
+    }
+    // All merge sources have compatible merge keys, so the first one determines the order
+    let keys = sources.first().map_or(&[][..], |s| &s.keys[..]);
+    // The sort is stable, so items with equal keys remain in the order of their merge sources
+    entries.sort_by(|a, b| compare_sort_keys(&a.2, &b.2, keys));
+    let mut result = vec![];
+    let mut it = entries.into_iter().peekable();
+    while let Some((i, item, key)) = it.next() {
+        let mut group: Vec<(Option<String>, Sequence<N>)> =
+            sources.iter().map(|s| (s.name.clone(), vec![])).collect();
+        let first = item.clone();
+        group[i].1.push(item);
+        while it
+            .peek()
+            .is_some_and(|(_, _, k)| compare_sort_keys(k, &key, keys) == Ordering::Equal)
+        {
+            let (j, item, _) = it.next().unwrap();
+            group[j].1.push(item);
+        }
+        let mut r = ContextBuilder::from(ctxt)
+            .context(vec![first])
+            .index(0)
+            .current_merge_group(group)
+            .current_merge_key(
+                key.iter()
+                    .flatten()
+                    .map(|v| Item::Value(v.clone()))
+                    .collect(),
+            )
+            .build()
+            .dispatch(stctxt, action)?;
+        result.append(&mut r);
+    }
+    Ok(result)
+}
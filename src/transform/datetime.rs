@@ -3,31 +3,61 @@
 use std::rc::Rc;
 
 #[allow(unused_imports)]
-use chrono::{DateTime, Datelike, FixedOffset, Local, Timelike};
+use chrono::{
+    DateTime, Datelike, FixedOffset, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta,
+    Timelike,
+};
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::Decimal;
 use url::Url;
 
 use crate::item::{Item, Node, Sequence, SequenceTrait};
 use crate::parser::datetime::parse as picture_parse;
+use crate::qname::QualifiedName;
 use crate::transform::context::{Context, StaticContext};
-use crate::transform::Transform;
-use crate::value::Value;
+use crate::transform::maps::atomize_key;
+use crate::transform::types::{cast_value, derives_from};
+use crate::transform::{ArithmeticOperator, Transform};
+use crate::value::{instant, time_on_reference_date, Duration, Value};
 use crate::xdmerror::{Error, ErrorKind};
 
 /// XPath current-date-time function.
-pub fn current_date_time<N: Node>(_ctxt: &Context<N>) -> Result<Sequence<N>, Error> {
-    Ok(vec![Item::Value(Rc::new(Value::DateTime(Local::now())))])
+/// The result is in the implicit timezone.
+pub fn current_date_time<N: Node>(ctxt: &Context<N>) -> Result<Sequence<N>, Error> {
+    let now = ctxt.current_date_time();
+    Ok(vec![Item::Value(Rc::new(Value::DateTime(
+        now.naive_local(),
+        Some(*now.offset()),
+    )))])
 }
 
 /// XPath current-date function.
-pub fn current_date<N: Node>(_ctxt: &Context<N>) -> Result<Sequence<N>, Error> {
+/// The result is in the implicit timezone.
+pub fn current_date<N: Node>(ctxt: &Context<N>) -> Result<Sequence<N>, Error> {
+    let now = ctxt.current_date_time();
     Ok(vec![Item::Value(Rc::new(Value::Date(
-        Local::now().date_naive(),
+        now.date_naive(),
+        Some(*now.offset()),
     )))])
 }
 
 /// XPath current-time function.
-pub fn current_time<N: Node>(_ctxt: &Context<N>) -> Result<Sequence<N>, Error> {
-    Ok(vec![Item::Value(Rc::new(Value::Time(Local::now())))])
+pub fn current_time<N: Node>(ctxt: &Context<N>) -> Result<Sequence<N>, Error> {
+    let now = ctxt.current_date_time();
+    Ok(vec![Item::Value(Rc::new(Value::Time(
+        now.time(),
+        Some(*now.offset()),
+    )))])
+}
+
+// A date/time value in its timezone. If it does not have a timezone then the implicit timezone is used.
+fn in_timezone(
+    dt: &NaiveDateTime,
+    tz: &Option<FixedOffset>,
+    implicit: &FixedOffset,
+) -> DateTime<FixedOffset> {
+    let o = tz.unwrap_or(*implicit);
+    DateTime::from_naive_utc_and_offset(*dt - TimeDelta::seconds(o.local_minus_utc() as i64), o)
 }
 
 /// XPath format-date-time function.
@@ -53,15 +83,23 @@ pub fn format_date_time<
         1 => {
             match &dt[0] {
                 Item::Value(d) => match **d {
-                    Value::DateTime(i) => Ok(vec![Item::Value(Rc::new(Value::String(
-                        i.format(&pic).to_string(),
-                    )))]),
-                    Value::String(ref s) => {
+                    Value::DateTime(ref i, ref tz) => {
+                        Ok(vec![Item::Value(Rc::new(Value::String(
+                            in_timezone(i, tz, &ctxt.implicit_timezone())
+                                .format(&pic)
+                                .to_string(),
+                        )))])
+                    }
+                    Value::String(_) => {
                         // Try and coerce into a DateTime value
-                        match DateTime::<FixedOffset>::parse_from_rfc3339(s.as_str()) {
-                            Ok(j) => Ok(vec![Item::Value(Rc::new(Value::String(
-                                j.format(&pic).to_string(),
-                            )))]),
+                        match cast_value(d, &Value::DateTime(NaiveDateTime::default(), None)) {
+                            Ok(Value::DateTime(i, tz)) => {
+                                Ok(vec![Item::Value(Rc::new(Value::String(
+                                    in_timezone(&i, &tz, &ctxt.implicit_timezone())
+                                        .format(&pic)
+                                        .to_string(),
+                                )))])
+                            }
                             _ => Err(Error::new(
                                 ErrorKind::TypeError,
                                 String::from("unable to determine date value"),
@@ -109,7 +147,7 @@ pub fn format_date<
         1 => {
             match &dt[0] {
                 Item::Value(d) => match **d {
-                    Value::Date(i) => Ok(vec![Item::Value(Rc::new(Value::String(
+                    Value::Date(i, _) => Ok(vec![Item::Value(Rc::new(Value::String(
                         i.format(&pic).to_string(),
                     )))]),
                     Value::String(ref s) => {
@@ -166,8 +204,14 @@ pub fn format_time<
         1 => {
             match &dt[0] {
                 Item::Value(d) => match **d {
-                    Value::Time(i) => Ok(vec![Item::Value(Rc::new(Value::String(
-                        i.format(&pic).to_string(),
+                    Value::Time(ref i, ref tz) => Ok(vec![Item::Value(Rc::new(Value::String(
+                        in_timezone(
+                            &ctxt.current_date_time().date_naive().and_time(*i),
+                            tz,
+                            &ctxt.implicit_timezone(),
+                        )
+                        .format(&pic)
+                        .to_string(),
                     )))]),
                     Value::String(ref s) => {
                         // Try and coerce into a DateTime value
@@ -199,3 +243,410 @@ pub fn format_time<
         )),
     }
 }
+
+/// A component of a date, time or duration value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Component {
+    Years,
+    Months,
+    Days,
+    Hours,
+    Minutes,
+    Seconds,
+    Timezone,
+}
+
+fn dynamic_error(code: &str, msg: impl Into<String>) -> Error {
+    Error::new_with_code(
+        ErrorKind::Unknown,
+        msg,
+        Some(QualifiedName::new(None, None, code)),
+    )
+}
+
+fn type_error(msg: impl Into<String>) -> Error {
+    Error::new_with_code(
+        ErrorKind::TypeError,
+        msg,
+        Some(QualifiedName::new(None, None, "XPTY0004")),
+    )
+}
+
+/// Is the value a date, time or duration?
+pub(crate) fn is_temporal(v: &Value) -> bool {
+    matches!(
+        v,
+        Value::DateTime(..)
+            | Value::Date(..)
+            | Value::Time(..)
+            | Value::Duration(_)
+            | Value::YearMonthDuration(_)
+            | Value::DayTimeDuration(_)
+    )
+}
+
+/// Give a date/time value that does not have a timezone the implicit timezone.
+/// This is used to compare date/time values.
+pub(crate) fn with_implicit_timezone<N: Node>(i: Item<N>, implicit: &FixedOffset) -> Item<N> {
+    match &i {
+        Item::Value(v) => match **v {
+            Value::DateTime(dt, None) => Item::Value(Rc::new(Value::DateTime(dt, Some(*implicit)))),
+            Value::Time(t, None) => Item::Value(Rc::new(Value::Time(t, Some(*implicit)))),
+            Value::Date(d, None) => Item::Value(Rc::new(Value::Date(d, Some(*implicit)))),
+            _ => i,
+        },
+        _ => i,
+    }
+}
+
+// The seconds, with fractional part, as a chrono delta.
+fn to_delta(seconds: Decimal) -> Result<TimeDelta, Error> {
+    let whole = seconds.trunc();
+    let nanos = ((seconds - whole) * Decimal::from(1_000_000_000))
+        .to_i64()
+        .unwrap_or(0);
+    whole
+        .to_i64()
+        .and_then(TimeDelta::try_seconds)
+        .and_then(|d| d.checked_add(&TimeDelta::nanoseconds(nanos)))
+        .ok_or_else(|| dynamic_error("FODT0002", "duration overflow"))
+}
+
+// The chrono delta as seconds, with fractional part.
+fn from_delta(d: TimeDelta) -> Duration {
+    let nanos = Decimal::new(d.subsec_nanos() as i64, 9);
+    Duration::from_seconds((Decimal::from(d.num_seconds()) + nanos).normalize())
+}
+
+fn add_months(dt: NaiveDateTime, months: i64) -> Result<NaiveDateTime, Error> {
+    let m = Months::new(
+        u32::try_from(months.unsigned_abs())
+            .map_err(|_| dynamic_error("FODT0001", "date/time overflow"))?,
+    );
+    if months < 0 {
+        dt.checked_sub_months(m)
+    } else {
+        dt.checked_add_months(m)
+    }
+    .ok_or_else(|| dynamic_error("FODT0001", "date/time overflow"))
+}
+
+// Add a duration to a dateTime. The result has the same timezone as the dateTime.
+fn add_duration(dt: NaiveDateTime, d: &Value, negate: bool) -> Result<NaiveDateTime, Error> {
+    match d {
+        Value::YearMonthDuration(y) => {
+            add_months(dt, if negate { -y.months() } else { y.months() })
+        }
+        Value::DayTimeDuration(s) => {
+            let delta = to_delta(if negate { -s.seconds() } else { s.seconds() })?;
+            dt.checked_add_signed(delta)
+                .ok_or_else(|| dynamic_error("FODT0001", "date/time overflow"))
+        }
+        _ => Err(type_error(
+            "operand is not a yearMonthDuration or dayTimeDuration",
+        )),
+    }
+}
+
+fn reference_date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(1972, 12, 31).unwrap()
+}
+
+fn midnight(d: &NaiveDate) -> NaiveDateTime {
+    d.and_time(NaiveTime::MIN)
+}
+
+fn multiply_duration(d: &Value, f: f64) -> Result<Value, Error> {
+    if f.is_nan() {
+        return Err(dynamic_error(
+            "FOCA0005",
+            "NaN supplied as a float/double value",
+        ));
+    }
+    if f.is_infinite() {
+        return Err(dynamic_error("FODT0002", "duration overflow"));
+    }
+    match d {
+        Value::YearMonthDuration(y) => Ok(Value::YearMonthDuration(Duration::from_months(
+            (y.months() as f64 * f).round() as i64,
+        ))),
+        Value::DayTimeDuration(s) => Decimal::from_f64(f)
+            .and_then(|g| s.seconds().checked_mul(g))
+            .map(|t| Value::DayTimeDuration(Duration::from_seconds(t.normalize())))
+            .ok_or_else(|| dynamic_error("FODT0002", "duration overflow")),
+        _ => Err(type_error(
+            "operand is not a yearMonthDuration or dayTimeDuration",
+        )),
+    }
+}
+
+/// Perform an arithmetic operation where at least one operand is a date, time or duration.
+/// Operations where both operands are numeric are performed as doubles.
+/// When subtracting date/time values, a value without a timezone is in the given implicit timezone.
+pub(crate) fn datetime_arithmetic(
+    a: &Value,
+    op: ArithmeticOperator,
+    b: &Value,
+    implicit: &FixedOffset,
+) -> Result<Value, Error> {
+    use ArithmeticOperator::*;
+    match (a, op, b) {
+        (_, Noop, _) => Ok(b.clone()),
+        (Value::YearMonthDuration(x), Add, Value::YearMonthDuration(y)) => x
+            .months()
+            .checked_add(y.months())
+            .map(|m| Value::YearMonthDuration(Duration::from_months(m)))
+            .ok_or_else(|| dynamic_error("FODT0002", "duration overflow")),
+        (Value::YearMonthDuration(x), Subtract, Value::YearMonthDuration(y)) => x
+            .months()
+            .checked_sub(y.months())
+            .map(|m| Value::YearMonthDuration(Duration::from_months(m)))
+            .ok_or_else(|| dynamic_error("FODT0002", "duration overflow")),
+        (Value::DayTimeDuration(x), Add, Value::DayTimeDuration(y)) => x
+            .seconds()
+            .checked_add(y.seconds())
+            .map(|t| Value::DayTimeDuration(Duration::from_seconds(t)))
+            .ok_or_else(|| dynamic_error("FODT0002", "duration overflow")),
+        (Value::DayTimeDuration(x), Subtract, Value::DayTimeDuration(y)) => x
+            .seconds()
+            .checked_sub(y.seconds())
+            .map(|t| Value::DayTimeDuration(Duration::from_seconds(t)))
+            .ok_or_else(|| dynamic_error("FODT0002", "duration overflow")),
+        (Value::YearMonthDuration(x), Divide, Value::YearMonthDuration(y)) => {
+            if y.is_zero() {
+                Err(dynamic_error("FOAR0001", "division by zero"))
+            } else {
+                Ok(Value::Decimal(
+                    Decimal::from(x.months()) / Decimal::from(y.months()),
+                ))
+            }
+        }
+        (Value::DayTimeDuration(x), Divide, Value::DayTimeDuration(y)) => {
+            if y.is_zero() {
+                Err(dynamic_error("FOAR0001", "division by zero"))
+            } else {
+                Ok(Value::Decimal((x.seconds() / y.seconds()).normalize()))
+            }
+        }
+        (Value::YearMonthDuration(_) | Value::DayTimeDuration(_), Multiply, n)
+            if n.is_numeric() =>
+        {
+            multiply_duration(a, n.to_double())
+        }
+        (n, Multiply, Value::YearMonthDuration(_) | Value::DayTimeDuration(_))
+            if n.is_numeric() =>
+        {
+            multiply_duration(b, n.to_double())
+        }
+        (Value::YearMonthDuration(_) | Value::DayTimeDuration(_), Divide, n) if n.is_numeric() => {
+            let f = n.to_double();
+            if f == 0.0 {
+                Err(dynamic_error("FODT0002", "duration overflow"))
+            } else {
+                multiply_duration(a, 1.0 / f)
+            }
+        }
+        (Value::DateTime(x, s), Subtract, Value::DateTime(y, t)) => Ok(Value::DayTimeDuration(
+            from_delta(instant(x, s, implicit) - instant(y, t, implicit)),
+        )),
+        (Value::Date(x, s), Subtract, Value::Date(y, t)) => Ok(Value::DayTimeDuration(from_delta(
+            instant(&midnight(x), s, implicit) - instant(&midnight(y), t, implicit),
+        ))),
+        (Value::Time(x, s), Subtract, Value::Time(y, t)) => Ok(Value::DayTimeDuration(from_delta(
+            time_on_reference_date(x, s, implicit) - time_on_reference_date(y, t, implicit),
+        ))),
+        (Value::DateTime(x, tz), Add | Subtract, d) => {
+            add_duration(*x, d, op == Subtract).map(|dt| Value::DateTime(dt, *tz))
+        }
+        (d, Add, Value::DateTime(x, tz)) => {
+            add_duration(*x, d, false).map(|dt| Value::DateTime(dt, *tz))
+        }
+        (Value::Date(x, tz), Add | Subtract, d) => {
+            add_duration(midnight(x), d, op == Subtract).map(|dt| Value::Date(dt.date(), *tz))
+        }
+        (d, Add, Value::Date(x, tz)) => {
+            add_duration(midnight(x), d, false).map(|dt| Value::Date(dt.date(), *tz))
+        }
+        // The result of adding a duration to a time wraps around midnight
+        (Value::Time(x, tz), Add | Subtract, Value::DayTimeDuration(_)) => {
+            add_duration(reference_date().and_time(*x), b, op == Subtract)
+                .map(|dt| Value::Time(dt.time(), *tz))
+        }
+        (Value::DayTimeDuration(_), Add, Value::Time(x, tz)) => {
+            add_duration(reference_date().and_time(*x), a, false)
+                .map(|dt| Value::Time(dt.time(), *tz))
+        }
+        _ if !is_temporal(a) && !is_temporal(b) => {
+            let (u, v) = (a.to_double(), b.to_double());
+            Ok(Value::from(match op {
+                Add => u + v,
+                Subtract => u - v,
+                Multiply => u * v,
+                Divide | IntegerDivide => u / v,
+                Modulo => u % v,
+                Noop => v,
+            }))
+        }
+        _ => Err(type_error(format!(
+            "arithmetic operation is not defined for {} and {}",
+            a.value_type(),
+            b.value_type()
+        ))),
+    }
+}
+
+// Evaluate the argument of a component or adjustment function.
+// The value must be an instance of the given type, or be cast to it if it is untyped.
+fn temporal_argument<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    t: &Value,
+    arg: &Transform<N>,
+) -> Result<Option<Value>, Error> {
+    let s = ctxt.dispatch(stctxt, arg)?;
+    match s.len() {
+        0 => Ok(None),
+        1 => {
+            let v = atomize_key(&s[0])?;
+            match *v {
+                Value::String(_) | Value::UntypedAtomic => cast_value(&v, t).map(Some),
+                _ if derives_from(&v, t) => Ok(Some((*v).clone())),
+                _ => Err(type_error(format!(
+                    "expected a {} value, got a {}",
+                    t.value_type(),
+                    v.value_type()
+                ))),
+            }
+        }
+        _ => Err(type_error("argument is a sequence of more than one item")),
+    }
+}
+
+/// XPath component extraction functions, such as year-from-date and hours-from-duration.
+/// The type of the argument is given by an exemplar value.
+pub(crate) fn date_time_component<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    c: &Component,
+    t: &Value,
+    arg: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let v = match temporal_argument(ctxt, stctxt, t, arg)? {
+        Some(v) => v,
+        None => return Ok(vec![]),
+    };
+    let seconds = |t: &NaiveTime| {
+        Value::Decimal(
+            (Decimal::from(t.second()) + Decimal::new(t.nanosecond() as i64, 9)).normalize(),
+        )
+    };
+    let result = match (c, &v) {
+        (Component::Years, Value::DateTime(dt, _)) => Value::Integer(dt.year() as i64),
+        (Component::Months, Value::DateTime(dt, _)) => Value::Integer(dt.month() as i64),
+        (Component::Days, Value::DateTime(dt, _)) => Value::Integer(dt.day() as i64),
+        (Component::Hours, Value::DateTime(dt, _)) => Value::Integer(dt.hour() as i64),
+        (Component::Minutes, Value::DateTime(dt, _)) => Value::Integer(dt.minute() as i64),
+        (Component::Seconds, Value::DateTime(dt, _)) => seconds(&dt.time()),
+        (Component::Hours, Value::Time(t, _)) => Value::Integer(t.hour() as i64),
+        (Component::Minutes, Value::Time(t, _)) => Value::Integer(t.minute() as i64),
+        (Component::Seconds, Value::Time(t, _)) => seconds(t),
+        (Component::Timezone, Value::DateTime(_, tz) | Value::Time(_, tz) | Value::Date(_, tz)) => {
+            match tz {
+                Some(o) => Value::DayTimeDuration(Duration::from_seconds(Decimal::from(
+                    o.local_minus_utc(),
+                ))),
+                None => return Ok(vec![]),
+            }
+        }
+        (Component::Years, Value::Date(d, _)) => Value::Integer(d.year() as i64),
+        (Component::Months, Value::Date(d, _)) => Value::Integer(d.month() as i64),
+        (Component::Days, Value::Date(d, _)) => Value::Integer(d.day() as i64),
+        (_, Value::Duration(d) | Value::YearMonthDuration(d) | Value::DayTimeDuration(d)) => {
+            match c {
+                Component::Years => Value::Integer(d.year_part()),
+                Component::Months => Value::Integer(d.month_part()),
+                Component::Days => Value::Integer(d.day_part()),
+                Component::Hours => Value::Integer(d.hour_part()),
+                Component::Minutes => Value::Integer(d.minute_part()),
+                Component::Seconds => Value::Decimal(d.second_part()),
+                Component::Timezone => {
+                    return Err(type_error("a duration does not have a timezone"))
+                }
+            }
+        }
+        _ => {
+            return Err(type_error(format!(
+                "unable to extract {:?} from a {} value",
+                c,
+                v.value_type()
+            )))
+        }
+    };
+    Ok(vec![Item::Value(Rc::new(result))])
+}
+
+/// XPath adjust-dateTime-to-timezone, adjust-date-to-timezone and adjust-time-to-timezone functions.
+/// If the timezone is not given then the implicit timezone is used. If the timezone is the empty sequence then the timezone is removed.
+pub(crate) fn adjust_to_timezone<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    t: &Value,
+    arg: &Transform<N>,
+    timezone: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let v = match temporal_argument(ctxt, stctxt, t, arg)? {
+        Some(v) => v,
+        None => return Ok(vec![]),
+    };
+    let offset = match timezone {
+        None => Some(ctxt.implicit_timezone()),
+        Some(tz) => match temporal_argument(
+            ctxt,
+            stctxt,
+            &Value::DayTimeDuration(Duration::default()),
+            tz,
+        )? {
+            Some(Value::DayTimeDuration(d)) => Some(
+                d.seconds()
+                    .to_i32()
+                    .filter(|s| s % 60 == 0 && d.seconds().fract().is_zero())
+                    .and_then(FixedOffset::east_opt)
+                    .filter(|o| o.local_minus_utc().abs() <= 14 * 3600)
+                    .ok_or_else(|| {
+                        dynamic_error("FODT0003", format!("invalid timezone \"{}\"", d))
+                    })?,
+            ),
+            // The empty sequence removes the timezone
+            _ => None,
+        },
+    };
+    // The local date and time of the value, adjusted to the new timezone
+    let adjust = |dt: &NaiveDateTime, tz: &Option<FixedOffset>| match (tz, offset) {
+        (Some(_), Some(o)) => instant(dt, tz, &o) + TimeDelta::seconds(o.local_minus_utc() as i64),
+        _ => *dt,
+    };
+    let result = match v {
+        Value::DateTime(dt, tz) => Value::DateTime(adjust(&dt, &tz), offset),
+        Value::Date(d, tz) => Value::Date(adjust(&midnight(&d), &tz).date(), offset),
+        Value::Time(t, tz) => {
+            Value::Time(adjust(&reference_date().and_time(t), &tz).time(), offset)
+        }
+        _ => return Err(type_error("expected a dateTime, date or time value")),
+    };
+    Ok(vec![Item::Value(Rc::new(result))])
+}
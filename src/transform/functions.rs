@@ -259,6 +259,90 @@ pub fn document<
     }
 }
 
+/// XPath error function. See XPath Functions 3.1 section 3.1.1.
+/// The arguments are the error code, the description, and the error object.
+/// The error object is available to xsl:catch as $err:value.
+pub(crate) fn error<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    code: &Option<Box<Transform<N>>>,
+    description: &Option<Box<Transform<N>>>,
+    object: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let qn = match code {
+        Some(c) => match ctxt.dispatch(stctxt, c)?.first() {
+            None => None,
+            Some(Item::Value(v)) => match &**v {
+                Value::QName(q) => Some(q.clone()),
+                Value::RQName(q) => Some((**q).clone()),
+                _ => {
+                    return Err(Error::new_with_code(
+                        ErrorKind::TypeError,
+                        "error code must be a QName",
+                        Some(QualifiedName::new(None, None, "XPTY0004")),
+                    ))
+                }
+            },
+            Some(_) => {
+                return Err(Error::new_with_code(
+                    ErrorKind::TypeError,
+                    "error code must be a QName",
+                    Some(QualifiedName::new(None, None, "XPTY0004")),
+                ))
+            }
+        },
+        None => None,
+    };
+    let desc = match description {
+        Some(d) => ctxt.dispatch(stctxt, d)?.to_string(),
+        None => String::from("error raised by the error function"),
+    };
+    if let Some(o) = object {
+        let v = ctxt.dispatch(stctxt, o)?;
+        ctxt.error_object.replace(Some(v));
+    }
+    Err(Error::new_with_code(
+        ErrorKind::Unknown,
+        desc,
+        Some(qn.unwrap_or_else(|| QualifiedName::new(None, None, "FOER0000"))),
+    ))
+}
+
+/// XPath QName function. Creates a QName from a namespace URI and a lexical QName.
+pub(crate) fn qname<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    uri: &Transform<N>,
+    qn: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let u = ctxt.dispatch(stctxt, uri)?.to_string();
+    let q = ctxt.dispatch(stctxt, qn)?.to_string();
+    let (prefix, local) = match q.split_once(':') {
+        Some((p, l)) => (Some(p.to_string()), l.to_string()),
+        None => (None, q.clone()),
+    };
+    if local.is_empty() || (u.is_empty() && prefix.is_some()) {
+        return Err(Error::new_with_code(
+            ErrorKind::TypeError,
+            format!("invalid QName \"{}\"", q),
+            Some(QualifiedName::new(None, None, "FOCA0002")),
+        ));
+    }
+    Ok(vec![Item::Value(Rc::new(Value::QName(
+        QualifiedName::new(if u.is_empty() { None } else { Some(u) }, prefix, local),
+    )))])
+}
+
 pub(crate) fn tr_error<N: Node>(
     _ctxt: &Context<N>,
     kind: &ErrorKind,
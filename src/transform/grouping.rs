@@ -1,8 +1,11 @@
 //! These functions are for features defined in XPath Functions 1.0 and 2.0.
 
-use crate::item::{Item, Node, Sequence};
-use crate::transform::context::Context;
+use crate::item::{Item, Node, Sequence, SequenceTrait};
+use crate::qname::QualifiedName;
+use crate::transform::context::{Context, StaticContext};
+use crate::transform::Transform;
 use crate::xdmerror::{Error, ErrorKind};
+use url::Url;
 
 /// XSLT current-group function.
 pub fn current_group<N: Node>(ctxt: &Context<N>) -> Result<Sequence<N>, Error> {
@@ -21,3 +24,55 @@ pub fn current_grouping_key<N: Node>(ctxt: &Context<N>) -> Result<Sequence<N>, E
         |k| Ok(vec![Item::Value(k)]),
     )
 }
+
+/// XSLT current-merge-group function. If a merge source is named then only the items from that source are returned.
+pub(crate) fn current_merge_group<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    name: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    if ctxt.current_merge_key.is_none() {
+        return Err(Error::new_with_code(
+            ErrorKind::DynamicAbsent,
+            "no current merge group",
+            Some(QualifiedName::new(None, None, "XTDE3480")),
+        ));
+    }
+    match name {
+        None => Ok(ctxt
+            .current_merge_group
+            .iter()
+            .flat_map(|(_, s)| s.clone())
+            .collect()),
+        Some(n) => {
+            let source = ctxt.dispatch(stctxt, n)?.to_string();
+            ctxt.current_merge_group
+                .iter()
+                .find(|(m, _)| m.as_deref() == Some(source.as_str()))
+                .map(|(_, s)| s.clone())
+                .ok_or_else(|| {
+                    Error::new_with_code(
+                        ErrorKind::DynamicAbsent,
+                        format!("no merge source named \"{}\"", source),
+                        Some(QualifiedName::new(None, None, "XTDE3490")),
+                    )
+                })
+        }
+    }
+}
+
+/// XSLT current-merge-key function.
+pub(crate) fn current_merge_key<N: Node>(ctxt: &Context<N>) -> Result<Sequence<N>, Error> {
+    ctxt.current_merge_key.clone().ok_or_else(|| {
+        Error::new_with_code(
+            ErrorKind::DynamicAbsent,
+            "no current merge key",
+            Some(QualifiedName::new(None, None, "XTDE3510")),
+        )
+    })
+}
@@ -0,0 +1,208 @@
+//! These functions are for the higher-order functions defined in XPath Functions 3.1.
+
+use std::cmp::Ordering;
+use std::rc::Rc;
+use url::Url;
+
+use crate::item::{Item, Node, Sequence};
+use crate::qname::QualifiedName;
+use crate::transform::callable::{call_item, single_function};
+use crate::transform::context::{Context, StaticContext};
+use crate::transform::maps::atomize_key;
+use crate::transform::Transform;
+use crate::value::Value;
+use crate::xdmerror::{Error, ErrorKind};
+
+/// XPath for-each function.
+pub(crate) fn fn_for_each<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    f: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let seq = ctxt.dispatch(stctxt, s)?;
+    let func = single_function(ctxt, stctxt, f)?;
+    let mut result = vec![];
+    for i in seq {
+        result.append(&mut call_item(ctxt, stctxt, &func, vec![vec![i]])?)
+    }
+    Ok(result)
+}
+
+/// XPath filter function.
+pub(crate) fn fn_filter<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    f: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let seq = ctxt.dispatch(stctxt, s)?;
+    let func = single_function(ctxt, stctxt, f)?;
+    let mut result = vec![];
+    for i in seq {
+        if predicate(ctxt, stctxt, &func, vec![i.clone()])? {
+            result.push(i)
+        }
+    }
+    Ok(result)
+}
+
+/// Call a predicate function. The result must be a single boolean value.
+pub(crate) fn predicate<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    func: &Item<N>,
+    arg: Sequence<N>,
+) -> Result<bool, Error> {
+    let r = call_item(ctxt, stctxt, func, vec![arg])?;
+    if r.len() == 1 {
+        if let Item::Value(v) = &r[0] {
+            if let Value::Boolean(b) = **v {
+                return Ok(b);
+            }
+        }
+    }
+    Err(Error::new_with_code(
+        ErrorKind::TypeError,
+        "predicate function must return a single boolean value",
+        Some(QualifiedName::new(None, None, "XPTY0004")),
+    ))
+}
+
+/// XPath fold-left function.
+pub(crate) fn fold_left<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    z: &Transform<N>,
+    f: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let seq = ctxt.dispatch(stctxt, s)?;
+    let zero = ctxt.dispatch(stctxt, z)?;
+    let func = single_function(ctxt, stctxt, f)?;
+    seq.into_iter().try_fold(zero, |acc, i| {
+        call_item(ctxt, stctxt, &func, vec![acc, vec![i]])
+    })
+}
+
+/// XPath fold-right function.
+pub(crate) fn fold_right<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    z: &Transform<N>,
+    f: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let seq = ctxt.dispatch(stctxt, s)?;
+    let zero = ctxt.dispatch(stctxt, z)?;
+    let func = single_function(ctxt, stctxt, f)?;
+    seq.into_iter().rev().try_fold(zero, |acc, i| {
+        call_item(ctxt, stctxt, &func, vec![vec![i], acc])
+    })
+}
+
+/// XPath sort function.
+/// Only the Unicode codepoint collation is supported, so the collation argument is ignored.
+pub(crate) fn fn_sort<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    _c: &Option<Box<Transform<N>>>,
+    k: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let seq = ctxt.dispatch(stctxt, s)?;
+    Ok(
+        sort_members(ctxt, stctxt, seq.into_iter().map(|i| vec![i]).collect(), k)?
+            .into_iter()
+            .flatten()
+            .collect(),
+    )
+}
+
+/// Sort a list of sequences, optionally using a key function.
+/// Without a key function, the sort key is the atomized value of each sequence.
+/// The sort is stable.
+pub(crate) fn sort_members<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    members: Vec<Sequence<N>>,
+    k: &Option<Box<Transform<N>>>,
+) -> Result<Vec<Sequence<N>>, Error> {
+    let func = k
+        .as_ref()
+        .map(|f| single_function(ctxt, stctxt, f))
+        .transpose()?;
+    let mut keyed = members
+        .into_iter()
+        .map(|m| {
+            let key = match &func {
+                Some(f) => call_item(ctxt, stctxt, f, vec![m.clone()])?,
+                None => m.clone(),
+            };
+            let atoms = key
+                .iter()
+                .map(atomize_key)
+                .collect::<Result<Vec<Rc<Value>>, Error>>()?;
+            Ok((atoms, m))
+        })
+        .collect::<Result<Vec<(Vec<Rc<Value>>, Sequence<N>)>, Error>>()?;
+    keyed.sort_by(|(a, _), (b, _)| compare_keys(a, b));
+    Ok(keyed.into_iter().map(|(_, m)| m).collect())
+}
+
+// Compare two sort keys. Numeric values are compared numerically, all other values as strings.
+// NaN sorts before all other numbers.
+fn compare_keys(a: &[Rc<Value>], b: &[Rc<Value>]) -> Ordering {
+    for (x, y) in a.iter().zip(b.iter()) {
+        let o = if x.is_numeric() && y.is_numeric() {
+            let (f, g) = (x.to_double(), y.to_double());
+            match (f.is_nan(), g.is_nan()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Less,
+                (false, true) => Ordering::Greater,
+                (false, false) => f.partial_cmp(&g).unwrap_or(Ordering::Equal),
+            }
+        } else {
+            x.to_string().cmp(&y.to_string())
+        };
+        if o != Ordering::Equal {
+            return o;
+        }
+    }
+    a.len().cmp(&b.len())
+}
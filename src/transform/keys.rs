@@ -6,6 +6,7 @@ use crate::transform::Transform;
 use crate::xdmerror::Error;
 use crate::{Item, SequenceTrait};
 use std::collections::HashMap;
+use std::rc::Rc;
 use url::Url;
 
 /// For each key declaration:
@@ -26,13 +27,25 @@ pub(crate) fn populate_key_values<
 ) -> Result<(), Error> {
     // We have to visit N nodes to compute K keys.
     // In a typical scenario, N >> K so we want to perform a single pass over the nodes.
+    // The code of each key is evaluated as code of the package that declares it.
+    let package_ctxts: HashMap<Option<Rc<str>>, Context<N>> = ctxt
+        .keys
+        .keys()
+        .map(|(p, _)| {
+            (
+                p.clone(),
+                ContextBuilder::from(&*ctxt).package(p.clone()).build(),
+            )
+        })
+        .collect();
     for n in sd.owner_document().descend_iter() {
         // Descend visits all nodes except attributes
         // TODO: support attributes
         for (name, d) in &ctxt.keys {
+            let pctxt = &package_ctxts[&name.0];
             for (m, u) in d {
-                if m.matches(ctxt, stctxt, &Item::Node(n.clone())) {
-                    let newctxt = ContextBuilder::from(&*ctxt)
+                if m.matches(pctxt, stctxt, &Item::Node(n.clone())) {
+                    let newctxt = ContextBuilder::from(pctxt)
                         .context(vec![Item::Node(n.clone())])
                         .build();
                     let values = newctxt.dispatch(stctxt, u)?;
@@ -74,7 +87,11 @@ pub fn key<
     name: &Box<Transform<N>>,
     v: &Box<Transform<N>>,
 ) -> Result<Sequence<N>, Error> {
-    let keyname = ctxt.dispatch(stctxt, name)?.to_string();
+    // Only the keys of the package whose code is being evaluated are used
+    let keyname = (
+        ctxt.package.clone(),
+        ctxt.dispatch(stctxt, name)?.to_string(),
+    );
     Ok(ctxt.dispatch(stctxt, v)?.iter().fold(vec![], |mut acc, s| {
         if let Some(u) = ctxt.key_values.get(&keyname) {
             if let Some(a) = u.get(&s.to_string()) {
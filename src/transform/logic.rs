@@ -1,10 +1,14 @@
 //! These functions are for features defined in XPath Functions 1.0 and 2.0.
 
+use std::cmp::Ordering;
 use std::rc::Rc;
 use url::Url;
 
-use crate::item::{Item, Node, Sequence, SequenceTrait};
+use crate::item::{Item, Node, Sequence};
+use crate::qname::QualifiedName;
+use crate::transform::booleans::effective_boolean_value;
 use crate::transform::context::{Context, StaticContext};
+use crate::transform::datetime::with_implicit_timezone;
 use crate::transform::Transform;
 use crate::value::{Operator, Value};
 use crate::xdmerror::{Error, ErrorKind};
@@ -26,7 +30,7 @@ pub(crate) fn tr_or<
     loop {
         match v.get(i) {
             Some(a) => {
-                if ctxt.dispatch(stctxt, a)?.to_bool() {
+                if effective_boolean_value(&ctxt.dispatch(stctxt, a)?)? {
                     b = true;
                     break;
                 }
@@ -55,7 +59,7 @@ pub(crate) fn tr_and<
     loop {
         match v.get(i) {
             Some(a) => {
-                if !ctxt.dispatch(stctxt, a)?.to_bool() {
+                if !effective_boolean_value(&ctxt.dispatch(stctxt, a)?)? {
                     b = false;
                     break;
                 }
@@ -80,8 +84,17 @@ pub(crate) fn general_comparison<
     l: &Transform<N>,
     r: &Transform<N>,
 ) -> Result<Sequence<N>, Error> {
-    let left = ctxt.dispatch(stctxt, l)?;
-    let right = ctxt.dispatch(stctxt, r)?;
+    let tz = ctxt.implicit_timezone();
+    let left: Sequence<N> = ctxt
+        .dispatch(stctxt, l)?
+        .into_iter()
+        .map(|i| with_implicit_timezone(i, &tz))
+        .collect();
+    let right: Sequence<N> = ctxt
+        .dispatch(stctxt, r)?
+        .into_iter()
+        .map(|i| with_implicit_timezone(i, &tz))
+        .collect();
 
     let mut b = false;
     for i in left {
@@ -127,11 +140,56 @@ pub(crate) fn value_comparison<
         ));
     }
 
+    let tz = ctxt.implicit_timezone();
     Ok(vec![Item::Value(Rc::new(Value::from(
-        left[0].compare(&right[0], *o)?,
+        with_implicit_timezone(left[0].clone(), &tz)
+            .compare(&with_implicit_timezone(right[0].clone(), &tz), *o)?,
     )))])
 }
 
+/// XPath node comparison.
+/// If either operand is the empty sequence then the result is the empty sequence.
+/// Otherwise both operands must be a single node.
+pub(crate) fn node_comparison<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    o: &Operator,
+    l: &Transform<N>,
+    r: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let left = ctxt.dispatch(stctxt, l)?;
+    let right = ctxt.dispatch(stctxt, r)?;
+    if left.is_empty() || right.is_empty() {
+        return Ok(vec![]);
+    }
+    match (left.as_slice(), right.as_slice()) {
+        ([Item::Node(m)], [Item::Node(n)]) => {
+            let b = match o {
+                Operator::Is => m.is_same(n),
+                Operator::Before => m.cmp_document_order(n) == Ordering::Less,
+                Operator::After => m.cmp_document_order(n) == Ordering::Greater,
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::TypeError,
+                        format!("\"{}\" is not a node comparison operator", o),
+                    ))
+                }
+            };
+            Ok(vec![Item::Value(Rc::new(Value::from(b)))])
+        }
+        _ => Err(Error::new_with_code(
+            ErrorKind::TypeError,
+            "operands of a node comparison must be single nodes",
+            Some(QualifiedName::new(None, None, "XPTY0004")),
+        )),
+    }
+}
+
 /// Each function in the supplied vector is evaluated, and the resulting sequences are combined into a single sequence.
 /// All items must be nodes.
 /// TODO: eliminate duplicates, sort by document order (XPath 3.1 3.4.2).
@@ -0,0 +1,416 @@
+//! These functions are for features defined in XPath 3.1 and XPath Functions 3.1 for maps.
+//!
+//! The lookup operator is also defined here, since it applies to both maps and arrays.
+
+use rust_decimal::prelude::ToPrimitive;
+use std::rc::Rc;
+use url::Url;
+
+use crate::item::{Array, Item, Map, Node, Sequence};
+use crate::qname::QualifiedName;
+use crate::transform::callable::{call_item, single_function};
+use crate::transform::context::{Context, StaticContext};
+use crate::transform::types::derives_from;
+use crate::transform::Transform;
+use crate::value::Value;
+use crate::xdmerror::{Error, ErrorKind};
+
+/// Atomize a singleton item so that it may be used as a map key.
+pub(crate) fn atomize_key<N: Node>(i: &Item<N>) -> Result<Rc<Value>, Error> {
+    match i {
+        Item::Value(v) => Ok(v.clone()),
+        Item::Node(n) => Ok(Rc::new(Value::from(n.to_string()))),
+        _ => Err(Error::new_with_code(
+            ErrorKind::TypeError,
+            "unable to atomize a function, map or array",
+            Some(QualifiedName::new(None, None, "FOTY0013")),
+        )),
+    }
+}
+
+/// Evaluate a transformation that must produce a single atomic value.
+fn single_key<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    t: &Transform<N>,
+) -> Result<Rc<Value>, Error> {
+    let k = ctxt.dispatch(stctxt, t)?;
+    if k.len() == 1 {
+        atomize_key(&k[0])
+    } else {
+        Err(Error::new_with_code(
+            ErrorKind::TypeError,
+            "map key must be a single atomic value",
+            Some(QualifiedName::new(None, None, "XPTY0004")),
+        ))
+    }
+}
+
+/// Evaluate a transformation that must produce a single map.
+pub(crate) fn single_map<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    t: &Transform<N>,
+) -> Result<Rc<Map<N>>, Error> {
+    let m = ctxt.dispatch(stctxt, t)?;
+    match m.as_slice() {
+        [Item::Map(m)] => Ok(m.clone()),
+        _ => Err(Error::new_with_code(
+            ErrorKind::TypeError,
+            "not a singleton map",
+            Some(QualifiedName::new(None, None, "XPTY0004")),
+        )),
+    }
+}
+
+/// XPath map constructor.
+pub(crate) fn map_constructor<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    entries: &[(Transform<N>, Transform<N>)],
+) -> Result<Sequence<N>, Error> {
+    let mut m = Map::new();
+    for (k, v) in entries {
+        let key = single_key(ctxt, stctxt, k)?;
+        if m.contains(&key) {
+            return Err(Error::new_with_code(
+                ErrorKind::TypeError,
+                format!("duplicate key \"{}\" in map constructor", key),
+                Some(QualifiedName::new(None, None, "XQDY0137")),
+            ));
+        }
+        m.insert(key, ctxt.dispatch(stctxt, v)?);
+    }
+    Ok(vec![Item::Map(Rc::new(m))])
+}
+
+/// XPath lookup operator. The key specifier is either a sequence of keys, or a wildcard (None).
+pub(crate) fn lookup<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    e: &Transform<N>,
+    k: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let base = ctxt.dispatch(stctxt, e)?;
+    let keys = match k {
+        Some(t) => Some(ctxt.dispatch(stctxt, t)?),
+        None => None,
+    };
+    let mut result = vec![];
+    for i in base {
+        match (i, &keys) {
+            (Item::Map(m), None) => m.iter().for_each(|(_, v)| result.extend(v.clone())),
+            (Item::Map(m), Some(ks)) => {
+                for key in ks {
+                    if let Some(v) = m.get(&*atomize_key(key)?) {
+                        result.extend(v.clone())
+                    }
+                }
+            }
+            (Item::Array(a), None) => a.iter().for_each(|v| result.extend(v.clone())),
+            (Item::Array(a), Some(ks)) => {
+                for key in ks {
+                    let i = match (key, atomize_key(key)?.as_ref()) {
+                        // An untyped value is cast to an integer
+                        (Item::Node(_), v) => {
+                            v.to_string().trim().parse::<i64>().map_err(|_| {
+                                Error::new_with_code(
+                                    ErrorKind::TypeError,
+                                    format!("array index \"{}\" is not a valid xs:integer", v),
+                                    Some(QualifiedName::new(None, None, "FORG0001")),
+                                )
+                            })?
+                        }
+                        // An integer too large to be a position is out of bounds
+                        (_, Value::Decimal(d)) if d.fract().is_zero() && d.to_i64().is_none() => {
+                            return Err(Error::new_with_code(
+                                ErrorKind::Unknown,
+                                format!("array index {} out of bounds", d),
+                                Some(QualifiedName::new(None, None, "FOAY0001")),
+                            ))
+                        }
+                        (_, v) if derives_from(v, &Value::Integer(0)) => v.to_int()?,
+                        (_, v) => {
+                            return Err(Error::new_with_code(
+                                ErrorKind::TypeError,
+                                format!("array index \"{}\" is not an xs:integer", v),
+                                Some(QualifiedName::new(None, None, "XPTY0004")),
+                            ))
+                        }
+                    };
+                    result.extend(a.get(i)?.clone())
+                }
+            }
+            _ => {
+                return Err(Error::new_with_code(
+                    ErrorKind::TypeError,
+                    "lookup operator applied to an item that is not a map or array",
+                    Some(QualifiedName::new(None, None, "XPTY0004")),
+                ))
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// XPath map:merge function.
+/// The only option supported is "duplicates".
+pub(crate) fn map_merge<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    maps: &Transform<N>,
+    options: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let duplicates = match options {
+        Some(o) => single_map(ctxt, stctxt, o)?
+            .get(&Value::from("duplicates"))
+            .map_or(String::from("use-first"), |d| {
+                d.iter().map(|i| i.to_string()).collect()
+            }),
+        None => String::from("use-first"),
+    };
+    let mut result = Map::new();
+    for i in ctxt.dispatch(stctxt, maps)? {
+        if let Item::Map(m) = i {
+            for (k, v) in m.iter() {
+                match (result.get(k), duplicates.as_str()) {
+                    (None, _) | (Some(_), "use-last") => result.insert(k.clone(), v.clone()),
+                    (Some(_), "use-first") | (Some(_), "use-any") => {}
+                    (Some(w), "combine") => {
+                        let mut c = w.clone();
+                        c.extend(v.clone());
+                        result.insert(k.clone(), c)
+                    }
+                    (Some(_), "reject") => {
+                        return Err(Error::new_with_code(
+                            ErrorKind::Unknown,
+                            format!("duplicate key \"{}\"", k),
+                            Some(QualifiedName::new(None, None, "FOJS0003")),
+                        ))
+                    }
+                    _ => {
+                        return Err(Error::new_with_code(
+                            ErrorKind::TypeError,
+                            format!("invalid value \"{}\" for duplicates option", duplicates),
+                            Some(QualifiedName::new(None, None, "FOJS0005")),
+                        ))
+                    }
+                }
+            }
+        } else {
+            return Err(Error::new_with_code(
+                ErrorKind::TypeError,
+                "not a map",
+                Some(QualifiedName::new(None, None, "XPTY0004")),
+            ));
+        }
+    }
+    Ok(vec![Item::Map(Rc::new(result))])
+}
+
+/// XPath map:size function.
+pub(crate) fn map_size<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    m: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    Ok(vec![Item::Value(Rc::new(Value::from(
+        single_map(ctxt, stctxt, m)?.len() as i64,
+    )))])
+}
+
+/// XPath map:keys function.
+pub(crate) fn map_keys<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    m: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    Ok(single_map(ctxt, stctxt, m)?
+        .keys()
+        .into_iter()
+        .map(Item::Value)
+        .collect())
+}
+
+/// XPath map:contains function.
+pub(crate) fn map_contains<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    m: &Transform<N>,
+    k: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let map = single_map(ctxt, stctxt, m)?;
+    let key = single_key(ctxt, stctxt, k)?;
+    Ok(vec![Item::Value(Rc::new(Value::from(map.contains(&key))))])
+}
+
+/// XPath map:get function.
+pub(crate) fn map_get<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    m: &Transform<N>,
+    k: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let map = single_map(ctxt, stctxt, m)?;
+    let key = single_key(ctxt, stctxt, k)?;
+    Ok(map.get(&key).cloned().unwrap_or_default())
+}
+
+/// XPath map:find function.
+/// Searches the input sequence, including nested maps and arrays, for entries with the given key.
+/// The result is an array of the values found.
+pub(crate) fn map_find<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    i: &Transform<N>,
+    k: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let input = ctxt.dispatch(stctxt, i)?;
+    let key = single_key(ctxt, stctxt, k)?;
+    let mut result = Array::new();
+    find_aux(&input, &key, &mut result);
+    Ok(vec![Item::Array(Rc::new(result))])
+}
+fn find_aux<N: Node>(s: &Sequence<N>, k: &Value, result: &mut Array<N>) {
+    s.iter().for_each(|i| match i {
+        Item::Map(m) => {
+            if let Some(v) = m.get(k) {
+                result.push(v.clone())
+            }
+            m.iter().for_each(|(_, v)| find_aux(v, k, result))
+        }
+        Item::Array(a) => a.iter().for_each(|m| find_aux(m, k, result)),
+        _ => {}
+    })
+}
+
+/// XPath map:put function.
+pub(crate) fn map_put<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    m: &Transform<N>,
+    k: &Transform<N>,
+    v: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let mut map = (*single_map(ctxt, stctxt, m)?).clone();
+    let key = single_key(ctxt, stctxt, k)?;
+    map.insert(key, ctxt.dispatch(stctxt, v)?);
+    Ok(vec![Item::Map(Rc::new(map))])
+}
+
+/// XPath map:entry function.
+pub(crate) fn map_entry<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    k: &Transform<N>,
+    v: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let mut map = Map::new();
+    map.insert(single_key(ctxt, stctxt, k)?, ctxt.dispatch(stctxt, v)?);
+    Ok(vec![Item::Map(Rc::new(map))])
+}
+
+/// XPath map:remove function.
+pub(crate) fn map_remove<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    m: &Transform<N>,
+    k: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let mut map = (*single_map(ctxt, stctxt, m)?).clone();
+    for key in ctxt.dispatch(stctxt, k)? {
+        map.remove(&*atomize_key(&key)?)
+    }
+    Ok(vec![Item::Map(Rc::new(map))])
+}
+
+/// XPath map:for-each function.
+pub(crate) fn map_for_each<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    m: &Transform<N>,
+    f: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let map = single_map(ctxt, stctxt, m)?;
+    let func = single_function(ctxt, stctxt, f)?;
+    let mut result = vec![];
+    for (k, v) in map.iter() {
+        result.append(&mut call_item(
+            ctxt,
+            stctxt,
+            &func,
+            vec![vec![Item::Value(k.clone())], v.clone()],
+        )?)
+    }
+    Ok(result)
+}
@@ -37,35 +37,46 @@ assert_eq!(sequence.to_string(), "2")
 ```
 */
 
+pub mod accumulators;
+pub(crate) mod arrays;
 pub(crate) mod booleans;
 pub mod callable;
 pub(crate) mod construct;
 pub mod context;
 pub(crate) mod controlflow;
-pub(crate) mod datetime;
+pub mod datetime;
 pub(crate) mod functions;
 pub(crate) mod grouping;
+pub(crate) mod hof;
 mod keys;
 pub(crate) mod logic;
+pub(crate) mod maps;
 pub(crate) mod misc;
 pub(crate) mod navigate;
 pub mod numbers;
+pub(crate) mod regexp;
+pub(crate) mod sequences;
 pub(crate) mod strings;
 pub mod template;
+pub mod types;
 pub(crate) mod variables;
 
 #[allow(unused_imports)]
 use crate::item::Sequence;
-use crate::item::{Item, Node, NodeType, SequenceTrait};
+use crate::item::{Item, Node, NodeType};
 use crate::namespace::NamespaceMap;
+use crate::output::OutputDefinition;
 use crate::qname::QualifiedName;
 use crate::transform::callable::ActualParameters;
 use crate::transform::context::{Context, ContextBuilder, StaticContext};
-use crate::transform::numbers::Numbering;
+use crate::transform::maps::atomize_key;
+use crate::transform::numbers::{NumberFormat, Numbering};
+use crate::transform::types::{ItemType, Quantifier, SequenceType};
 use crate::value::Operator;
 #[allow(unused_imports)]
 use crate::value::Value;
 use crate::xdmerror::{Error, ErrorKind};
+use std::cmp::Ordering;
 use std::convert::TryFrom;
 use std::fmt;
 use std::fmt::{Debug, Formatter};
@@ -102,6 +113,10 @@ pub enum Transform<N: Node> {
     LiteralElement(Rc<QualifiedName>, Box<Transform<N>>),
     /// A constructed element. Consists of the name and content.
     Element(Box<Transform<N>>, Box<Transform<N>>),
+    /// A new document node, i.e. a temporary tree. Consists of the content of the document.
+    DocumentNode(Box<Transform<N>>),
+    /// A secondary result document. Consists of the URI of the document, how it is to be serialised, and the content of the document.
+    ResultDocument(Box<Transform<N>>, Box<OutputDefinition>, Box<Transform<N>>),
     /// A literal text node. Consists of the value of the node. Second argument gives whether to disable output escaping.
     LiteralText(Box<Transform<N>>, bool),
     /// A literal attribute. Consists of the attribute name and value.
@@ -131,6 +146,9 @@ pub enum Transform<N: Node> {
     /// XPath value comparison.
     /// The first singleton sequence is compared against the second singleton sequence.
     ValueComparison(Operator, Box<Transform<N>>, Box<Transform<N>>),
+    /// XPath node comparison.
+    /// The operator is one of is, << or >>. Each operand must be a single node or the empty sequence.
+    NodeComparison(Operator, Box<Transform<N>>, Box<Transform<N>>),
 
     /// Concatenate string values
     Concat(Vec<Transform<N>>),
@@ -144,6 +162,19 @@ pub enum Transform<N: Node> {
     Loop(Vec<(String, Transform<N>)>, Box<Transform<N>>),
     /// A branching transformation. Consists of (test, body) clauses and an otherwise clause.
     Switch(Vec<(Transform<N>, Transform<N>)>, Box<Transform<N>>),
+    /// A quantified expression. Consists of the quantifier, variable declarations, and the test expression.
+    Quantified(Quantifier, Vec<(String, Transform<N>)>, Box<Transform<N>>),
+
+    /// Test whether a sequence matches a sequence type.
+    InstanceOf(Box<Transform<N>>, SequenceType),
+    /// Assert that a sequence matches a sequence type.
+    Treat(Box<Transform<N>>, SequenceType),
+    /// Convert a sequence to a sequence type using the function conversion rules. Consists of the operand, the required type, the error code used if the conversion fails, and whether the operand is a sequence constructor, so that its strings are untyped.
+    Convert(Box<Transform<N>>, SequenceType, String, bool),
+    /// Cast a value to an atomic type. Consists of the operand, the target type, and whether the empty sequence is allowed.
+    Cast(Box<Transform<N>>, Value, bool),
+    /// Test whether a value can be cast to an atomic type. Consists of the operand, the target type, and whether the empty sequence is allowed.
+    Castable(Box<Transform<N>>, Value, bool),
 
     /// Evaluate a transformation for each selected item, with possible grouping and sorting.
     ForEach(
@@ -152,6 +183,25 @@ pub enum Transform<N: Node> {
         Box<Transform<N>>,
         Vec<(Order, Transform<N>)>,
     ),
+    /// Merge sequences of items that are sorted by the same keys. See XSLT 15.
+    /// Consists of the merge sources and the merge action, which is evaluated for each group of items with the same merge key values.
+    Merge(Vec<MergeSource<N>>, Box<Transform<N>>),
+    /// Iterate over a sequence, with parameters whose values may change for each item. See XSLT 7.1.
+    /// Consists of the select expression, the parameters and their initial values, the body, and the on-completion instruction.
+    Iterate(
+        Box<Transform<N>>,
+        Vec<(String, Transform<N>)>,
+        Box<Transform<N>>,
+        Box<Transform<N>>,
+    ),
+    /// Stop the enclosing iteration. The argument is the value of the instruction.
+    Break(Box<Transform<N>>),
+    /// Continue the enclosing iteration with new values for the given parameters.
+    NextIteration(Vec<(String, Transform<N>)>),
+    /// Recover from dynamic errors. See XSLT 8.3.
+    /// Consists of the body, the catch clauses, and whether secondary result documents are rolled back.
+    /// Each catch clause is the name tests for the error codes that it matches and the transform to evaluate.
+    Try(Box<Transform<N>>, Vec<(Vec<NameTest>, Transform<N>)>, bool),
     /// Find a template that matches an item and evaluate its body with the item as the context.
     /// Consists of the selector for items to be matched, the mode, and sort keys.
     ApplyTemplates(
@@ -159,6 +209,9 @@ pub enum Transform<N: Node> {
         Option<Rc<QualifiedName>>,
         Vec<(Order, Transform<N>)>,
     ),
+    /// Apply templates in the current mode, i.e. the mode of the template rule being evaluated.
+    /// Consists of the selector for items to be matched and sort keys.
+    ApplyTemplatesCurrentMode(Box<Transform<N>>, Vec<(Order, Transform<N>)>),
     /// Find templates at the next import level and evaluate its body.
     ApplyImports,
     NextMatch,
@@ -205,6 +258,95 @@ pub enum Transform<N: Node> {
     SubstringAfter(Box<Transform<N>>, Box<Transform<N>>),
     NormalizeSpace(Option<Box<Transform<N>>>),
     Translate(Box<Transform<N>>, Box<Transform<N>>, Box<Transform<N>>),
+    UpperCase(Box<Transform<N>>),
+    LowerCase(Box<Transform<N>>),
+    StringJoin(Box<Transform<N>>, Option<Box<Transform<N>>>),
+    StringLength(Option<Box<Transform<N>>>),
+    /// Tokenize a string. Arguments are the input string, the pattern and the flags.
+    /// If there is no pattern the string is split at whitespace.
+    Tokenize(
+        Box<Transform<N>>,
+        Option<Box<Transform<N>>>,
+        Option<Box<Transform<N>>>,
+    ),
+    Matches(
+        Box<Transform<N>>,
+        Box<Transform<N>>,
+        Option<Box<Transform<N>>>,
+    ),
+    Replace(
+        Box<Transform<N>>,
+        Box<Transform<N>>,
+        Box<Transform<N>>,
+        Option<Box<Transform<N>>>,
+    ),
+    AnalyzeString(
+        Box<Transform<N>>,
+        Box<Transform<N>>,
+        Option<Box<Transform<N>>>,
+    ),
+    /// XSLT analyze-string instruction.
+    /// The arguments are the input string, the regular expression, the flags,
+    /// the sequence constructor for matching substrings and the sequence constructor for non-matching substrings.
+    AnalyzeStringInstruction(
+        Box<Transform<N>>,
+        Box<Transform<N>>,
+        Box<Transform<N>>,
+        Box<Transform<N>>,
+        Box<Transform<N>>,
+    ),
+    /// A captured group of the current matching substring.
+    RegexGroup(Box<Transform<N>>),
+    CodepointsToString(Box<Transform<N>>),
+    StringToCodepoints(Box<Transform<N>>),
+    Compare(
+        Box<Transform<N>>,
+        Box<Transform<N>>,
+        Option<Box<Transform<N>>>,
+    ),
+    NormalizeUnicode(Box<Transform<N>>, Option<Box<Transform<N>>>),
+    EncodeForUri(Box<Transform<N>>),
+    EscapeHtmlUri(Box<Transform<N>>),
+    IriToUri(Box<Transform<N>>),
+    /// The empty function (not to be confused with [Transform::Empty]).
+    FnEmpty(Box<Transform<N>>),
+    Exists(Box<Transform<N>>),
+    DistinctValues(Box<Transform<N>>, Option<Box<Transform<N>>>),
+    IndexOf(
+        Box<Transform<N>>,
+        Box<Transform<N>>,
+        Option<Box<Transform<N>>>,
+    ),
+    /// Insert items into a sequence. Arguments are the sequence, the position and the items to insert.
+    InsertBefore(Box<Transform<N>>, Box<Transform<N>>, Box<Transform<N>>),
+    Remove(Box<Transform<N>>, Box<Transform<N>>),
+    Reverse(Box<Transform<N>>),
+    Subsequence(
+        Box<Transform<N>>,
+        Box<Transform<N>>,
+        Option<Box<Transform<N>>>,
+    ),
+    Head(Box<Transform<N>>),
+    Tail(Box<Transform<N>>),
+    ZeroOrOne(Box<Transform<N>>),
+    OneOrMore(Box<Transform<N>>),
+    ExactlyOne(Box<Transform<N>>),
+    DeepEqual(
+        Box<Transform<N>>,
+        Box<Transform<N>>,
+        Option<Box<Transform<N>>>,
+    ),
+    Innermost(Box<Transform<N>>),
+    Outermost(Box<Transform<N>>),
+    /// The root function with an argument. Without an argument, use [Transform::Root].
+    FnRoot(Box<Transform<N>>),
+    Path(Option<Box<Transform<N>>>),
+    HasChildren(Option<Box<Transform<N>>>),
+    NamespaceUri(Option<Box<Transform<N>>>),
+    /// The lang function. Arguments are the language to test and the node.
+    Lang(Box<Transform<N>>, Option<Box<Transform<N>>>),
+    Id(Box<Transform<N>>, Option<Box<Transform<N>>>),
+    Idref(Box<Transform<N>>, Option<Box<Transform<N>>>),
     GenerateId(Option<Box<Transform<N>>>),
     Boolean(Box<Transform<N>>),
     Not(Box<Transform<N>>),
@@ -219,6 +361,10 @@ pub enum Transform<N: Node> {
     Ceiling(Box<Transform<N>>),
     Round(Box<Transform<N>>, Option<Box<Transform<N>>>),
     CurrentDateTime,
+    /// Extract a component from a date, time or duration. The value gives the type of the argument.
+    DateTimeComponent(datetime::Component, Value, Box<Transform<N>>),
+    /// Adjust a dateTime or time to a timezone. The value gives the type of the argument.
+    AdjustToTimezone(Value, Box<Transform<N>>, Option<Box<Transform<N>>>),
     CurrentDate,
     CurrentTime,
     FormatDateTime(
@@ -242,10 +388,14 @@ pub enum Transform<N: Node> {
         Option<Box<Transform<N>>>,
         Option<Box<Transform<N>>>,
     ),
+    /// Format a number. The first argument is the number, the second argument is the picture string,
+    /// the third argument is the name of a decimal format,
+    /// the fourth argument is the in-scope namespaces.
     FormatNumber(
         Box<Transform<N>>,
         Box<Transform<N>>,
         Option<Box<Transform<N>>>,
+        Rc<NamespaceMap>,
     ),
     /// Convert a number to a string.
     /// This is one half of the functionality of xsl:number, as well as format-integer().
@@ -253,6 +403,12 @@ pub enum Transform<N: Node> {
     /// First argument is the integer to be formatted.
     /// Second argument is the format specification.
     FormatInteger(Box<Transform<N>>, Box<Transform<N>>),
+    /// Convert a sequence of numbers to a string, as for xsl:number.
+    /// See XSLT 12.4.
+    /// First argument is the sequence of integers to be formatted.
+    /// Second argument is the format specification.
+    /// Third argument gives the grouping, language and letter-value settings.
+    NumberToString(Box<Transform<N>>, Box<Transform<N>>, Box<NumberFormat>),
     /// Generate a sequence of integers. This is one half of the functionality of xsl:number.
     /// First argument is the start-at specification.
     /// Second argument is the select expression.
@@ -262,6 +418,9 @@ pub enum Transform<N: Node> {
     GenerateIntegers(Box<Transform<N>>, Box<Transform<N>>, Box<Numbering<N>>),
     CurrentGroup,
     CurrentGroupingKey,
+    /// The items in the current merge group. The argument is the name of a merge source, to select only the items from that source.
+    CurrentMergeGroup(Option<Box<Transform<N>>>),
+    CurrentMergeKey,
     /// Look up a key. The first argument is the key name, the second argument is the key value,
     /// the third argument is the top of the tree for the resulting nodes,
     /// the fourth argument is the in-scope namespaces.
@@ -271,14 +430,104 @@ pub enum Transform<N: Node> {
         Option<Box<Transform<N>>>,
         Rc<NamespaceMap>,
     ),
+    /// The value of an accumulator before the descendants of the context node are visited. Consists of the accumulator name and the in-scope namespaces.
+    AccumulatorBefore(Box<Transform<N>>, Rc<NamespaceMap>),
+    /// The value of an accumulator after the descendants of the context node are visited. Consists of the accumulator name and the in-scope namespaces.
+    AccumulatorAfter(Box<Transform<N>>, Rc<NamespaceMap>),
     /// Get information about the processor
     SystemProperty(Box<Transform<N>>, Rc<NamespaceMap>),
     AvailableSystemProperties,
     /// Read an external document
     Document(Box<Transform<N>>, Option<Box<Transform<N>>>),
 
+    /// Construct a map. Each entry is a (key, value) pair.
+    MapConstructor(Vec<(Transform<N>, Transform<N>)>),
+    /// Construct an array. Each transformation produces one member.
+    SquareArrayConstructor(Vec<Transform<N>>),
+    /// Construct an array. Each item produced by the transformation becomes a member.
+    CurlyArrayConstructor(Box<Transform<N>>),
+    /// The lookup operator for maps and arrays.
+    /// Consists of the maps/arrays to look up and the key specifier. A key specifier of None is the wildcard.
+    Lookup(Box<Transform<N>>, Option<Box<Transform<N>>>),
+
+    /// Create a function item. Consists of the function name (for a named function reference),
+    /// the parameter names and their declared types, and the function body.
+    InlineFunction(
+        Option<Rc<QualifiedName>>,
+        Vec<(QualifiedName, Option<SequenceType>)>,
+        Box<Transform<N>>,
+    ),
+    /// Call a function item. Consists of the function and the arguments.
+    DynamicCall(Box<Transform<N>>, Vec<Transform<N>>),
+    /// Partial function application. Consists of the function and the arguments.
+    /// An argument placeholder ("?") is None.
+    PartialApplication(Box<Transform<N>>, Vec<Option<Transform<N>>>),
+
+    /// Higher-order functions
+    FnForEach(Box<Transform<N>>, Box<Transform<N>>),
+    FnFilter(Box<Transform<N>>, Box<Transform<N>>),
+    FoldLeft(Box<Transform<N>>, Box<Transform<N>>, Box<Transform<N>>),
+    FoldRight(Box<Transform<N>>, Box<Transform<N>>, Box<Transform<N>>),
+    /// Sort a sequence. Consists of the input sequence, the collation, and the key function.
+    FnSort(
+        Box<Transform<N>>,
+        Option<Box<Transform<N>>>,
+        Option<Box<Transform<N>>>,
+    ),
+
+    /// Map functions
+    MapMerge(Box<Transform<N>>, Option<Box<Transform<N>>>),
+    MapSize(Box<Transform<N>>),
+    MapKeys(Box<Transform<N>>),
+    MapContains(Box<Transform<N>>, Box<Transform<N>>),
+    MapGet(Box<Transform<N>>, Box<Transform<N>>),
+    MapFind(Box<Transform<N>>, Box<Transform<N>>),
+    MapPut(Box<Transform<N>>, Box<Transform<N>>, Box<Transform<N>>),
+    MapEntry(Box<Transform<N>>, Box<Transform<N>>),
+    MapRemove(Box<Transform<N>>, Box<Transform<N>>),
+    MapForEach(Box<Transform<N>>, Box<Transform<N>>),
+
+    /// Array functions
+    ArraySize(Box<Transform<N>>),
+    ArrayGet(Box<Transform<N>>, Box<Transform<N>>),
+    ArrayPut(Box<Transform<N>>, Box<Transform<N>>, Box<Transform<N>>),
+    ArrayAppend(Box<Transform<N>>, Box<Transform<N>>),
+    ArraySubarray(
+        Box<Transform<N>>,
+        Box<Transform<N>>,
+        Option<Box<Transform<N>>>,
+    ),
+    ArrayRemove(Box<Transform<N>>, Box<Transform<N>>),
+    ArrayInsertBefore(Box<Transform<N>>, Box<Transform<N>>, Box<Transform<N>>),
+    ArrayHead(Box<Transform<N>>),
+    ArrayTail(Box<Transform<N>>),
+    ArrayReverse(Box<Transform<N>>),
+    ArrayJoin(Box<Transform<N>>),
+    ArrayFlatten(Box<Transform<N>>),
+    ArrayForEach(Box<Transform<N>>, Box<Transform<N>>),
+    ArrayFilter(Box<Transform<N>>, Box<Transform<N>>),
+    ArrayFoldLeft(Box<Transform<N>>, Box<Transform<N>>, Box<Transform<N>>),
+    ArrayFoldRight(Box<Transform<N>>, Box<Transform<N>>, Box<Transform<N>>),
+    ArraySort(
+        Box<Transform<N>>,
+        Option<Box<Transform<N>>>,
+        Option<Box<Transform<N>>>,
+    ),
+
     /// Invoke a callable component. Consists of a name, an actual argument list, and in-scope namespace declarations.
     Invoke(Rc<QualifiedName>, ActualParameters<N>, Rc<NamespaceMap>),
+    /// Evaluate a transformation with additional tunnel parameters. Consists of the parameter names and values, and the transformation.
+    Tunnel(Vec<(String, Transform<N>)>, Box<Transform<N>>),
+    /// The value of a tunnel parameter. Consists of the parameter name and its default value.
+    TunnelParameter(String, Box<Transform<N>>),
+    /// Apply templates, passing non-tunnel parameters to the template rules that are invoked.
+    /// Consists of the parameter names and values, and the xsl:apply-templates transformation.
+    WithParameters(Vec<(String, Transform<N>)>, Box<Transform<N>>),
+    /// Apply templates, passing on the non-tunnel parameters supplied to the template rule being evaluated.
+    /// This is how the built-in template rules pass their parameters. See XSLT 6.7.
+    PassParameters(Box<Transform<N>>),
+    /// The value of a parameter of a template rule. Consists of the parameter name and its default value.
+    TemplateParameter(String, Box<Transform<N>>),
 
     /// Emit a message. Consists of a select expression, a terminate attribute, an error-code, and a body.
     Message(
@@ -289,11 +538,20 @@ pub enum Transform<N: Node> {
     ),
 
     /// For things that are not yet implemented, such as:
-    /// Union, IntersectExcept, InstanceOf, Treat, Castable, Cast, Arrow, Unary, SimpleMap, Is, Before, After.
+    /// Union, IntersectExcept, Unary.
     NotImplemented(String),
 
     /// Error condition.
     Error(ErrorKind, String),
+    /// Raise a dynamic error, as for the XPath error function.
+    /// Consists of the error code, description and error object.
+    RaiseError(
+        Option<Box<Transform<N>>>,
+        Option<Box<Transform<N>>>,
+        Option<Box<Transform<N>>>,
+    ),
+    /// Create a QName value. Consists of the namespace URI and the lexical QName.
+    QName(Box<Transform<N>>, Box<Transform<N>>),
 }
 
 impl<N: Node> Debug for Transform<N> {
@@ -320,6 +578,8 @@ impl<N: Node> Debug for Transform<N> {
             Transform::LiteralText(_, b) => write!(f, "literal text (disable escaping {})", b),
             Transform::LiteralAttribute(qn, _) => write!(f, "literal attribute named \"{}\"", qn),
             Transform::LiteralComment(_) => write!(f, "literal comment"),
+            Transform::DocumentNode(_) => write!(f, "document node"),
+            Transform::ResultDocument(h, _, _) => write!(f, "result document {:?}", h),
             Transform::LiteralProcessingInstruction(_, _) => {
                 write!(f, "literal processing-instruction")
             }
@@ -331,6 +591,9 @@ impl<N: Node> Debug for Transform<N> {
             Transform::ValueComparison(o, v, u) => {
                 write!(f, "value comparison {} of {:?} and {:?}", o, v, u)
             }
+            Transform::NodeComparison(o, v, u) => {
+                write!(f, "node comparison {} of {:?} and {:?}", o, v, u)
+            }
             Transform::Concat(o) => write!(f, "Concatenate {} operands", o.len()),
             Transform::Range(_, _) => write!(f, "range"),
             Transform::Arithmetic(o) => write!(f, "Arithmetic {} operands", o.len()),
@@ -338,11 +601,31 @@ impl<N: Node> Debug for Transform<N> {
             Transform::Or(o) => write!(f, "OR {} operands", o.len()),
             Transform::Loop(_, _) => write!(f, "loop"),
             Transform::Switch(c, _) => write!(f, "switch {} clauses", c.len()),
+            Transform::Quantified(q, v, _) => write!(f, "{:?} quantified {} variables", q, v.len()),
+            Transform::InstanceOf(s, t) => write!(f, "{:?} instance of {}", s, t),
+            Transform::Treat(s, t) => write!(f, "{:?} treat as {}", s, t),
+            Transform::Convert(s, t, _, _) => write!(f, "{:?} converted to {}", s, t),
+            Transform::Cast(s, t, _) => {
+                write!(f, "{:?} cast as {}", s, ItemType::Atomic(t.clone()))
+            }
+            Transform::Castable(s, t, _) => {
+                write!(f, "{:?} castable as {}", s, ItemType::Atomic(t.clone()))
+            }
             Transform::ForEach(_g, _, _, o) => write!(f, "for-each ({} sort keys)", o.len()),
+            Transform::Merge(s, _) => write!(f, "merge ({} sources)", s.len()),
+            Transform::Iterate(_, p, _, _) => write!(f, "iterate ({} parameters)", p.len()),
+            Transform::Break(_) => write!(f, "break"),
+            Transform::NextIteration(p) => write!(f, "next-iteration ({} parameters)", p.len()),
+            Transform::Try(_, c, _) => write!(f, "try ({} catch clauses)", c.len()),
+            Transform::RaiseError(_, _, _) => write!(f, "error()"),
+            Transform::QName(u, q) => write!(f, "QName({:?}, {:?})", u, q),
             Transform::Union(v) => write!(f, "union of {} operands", v.len()),
             Transform::ApplyTemplates(_, m, o) => {
                 write!(f, "Apply templates (mode {:?}, {} sort keys)", m, o.len())
             }
+            Transform::ApplyTemplatesCurrentMode(_, o) => {
+                write!(f, "Apply templates (current mode, {} sort keys)", o.len())
+            }
             Transform::Call(_, a, _) => write!(f, "Call transform with {} arguments", a.len()),
             Transform::ApplyImports => write!(f, "Apply imports"),
             Transform::NextMatch => write!(f, "next-match"),
@@ -363,6 +646,54 @@ impl<N: Node> Debug for Transform<N> {
             Transform::SubstringAfter(s, t) => write!(f, "substring-after({:?}, {:?})", s, t),
             Transform::NormalizeSpace(_s) => write!(f, "normalize-space()"),
             Transform::Translate(s, t, u) => write!(f, "translate({:?}, {:?}, {:?})", s, t, u),
+            Transform::UpperCase(s) => write!(f, "upper-case({:?})", s),
+            Transform::LowerCase(s) => write!(f, "lower-case({:?})", s),
+            Transform::StringJoin(s, _) => write!(f, "string-join({:?}, ...)", s),
+            Transform::StringLength(_) => write!(f, "string-length()"),
+            Transform::Tokenize(s, _, _) => write!(f, "tokenize({:?}, ...)", s),
+            Transform::Matches(s, p, _) => write!(f, "matches({:?}, {:?}, ...)", s, p),
+            Transform::Replace(s, p, r, _) => {
+                write!(f, "replace({:?}, {:?}, {:?}, ...)", s, p, r)
+            }
+            Transform::AnalyzeString(s, p, _) => {
+                write!(f, "analyze-string({:?}, {:?}, ...)", s, p)
+            }
+            Transform::AnalyzeStringInstruction(s, p, _, _, _) => {
+                write!(f, "analyze-string instruction({:?}, {:?}, ...)", s, p)
+            }
+            Transform::RegexGroup(n) => write!(f, "regex-group({:?})", n),
+            Transform::CodepointsToString(s) => write!(f, "codepoints-to-string({:?})", s),
+            Transform::StringToCodepoints(s) => write!(f, "string-to-codepoints({:?})", s),
+            Transform::Compare(s, t, _) => write!(f, "compare({:?}, {:?}, ...)", s, t),
+            Transform::NormalizeUnicode(s, _) => write!(f, "normalize-unicode({:?}, ...)", s),
+            Transform::EncodeForUri(s) => write!(f, "encode-for-uri({:?})", s),
+            Transform::EscapeHtmlUri(s) => write!(f, "escape-html-uri({:?})", s),
+            Transform::IriToUri(s) => write!(f, "iri-to-uri({:?})", s),
+            Transform::FnEmpty(s) => write!(f, "empty({:?})", s),
+            Transform::Exists(s) => write!(f, "exists({:?})", s),
+            Transform::DistinctValues(s, _) => write!(f, "distinct-values({:?}, ...)", s),
+            Transform::IndexOf(s, t, _) => write!(f, "index-of({:?}, {:?}, ...)", s, t),
+            Transform::InsertBefore(s, p, i) => {
+                write!(f, "insert-before({:?}, {:?}, {:?})", s, p, i)
+            }
+            Transform::Remove(s, p) => write!(f, "remove({:?}, {:?})", s, p),
+            Transform::Reverse(s) => write!(f, "reverse({:?})", s),
+            Transform::Subsequence(s, p, _) => write!(f, "subsequence({:?}, {:?}, ...)", s, p),
+            Transform::Head(s) => write!(f, "head({:?})", s),
+            Transform::Tail(s) => write!(f, "tail({:?})", s),
+            Transform::ZeroOrOne(s) => write!(f, "zero-or-one({:?})", s),
+            Transform::OneOrMore(s) => write!(f, "one-or-more({:?})", s),
+            Transform::ExactlyOne(s) => write!(f, "exactly-one({:?})", s),
+            Transform::DeepEqual(s, t, _) => write!(f, "deep-equal({:?}, {:?}, ...)", s, t),
+            Transform::Innermost(s) => write!(f, "innermost({:?})", s),
+            Transform::Outermost(s) => write!(f, "outermost({:?})", s),
+            Transform::FnRoot(s) => write!(f, "root({:?})", s),
+            Transform::Path(_) => write!(f, "path()"),
+            Transform::HasChildren(_) => write!(f, "has-children()"),
+            Transform::NamespaceUri(_) => write!(f, "namespace-uri()"),
+            Transform::Lang(l, _) => write!(f, "lang({:?}, ...)", l),
+            Transform::Id(s, _) => write!(f, "id({:?}, ...)", s),
+            Transform::Idref(s, _) => write!(f, "idref({:?}, ...)", s),
             Transform::GenerateId(_) => write!(f, "generate-id()"),
             Transform::Boolean(b) => write!(f, "boolean({:?})", b),
             Transform::Not(b) => write!(f, "not({:?})", b),
@@ -377,6 +708,12 @@ impl<N: Node> Debug for Transform<N> {
             Transform::Ceiling(n) => write!(f, "ceiling({:?})", n),
             Transform::Round(n, _p) => write!(f, "round({:?},...)", n),
             Transform::CurrentDateTime => write!(f, "current-date-time"),
+            Transform::DateTimeComponent(c, t, v) => {
+                write!(f, "{:?}-from-{}({:?})", c, t.value_type(), v)
+            }
+            Transform::AdjustToTimezone(t, v, _) => {
+                write!(f, "adjust-{}-to-timezone({:?}, ...)", t.value_type(), v)
+            }
             Transform::CurrentDate => write!(f, "current-date"),
             Transform::CurrentTime => write!(f, "current-time"),
             Transform::FormatDateTime(p, q, _, _, _) => {
@@ -384,16 +721,80 @@ impl<N: Node> Debug for Transform<N> {
             }
             Transform::FormatDate(p, q, _, _, _) => write!(f, "format-date({:?}, {:?}, ...)", p, q),
             Transform::FormatTime(p, q, _, _, _) => write!(f, "format-time({:?}, {:?}, ...)", p, q),
-            Transform::FormatNumber(v, p, _) => write!(f, "format-number({:?}, {:?})", v, p),
+            Transform::FormatNumber(v, p, _, _) => write!(f, "format-number({:?}, {:?})", v, p),
             Transform::FormatInteger(i, s) => write!(f, "format-integer({:?}, {:?})", i, s),
+            Transform::NumberToString(i, s, _) => write!(f, "number-to-string({:?}, {:?})", i, s),
             Transform::GenerateIntegers(_start_at, _select, _n) => write!(f, "generate-integers"),
             Transform::CurrentGroup => write!(f, "current-group"),
             Transform::CurrentGroupingKey => write!(f, "current-grouping-key"),
+            Transform::CurrentMergeGroup(_) => write!(f, "current-merge-group"),
+            Transform::CurrentMergeKey => write!(f, "current-merge-key"),
             Transform::Key(s, _, _, _) => write!(f, "key({:?}, ...)", s),
+            Transform::AccumulatorBefore(s, _) => write!(f, "accumulator-before({:?})", s),
+            Transform::AccumulatorAfter(s, _) => write!(f, "accumulator-after({:?})", s),
             Transform::SystemProperty(p, _) => write!(f, "system-properties({:?})", p),
             Transform::AvailableSystemProperties => write!(f, "available-system-properties"),
             Transform::Document(uris, _) => write!(f, "document({:?})", uris),
+            Transform::MapConstructor(e) => write!(f, "map constructor ({} entries)", e.len()),
+            Transform::SquareArrayConstructor(m) => {
+                write!(f, "square array constructor ({} members)", m.len())
+            }
+            Transform::CurlyArrayConstructor(_) => write!(f, "curly array constructor"),
+            Transform::Lookup(e, k) => write!(f, "lookup {:?} in {:?}", k, e),
+            Transform::InlineFunction(n, p, _) => match n {
+                Some(qn) => write!(f, "function {}#{}", qn, p.len()),
+                None => write!(f, "inline function with {} parameters", p.len()),
+            },
+            Transform::DynamicCall(g, a) => {
+                write!(f, "dynamic call {:?} with {} arguments", g, a.len())
+            }
+            Transform::PartialApplication(g, a) => {
+                write!(f, "partial application {:?} with {} arguments", g, a.len())
+            }
+            Transform::FnForEach(s, g) => write!(f, "for-each({:?}, {:?})", s, g),
+            Transform::FnFilter(s, g) => write!(f, "filter({:?}, {:?})", s, g),
+            Transform::FoldLeft(s, z, g) => write!(f, "fold-left({:?}, {:?}, {:?})", s, z, g),
+            Transform::FoldRight(s, z, g) => write!(f, "fold-right({:?}, {:?}, {:?})", s, z, g),
+            Transform::FnSort(s, _, _) => write!(f, "sort({:?}, ...)", s),
+            Transform::MapMerge(m, _) => write!(f, "map:merge({:?}, ...)", m),
+            Transform::MapSize(m) => write!(f, "map:size({:?})", m),
+            Transform::MapKeys(m) => write!(f, "map:keys({:?})", m),
+            Transform::MapContains(m, k) => write!(f, "map:contains({:?}, {:?})", m, k),
+            Transform::MapGet(m, k) => write!(f, "map:get({:?}, {:?})", m, k),
+            Transform::MapFind(i, k) => write!(f, "map:find({:?}, {:?})", i, k),
+            Transform::MapPut(m, k, v) => write!(f, "map:put({:?}, {:?}, {:?})", m, k, v),
+            Transform::MapEntry(k, v) => write!(f, "map:entry({:?}, {:?})", k, v),
+            Transform::MapRemove(m, k) => write!(f, "map:remove({:?}, {:?})", m, k),
+            Transform::MapForEach(m, g) => write!(f, "map:for-each({:?}, {:?})", m, g),
+            Transform::ArraySize(a) => write!(f, "array:size({:?})", a),
+            Transform::ArrayGet(a, p) => write!(f, "array:get({:?}, {:?})", a, p),
+            Transform::ArrayPut(a, p, m) => write!(f, "array:put({:?}, {:?}, {:?})", a, p, m),
+            Transform::ArrayAppend(a, m) => write!(f, "array:append({:?}, {:?})", a, m),
+            Transform::ArraySubarray(a, s, _) => write!(f, "array:subarray({:?}, {:?}, ...)", a, s),
+            Transform::ArrayRemove(a, p) => write!(f, "array:remove({:?}, {:?})", a, p),
+            Transform::ArrayInsertBefore(a, p, m) => {
+                write!(f, "array:insert-before({:?}, {:?}, {:?})", a, p, m)
+            }
+            Transform::ArrayHead(a) => write!(f, "array:head({:?})", a),
+            Transform::ArrayTail(a) => write!(f, "array:tail({:?})", a),
+            Transform::ArrayReverse(a) => write!(f, "array:reverse({:?})", a),
+            Transform::ArrayJoin(a) => write!(f, "array:join({:?})", a),
+            Transform::ArrayFlatten(a) => write!(f, "array:flatten({:?})", a),
+            Transform::ArrayForEach(a, g) => write!(f, "array:for-each({:?}, {:?})", a, g),
+            Transform::ArrayFilter(a, g) => write!(f, "array:filter({:?}, {:?})", a, g),
+            Transform::ArrayFoldLeft(a, z, g) => {
+                write!(f, "array:fold-left({:?}, {:?}, {:?})", a, z, g)
+            }
+            Transform::ArrayFoldRight(a, z, g) => {
+                write!(f, "array:fold-right({:?}, {:?}, {:?})", a, z, g)
+            }
+            Transform::ArraySort(a, _, _) => write!(f, "array:sort({:?}, ...)", a),
             Transform::Invoke(qn, _a, _) => write!(f, "invoke \"{}\"", qn),
+            Transform::Tunnel(p, b) => write!(f, "{:?} with {} tunnel parameters", b, p.len()),
+            Transform::TunnelParameter(n, _) => write!(f, "tunnel parameter \"{}\"", n),
+            Transform::WithParameters(p, b) => write!(f, "{:?} with {} parameters", b, p.len()),
+            Transform::PassParameters(b) => write!(f, "{:?} passing parameters", b),
+            Transform::TemplateParameter(n, _) => write!(f, "template parameter \"{}\"", n),
             Transform::Message(_, _, _, _) => write!(f, "message"),
             Transform::NotImplemented(s) => write!(f, "Not implemented: \"{}\"", s),
             Transform::Error(k, s) => write!(f, "Error: {} \"{}\"", k, s),
@@ -401,6 +802,15 @@ impl<N: Node> Debug for Transform<N> {
     }
 }
 
+/// The name that identifies a variable.
+/// A variable in no namespace is identified by its local name, otherwise by its expanded name, Q{uri}local.
+pub(crate) fn variable_name(qn: &QualifiedName) -> String {
+    qn.namespace_uri_to_string().map_or_else(
+        || qn.localname_to_string(),
+        |ns| format!("Q{{{}}}{}", ns, qn.localname_to_string()),
+    )
+}
+
 /// A convenience function to create a namespace mapping from a [Node].
 pub fn in_scope_namespaces<N: Node>(n: Option<N>) -> Rc<NamespaceMap> {
     if let Some(nn) = n {
@@ -421,6 +831,7 @@ pub enum Order {
 }
 
 /// Performing sorting of a [Sequence] using the given sort keys.
+/// The sort is stable, so items with equal sort key values remain in their original order.
 pub(crate) fn do_sort<
     N: Node,
     F: FnMut(&str) -> Result<(), Error>,
@@ -432,28 +843,111 @@ pub(crate) fn do_sort<
     ctxt: &Context<N>,
     stctxt: &mut StaticContext<N, F, G, H>,
 ) -> Result<(), Error> {
-    // Optionally sort the select sequence
-    // TODO: multiple sort keys
     if !o.is_empty() {
-        seq.sort_by_cached_key(|k| {
-            // TODO: Don't panic
-            let key_seq = ContextBuilder::from(ctxt)
-                .context(vec![k.clone()])
-                .build()
-                .dispatch(stctxt, &o[0].1)
-                .expect("unable to determine key value");
-            // Assume string data type for now
-            // TODO: support number data type
-            // TODO: support all data types
-            key_seq.to_string()
-        });
-        if o[0].0 == Order::Descending {
-            seq.reverse();
-        }
+        let mut keyed = seq
+            .drain(..)
+            .map(|i| Ok((sort_key_values(ctxt, stctxt, &i, o)?, i)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        keyed.sort_by(|(a, _), (b, _)| compare_sort_keys(a, b, o));
+        seq.extend(keyed.into_iter().map(|(_, i)| i));
     }
     Ok(())
 }
 
+/// Evaluate the sort keys for an item, with the item as the context item.
+/// Each sort key value is atomized. An empty sort key has no value.
+pub(crate) fn sort_key_values<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    item: &Item<N>,
+    keys: &[(Order, Transform<N>)],
+) -> Result<Vec<Option<Rc<Value>>>, Error> {
+    let kctxt = ContextBuilder::from(ctxt)
+        .context(vec![item.clone()])
+        .index(0)
+        .build();
+    keys.iter()
+        .map(|(_, k)| {
+            let v = kctxt.dispatch(stctxt, k)?;
+            match v.len() {
+                0 => Ok(None),
+                1 => Ok(Some(atomize_key(&v[0])?)),
+                _ => Err(Error::new_with_code(
+                    ErrorKind::TypeError,
+                    "sort key value is a sequence of more than one item",
+                    Some(QualifiedName::new(None, None, "XTTE1020")),
+                )),
+            }
+        })
+        .collect()
+}
+
+/// Compare sort key values. Numbers are compared numerically, all other values are compared as strings.
+/// An empty sort key sorts before any other value.
+pub(crate) fn compare_sort_keys<N: Node>(
+    a: &[Option<Rc<Value>>],
+    b: &[Option<Rc<Value>>],
+    keys: &[(Order, Transform<N>)],
+) -> Ordering {
+    a.iter()
+        .zip(b.iter())
+        .zip(keys.iter())
+        .map(|((x, y), (o, _))| {
+            let c = match (x, y) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Less,
+                (Some(_), None) => Ordering::Greater,
+                (Some(x), Some(y)) if x.is_numeric() && y.is_numeric() => x
+                    .to_double()
+                    .partial_cmp(&y.to_double())
+                    .unwrap_or(Ordering::Equal),
+                (Some(x), Some(y)) => x.to_string().cmp(&y.to_string()),
+            };
+            if *o == Order::Descending {
+                c.reverse()
+            } else {
+                c
+            }
+        })
+        .find(|c| *c != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
+
+/// A source of items for xsl:merge.
+/// The select expression is evaluated once for each item of the for-each expression, if there is one,
+/// with that item as the context item. Each evaluation is an input sequence, sorted by the merge keys.
+#[derive(Clone, Debug)]
+pub struct MergeSource<N: Node> {
+    pub(crate) name: Option<String>,
+    pub(crate) for_each: Option<Transform<N>>,
+    pub(crate) select: Transform<N>,
+    pub(crate) sort_before_merge: bool,
+    pub(crate) keys: Vec<(Order, Transform<N>)>,
+}
+
+impl<N: Node> MergeSource<N> {
+    pub fn new(
+        name: Option<String>,
+        for_each: Option<Transform<N>>,
+        select: Transform<N>,
+        sort_before_merge: bool,
+        keys: Vec<(Order, Transform<N>)>,
+    ) -> Self {
+        MergeSource {
+            name,
+            for_each,
+            select,
+            sort_before_merge,
+            keys,
+        }
+    }
+}
+
 /// Determine how a collection is to be divided into groups.
 /// This value would normally be inside an Option.
 /// A None value for the option means that the collection is not to be grouped.
@@ -1,10 +1,14 @@
 //! Navigation routines
 
 use crate::item::{Node, NodeType, Sequence, SequenceTrait};
+use crate::qname::QualifiedName;
+use crate::transform::booleans::effective_boolean_value;
 use crate::transform::context::{Context, ContextBuilder, StaticContext};
 use crate::transform::{Axis, NodeMatch, Transform};
+use crate::value::Value;
 use crate::xdmerror::{Error, ErrorKind};
 use crate::Item;
+use std::rc::Rc;
 use url::Url;
 
 /// The root node of the context item.
@@ -318,15 +322,404 @@ pub(crate) fn filter<
     predicate: &Transform<N>,
 ) -> Result<Sequence<N>, Error> {
     ctxt.cur.iter().try_fold(vec![], |mut acc, i| {
-        if ContextBuilder::from(ctxt)
-            .context(vec![i.clone()])
-            .previous_context(ctxt.previous_context.clone())
-            .build()
-            .dispatch(stctxt, predicate)?
-            .to_bool()
-        {
+        if effective_boolean_value(
+            &ContextBuilder::from(ctxt)
+                .context(vec![i.clone()])
+                .previous_context(ctxt.previous_context.clone())
+                .build()
+                .dispatch(stctxt, predicate)?,
+        )? {
             acc.push(i.clone())
         }
         Ok(acc)
     })
 }
+
+// The node supplied as an optional argument. If the argument is absent, the context item is used.
+// The empty sequence gives None.
+fn optional_node<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    t: &Option<Box<Transform<N>>>,
+) -> Result<Option<N>, Error> {
+    let seq = match t {
+        Some(u) => ctxt.dispatch(stctxt, u)?,
+        None => match ctxt.cur.get(ctxt.i) {
+            Some(i) => vec![i.clone()],
+            None => {
+                return Err(Error::new_with_code(
+                    ErrorKind::DynamicAbsent,
+                    "no context item",
+                    Some(QualifiedName::new(None, None, "XPDY0002")),
+                ))
+            }
+        },
+    };
+    match seq.len() {
+        0 => Ok(None),
+        1 => match &seq[0] {
+            Item::Node(n) => Ok(Some(n.clone())),
+            _ => Err(Error::new_with_code(
+                ErrorKind::TypeError,
+                "item is not a node",
+                Some(QualifiedName::new(None, None, "XPTY0004")),
+            )),
+        },
+        _ => Err(Error::new_with_code(
+            ErrorKind::TypeError,
+            "expected a single node",
+            Some(QualifiedName::new(None, None, "XPTY0004")),
+        )),
+    }
+}
+
+// The nodes in a sequence, in document order without duplicates.
+fn nodes_in_order<N: Node>(seq: Sequence<N>) -> Result<Vec<N>, Error> {
+    let mut nodes = seq
+        .into_iter()
+        .map(|i| match i {
+            Item::Node(n) => Ok(n),
+            _ => Err(Error::new_with_code(
+                ErrorKind::TypeError,
+                "item is not a node",
+                Some(QualifiedName::new(None, None, "XPTY0004")),
+            )),
+        })
+        .collect::<Result<Vec<N>, Error>>()?;
+    nodes.sort_by(|a, b| a.cmp_document_order(b));
+    nodes.dedup_by(|a, b| a.is_same(b));
+    Ok(nodes)
+}
+
+// The top of the tree containing the node.
+fn tree_root<N: Node>(n: &N) -> N {
+    n.ancestor_iter().last().unwrap_or_else(|| n.clone())
+}
+
+/// XPath root function, with an argument.
+pub(crate) fn fn_root<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    t: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    Ok(optional_node(ctxt, stctxt, &Some(Box::new(t.clone())))?
+        .map_or(vec![], |n| vec![Item::Node(tree_root(&n))]))
+}
+
+/// XPath innermost function. Nodes that are an ancestor of another node in the sequence are removed.
+pub(crate) fn innermost<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    t: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let nodes = nodes_in_order(ctxt.dispatch(stctxt, t)?)?;
+    Ok(nodes
+        .iter()
+        .filter(|n| {
+            !nodes
+                .iter()
+                .any(|m| m.ancestor_iter().any(|a| a.is_same(n)))
+        })
+        .map(|n| Item::Node(n.clone()))
+        .collect())
+}
+
+/// XPath outermost function. Nodes that have an ancestor in the sequence are removed.
+pub(crate) fn outermost<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    t: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let nodes = nodes_in_order(ctxt.dispatch(stctxt, t)?)?;
+    Ok(nodes
+        .iter()
+        .filter(|n| {
+            !n.ancestor_iter()
+                .any(|a| nodes.iter().any(|m| m.is_same(&a)))
+        })
+        .map(|n| Item::Node(n.clone()))
+        .collect())
+}
+
+/// XPath has-children function.
+pub(crate) fn has_children<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    t: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    Ok(vec![Item::Value(Rc::new(Value::from(
+        optional_node(ctxt, stctxt, t)?.is_some_and(|n| n.first_child().is_some()),
+    )))])
+}
+
+/// XPath namespace-uri function.
+/// For nodes other than elements and attributes, or names with no namespace, the result is the empty string.
+pub(crate) fn namespace_uri<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    t: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let uri = optional_node(ctxt, stctxt, t)?
+        .filter(|n| matches!(n.node_type(), NodeType::Element | NodeType::Attribute))
+        .and_then(|n| n.name().namespace_uri_to_string())
+        .unwrap_or_default();
+    Ok(vec![Item::Value(Rc::new(Value::from(uri)))])
+}
+
+const XMLNS: &str = "http://www.w3.org/XML/1998/namespace";
+const XPATH_FUNCTIONS_NS: &str = "http://www.w3.org/2005/xpath-functions";
+
+// Is this attribute in the XML namespace, with the given local name?
+// The name may not have been resolved, so the xml prefix is also accepted.
+fn is_xml_attribute<N: Node>(a: &N, local: &str) -> bool {
+    let qn = a.name();
+    qn.localname_to_string() == local
+        && (qn.namespace_uri_to_string().as_deref() == Some(XMLNS)
+            || qn.prefix_to_string().as_deref() == Some("xml"))
+}
+
+/// XPath lang function.
+/// The language is given by the xml:lang attribute of the node, or its nearest ancestor that has one.
+pub(crate) fn lang<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    l: &Transform<N>,
+    t: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let test = ctxt.dispatch(stctxt, l)?.to_string().to_lowercase();
+    let n = optional_node(ctxt, stctxt, t)?.ok_or_else(|| {
+        Error::new_with_code(
+            ErrorKind::TypeError,
+            "lang requires a node",
+            Some(QualifiedName::new(None, None, "XPTY0004")),
+        )
+    })?;
+    let found = std::iter::once(n.clone())
+        .chain(n.ancestor_iter())
+        .find_map(|a| a.attribute_iter().find(|b| is_xml_attribute(b, "lang")))
+        .map(|a| a.to_string().to_lowercase());
+    let result = found.is_some_and(|v| {
+        v == test || (v.starts_with(test.as_str()) && v[test.len()..].starts_with('-'))
+    });
+    Ok(vec![Item::Value(Rc::new(Value::from(result)))])
+}
+
+/// XPath path function.
+pub(crate) fn path<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    t: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let n = match optional_node(ctxt, stctxt, t)? {
+        Some(n) => n,
+        None => return Ok(vec![]),
+    };
+    let mut steps: Vec<String> = std::iter::once(n.clone())
+        .chain(n.ancestor_iter())
+        .filter(|a| a.node_type() != NodeType::Document)
+        .map(|a| path_step(&a))
+        .collect();
+    steps.reverse();
+    let top = tree_root(&n);
+    let result = if top.node_type() == NodeType::Document {
+        format!("/{}", steps.join("/"))
+    } else {
+        // The first step is the root of the tree
+        steps.remove(0);
+        let prefix = format!("Q{{{}}}root()", XPATH_FUNCTIONS_NS);
+        if steps.is_empty() {
+            prefix
+        } else {
+            format!("{}/{}", prefix, steps.join("/"))
+        }
+    };
+    Ok(vec![Item::Value(Rc::new(Value::from(result)))])
+}
+
+// A step in the result of the path function.
+fn path_step<N: Node>(n: &N) -> String {
+    let qn = n.name();
+    let eqname = || {
+        format!(
+            "Q{{{}}}{}",
+            qn.namespace_uri_to_string().unwrap_or_default(),
+            qn.localname_to_string()
+        )
+    };
+    // The position of the node amongst its siblings of the same kind (and name)
+    let position = || {
+        n.prev_iter()
+            .filter(|s| {
+                s.node_type() == n.node_type()
+                    && match n.node_type() {
+                        NodeType::Element | NodeType::ProcessingInstruction => s.name() == qn,
+                        _ => true,
+                    }
+            })
+            .count()
+            + 1
+    };
+    match n.node_type() {
+        NodeType::Element => format!("{}[{}]", eqname(), position()),
+        NodeType::Attribute => match qn.namespace_uri_to_string() {
+            Some(_) => format!("@{}", eqname()),
+            None => format!("@{}", qn.localname_to_string()),
+        },
+        NodeType::Text => format!("text()[{}]", position()),
+        NodeType::Comment => format!("comment()[{}]", position()),
+        NodeType::ProcessingInstruction => format!(
+            "processing-instruction({})[{}]",
+            qn.localname_to_string(),
+            position()
+        ),
+        NodeType::Namespace => {
+            let prefix = qn.localname_to_string();
+            if prefix.is_empty() {
+                format!("namespace::*[Q{{{}}}local-name()=\"\"]", XPATH_FUNCTIONS_NS)
+            } else {
+                format!("namespace::{}", prefix)
+            }
+        }
+        _ => String::new(),
+    }
+}
+
+/// XPath id function.
+/// Elements are found by an attribute of type ID, or an xml:id attribute.
+/// Attributes are only typed as ID if the document was parsed with a DTD that declares them.
+pub(crate) fn id<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    t: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let ids = id_tokens(ctxt.dispatch(stctxt, s)?);
+    let top = id_tree(ctxt, stctxt, t)?;
+    Ok(top
+        .descend_iter()
+        .filter(|e| {
+            e.node_type() == NodeType::Element
+                && e.attribute_iter().any(|a| {
+                    (a.is_id() || is_xml_attribute(&a, "id"))
+                        && ids.contains(&a.to_string().trim().to_string())
+                })
+        })
+        .map(Item::Node)
+        .collect())
+}
+
+/// XPath idref function.
+/// Attributes are found that have type IDREF or IDREFS, and refer to one of the given IDs.
+pub(crate) fn idref<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    t: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let ids = id_tokens(ctxt.dispatch(stctxt, s)?);
+    let top = id_tree(ctxt, stctxt, t)?;
+    Ok(top
+        .descend_iter()
+        .filter(|e| e.node_type() == NodeType::Element)
+        .flat_map(|e| e.attribute_iter().collect::<Vec<N>>())
+        .filter(|a| {
+            a.is_idrefs()
+                && a.to_string()
+                    .split_whitespace()
+                    .any(|r| ids.iter().any(|i| i == r))
+        })
+        .map(Item::Node)
+        .collect())
+}
+
+// The ID values in the argument to the id or idref function.
+fn id_tokens<N: Node>(seq: Sequence<N>) -> Vec<String> {
+    seq.iter()
+        .flat_map(|i| {
+            i.to_string()
+                .split_whitespace()
+                .map(String::from)
+                .collect::<Vec<String>>()
+        })
+        .collect()
+}
+
+// The tree to search for the id and idref functions. This must be a document.
+fn id_tree<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    t: &Option<Box<Transform<N>>>,
+) -> Result<N, Error> {
+    let n = optional_node(ctxt, stctxt, t)?.ok_or_else(|| {
+        Error::new_with_code(
+            ErrorKind::TypeError,
+            "expected a node",
+            Some(QualifiedName::new(None, None, "XPTY0004")),
+        )
+    })?;
+    let top = tree_root(&n);
+    if top.node_type() == NodeType::Document {
+        Ok(top)
+    } else {
+        Err(Error::new_with_code(
+            ErrorKind::Unknown,
+            "the tree containing the node is not a document",
+            Some(QualifiedName::new(None, None, "FODC0001")),
+        ))
+    }
+}
@@ -4,13 +4,14 @@ use std::rc::Rc;
 use url::Url;
 
 use english_numbers::{convert, Formatting};
-use formato::Formato;
 use italian_numbers::roman_converter;
 
 use crate::item::{Item, Node, NodeType, Sequence, SequenceTrait};
+use crate::namespace::NamespaceMap;
 use crate::pattern::{Branch, Pattern, Step};
 use crate::qname::QualifiedName;
 use crate::transform::context::{Context, StaticContext};
+use crate::transform::datetime::{datetime_arithmetic, is_temporal};
 use crate::transform::{
     ArithmeticOperand, ArithmeticOperator, Axis, KindTest, NameTest, NodeTest, Transform,
     WildcardOrName,
@@ -53,111 +54,137 @@ pub fn generate_integers<
     select: &Transform<N>,
     num: &Numbering<N>,
 ) -> Result<Sequence<N>, Error> {
-    // This implements "single" level. "multiple" and "any" are TODO
-    if num.level != Level::Single {
-        return Err(Error::new(
-            ErrorKind::NotImplemented,
-            "only single level is implemented",
-        ));
-    }
-
     // The select expression must evaluate to a single node item (XSLT error XTTE1000)
     let n = ctxt.dispatch(stctxt, select)?;
-    if n.len() == 1 {
-        if let Item::Node(m) = &n[0] {
-            // Determine the count pattern
-            let count_pat = (num.count)
-                .clone()
-                .unwrap_or(Pattern::Selection(Branch::SingleStep(
-                    match m.node_type() {
-                        NodeType::Element => Step::new(
-                            Axis::SelfAxis,
-                            Axis::SelfAxis,
-                            NodeTest::Name(NameTest::new(
-                                m.name().namespace_uri().map(WildcardOrName::Name),
-                                None,
-                                Some(WildcardOrName::Name(m.name().localname())),
-                            )),
-                        ),
-                        NodeType::Text => Step::new(
-                            Axis::SelfAxis,
-                            Axis::SelfAxis,
-                            NodeTest::Kind(KindTest::Text),
-                        ),
-                        _ => {
-                            return Err(Error::new(
-                                ErrorKind::TypeError,
-                                "cannot match this type of node",
-                            ))
-                        }
-                    },
-                )));
-
-            // let a = $S/ancestor-or-self::node()[matches-count(.)][1]
-            // TODO: Don't Panic
-            let a = if count_pat.matches(ctxt, stctxt, &Item::Node(m.clone())) {
-                vec![m.clone()]
-            } else {
-                m.ancestor_iter()
-                    .filter(|i| count_pat.matches(ctxt, stctxt, &Item::Node(i.clone())))
-                    .take(1)
-                    .collect()
-            };
-            if a.is_empty() {
-                return Ok(vec![]);
+    let m = match (n.len(), n.first()) {
+        (1, Some(Item::Node(m))) => m.clone(),
+        _ => {
+            return Err(Error::new_with_code(
+                ErrorKind::TypeError,
+                "not a singleton node",
+                Some(QualifiedName::new(None, None, "XTTE1000")),
+            ))
+        }
+    };
+
+    // Determine the count pattern
+    let count_pat = (num.count)
+        .clone()
+        .unwrap_or(Pattern::Selection(Branch::SingleStep(
+            match m.node_type() {
+                NodeType::Element => Step::new(
+                    Axis::SelfAxis,
+                    Axis::SelfAxis,
+                    NodeTest::Name(NameTest::new(
+                        m.name().namespace_uri().map(WildcardOrName::Name),
+                        None,
+                        Some(WildcardOrName::Name(m.name().localname())),
+                    )),
+                ),
+                NodeType::Text => Step::new(
+                    Axis::SelfAxis,
+                    Axis::SelfAxis,
+                    NodeTest::Kind(KindTest::Text),
+                ),
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::TypeError,
+                        "cannot match this type of node",
+                    ))
+                }
+            },
+        )));
+    // When there is no from pattern then only the root node matches
+    let matches_from = |i: &N, stctxt: &mut StaticContext<N, F, G, H>| match &num.from {
+        Some(fr) => fr.matches(ctxt, stctxt, &Item::Node(i.clone())),
+        None => i.node_type() == NodeType::Document,
+    };
+    // The ancestor-or-self nodes of the selected node, nearest first
+    let ancestors: Vec<N> = vec![m.clone()]
+        .into_iter()
+        .chain(m.ancestor_iter())
+        .collect();
+
+    match num.level {
+        Level::Single | Level::Multiple => {
+            // let $f = $S/ancestor-or-self::node()[matches-from(.)][1]
+            // let $af = $S/ancestor-or-self::node()[matches-count(.)][ancestor-or-self::node()[. is $f]]
+            // For single level, only the nearest counted node is used.
+            // If no ancestor matches the from pattern then there are no counted nodes.
+            let mut af = vec![];
+            let mut from_found = false;
+            for a in &ancestors {
+                if (num.level == Level::Multiple || af.is_empty())
+                    && count_pat.matches(ctxt, stctxt, &Item::Node(a.clone()))
+                {
+                    af.push(a.clone());
+                }
+                if matches_from(a, stctxt) {
+                    from_found = true;
+                    break;
+                }
+            }
+            if !from_found {
+                af.clear()
             }
-            // let f = $S/ancestor-or-self::node()[matches-from(.)][1]
-            // TODO: Don't Panic
-            let f: Vec<N> = if let Some(fr) = &num.from.clone() {
-                m.ancestor_iter()
-                    .filter(|i| {
-                        if i.node_type() == NodeType::Document {
-                            true
-                        } else {
-                            fr.matches(ctxt, stctxt, &Item::Node(i.clone()))
-                        }
-                    })
-                    .take(1)
-                    .collect()
+            // 1 + count($af/preceding-sibling::node()[matches-count(.)])
+            Ok(af
+                .iter()
+                .rev()
+                .map(|a| {
+                    Item::Value(Rc::new(Value::from(
+                        1 + a
+                            .prev_iter()
+                            .filter(|i| count_pat.matches(ctxt, stctxt, &Item::Node(i.clone())))
+                            .count(),
+                    )))
+                })
+                .collect())
+        }
+        Level::Any => {
+            // let $c = $S/(preceding::node()|ancestor-or-self::node())[matches-count(.)]
+            // let $f = $S/(preceding::node()|ancestor-or-self::node())[matches-from(.)][last()]
+            // count($c[. is $f or . >> $f])
+            let mut nodes = vec![];
+            if m.node_type() == NodeType::Attribute {
+                if let Some(p) = m.parent() {
+                    preceding_or_ancestors(&m.owner_document(), &p, &mut nodes);
+                }
+                nodes.push(m.clone());
             } else {
-                // When there is no from pattern specified then use the root node
-                vec![m.owner_document().clone()]
-            };
-            if f.is_empty() {
-                return Ok(vec![]);
+                preceding_or_ancestors(&m.owner_document(), &m, &mut nodes);
+            }
+            let mut count = 0;
+            let mut from_found = false;
+            for i in nodes.iter().rev() {
+                if count_pat.matches(ctxt, stctxt, &Item::Node(i.clone())) {
+                    count += 1
+                }
+                if matches_from(i, stctxt) {
+                    from_found = true;
+                    break;
+                }
             }
-            // let af = $a[ancestor-or-self::node()[. is $f]]
-            let af_test: Vec<N> = if a[0].is_same(&f[0]) {
-                vec![a[0].clone()]
+            if count == 0 || !from_found {
+                Ok(vec![])
             } else {
-                a[0].ancestor_iter().filter(|i| i.is_same(&f[0])).collect()
-            };
-            let af = if af_test.is_empty() { vec![] } else { a };
-            if af.is_empty() {
-                return Ok(vec![]);
+                Ok(vec![Item::Value(Rc::new(Value::from(count)))])
             }
-            // 1 + count($af/preceding-sibling::node()[matches-count(.)])
-            let result: Vec<N> = af[0]
-                .prev_iter()
-                .filter(|i| count_pat.matches(ctxt, stctxt, &Item::Node(i.clone())))
-                .collect();
-            Ok(vec![Item::Value(Rc::new(Value::from(1 + result.len())))])
-        } else {
-            Err(Error::new_with_code(
-                ErrorKind::TypeError,
-                "not a singleton node",
-                Some(QualifiedName::new(None, None, "XTTE1000")),
-            ))
         }
-    } else {
-        Err(Error::new_with_code(
-            ErrorKind::TypeError,
-            "not a singleton node",
-            Some(QualifiedName::new(None, None, "XTTE1000")),
-        ))
     }
 }
 
+// Find the nodes that precede the target node, and its ancestors, in document order, and the target node itself.
+// Returns whether the target has been found.
+fn preceding_or_ancestors<N: Node>(n: &N, target: &N, acc: &mut Vec<N>) -> bool {
+    acc.push(n.clone());
+    if n.is_same(target) {
+        return true;
+    }
+    n.child_iter()
+        .any(|c| preceding_or_ancestors(&c, target, acc))
+}
+
 /// XPath number function.
 pub fn number<
     N: Node,
@@ -172,13 +199,18 @@ pub fn number<
     let n = ctxt.dispatch(stctxt, num)?;
     match n.len() {
         1 => {
+            // A node is atomized to its string value
+            let v = match &n[0] {
+                Item::Node(nd) => Item::Value(Rc::new(Value::from(nd.to_string().trim()))),
+                i => i.clone(),
+            };
             // First try converting to an integer
-            match n[0].to_int() {
+            match v.to_int() {
                 Ok(i) => Ok(vec![Item::Value(Rc::new(Value::Integer(i)))]),
                 _ => {
                     // Otherwise convert to double.
                     // NB. This can't fail. At worst it returns NaN.
-                    Ok(vec![Item::Value(Rc::new(Value::Double(n[0].to_double())))])
+                    Ok(vec![Item::Value(Rc::new(Value::Double(v.to_double())))])
                 }
             }
         }
@@ -436,21 +468,33 @@ pub(crate) fn arithmetic<
     // If all of the operands are integers, then the result is integer otherwise double
     // TODO: check the type of all operands to determine type of result (can probably do this in static analysis phase)
     // In the meantime, let's assume the result will be double and convert any integers
-    let mut acc = 0.0;
+    let mut operands = vec![];
     for o in ops {
-        let j = match ctxt.dispatch(stctxt, &o.operand) {
-            Ok(s) => s,
-            Err(_) => {
-                acc = f64::NAN;
-                break;
-            }
-        };
-        if j.len() != 1 {
-            acc = f64::NAN;
-            break;
+        match ctxt.dispatch(stctxt, &o.operand) {
+            Ok(j) if j.len() == 1 => operands.push((o.op, j)),
+            _ => return Ok(vec![Item::Value(Rc::new(Value::from(f64::NAN)))]),
+        }
+    }
+    // Dates, times and durations have their own arithmetic
+    if operands
+        .iter()
+        .any(|(_, j)| matches!(&j[0], Item::Value(v) if is_temporal(v)))
+    {
+        let tz = ctxt.implicit_timezone();
+        let mut acc = Value::from(0.0);
+        for (op, j) in operands {
+            let v = match &j[0] {
+                Item::Value(v) => (**v).clone(),
+                i => Value::from(i.to_double()),
+            };
+            acc = datetime_arithmetic(&acc, op, &v, &tz)?;
         }
+        return Ok(vec![Item::Value(Rc::new(acc))]);
+    }
+    let mut acc = 0.0;
+    for (op, j) in operands {
         let u = j[0].to_double();
-        match o.op {
+        match op {
             ArithmeticOperator::Noop => acc = u,
             ArithmeticOperator::Add => acc += u,
             ArithmeticOperator::Subtract => acc -= u,
@@ -463,6 +507,422 @@ pub(crate) fn arithmetic<
     Ok(vec![Item::Value(Rc::new(Value::from(acc)))])
 }
 
+/// A decimal format. This controls how format-number interprets its picture string and presents the number.
+/// See XPath and XQuery Functions and Operators 3.1 section 4.7.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecimalFormat {
+    pub decimal_separator: char,
+    pub grouping_separator: char,
+    pub exponent_separator: char,
+    pub infinity: String,
+    pub minus_sign: char,
+    pub nan: String,
+    pub percent: char,
+    pub per_mille: char,
+    pub zero_digit: char,
+    pub digit: char,
+    pub pattern_separator: char,
+}
+
+impl Default for DecimalFormat {
+    fn default() -> Self {
+        DecimalFormat {
+            decimal_separator: '.',
+            grouping_separator: ',',
+            exponent_separator: 'e',
+            infinity: String::from("Infinity"),
+            minus_sign: '-',
+            nan: String::from("NaN"),
+            percent: '%',
+            per_mille: '\u{2030}',
+            zero_digit: '0',
+            digit: '#',
+            pattern_separator: ';',
+        }
+    }
+}
+
+impl DecimalFormat {
+    // Is the character a member of the decimal digit family?
+    fn digit_value(&self, c: char) -> Option<u8> {
+        let z = self.zero_digit as u32;
+        let d = c as u32;
+        (d >= z && d < z + 10).then(|| (d - z) as u8)
+    }
+    fn digit_char(&self, d: u8) -> char {
+        char::from_u32(self.zero_digit as u32 + d as u32).unwrap_or('?')
+    }
+    // Active characters make up the mantissa of a sub-picture.
+    fn is_active(&self, c: char) -> bool {
+        c == self.decimal_separator
+            || c == self.grouping_separator
+            || c == self.digit
+            || self.digit_value(c).is_some()
+    }
+}
+
+/// The analysis of one sub-picture of a format-number picture string.
+struct SubPicture {
+    prefix: String,
+    suffix: String,
+    integer_grouping: Vec<usize>,
+    regular_grouping: Option<usize>,
+    fractional_grouping: Vec<usize>,
+    minimum_integer: usize,
+    minimum_fractional: usize,
+    maximum_fractional: usize,
+    // None if there is no exponent
+    minimum_exponent: Option<usize>,
+    // The power of 10 by which to multiply the number, i.e. 2 for percent and 3 for per-mille
+    scale: i64,
+}
+
+fn picture_error(p: &[char], msg: &str) -> Error {
+    Error::new_with_code(
+        ErrorKind::DynamicAbsent,
+        format!(
+            "invalid picture string \"{}\": {}",
+            p.iter().collect::<String>(),
+            msg
+        ),
+        Some(QualifiedName::new(None, None, "FODF1310")),
+    )
+}
+
+impl SubPicture {
+    fn parse(p: &[char], df: &DecimalFormat) -> Result<Self, Error> {
+        // The mantissa is the first run of active characters.
+        // It may be followed by an exponent separator and the digits of the exponent.
+        let start = p
+            .iter()
+            .position(|c| df.is_active(*c))
+            .ok_or_else(|| picture_error(p, "no digits"))?;
+        let mut end = start;
+        while end < p.len() && df.is_active(p[end]) {
+            end += 1
+        }
+        let mantissa = &p[start..end];
+        let mut minimum_exponent = None;
+        if end + 1 < p.len()
+            && p[end] == df.exponent_separator
+            && df.digit_value(p[end + 1]).is_some()
+        {
+            let mut e = end + 1;
+            while e < p.len() && df.digit_value(p[e]).is_some() {
+                e += 1
+            }
+            minimum_exponent = Some(e - end - 1);
+            end = e;
+        }
+        let prefix = &p[..start];
+        let suffix = &p[end..];
+        if suffix.iter().any(|c| df.is_active(*c)) {
+            return Err(picture_error(
+                p,
+                "passive character between active characters",
+            ));
+        }
+        let percent = prefix
+            .iter()
+            .chain(suffix.iter())
+            .filter(|c| **c == df.percent)
+            .count();
+        let per_mille = prefix
+            .iter()
+            .chain(suffix.iter())
+            .filter(|c| **c == df.per_mille)
+            .count();
+        if percent + per_mille > 1 {
+            return Err(picture_error(p, "more than one percent or per-mille sign"));
+        }
+        if percent + per_mille > 0 && minimum_exponent.is_some() {
+            return Err(picture_error(
+                p,
+                "percent or per-mille sign with an exponent",
+            ));
+        }
+        if !mantissa
+            .iter()
+            .any(|c| *c == df.digit || df.digit_value(*c).is_some())
+        {
+            return Err(picture_error(p, "no digits"));
+        }
+        let mut parts = mantissa.split(|c| *c == df.decimal_separator);
+        let integer = parts.next().unwrap_or(&[]);
+        let fraction = parts.next();
+        if parts.next().is_some() {
+            return Err(picture_error(p, "more than one decimal separator"));
+        }
+        let fraction = fraction.unwrap_or(&[]);
+        if mantissa
+            .windows(2)
+            .any(|w| w[0] == df.grouping_separator && w[1] == df.grouping_separator)
+        {
+            return Err(picture_error(p, "adjacent grouping separators"));
+        }
+        if integer.last() == Some(&df.grouping_separator)
+            || fraction.first() == Some(&df.grouping_separator)
+        {
+            return Err(picture_error(
+                p,
+                "grouping separator adjacent to the decimal separator",
+            ));
+        }
+        // In the integer part, an optional digit cannot follow a decimal digit.
+        // In the fractional part, a decimal digit cannot follow an optional digit.
+        let mut seen_digit = false;
+        for c in integer {
+            if df.digit_value(*c).is_some() {
+                seen_digit = true
+            } else if *c == df.digit && seen_digit {
+                return Err(picture_error(p, "optional digit follows a decimal digit"));
+            }
+        }
+        let mut seen_optional = false;
+        for c in fraction {
+            if *c == df.digit {
+                seen_optional = true
+            } else if df.digit_value(*c).is_some() && seen_optional {
+                return Err(picture_error(p, "decimal digit follows an optional digit"));
+            }
+        }
+
+        // Grouping positions are counted in digits from the decimal separator.
+        let mut integer_grouping = vec![];
+        let mut count = 0;
+        for c in integer.iter().rev() {
+            if *c == df.grouping_separator {
+                integer_grouping.push(count)
+            } else {
+                count += 1
+            }
+        }
+        let regular_grouping = integer_grouping.first().and_then(|g| {
+            integer_grouping
+                .iter()
+                .enumerate()
+                .all(|(i, p)| *p == g * (i + 1))
+                .then_some(*g)
+        });
+        let mut fractional_grouping = vec![];
+        let mut count = 0;
+        for c in fraction {
+            if *c == df.grouping_separator {
+                fractional_grouping.push(count)
+            } else {
+                count += 1
+            }
+        }
+        let mut minimum_integer = integer
+            .iter()
+            .filter(|c| df.digit_value(**c).is_some())
+            .count();
+        let mut minimum_fractional = fraction
+            .iter()
+            .filter(|c| df.digit_value(**c).is_some())
+            .count();
+        let mut maximum_fractional = fraction
+            .iter()
+            .filter(|c| **c == df.digit || df.digit_value(**c).is_some())
+            .count();
+        if minimum_integer == 0 && maximum_fractional == 0 {
+            if minimum_exponent.is_some() {
+                minimum_fractional = 1;
+                maximum_fractional = 1;
+            } else {
+                minimum_integer = 1;
+            }
+        }
+
+        Ok(SubPicture {
+            prefix: prefix.iter().collect(),
+            suffix: suffix.iter().collect(),
+            integer_grouping,
+            regular_grouping,
+            fractional_grouping,
+            minimum_integer,
+            minimum_fractional,
+            maximum_fractional,
+            minimum_exponent,
+            scale: if percent > 0 {
+                2
+            } else if per_mille > 0 {
+                3
+            } else {
+                0
+            },
+        })
+    }
+
+    // Format the absolute value of a finite number.
+    // The number is given as its decimal digits, without leading or trailing zeroes,
+    // and the position of the decimal point relative to the first digit.
+    fn format(&self, mut digits: Vec<u8>, mut point: i64, df: &DecimalFormat) -> String {
+        point += self.scale;
+        let mut exponent = 0;
+        if self.minimum_exponent.is_some() && !digits.is_empty() {
+            exponent = point - self.minimum_integer as i64;
+            point = self.minimum_integer as i64;
+        }
+        round_half_even(&mut digits, &mut point, self.maximum_fractional);
+        if self.minimum_exponent.is_some()
+            && !digits.is_empty()
+            && point > self.minimum_integer as i64
+        {
+            // Rounding has carried into another integer digit
+            exponent += point - self.minimum_integer as i64;
+            point = self.minimum_integer as i64;
+        }
+
+        let mut integer: Vec<u8> = (0..point.max(0))
+            .map(|i| digits.get(i as usize).copied().unwrap_or(0))
+            .collect();
+        let mut fraction: Vec<u8> = (point.min(0)..0).map(|_| 0).collect();
+        fraction.extend(digits.iter().skip(point.max(0) as usize));
+        while fraction.len() > self.minimum_fractional && fraction.last() == Some(&0) {
+            fraction.pop();
+        }
+        while fraction.len() < self.minimum_fractional {
+            fraction.push(0)
+        }
+        while integer.len() < self.minimum_integer {
+            integer.insert(0, 0)
+        }
+        if integer.is_empty() && fraction.is_empty() {
+            integer.push(0)
+        }
+
+        let mut result = self.prefix.clone();
+        let len = integer.len();
+        integer.iter().enumerate().for_each(|(i, d)| {
+            let pos = len - i;
+            if i > 0
+                && (self
+                    .regular_grouping
+                    .map_or(self.integer_grouping.contains(&pos), |g| pos % g == 0))
+            {
+                result.push(df.grouping_separator)
+            }
+            result.push(df.digit_char(*d))
+        });
+        if !fraction.is_empty() {
+            result.push(df.decimal_separator);
+            fraction.iter().enumerate().for_each(|(i, d)| {
+                if i > 0 && self.fractional_grouping.contains(&i) {
+                    result.push(df.grouping_separator)
+                }
+                result.push(df.digit_char(*d))
+            });
+        }
+        if let Some(m) = self.minimum_exponent {
+            result.push(df.exponent_separator);
+            if exponent < 0 {
+                result.push(df.minus_sign)
+            }
+            let e = exponent.unsigned_abs().to_string();
+            (e.len()..m).for_each(|_| result.push(df.zero_digit));
+            e.chars()
+                .for_each(|c| result.push(df.digit_char(c as u8 - b'0')));
+        }
+        result.push_str(self.suffix.as_str());
+        result
+    }
+}
+
+// Round the number to the given number of fractional digits, using round-half-to-even.
+fn round_half_even(digits: &mut Vec<u8>, point: &mut i64, places: usize) {
+    let keep = *point + places as i64;
+    if keep < 0 {
+        digits.clear();
+        return;
+    }
+    let keep = keep as usize;
+    if digits.len() <= keep {
+        return;
+    }
+    let first = digits[keep];
+    let rest = digits[keep + 1..].iter().any(|d| *d != 0);
+    let odd = keep > 0 && digits[keep - 1] % 2 == 1;
+    digits.truncate(keep);
+    if first > 5 || (first == 5 && (rest || odd)) {
+        let mut i = keep;
+        loop {
+            if i == 0 {
+                digits.insert(0, 1);
+                *point += 1;
+                break;
+            }
+            i -= 1;
+            if digits[i] == 9 {
+                digits[i] = 0
+            } else {
+                digits[i] += 1;
+                break;
+            }
+        }
+    }
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+    while digits.first() == Some(&0) {
+        digits.remove(0);
+        *point -= 1;
+    }
+}
+
+// A number to be formatted. A finite number has its digits, the position of the decimal point, and whether it is negative.
+enum Numeral {
+    NaN,
+    Infinite(bool),
+    Finite((Vec<u8>, i64), bool),
+}
+
+impl From<f64> for Numeral {
+    fn from(d: f64) -> Self {
+        if d.is_nan() {
+            Numeral::NaN
+        } else if d.is_infinite() {
+            Numeral::Infinite(d.is_sign_negative())
+        } else {
+            Numeral::Finite(decimal_digits(d.to_string().as_str()), d.is_sign_negative())
+        }
+    }
+}
+
+impl Numeral {
+    // Replace the digits of a finite number
+    fn with_digits(self, f: impl FnOnce() -> (Vec<u8>, i64)) -> Self {
+        match self {
+            Numeral::Finite(_, neg) => Numeral::Finite(f(), neg),
+            n => n,
+        }
+    }
+}
+
+// Convert the decimal representation of a number to its digits and the position of the decimal point.
+fn decimal_digits(s: &str) -> (Vec<u8>, i64) {
+    let s = s.trim_start_matches(['-', '+']);
+    let (i, f) = s.split_once('.').unwrap_or((s, ""));
+    let mut digits: Vec<u8> = i
+        .bytes()
+        .chain(f.bytes())
+        .filter(|b| b.is_ascii_digit())
+        .map(|b| b - b'0')
+        .collect();
+    let mut point = i.len() as i64;
+    while digits.first() == Some(&0) {
+        digits.remove(0);
+        point -= 1;
+    }
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+    if digits.is_empty() {
+        point = 0
+    }
+    (digits, point)
+}
+
 /// XPath format-number function.
 pub fn format_number<
     N: Node,
@@ -474,31 +934,118 @@ pub fn format_number<
     stctxt: &mut StaticContext<N, F, G, H>,
     num: &Transform<N>,
     picture: &Transform<N>,
-    _name: &Option<Box<Transform<N>>>,
+    name: &Option<Box<Transform<N>>>,
+    ns: &Rc<NamespaceMap>,
 ) -> Result<Sequence<N>, Error> {
-    let p = ctxt.dispatch(stctxt, picture)?.to_string();
+    let p: Vec<char> = ctxt
+        .dispatch(stctxt, picture)?
+        .to_string()
+        .chars()
+        .collect();
     let n = ctxt.dispatch(stctxt, num)?;
-    match n.len() {
-        1 => {
-            // First try converting to an integer
-            match n[0].to_int() {
-                Ok(i) => Ok(vec![Item::Value(Rc::new(Value::String(
-                    i.formato(p.as_str()),
-                )))]),
-                _ => {
-                    // Otherwise convert to double.
-                    // NB. This can't fail. At worst it returns NaN.
-                    Ok(vec![Item::Value(Rc::new(Value::String(
-                        n[0].to_double().formato(p.as_str()),
-                    )))])
+    let df = match name {
+        Some(nm) => {
+            let s = ctxt.dispatch(stctxt, nm)?.to_string();
+            let unknown = || {
+                Error::new_with_code(
+                    ErrorKind::DynamicAbsent,
+                    format!("unknown decimal format \"{}\"", s),
+                    Some(QualifiedName::new(None, None, "FODF1280")),
+                )
+            };
+            let qn = QualifiedName::try_from((s.trim(), ns.clone())).map_err(|_| unknown())?;
+            ctxt.decimal_format(&qn).ok_or_else(unknown)?
+        }
+        None => ctxt.default_decimal_format(),
+    };
+
+    let mut sub_pictures = p.split(|c| *c == df.pattern_separator);
+    let positive = SubPicture::parse(sub_pictures.next().unwrap_or(&[]), &df)?;
+    let negative = sub_pictures
+        .next()
+        .map(|sp| SubPicture::parse(sp, &df))
+        .transpose()?;
+    if sub_pictures.next().is_some() {
+        return Err(picture_error(&p, "more than two sub-pictures"));
+    }
+
+    // Find the digits of the number, or whether it is not finite
+    let value = match n.len() {
+        0 => Numeral::NaN,
+        1 => match &n[0] {
+            Item::Value(v) => match &**v {
+                Value::Double(d) => Numeral::from(*d),
+                Value::Float(f) => {
+                    Numeral::from(*f as f64).with_digits(|| decimal_digits(f.to_string().as_str()))
                 }
+                Value::Decimal(d) => Numeral::Finite(
+                    decimal_digits(d.to_string().as_str()),
+                    d.is_sign_negative() && !d.is_zero(),
+                ),
+                w if w.is_numeric() => Numeral::Finite(
+                    decimal_digits(w.to_string().as_str()),
+                    w.to_string().starts_with('-'),
+                ),
+                w => Numeral::from(w.to_double()),
+            },
+            // A node is atomized, and its string value converted to a double
+            Item::Node(nd) => {
+                Numeral::from(nd.to_string().trim().parse::<f64>().unwrap_or(f64::NAN))
             }
+            i => Numeral::from(i.to_double()),
+        },
+        _ => {
+            return Err(Error::new(
+                ErrorKind::TypeError,
+                String::from("not a singleton sequence"),
+            ))
+        }
+    };
+    let negative_value = match value {
+        Numeral::NaN => {
+            return Ok(vec![Item::Value(Rc::new(Value::from(df.nan.clone())))]);
+        }
+        Numeral::Infinite(neg) | Numeral::Finite(_, neg) => neg,
+    };
+
+    // The negative sub-picture is, by default, the positive sub-picture prefixed by the minus sign
+    let (sp, minus) = match (negative_value, &negative) {
+        (false, _) => (&positive, false),
+        (true, Some(neg)) => (neg, false),
+        (true, None) => (&positive, true),
+    };
+    let mut result = String::new();
+    if minus {
+        result.push(df.minus_sign)
+    }
+    match value {
+        Numeral::Finite((digits, point), _) => {
+            result.push_str(sp.format(digits, point, &df).as_str())
+        }
+        _ => {
+            result.push_str(sp.prefix.as_str());
+            result.push_str(df.infinity.as_str());
+            result.push_str(sp.suffix.as_str());
         }
-        _ => Err(Error::new(
-            ErrorKind::TypeError,
-            String::from("not a singleton sequence"),
-        )),
     }
+    Ok(vec![Item::Value(Rc::new(Value::from(result)))])
+}
+
+/// The letter-value attribute of xsl:number. This distinguishes numbering sequences that use letters.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LetterValue {
+    Alphabetic,
+    Traditional,
+}
+
+/// How numbers are formatted by xsl:number, apart from the format picture. See XSLT 12.4.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NumberFormat {
+    pub grouping_separator: Option<String>,
+    pub grouping_size: Option<usize>,
+    /// The language for numbers expressed as words. Only English is supported, and is used for all languages.
+    pub lang: Option<String>,
+    pub letter_value: Option<LetterValue>,
 }
 
 /// XSLT xsl:number and XPath format-integer function.
@@ -513,170 +1060,167 @@ pub fn format_integer<
     num: &Transform<N>,
     picture: &Transform<N>,
 ) -> Result<Sequence<N>, Error> {
-    let p = ctxt.dispatch(stctxt, picture)?.to_string();
-    let numbers = ctxt.dispatch(stctxt, num)?;
-    let mut nit = numbers.iter();
-
-    let mut result = String::new();
+    number_to_string(ctxt, stctxt, num, picture, &NumberFormat::default())
+}
 
-    // Interpret the picture string.
-    // Most of the tokens are one character, except for 'Ww'.
-    let mut pit = p.chars().peekable();
-    loop {
-        let c = pit.next();
-        if let Some(d) = c {
-            if d.is_alphanumeric() {
-                match d {
-                    '0' => {
-                        // 01, 02, 03, 04, ...
-                        // length specification
-                        // TODO: non-arabic-roman numerals
-                        let mut token = String::from(d);
-                        loop {
-                            if let Some(p) = pit.peek() {
-                                if p.eq(&'0') {
-                                    pit.next();
-                                    token.push('0');
-                                } else if p.eq(&'1') {
-                                    pit.next();
-                                    token.push('1');
-                                } else {
-                                    break;
-                                }
-                            } else {
-                                break;
-                            }
-                        }
-                        if let Some(num) = nit.next() {
-                            result.push_str(
-                                format!("{:0>1$}", num.to_int()?.to_string(), token.len()).as_str(),
-                            );
-                        } else {
-                            break;
-                        }
-                    }
-                    '1' => {
-                        // 1, 2, 3, ...
-                        if let Some(num) = nit.next() {
-                            result.push_str(num.to_int()?.to_string().as_str())
-                        } else {
-                            break;
-                        }
-                    }
-                    'A' => {
-                        // A, B, C, ..., AA, BB, CC, ...
-                    }
-                    'a' => {
-                        // a, b, c, ..., aa, bb, cc, ...
-                    }
-                    'i' => {
-                        // i, ii, iii, iv, v, vi, ...
-                        if let Some(num) = nit.next() {
-                            result.push_str(
-                                roman_converter(u16::try_from(num.to_int()?).map_err(|e| {
-                                    Error::new(ErrorKind::ParseError, e.to_string())
-                                })?)
-                                .map_err(|e| Error::new(ErrorKind::ParseError, e))?
-                                .to_lowercase()
-                                .as_str(),
-                            )
-                        } else {
-                            break;
-                        }
-                    }
-                    'I' => {
-                        // I, II, III, IV, V, VI, ...
-                        if let Some(num) = nit.next() {
-                            result.push_str(
-                                roman_converter(u16::try_from(num.to_int()?).map_err(|e| {
-                                    Error::new(ErrorKind::ParseError, e.to_string())
-                                })?)
-                                .map_err(|e| Error::new(ErrorKind::ParseError, e))?
-                                .as_str(),
-                            )
-                        } else {
-                            break;
-                        }
-                    }
-                    'w' => {
-                        // one, two, three, ...
-                        if let Some(num) = nit.next() {
-                            result.push_str(
-                                convert(
-                                    num.to_int()?,
-                                    Formatting {
-                                        title_case: false,
-                                        spaces: true,
-                                        conjunctions: false,
-                                        commas: false,
-                                        dashes: false,
-                                    },
-                                )
-                                .to_string()
-                                .as_str(),
-                            )
-                        } else {
-                            break;
-                        }
-                    }
-                    'W' => {
-                        // 'Ww'
-                        if let Some('w') = pit.peek() {
-                            // One, Two, Three, ...
-                            pit.next();
-                            if let Some(num) = nit.next() {
-                                result.push_str(
-                                    convert(
-                                        num.to_int()?,
-                                        Formatting {
-                                            title_case: true,
-                                            spaces: true,
-                                            conjunctions: false,
-                                            commas: false,
-                                            dashes: false,
-                                        },
-                                    )
-                                    .to_string()
-                                    .as_str(),
-                                )
-                            } else {
-                                break;
-                            }
-                        } else {
-                            // ONE, TWO, THREE, ...
-                            if let Some(num) = nit.next() {
-                                result.push_str(
-                                    convert(
-                                        num.to_int()?,
-                                        Formatting {
-                                            title_case: false,
-                                            spaces: true,
-                                            conjunctions: false,
-                                            commas: false,
-                                            dashes: false,
-                                        },
-                                    )
-                                    .to_string()
-                                    .to_uppercase()
-                                    .as_str(),
-                                )
-                            } else {
-                                break;
-                            }
-                        }
-                    }
-                    // TODO: non-English words
-                    // Use french-numbers crate
-                    // Use italian-numbers crate
-                    _ => {}
+/// Convert a sequence of numbers to a string, as for xsl:number. See XSLT 12.4.
+/// The picture is divided into alphanumeric format tokens and the punctuation between them.
+/// If there are more numbers than format tokens then the last format token is reused.
+pub fn number_to_string<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    num: &Transform<N>,
+    picture: &Transform<N>,
+    nf: &NumberFormat,
+) -> Result<Sequence<N>, Error> {
+    let p = ctxt.dispatch(stctxt, picture)?.to_string();
+    let numbers = ctxt
+        .dispatch(stctxt, num)?
+        .iter()
+        .map(|i| match i.to_int() {
+            Ok(j) if j >= 0 => Ok(j),
+            _ => {
+                let d = i.to_double().round();
+                if d.is_finite() && d >= 0.0 {
+                    Ok(d as i64)
+                } else {
+                    Err(Error::new_with_code(
+                        ErrorKind::TypeError,
+                        format!("cannot number \"{}\"", i),
+                        Some(QualifiedName::new(None, None, "XTDE0980")),
+                    ))
                 }
-            } else {
-                result.push(d)
             }
+        })
+        .collect::<Result<Vec<i64>, Error>>()?;
+
+    // Divide the picture into runs of alphanumeric and non-alphanumeric characters
+    let mut runs: Vec<(bool, String)> = vec![];
+    p.chars().for_each(|c| match runs.last_mut() {
+        Some((alnum, r)) if *alnum == c.is_alphanumeric() => r.push(c),
+        _ => runs.push((c.is_alphanumeric(), String::from(c))),
+    });
+    let prefix = match runs.first() {
+        Some((false, _)) => runs.remove(0).1,
+        _ => String::new(),
+    };
+    let suffix = match runs.last() {
+        Some((false, _)) => runs.pop().unwrap().1,
+        _ => String::new(),
+    };
+    let mut tokens: Vec<String> = vec![];
+    let mut separators: Vec<String> = vec![];
+    runs.into_iter().for_each(|(alnum, r)| {
+        if alnum {
+            tokens.push(r)
         } else {
-            break;
+            separators.push(r)
         }
+    });
+    if tokens.is_empty() {
+        tokens.push(String::from("1"))
     }
 
+    let mut result = prefix;
+    numbers.iter().enumerate().for_each(|(i, n)| {
+        if i > 0 {
+            result.push_str(
+                separators
+                    .get(i - 1)
+                    .or(separators.last())
+                    .map_or(".", |s| s.as_str()),
+            )
+        }
+        result.push_str(format_token(*n, tokens.get(i).or(tokens.last()).unwrap(), nf).as_str())
+    });
+    result.push_str(suffix.as_str());
+
     Ok(vec![Item::Value(Rc::new(Value::from(result)))])
 }
+
+// Format a single number using a format token.
+// A token that is not supported is treated as the token "1".
+fn format_token(n: i64, token: &str, nf: &NumberFormat) -> String {
+    let first = token.chars().next().unwrap_or('1');
+    let alphabetic = token.chars().count() == 1
+        && first.is_ascii_alphabetic()
+        && (nf.letter_value == Some(LetterValue::Alphabetic) || !"iIwW".contains(first));
+    if alphabetic {
+        // a, b, c, ..., z, aa, ab, ...
+        // The token represents the number one.
+        let base = if first.is_ascii_lowercase() {
+            b'a'
+        } else {
+            b'A'
+        };
+        if n == 0 {
+            return String::from("0");
+        }
+        let mut m = n + (first as u8 - base) as i64;
+        let mut r = vec![];
+        while m > 0 {
+            m -= 1;
+            r.push((base + (m % 26) as u8) as char);
+            m /= 26;
+        }
+        return r.iter().rev().collect();
+    }
+    match token {
+        "i" | "I" => match u16::try_from(n).ok().and_then(|u| roman_converter(u).ok()) {
+            Some(r) if token == "i" => r.to_lowercase(),
+            Some(r) => r,
+            None => decimal_token(n, "1", nf),
+        },
+        "w" | "W" | "Ww" => {
+            let w = convert(
+                n,
+                Formatting {
+                    title_case: token == "Ww",
+                    spaces: true,
+                    conjunctions: false,
+                    commas: false,
+                    dashes: false,
+                },
+            );
+            if token == "W" {
+                w.to_uppercase()
+            } else {
+                w
+            }
+        }
+        t if !t.is_empty()
+            && t.chars().all(|c| c.is_ascii_digit())
+            && t.ends_with('1')
+            && t[..t.len() - 1].chars().all(|c| c == '0') =>
+        {
+            decimal_token(n, t, nf)
+        }
+        _ => decimal_token(n, "1", nf),
+    }
+}
+
+// Format a number as decimal digits, padded to the width of the token and grouped.
+fn decimal_token(n: i64, token: &str, nf: &NumberFormat) -> String {
+    let digits = format!("{:0>1$}", n, token.len());
+    match (&nf.grouping_separator, nf.grouping_size) {
+        (Some(sep), Some(size)) if size > 0 => {
+            let len = digits.len();
+            digits
+                .chars()
+                .enumerate()
+                .fold(String::new(), |mut acc, (i, c)| {
+                    if i > 0 && (len - i) % size == 0 {
+                        acc.push_str(sep)
+                    }
+                    acc.push(c);
+                    acc
+                })
+        }
+        _ => digits,
+    }
+}
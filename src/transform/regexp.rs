@@ -0,0 +1,631 @@
+//! # Regular expressions
+//! XPath regular expressions are based on XML Schema regular expressions, with extensions defined in XPath Functions 3.1 section 5.6.1.
+//! They are translated into the syntax used by the regex crate.
+//!
+//! The regex crate does not support back-references, so an expression that uses them is matched by the backtracking engine of the fancy-regex crate instead.
+
+use crate::qname::QualifiedName;
+use crate::xdmerror::{Error, ErrorKind};
+use regex::Regex;
+
+// XML 1.0 NameStartChar, for the \i escape
+const NAME_START_CHAR: &str = r":A-Z_a-z\x{C0}-\x{D6}\x{D8}-\x{F6}\x{F8}-\x{2FF}\x{370}-\x{37D}\x{37F}-\x{1FFF}\x{200C}-\x{200D}\x{2070}-\x{218F}\x{2C00}-\x{2FEF}\x{3001}-\x{D7FF}\x{F900}-\x{FDCF}\x{FDF0}-\x{FFFD}\x{10000}-\x{EFFFF}";
+// Additional characters allowed in an XML 1.0 NameChar, for the \c escape
+const NAME_CHAR: &str = r"\-.0-9\x{B7}\x{300}-\x{36F}\x{203F}-\x{2040}";
+// Whitespace, as defined by XML Schema, for the \s escape
+const SPACE: &str = r"\t\n\r ";
+
+// The Unicode blocks recognised by the \p{IsX} escape (XML Schema Part 2, F.1.1).
+// A block may have more than one range. The surrogate blocks are omitted, since a string cannot contain surrogates.
+const UNICODE_BLOCKS: &[(&str, u32, u32)] = &[
+    ("BasicLatin", 0x0000, 0x007F),
+    ("Latin-1Supplement", 0x0080, 0x00FF),
+    ("LatinExtended-A", 0x0100, 0x017F),
+    ("LatinExtended-B", 0x0180, 0x024F),
+    ("IPAExtensions", 0x0250, 0x02AF),
+    ("SpacingModifierLetters", 0x02B0, 0x02FF),
+    ("CombiningDiacriticalMarks", 0x0300, 0x036F),
+    ("Greek", 0x0370, 0x03FF),
+    ("GreekandCoptic", 0x0370, 0x03FF),
+    ("Cyrillic", 0x0400, 0x04FF),
+    ("CyrillicSupplement", 0x0500, 0x052F),
+    ("Armenian", 0x0530, 0x058F),
+    ("Hebrew", 0x0590, 0x05FF),
+    ("Arabic", 0x0600, 0x06FF),
+    ("Syriac", 0x0700, 0x074F),
+    ("Thaana", 0x0780, 0x07BF),
+    ("Devanagari", 0x0900, 0x097F),
+    ("Bengali", 0x0980, 0x09FF),
+    ("Gurmukhi", 0x0A00, 0x0A7F),
+    ("Gujarati", 0x0A80, 0x0AFF),
+    ("Oriya", 0x0B00, 0x0B7F),
+    ("Tamil", 0x0B80, 0x0BFF),
+    ("Telugu", 0x0C00, 0x0C7F),
+    ("Kannada", 0x0C80, 0x0CFF),
+    ("Malayalam", 0x0D00, 0x0D7F),
+    ("Sinhala", 0x0D80, 0x0DFF),
+    ("Thai", 0x0E00, 0x0E7F),
+    ("Lao", 0x0E80, 0x0EFF),
+    ("Tibetan", 0x0F00, 0x0FFF),
+    ("Myanmar", 0x1000, 0x109F),
+    ("Georgian", 0x10A0, 0x10FF),
+    ("HangulJamo", 0x1100, 0x11FF),
+    ("Ethiopic", 0x1200, 0x137F),
+    ("Cherokee", 0x13A0, 0x13FF),
+    ("UnifiedCanadianAboriginalSyllabics", 0x1400, 0x167F),
+    ("Ogham", 0x1680, 0x169F),
+    ("Runic", 0x16A0, 0x16FF),
+    ("Khmer", 0x1780, 0x17FF),
+    ("Mongolian", 0x1800, 0x18AF),
+    ("LatinExtendedAdditional", 0x1E00, 0x1EFF),
+    ("GreekExtended", 0x1F00, 0x1FFF),
+    ("GeneralPunctuation", 0x2000, 0x206F),
+    ("SuperscriptsandSubscripts", 0x2070, 0x209F),
+    ("CurrencySymbols", 0x20A0, 0x20CF),
+    ("CombiningMarksforSymbols", 0x20D0, 0x20FF),
+    ("LetterlikeSymbols", 0x2100, 0x214F),
+    ("NumberForms", 0x2150, 0x218F),
+    ("Arrows", 0x2190, 0x21FF),
+    ("MathematicalOperators", 0x2200, 0x22FF),
+    ("MiscellaneousTechnical", 0x2300, 0x23FF),
+    ("ControlPictures", 0x2400, 0x243F),
+    ("OpticalCharacterRecognition", 0x2440, 0x245F),
+    ("EnclosedAlphanumerics", 0x2460, 0x24FF),
+    ("BoxDrawing", 0x2500, 0x257F),
+    ("BlockElements", 0x2580, 0x259F),
+    ("GeometricShapes", 0x25A0, 0x25FF),
+    ("MiscellaneousSymbols", 0x2600, 0x26FF),
+    ("Dingbats", 0x2700, 0x27BF),
+    ("BraillePatterns", 0x2800, 0x28FF),
+    ("CJKRadicalsSupplement", 0x2E80, 0x2EFF),
+    ("KangxiRadicals", 0x2F00, 0x2FDF),
+    ("IdeographicDescriptionCharacters", 0x2FF0, 0x2FFF),
+    ("CJKSymbolsandPunctuation", 0x3000, 0x303F),
+    ("Hiragana", 0x3040, 0x309F),
+    ("Katakana", 0x30A0, 0x30FF),
+    ("Bopomofo", 0x3100, 0x312F),
+    ("HangulCompatibilityJamo", 0x3130, 0x318F),
+    ("Kanbun", 0x3190, 0x319F),
+    ("BopomofoExtended", 0x31A0, 0x31BF),
+    ("EnclosedCJKLettersandMonths", 0x3200, 0x32FF),
+    ("CJKCompatibility", 0x3300, 0x33FF),
+    ("CJKUnifiedIdeographsExtensionA", 0x3400, 0x4DBF),
+    ("CJKUnifiedIdeographs", 0x4E00, 0x9FFF),
+    ("YiSyllables", 0xA000, 0xA48F),
+    ("YiRadicals", 0xA490, 0xA4CF),
+    ("HangulSyllables", 0xAC00, 0xD7AF),
+    ("PrivateUse", 0xE000, 0xF8FF),
+    ("PrivateUse", 0xF0000, 0x10FFFF),
+    ("PrivateUseArea", 0xE000, 0xF8FF),
+    ("CJKCompatibilityIdeographs", 0xF900, 0xFAFF),
+    ("AlphabeticPresentationForms", 0xFB00, 0xFB4F),
+    ("ArabicPresentationForms-A", 0xFB50, 0xFDFF),
+    ("CombiningHalfMarks", 0xFE20, 0xFE2F),
+    ("CJKCompatibilityForms", 0xFE30, 0xFE4F),
+    ("SmallFormVariants", 0xFE50, 0xFE6F),
+    ("ArabicPresentationForms-B", 0xFE70, 0xFEFF),
+    ("HalfwidthandFullwidthForms", 0xFF00, 0xFFEF),
+    ("Specials", 0xFFF0, 0xFFFF),
+    ("OldItalic", 0x10300, 0x1032F),
+    ("Gothic", 0x10330, 0x1034F),
+    ("Deseret", 0x10400, 0x1044F),
+    ("ByzantineMusicalSymbols", 0x1D000, 0x1D0FF),
+    ("MusicalSymbols", 0x1D100, 0x1D1FF),
+    ("MathematicalAlphanumericSymbols", 0x1D400, 0x1D7FF),
+    ("CJKUnifiedIdeographsExtensionB", 0x20000, 0x2A6DF),
+    ("CJKCompatibilityIdeographsSupplement", 0x2F800, 0x2FA1F),
+    ("Tags", 0xE0000, 0xE007F),
+];
+
+fn regex_error(code: &str, msg: impl Into<String>) -> Error {
+    Error::new_with_code(
+        ErrorKind::Unknown,
+        msg,
+        Some(QualifiedName::new(None, None, code)),
+    )
+}
+
+/// A compiled XPath regular expression.
+pub(crate) struct XPathRegex {
+    matcher: Matcher,
+    // The enclosing group of each capturing group, where 0 is the whole expression.
+    // Entry 0 is not used.
+    parents: Vec<usize>,
+}
+
+enum Matcher {
+    Automaton(Regex),
+    // Used only when the expression has back-references
+    Backtracking(fancy_regex::Regex),
+}
+
+/// The position of each group in a match, where group 0 is the whole match.
+/// A group that did not participate in the match has no position.
+pub(crate) type Groups = Vec<Option<(usize, usize)>>;
+
+impl XPathRegex {
+    /// Does the expression match any part of the string?
+    pub(crate) fn is_match(&self, s: &str) -> Result<bool, Error> {
+        match &self.matcher {
+            Matcher::Automaton(re) => Ok(re.is_match(s)),
+            Matcher::Backtracking(re) => re.is_match(s).map_err(backtrack_error),
+        }
+    }
+    /// Find all of the non-overlapping matches in the string.
+    pub(crate) fn captures(&self, s: &str) -> Result<Vec<Groups>, Error> {
+        match &self.matcher {
+            Matcher::Automaton(re) => Ok(re
+                .captures_iter(s)
+                .map(|caps| {
+                    caps.iter()
+                        .map(|g| g.map(|m| (m.start(), m.end())))
+                        .collect::<Groups>()
+                })
+                .collect()),
+            Matcher::Backtracking(re) => re
+                .captures_iter(s)
+                .map(|caps| {
+                    caps.map(|c| {
+                        c.iter()
+                            .map(|g| g.map(|m| (m.start(), m.end())))
+                            .collect::<Groups>()
+                    })
+                    .map_err(backtrack_error)
+                })
+                .collect(),
+        }
+    }
+    /// The group that encloses the given group, where 0 is the whole expression.
+    pub(crate) fn parent(&self, g: usize) -> usize {
+        self.parents.get(g).copied().unwrap_or(0)
+    }
+}
+
+// The backtracking engine may give up on a match that exceeds its backtracking limit.
+fn backtrack_error(e: fancy_regex::Error) -> Error {
+    Error::new(
+        ErrorKind::Unknown,
+        format!("unable to evaluate regular expression: {}", e),
+    )
+}
+
+/// Compile an XPath regular expression, using the given flags.
+/// The flags are s (dot-all mode), m (multi-line mode), i (case-insensitive), x (remove whitespace) and q (no metacharacters).
+pub(crate) fn compile(pattern: &str, flags: &str) -> Result<XPathRegex, Error> {
+    let mut prefix = String::new();
+    let (mut dotall, mut ws, mut literal) = (false, false, false);
+    for f in flags.chars() {
+        match f {
+            's' => dotall = true,
+            'm' => prefix.push('m'),
+            'i' => prefix.push('i'),
+            'x' => ws = true,
+            'q' => literal = true,
+            _ => {
+                return Err(regex_error(
+                    "FORX0001",
+                    format!("invalid regular expression flag \"{}\"", f),
+                ))
+            }
+        }
+    }
+    let (body, parents, backrefs) = if literal {
+        // The m, s and x flags have no effect when q is present
+        prefix.retain(|c| c == 'i');
+        (regex::escape(pattern), vec![0], false)
+    } else {
+        translate(pattern, dotall, ws)?
+    };
+    let re = if prefix.is_empty() {
+        body
+    } else {
+        format!("(?{}){}", prefix, body)
+    };
+    let invalid = |e: &dyn std::fmt::Display| {
+        regex_error(
+            "FORX0002",
+            format!("invalid regular expression \"{}\": {}", pattern, e),
+        )
+    };
+    let matcher = if backrefs {
+        Matcher::Backtracking(fancy_regex::Regex::new(&re).map_err(|e| invalid(&e))?)
+    } else {
+        Matcher::Automaton(Regex::new(&re).map_err(|e| invalid(&e))?)
+    };
+    Ok(XPathRegex { matcher, parents })
+}
+
+/// Compile an XPath regular expression that must not match a zero-length string.
+/// This is required by the replace, tokenize and analyze-string functions.
+pub(crate) fn compile_nonempty(pattern: &str, flags: &str) -> Result<XPathRegex, Error> {
+    let re = compile(pattern, flags)?;
+    if re.is_match("")? {
+        Err(regex_error(
+            "FORX0003",
+            format!(
+                "regular expression \"{}\" matches a zero-length string",
+                pattern
+            ),
+        ))
+    } else {
+        Ok(re)
+    }
+}
+
+// Translate the XPath regular expression syntax to the regex crate syntax.
+// The result also has the enclosing group of each capturing group, and whether there are back-references.
+fn translate(pattern: &str, dotall: bool, ws: bool) -> Result<(String, Vec<usize>, bool), Error> {
+    let invalid = |msg: &str| {
+        regex_error(
+            "FORX0002",
+            format!("invalid regular expression \"{}\": {}", pattern, msg),
+        )
+    };
+    let mut result = String::new();
+    // The nesting depth of character class expressions
+    let mut depth = 0;
+    // The enclosing group of each capturing group, and whether it has been closed
+    let mut parents = vec![0];
+    let mut closed = vec![false];
+    // The groups that are currently open. Non-capturing groups have no number.
+    let mut open: Vec<Option<usize>> = vec![];
+    let mut backrefs = false;
+    let mut it = pattern.chars().peekable();
+    while let Some(c) = it.next() {
+        match c {
+            '\t' | '\n' | '\r' | ' ' if ws && depth == 0 => {}
+            '\\' => match it.next() {
+                Some(
+                    e @ ('n' | 'r' | 't' | '\\' | '|' | '.' | '-' | '^' | '?' | '*' | '+' | '{'
+                    | '}' | '(' | ')' | '[' | ']' | '$'),
+                ) => {
+                    result.push('\\');
+                    result.push(e)
+                }
+                Some('s') => class_escape(&mut result, false, SPACE),
+                Some('S') => class_escape(&mut result, true, SPACE),
+                Some('i') => class_escape(&mut result, false, NAME_START_CHAR),
+                Some('I') => class_escape(&mut result, true, NAME_START_CHAR),
+                Some('c') => class_escape(
+                    &mut result,
+                    false,
+                    format!("{}{}", NAME_START_CHAR, NAME_CHAR).as_str(),
+                ),
+                Some('C') => class_escape(
+                    &mut result,
+                    true,
+                    format!("{}{}", NAME_START_CHAR, NAME_CHAR).as_str(),
+                ),
+                Some('d') => result.push_str(r"\p{Nd}"),
+                Some('D') => result.push_str(r"\P{Nd}"),
+                Some('w') => class_escape(&mut result, true, r"\p{P}\p{Z}\p{C}"),
+                Some('W') => class_escape(&mut result, false, r"\p{P}\p{Z}\p{C}"),
+                Some(p @ ('p' | 'P')) => {
+                    if it.next() != Some('{') {
+                        return Err(invalid("expected '{' after category escape"));
+                    }
+                    let mut name = String::new();
+                    loop {
+                        match it.next() {
+                            Some('}') => break,
+                            Some(d) => name.push(d),
+                            None => return Err(invalid("unterminated category escape")),
+                        }
+                    }
+                    if let Some(block) = name.strip_prefix("Is") {
+                        let ranges: String = UNICODE_BLOCKS
+                            .iter()
+                            .filter(|(b, _, _)| *b == block)
+                            .map(|(_, from, to)| format!(r"\x{{{:X}}}-\x{{{:X}}}", from, to))
+                            .collect();
+                        if ranges.is_empty() {
+                            return Err(invalid(
+                                format!("unknown Unicode block \"{}\"", block).as_str(),
+                            ));
+                        }
+                        class_escape(&mut result, p == 'P', ranges.as_str())
+                    } else {
+                        result.push('\\');
+                        result.push(p);
+                        result.push('{');
+                        result.push_str(&name);
+                        result.push('}')
+                    }
+                }
+                Some(d @ '1'..='9') if depth == 0 => {
+                    // A back-reference uses as many digits as form the number of an existing group
+                    let mut n = d.to_digit(10).unwrap() as usize;
+                    while let Some(m) = it
+                        .peek()
+                        .and_then(|e| e.to_digit(10))
+                        .map(|e| n * 10 + e as usize)
+                        .filter(|m| *m < parents.len())
+                    {
+                        n = m;
+                        it.next();
+                    }
+                    if !closed.get(n).copied().unwrap_or(false) {
+                        return Err(invalid(
+                            format!("back-reference to group {} before it is closed", n).as_str(),
+                        ));
+                    }
+                    // The group stops a following digit being read as part of the group number
+                    result.push_str(format!(r"(?:\{})", n).as_str());
+                    backrefs = true
+                }
+                Some(e) => return Err(invalid(format!("invalid escape \"\\{}\"", e).as_str())),
+                None => return Err(invalid("expression ends with '\\'")),
+            },
+            '[' if depth == 0 => {
+                depth += 1;
+                result.push('[');
+                if it.peek() == Some(&'^') {
+                    it.next();
+                    result.push('^')
+                }
+            }
+            '-' if depth > 0 && it.peek() == Some(&'[') => {
+                // Character class subtraction
+                it.next();
+                depth += 1;
+                result.push_str("--[");
+                if it.peek() == Some(&'^') {
+                    it.next();
+                    result.push('^')
+                }
+            }
+            ']' if depth > 0 => {
+                depth -= 1;
+                result.push(']')
+            }
+            '[' | '&' | '~' if depth > 0 => {
+                result.push('\\');
+                result.push(c)
+            }
+            '.' if depth == 0 && !dotall => result.push_str(r"[^\n\r]"),
+            '.' if depth == 0 => result.push_str(r"(?s:.)"),
+            '(' if depth == 0 && it.peek() == Some(&'?') => {
+                it.next();
+                if it.next() == Some(':') {
+                    open.push(None);
+                    result.push_str("(?:")
+                } else {
+                    return Err(invalid("only non-capturing groups may follow '(?'"));
+                }
+            }
+            '(' if depth == 0 => {
+                open.push(Some(parents.len()));
+                parents.push(open.iter().rev().skip(1).find_map(|g| *g).unwrap_or(0));
+                closed.push(false);
+                result.push('(')
+            }
+            ')' if depth == 0 => {
+                match open.pop() {
+                    Some(Some(g)) => closed[g] = true,
+                    Some(None) => {}
+                    None => return Err(invalid("unmatched ')'")),
+                }
+                result.push(')')
+            }
+            _ => result.push(c),
+        }
+    }
+    if depth > 0 {
+        Err(invalid("unterminated character class"))
+    } else {
+        Ok((result, parents, backrefs))
+    }
+}
+
+// A multi-character escape, expressed as a character class.
+// Within a character class expression this is a nested class, which the regex crate treats as a union.
+fn class_escape(result: &mut String, negate: bool, chars: &str) {
+    result.push('[');
+    if negate {
+        result.push('^')
+    }
+    result.push_str(chars);
+    result.push(']')
+}
+
+/// Check that a replacement string is valid.
+/// The only valid uses of '\' and '$' are "\\", "\$" and "$N", where N is a group number.
+pub(crate) fn check_replacement(rep: &str) -> Result<(), Error> {
+    let invalid = || {
+        regex_error(
+            "FORX0004",
+            format!("invalid replacement string \"{}\"", rep),
+        )
+    };
+    let mut it = rep.chars().peekable();
+    while let Some(c) = it.next() {
+        match c {
+            '\\' => match it.next() {
+                Some('\\' | '$') => {}
+                _ => return Err(invalid()),
+            },
+            '$' => match it.peek() {
+                Some(d) if d.is_ascii_digit() => {}
+                _ => return Err(invalid()),
+            },
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Expand a replacement string for a match in the input string.
+/// $N is replaced by the string matched by the Nth group, using the longest group number that exists.
+/// The replacement string must have been checked by [check_replacement].
+pub(crate) fn expand_replacement(input: &str, groups: &Groups, rep: &str) -> String {
+    let mut result = String::new();
+    let mut it = rep.chars().peekable();
+    while let Some(c) = it.next() {
+        match c {
+            '\\' => {
+                if let Some(e) = it.next() {
+                    result.push(e)
+                }
+            }
+            '$' => {
+                let mut n: Option<usize> = None;
+                while let Some(d) = it.peek().and_then(|d| d.to_digit(10)) {
+                    let m = n.map_or(d as usize, |v| v * 10 + d as usize);
+                    if n.is_some() && m >= groups.len() {
+                        break;
+                    }
+                    n = Some(m);
+                    it.next();
+                }
+                if let Some((s, e)) = n.and_then(|g| groups.get(g).copied().flatten()) {
+                    result.push_str(&input[s..e])
+                }
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regex_flags() {
+        assert!(compile("^abc$", "i").unwrap().is_match("ABC").unwrap());
+        assert!(compile("a.c", "").unwrap().is_match("abc").unwrap());
+        assert!(!compile("a.c", "").unwrap().is_match("a\nc").unwrap());
+        assert!(compile("a.c", "s").unwrap().is_match("a\nc").unwrap());
+        assert!(compile("a b c", "x").unwrap().is_match("abc").unwrap());
+        assert!(compile("a.c", "q").unwrap().is_match("xa.cx").unwrap());
+        assert!(!compile("a.c", "q").unwrap().is_match("abc").unwrap());
+        assert_eq!(
+            compile("a", "z").err().unwrap().code.unwrap().to_string(),
+            "FORX0001"
+        );
+    }
+    #[test]
+    fn regex_escapes() {
+        assert!(compile(r"^\i\c*$", "").unwrap().is_match("a-b.c").unwrap());
+        assert!(!compile(r"^\i\c*$", "").unwrap().is_match("-ab").unwrap());
+        assert!(compile(r"^\s$", "").unwrap().is_match(" ").unwrap());
+        assert!(!compile(r"^\s$", "").unwrap().is_match("\u{A0}").unwrap());
+        assert!(compile(r"^[a-z-[aeiou]]+$", "")
+            .unwrap()
+            .is_match("bcd")
+            .unwrap());
+        assert!(!compile(r"^[a-z-[aeiou]]+$", "")
+            .unwrap()
+            .is_match("bad")
+            .unwrap());
+        assert!(compile(r"^[&~]+$", "").unwrap().is_match("&~").unwrap());
+    }
+    #[test]
+    fn regex_invalid() {
+        assert_eq!(
+            compile(r"\q", "").err().unwrap().code.unwrap().to_string(),
+            "FORX0002"
+        );
+        assert_eq!(
+            compile("(?i)a", "")
+                .err()
+                .unwrap()
+                .code
+                .unwrap()
+                .to_string(),
+            "FORX0002"
+        );
+        assert_eq!(
+            compile_nonempty("a*", "")
+                .err()
+                .unwrap()
+                .code
+                .unwrap()
+                .to_string(),
+            "FORX0003"
+        );
+    }
+    #[test]
+    fn regex_backreferences() {
+        let re = compile(r"^(a+)b\1$", "").unwrap();
+        assert!(re.is_match("aabaa").unwrap());
+        assert!(!re.is_match("aaba").unwrap());
+        // \12 is group 1 followed by "2" when there are fewer than 12 groups
+        assert!(compile(r"^(a)\12$", "").unwrap().is_match("aa2").unwrap());
+        // A group cannot be referred to before it is closed
+        assert_eq!(
+            compile(r"(a\1)", "")
+                .err()
+                .unwrap()
+                .code
+                .unwrap()
+                .to_string(),
+            "FORX0002"
+        );
+        assert_eq!(
+            compile(r"\1(a)", "")
+                .err()
+                .unwrap()
+                .code
+                .unwrap()
+                .to_string(),
+            "FORX0002"
+        );
+    }
+    #[test]
+    fn regex_blocks() {
+        assert!(compile(r"^\p{IsBasicLatin}+$", "")
+            .unwrap()
+            .is_match("abc")
+            .unwrap());
+        assert!(!compile(r"^\p{IsBasicLatin}+$", "")
+            .unwrap()
+            .is_match("\u{E9}")
+            .unwrap());
+        assert!(compile(r"^[\P{IsGreek}]+$", "")
+            .unwrap()
+            .is_match("abc")
+            .unwrap());
+        assert!(!compile(r"^[\P{IsGreek}]+$", "")
+            .unwrap()
+            .is_match("\u{3B1}")
+            .unwrap());
+        assert_eq!(
+            compile(r"\p{IsNoSuchBlock}", "")
+                .err()
+                .unwrap()
+                .code
+                .unwrap()
+                .to_string(),
+            "FORX0002"
+        );
+        // Category escapes are supported
+        assert!(compile(r"^\p{Lu}+$", "").unwrap().is_match("ABC").unwrap());
+        assert_eq!(
+            compile(r"\p{Lu", "")
+                .err()
+                .unwrap()
+                .code
+                .unwrap()
+                .to_string(),
+            "FORX0002"
+        );
+    }
+    #[test]
+    fn regex_group_parents() {
+        let re = compile("(a(b)(?:(c)))(d)", "").unwrap();
+        assert_eq!(re.parent(1), 0);
+        assert_eq!(re.parent(2), 1);
+        assert_eq!(re.parent(3), 1);
+        assert_eq!(re.parent(4), 0);
+    }
+    #[test]
+    fn regex_replacement() {
+        let re = compile("(a)(b)", "").unwrap();
+        let caps = re.captures("ab").unwrap();
+        assert_eq!(expand_replacement("ab", &caps[0], r"$2$1\$$12"), "ba$a2");
+        assert!(check_replacement(r"a\b").is_err());
+        assert!(check_replacement("a$").is_err());
+    }
+}
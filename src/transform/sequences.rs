@@ -0,0 +1,436 @@
+//! These functions are for features defined in XPath Functions 3.1 for sequences.
+
+use std::rc::Rc;
+use url::Url;
+
+use crate::item::{same_key, Item, Node, NodeType, Sequence, SequenceTrait};
+use crate::qname::QualifiedName;
+use crate::transform::context::{Context, StaticContext};
+use crate::transform::maps::atomize_key;
+use crate::transform::strings::check_collation;
+use crate::transform::Transform;
+use crate::value::Value;
+use crate::xdmerror::{Error, ErrorKind};
+
+/// XPath empty function.
+pub(crate) fn fn_empty<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    Ok(vec![Item::Value(Rc::new(Value::from(
+        ctxt.dispatch(stctxt, s)?.is_empty(),
+    )))])
+}
+
+/// XPath exists function.
+pub(crate) fn exists<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    Ok(vec![Item::Value(Rc::new(Value::from(
+        !ctxt.dispatch(stctxt, s)?.is_empty(),
+    )))])
+}
+
+/// XPath distinct-values function. The first occurrence of each value is kept.
+pub(crate) fn distinct_values<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    c: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let seq = ctxt.dispatch(stctxt, s)?;
+    check_collation(ctxt, stctxt, c)?;
+    let mut result: Vec<Rc<Value>> = vec![];
+    for i in seq.iter() {
+        let v = atomize_key(i)?;
+        if !result.iter().any(|w| same_key(w, &v)) {
+            result.push(v)
+        }
+    }
+    Ok(result.into_iter().map(Item::Value).collect())
+}
+
+/// XPath index-of function. Positions start at 1.
+pub(crate) fn index_of<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    t: &Transform<N>,
+    c: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let seq = ctxt.dispatch(stctxt, s)?;
+    let search = ctxt.dispatch(stctxt, t)?;
+    check_collation(ctxt, stctxt, c)?;
+    if search.len() != 1 {
+        return Err(Error::new_with_code(
+            ErrorKind::TypeError,
+            "search value must be a single atomic value",
+            Some(QualifiedName::new(None, None, "XPTY0004")),
+        ));
+    }
+    let v = atomize_key(&search[0])?;
+    let mut result = vec![];
+    for (p, i) in seq.iter().enumerate() {
+        let w = atomize_key(i)?;
+        // NaN is not equal to itself
+        if same_key(&w, &v) && !(w.is_numeric() && w.to_double().is_nan()) {
+            result.push(Item::Value(Rc::new(Value::from(p as i64 + 1))))
+        }
+    }
+    Ok(result)
+}
+
+/// XPath insert-before function.
+/// A position less than 1 inserts at the start, a position greater than the length of the sequence appends.
+pub(crate) fn insert_before<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    p: &Transform<N>,
+    i: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let mut seq = ctxt.dispatch(stctxt, s)?;
+    let pos = ctxt.dispatch(stctxt, p)?.to_int()?;
+    let ins = ctxt.dispatch(stctxt, i)?;
+    let at = pos.clamp(1, seq.len() as i64 + 1) as usize - 1;
+    seq.splice(at..at, ins);
+    Ok(seq)
+}
+
+/// XPath remove function. If the position is out of range the sequence is unchanged.
+pub(crate) fn remove<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    p: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let mut seq = ctxt.dispatch(stctxt, s)?;
+    let pos = ctxt.dispatch(stctxt, p)?.to_int()?;
+    if pos >= 1 && pos <= seq.len() as i64 {
+        seq.remove(pos as usize - 1);
+    }
+    Ok(seq)
+}
+
+/// XPath reverse function.
+pub(crate) fn reverse<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let mut seq = ctxt.dispatch(stctxt, s)?;
+    seq.reverse();
+    Ok(seq)
+}
+
+/// XPath subsequence function.
+/// The start and length are rounded, and items at positions p where start <= p < start + length are returned.
+pub(crate) fn subsequence<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    st: &Transform<N>,
+    l: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let seq = ctxt.dispatch(stctxt, s)?;
+    let start = round_half_up(single_double(ctxt.dispatch(stctxt, st)?)?);
+    let end = match l {
+        Some(m) => start + round_half_up(single_double(ctxt.dispatch(stctxt, m)?)?),
+        None => f64::INFINITY,
+    };
+    Ok(seq
+        .into_iter()
+        .enumerate()
+        .filter(|(p, _)| {
+            let q = *p as f64 + 1.0;
+            q >= start && q < end
+        })
+        .map(|(_, i)| i)
+        .collect())
+}
+
+// XPath round function semantics. NaN and infinity are unchanged.
+fn round_half_up(d: f64) -> f64 {
+    (d + 0.5).floor()
+}
+
+// The numeric value of a singleton sequence.
+fn single_double<N: Node>(s: Sequence<N>) -> Result<f64, Error> {
+    if s.len() == 1 {
+        Ok(s[0].to_double())
+    } else {
+        Err(Error::new_with_code(
+            ErrorKind::TypeError,
+            "expected a single numeric value",
+            Some(QualifiedName::new(None, None, "XPTY0004")),
+        ))
+    }
+}
+
+/// XPath head function.
+pub(crate) fn head<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let mut seq = ctxt.dispatch(stctxt, s)?;
+    seq.truncate(1);
+    Ok(seq)
+}
+
+/// XPath tail function.
+pub(crate) fn tail<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let seq = ctxt.dispatch(stctxt, s)?;
+    Ok(seq.into_iter().skip(1).collect())
+}
+
+/// XPath zero-or-one function.
+pub(crate) fn zero_or_one<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let seq = ctxt.dispatch(stctxt, s)?;
+    if seq.len() <= 1 {
+        Ok(seq)
+    } else {
+        Err(cardinality_error(
+            "FORG0003",
+            "zero-or-one called with a sequence containing more than one item",
+        ))
+    }
+}
+
+/// XPath one-or-more function.
+pub(crate) fn one_or_more<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let seq = ctxt.dispatch(stctxt, s)?;
+    if seq.is_empty() {
+        Err(cardinality_error(
+            "FORG0004",
+            "one-or-more called with an empty sequence",
+        ))
+    } else {
+        Ok(seq)
+    }
+}
+
+/// XPath exactly-one function.
+pub(crate) fn exactly_one<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let seq = ctxt.dispatch(stctxt, s)?;
+    if seq.len() == 1 {
+        Ok(seq)
+    } else {
+        Err(cardinality_error(
+            "FORG0005",
+            "exactly-one called with a sequence containing zero or more than one item",
+        ))
+    }
+}
+
+fn cardinality_error(code: &str, msg: &str) -> Error {
+    Error::new_with_code(
+        ErrorKind::Unknown,
+        msg,
+        Some(QualifiedName::new(None, None, code)),
+    )
+}
+
+/// XPath deep-equal function.
+pub(crate) fn deep_equal<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    t: &Transform<N>,
+    c: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let u = ctxt.dispatch(stctxt, s)?;
+    let v = ctxt.dispatch(stctxt, t)?;
+    check_collation(ctxt, stctxt, c)?;
+    Ok(vec![Item::Value(Rc::new(Value::from(
+        deep_equal_sequences(&u, &v)?,
+    )))])
+}
+
+/// Compare two sequences, as defined by the deep-equal function.
+pub(crate) fn deep_equal_sequences<N: Node>(
+    s: &Sequence<N>,
+    t: &Sequence<N>,
+) -> Result<bool, Error> {
+    if s.len() != t.len() {
+        return Ok(false);
+    }
+    for (i, j) in s.iter().zip(t.iter()) {
+        if !deep_equal_items(i, j)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn deep_equal_items<N: Node>(i: &Item<N>, j: &Item<N>) -> Result<bool, Error> {
+    match (i, j) {
+        (Item::Value(v), Item::Value(w)) => Ok(same_key(v, w)),
+        (Item::Node(n), Item::Node(m)) => Ok(deep_equal_nodes(n, m)),
+        (Item::Map(m), Item::Map(n)) => {
+            if m.len() != n.len() {
+                return Ok(false);
+            }
+            for (k, v) in m.iter() {
+                match n.get(k) {
+                    Some(w) => {
+                        if !deep_equal_sequences(v, w)? {
+                            return Ok(false);
+                        }
+                    }
+                    None => return Ok(false),
+                }
+            }
+            Ok(true)
+        }
+        (Item::Array(a), Item::Array(b)) => {
+            if a.len() != b.len() {
+                return Ok(false);
+            }
+            for (v, w) in a.iter().zip(b.iter()) {
+                if !deep_equal_sequences(v, w)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+        (Item::Function(_), _) | (_, Item::Function(_)) => Err(Error::new_with_code(
+            ErrorKind::TypeError,
+            "deep-equal cannot compare function items",
+            Some(QualifiedName::new(None, None, "FOTY0015")),
+        )),
+        _ => Ok(false),
+    }
+}
+
+// Compare two nodes. Comments and processing instructions are ignored in element and document content.
+fn deep_equal_nodes<N: Node>(n: &N, m: &N) -> bool {
+    if n.node_type() != m.node_type() {
+        return false;
+    }
+    match n.node_type() {
+        NodeType::Document => deep_equal_content(n, m),
+        NodeType::Element => {
+            if n.name() != m.name() {
+                return false;
+            }
+            let (na, ma): (Vec<N>, Vec<N>) =
+                (n.attribute_iter().collect(), m.attribute_iter().collect());
+            na.len() == ma.len()
+                && na.iter().all(|a| {
+                    m.get_attribute_node(&a.name())
+                        .is_some_and(|b| same_key(&a.value(), &b.value()))
+                })
+                && deep_equal_content(n, m)
+        }
+        NodeType::Attribute | NodeType::ProcessingInstruction | NodeType::Namespace => {
+            n.name() == m.name() && n.value().to_string() == m.value().to_string()
+        }
+        _ => n.to_string() == m.to_string(),
+    }
+}
+
+fn deep_equal_content<N: Node>(n: &N, m: &N) -> bool {
+    let significant = |c: &N| {
+        !matches!(
+            c.node_type(),
+            NodeType::Comment | NodeType::ProcessingInstruction
+        )
+    };
+    let nc: Vec<N> = n.child_iter().filter(significant).collect();
+    let mc: Vec<N> = m.child_iter().filter(significant).collect();
+    nc.len() == mc.len()
+        && nc
+            .iter()
+            .zip(mc.iter())
+            .all(|(c, d)| deep_equal_nodes(c, d))
+}
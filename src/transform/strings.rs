@@ -1,12 +1,18 @@
-//! These functions are for features defined in XPath Functions 1.0 and 2.0.
+//! These functions are for features defined in XPath Functions 1.0, 2.0 and 3.1.
 
+use std::cmp::Ordering;
 use std::rc::Rc;
 
+use unicode_normalization::UnicodeNormalization;
 use unicode_segmentation::UnicodeSegmentation;
 use url::Url;
 
 use crate::item::{Item, Node, Sequence, SequenceTrait};
-use crate::transform::context::{Context, StaticContext};
+use crate::qname::QualifiedName;
+use crate::transform::context::{Context, ContextBuilder, StaticContext};
+use crate::transform::regexp::{
+    check_replacement, compile, compile_nonempty, expand_replacement, Groups, XPathRegex,
+};
 use crate::transform::Transform;
 use crate::value::Value;
 use crate::xdmerror::{Error, ErrorKind};
@@ -112,9 +118,18 @@ pub fn string<
     stctxt: &mut StaticContext<N, F, G, H>,
     s: &Transform<N>,
 ) -> Result<Sequence<N>, Error> {
-    Ok(vec![Item::Value(Rc::new(Value::from(
-        ctxt.dispatch(stctxt, s)?.to_string(),
-    )))])
+    let seq = ctxt.dispatch(stctxt, s)?;
+    if seq
+        .iter()
+        .any(|i| matches!(i, Item::Map(_) | Item::Array(_) | Item::Function(_)))
+    {
+        return Err(Error::new_with_code(
+            ErrorKind::TypeError,
+            "maps, arrays and functions do not have a string value",
+            Some(QualifiedName::new(None, None, "FOTY0014")),
+        ));
+    }
+    Ok(vec![Item::Value(Rc::new(Value::from(seq.to_string())))])
 }
 
 /// XPath starts-with function.
@@ -381,3 +396,596 @@ pub(crate) fn tr_concat<
         Err(err) => Err(err),
     }
 }
+
+// Evaluate an optional argument to a string. The default is used if the argument is absent.
+fn optional_string<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    t: &Option<Box<Transform<N>>>,
+    default: &str,
+) -> Result<String, Error> {
+    t.as_ref().map_or_else(
+        || Ok(default.to_string()),
+        |u| Ok(ctxt.dispatch(stctxt, u)?.to_string()),
+    )
+}
+
+/// XPath upper-case function.
+pub(crate) fn upper_case<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    Ok(vec![Item::Value(Rc::new(Value::from(
+        ctxt.dispatch(stctxt, s)?.to_string().to_uppercase(),
+    )))])
+}
+
+/// XPath lower-case function.
+pub(crate) fn lower_case<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    Ok(vec![Item::Value(Rc::new(Value::from(
+        ctxt.dispatch(stctxt, s)?.to_string().to_lowercase(),
+    )))])
+}
+
+/// XPath string-join function.
+pub(crate) fn string_join<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    sep: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let seq = ctxt.dispatch(stctxt, s)?;
+    let separator = optional_string(ctxt, stctxt, sep, "")?;
+    Ok(vec![Item::Value(Rc::new(Value::from(
+        seq.iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<String>>()
+            .join(separator.as_str()),
+    )))])
+}
+
+/// XPath string-length function. Without an argument, the length of the context item is returned.
+pub(crate) fn string_length<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let t = match s {
+        Some(u) => ctxt.dispatch(stctxt, u)?.to_string(),
+        None => match ctxt.cur.get(ctxt.i) {
+            Some(i) => i.to_string(),
+            None => {
+                return Err(Error::new_with_code(
+                    ErrorKind::DynamicAbsent,
+                    "no context item",
+                    Some(QualifiedName::new(None, None, "XPDY0002")),
+                ))
+            }
+        },
+    };
+    Ok(vec![Item::Value(Rc::new(Value::from(
+        t.chars().count() as i64
+    )))])
+}
+
+/// XPath tokenize function. With a single argument the string is split at whitespace.
+pub(crate) fn tokenize<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    p: &Option<Box<Transform<N>>>,
+    f: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let input = ctxt.dispatch(stctxt, s)?.to_string();
+    match p {
+        None => Ok(input
+            .split([' ', '\t', '\n', '\r'])
+            .filter(|t| !t.is_empty())
+            .map(|t| Item::Value(Rc::new(Value::from(t))))
+            .collect()),
+        Some(q) => {
+            let pattern = ctxt.dispatch(stctxt, q)?.to_string();
+            let flags = optional_string(ctxt, stctxt, f, "")?;
+            let re = compile_nonempty(pattern.as_str(), flags.as_str())?;
+            if input.is_empty() {
+                Ok(vec![])
+            } else {
+                let mut result = vec![];
+                let mut last = 0;
+                for m in re.captures(input.as_str())? {
+                    let (start, end) = m[0].unwrap();
+                    result.push(Item::Value(Rc::new(Value::from(&input[last..start]))));
+                    last = end;
+                }
+                result.push(Item::Value(Rc::new(Value::from(&input[last..]))));
+                Ok(result)
+            }
+        }
+    }
+}
+
+/// XPath matches function.
+pub(crate) fn matches<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    p: &Transform<N>,
+    f: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let input = ctxt.dispatch(stctxt, s)?.to_string();
+    let pattern = ctxt.dispatch(stctxt, p)?.to_string();
+    let flags = optional_string(ctxt, stctxt, f, "")?;
+    let re = compile(pattern.as_str(), flags.as_str())?;
+    Ok(vec![Item::Value(Rc::new(Value::from(
+        re.is_match(input.as_str())?,
+    )))])
+}
+
+/// XPath replace function.
+pub(crate) fn replace<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    p: &Transform<N>,
+    r: &Transform<N>,
+    f: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let input = ctxt.dispatch(stctxt, s)?.to_string();
+    let pattern = ctxt.dispatch(stctxt, p)?.to_string();
+    let rep = ctxt.dispatch(stctxt, r)?.to_string();
+    let flags = optional_string(ctxt, stctxt, f, "")?;
+    let re = compile_nonempty(pattern.as_str(), flags.as_str())?;
+    let literal = flags.contains('q');
+    if !literal {
+        check_replacement(rep.as_str())?;
+    }
+    let mut result = String::new();
+    let mut last = 0;
+    for m in re.captures(input.as_str())? {
+        let (start, end) = m[0].unwrap();
+        result.push_str(&input[last..start]);
+        if literal {
+            result.push_str(rep.as_str())
+        } else {
+            result.push_str(expand_replacement(input.as_str(), &m, rep.as_str()).as_str())
+        }
+        last = end;
+    }
+    result.push_str(&input[last..]);
+    Ok(vec![Item::Value(Rc::new(Value::from(result)))])
+}
+
+/// The namespace URI for the result of the analyze-string function.
+const XPATH_FUNCTIONS_NS: &str = "http://www.w3.org/2005/xpath-functions";
+
+/// XPath analyze-string function.
+/// The result is a fn:analyze-string-result element, created in a new tree.
+pub(crate) fn analyze_string<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    p: &Transform<N>,
+    f: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let input = ctxt.dispatch(stctxt, s)?.to_string();
+    let pattern = ctxt.dispatch(stctxt, p)?.to_string();
+    let flags = optional_string(ctxt, stctxt, f, "")?;
+    let re = compile_nonempty(pattern.as_str(), flags.as_str())?;
+    let mut rd = N::new_document();
+    let fn_name = |local: &str| {
+        Rc::new(QualifiedName::new(
+            Some(XPATH_FUNCTIONS_NS.to_string()),
+            Some(String::from("fn")),
+            local,
+        ))
+    };
+    let mut result = rd.new_element(fn_name("analyze-string-result"))?;
+    result.add_namespace(rd.new_namespace(
+        Rc::new(Value::from(XPATH_FUNCTIONS_NS)),
+        Some(Rc::new(Value::from("fn"))),
+    )?)?;
+    let mut last = 0;
+    for m in re.captures(input.as_str())? {
+        let (start, end) = m[0].unwrap();
+        if start > last {
+            let mut nm = rd.new_element(fn_name("non-match"))?;
+            nm.push(rd.new_text(Rc::new(Value::from(&input[last..start])))?)?;
+            result.push(nm)?;
+        }
+        let mut e = rd.new_element(fn_name("match"))?;
+        analyze_groups(&rd, &mut e, &input, (start, end), 0, &m, &re, &fn_name)?;
+        result.push(e)?;
+        last = end;
+    }
+    if last < input.len() {
+        let mut nm = rd.new_element(fn_name("non-match"))?;
+        nm.push(rd.new_text(Rc::new(Value::from(&input[last..])))?)?;
+        result.push(nm)?;
+    }
+    rd.push(result.clone())?;
+    Ok(vec![Item::Node(result)])
+}
+
+/// XSLT analyze-string instruction.
+/// The input is divided into matching and non-matching substrings, and the corresponding sequence constructor is evaluated for each of them.
+/// The substring is the context item, and the substrings together form the context, so position() and last() refer to them.
+/// The regex-group function gives the captured groups of a matching substring.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn analyze_string_instruction<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    p: &Transform<N>,
+    f: &Transform<N>,
+    matching: &Transform<N>,
+    non_matching: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let input = ctxt.dispatch(stctxt, s)?.to_string();
+    let pattern = ctxt.dispatch(stctxt, p)?.to_string();
+    let flags = ctxt.dispatch(stctxt, f)?.to_string();
+    let re = compile_nonempty(pattern.as_str(), flags.as_str())?;
+    // Each segment is a substring and, if it is a match, the captured groups
+    let mut segments: Vec<(&str, Option<Vec<String>>)> = vec![];
+    let mut last = 0;
+    for m in re.captures(input.as_str())? {
+        let (start, end) = m[0].unwrap();
+        if start > last {
+            segments.push((&input[last..start], None));
+        }
+        segments.push((
+            &input[start..end],
+            Some(
+                m.iter()
+                    .map(|g| g.map_or(String::new(), |(s, e)| input[s..e].to_string()))
+                    .collect(),
+            ),
+        ));
+        last = end;
+    }
+    if last < input.len() {
+        segments.push((&input[last..], None));
+    }
+    let items: Sequence<N> = segments
+        .iter()
+        .map(|(t, _)| Item::Value(Rc::new(Value::from(*t))))
+        .collect();
+    segments
+        .into_iter()
+        .enumerate()
+        .try_fold(vec![], |mut result, (i, (_, groups))| {
+            let (body, groups) = match groups {
+                Some(g) => (matching, g),
+                None => (non_matching, vec![]),
+            };
+            let mut r = ContextBuilder::from(ctxt)
+                .context(items.clone())
+                .index(i)
+                .regex_groups(groups)
+                .build()
+                .dispatch(stctxt, body)?;
+            result.append(&mut r);
+            Ok(result)
+        })
+}
+
+/// XSLT regex-group function. If there is no such group then the result is the zero-length string.
+pub(crate) fn regex_group<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    n: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let g = ctxt.dispatch(stctxt, n)?.to_int()?;
+    Ok(vec![Item::Value(Rc::new(Value::from(
+        usize::try_from(g)
+            .ok()
+            .and_then(|g| ctxt.regex_groups.get(g))
+            .cloned()
+            .unwrap_or_default(),
+    )))])
+}
+
+// Add the content of a match or group element: text and the captured groups within the range start..end.
+// The groups added are those whose enclosing group in the regular expression is the given group.
+#[allow(clippy::too_many_arguments)]
+fn analyze_groups<N: Node>(
+    rd: &N,
+    parent: &mut N,
+    input: &str,
+    (start, end): (usize, usize),
+    group: usize,
+    groups: &Groups,
+    re: &XPathRegex,
+    fn_name: &dyn Fn(&str) -> Rc<QualifiedName>,
+) -> Result<(), Error> {
+    let mut pos = start;
+    for (g, span) in groups.iter().enumerate().skip(1) {
+        // A group within a repeated group may have been captured by an earlier repetition
+        let Some((s, e)) = *span else { continue };
+        if re.parent(g) != group || s < pos || e > end {
+            continue;
+        }
+        if s > pos {
+            parent.push(rd.new_text(Rc::new(Value::from(&input[pos..s])))?)?;
+        }
+        let mut ge = rd.new_element(fn_name("group"))?;
+        ge.add_attribute(rd.new_attribute(
+            Rc::new(QualifiedName::new(None, None, "nr")),
+            Rc::new(Value::from(g as i64)),
+        )?)?;
+        analyze_groups(rd, &mut ge, input, (s, e), g, groups, re, fn_name)?;
+        parent.push(ge)?;
+        pos = e;
+    }
+    if end > pos {
+        parent.push(rd.new_text(Rc::new(Value::from(&input[pos..end])))?)?;
+    }
+    Ok(())
+}
+
+/// XPath codepoints-to-string function.
+pub(crate) fn codepoints_to_string<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let result = ctxt
+        .dispatch(stctxt, s)?
+        .iter()
+        .map(|i| {
+            let cp = i.to_int()?;
+            u32::try_from(cp)
+                .ok()
+                .and_then(char::from_u32)
+                .filter(|c| is_xml_char(*c))
+                .ok_or_else(|| {
+                    Error::new_with_code(
+                        ErrorKind::Unknown,
+                        format!("{} is not a valid XML character", cp),
+                        Some(QualifiedName::new(None, None, "FOCH0001")),
+                    )
+                })
+        })
+        .collect::<Result<String, Error>>()?;
+    Ok(vec![Item::Value(Rc::new(Value::from(result)))])
+}
+
+// Is the character allowed in an XML 1.0 document?
+fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..='\u{10FFFF}')
+}
+
+/// XPath string-to-codepoints function.
+pub(crate) fn string_to_codepoints<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    Ok(ctxt
+        .dispatch(stctxt, s)?
+        .to_string()
+        .chars()
+        .map(|c| Item::Value(Rc::new(Value::from(c as i64))))
+        .collect())
+}
+
+/// Check that a collation argument, if present, is the Unicode codepoint collation. No other collation is supported.
+pub(crate) fn check_collation<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    c: &Option<Box<Transform<N>>>,
+) -> Result<(), Error> {
+    let collation = optional_string(ctxt, stctxt, c, CODEPOINT_COLLATION)?;
+    if collation == CODEPOINT_COLLATION {
+        Ok(())
+    } else {
+        Err(Error::new_with_code(
+            ErrorKind::Unknown,
+            format!("collation \"{}\" is not supported", collation),
+            Some(QualifiedName::new(None, None, "FOCH0002")),
+        ))
+    }
+}
+
+/// The Unicode codepoint collation URI.
+pub(crate) const CODEPOINT_COLLATION: &str =
+    "http://www.w3.org/2005/xpath-functions/collation/codepoint";
+
+/// XPath compare function.
+pub(crate) fn compare<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    t: &Transform<N>,
+    c: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let u = ctxt.dispatch(stctxt, s)?;
+    let v = ctxt.dispatch(stctxt, t)?;
+    check_collation(ctxt, stctxt, c)?;
+    if u.is_empty() || v.is_empty() {
+        return Ok(vec![]);
+    }
+    let result: i64 = match u.to_string().cmp(&v.to_string()) {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    };
+    Ok(vec![Item::Value(Rc::new(Value::from(result)))])
+}
+
+/// XPath normalize-unicode function. The default normalization form is NFC.
+pub(crate) fn normalize_unicode<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    form: &Option<Box<Transform<N>>>,
+) -> Result<Sequence<N>, Error> {
+    let u = ctxt.dispatch(stctxt, s)?.to_string();
+    let f = optional_string(ctxt, stctxt, form, "NFC")?;
+    let result = match f.trim().to_uppercase().as_str() {
+        "NFC" => u.nfc().collect(),
+        "NFD" => u.nfd().collect(),
+        "NFKC" => u.nfkc().collect(),
+        "NFKD" => u.nfkd().collect(),
+        "" => u,
+        g => {
+            return Err(Error::new_with_code(
+                ErrorKind::Unknown,
+                format!("normalization form \"{}\" is not supported", g),
+                Some(QualifiedName::new(None, None, "FOCH0003")),
+            ))
+        }
+    };
+    Ok(vec![Item::Value(Rc::new(Value::from(result)))])
+}
+
+// Percent-encode the characters in a string for which the function returns true.
+fn percent_encode(s: &str, escape: impl Fn(char) -> bool) -> String {
+    let mut result = String::new();
+    for c in s.chars() {
+        if escape(c) {
+            let mut buf = [0; 4];
+            for b in c.encode_utf8(&mut buf).bytes() {
+                result.push_str(format!("%{:02X}", b).as_str())
+            }
+        } else {
+            result.push(c)
+        }
+    }
+    result
+}
+
+/// XPath encode-for-uri function.
+pub(crate) fn encode_for_uri<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    Ok(vec![Item::Value(Rc::new(Value::from(percent_encode(
+        ctxt.dispatch(stctxt, s)?.to_string().as_str(),
+        |c| !(c.is_ascii_alphanumeric() || "-_.~".contains(c)),
+    ))))])
+}
+
+/// XPath iri-to-uri function.
+pub(crate) fn iri_to_uri<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    Ok(vec![Item::Value(Rc::new(Value::from(percent_encode(
+        ctxt.dispatch(stctxt, s)?.to_string().as_str(),
+        |c| !('\u{21}'..='\u{7E}').contains(&c) || "<>\"{}|\\^`".contains(c),
+    ))))])
+}
+
+/// XPath escape-html-uri function.
+pub(crate) fn escape_html_uri<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    Ok(vec![Item::Value(Rc::new(Value::from(percent_encode(
+        ctxt.dispatch(stctxt, s)?.to_string().as_str(),
+        |c| !('\u{20}'..='\u{7E}').contains(&c),
+    ))))])
+}
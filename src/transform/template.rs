@@ -1,6 +1,7 @@
 //! # Templates
 
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
 use std::rc::Rc;
 use url::Url;
@@ -19,6 +20,8 @@ pub struct Template<N: Node> {
     pub(crate) import: Vec<usize>,
     pub(crate) document_order: Option<usize>,
     pub(crate) mode: Option<Rc<QualifiedName>>,
+    // The package that declares the template rule. See XSLT 3.5.
+    pub(crate) package: Option<Rc<str>>,
 }
 
 impl<N: Node> Template<N> {
@@ -37,8 +40,102 @@ impl<N: Node> Template<N> {
             import,
             document_order,
             mode,
+            package: None,
         }
     }
+    /// The mode of the template rule. A template rule in the unnamed mode is in the unnamed mode of its package.
+    pub fn mode_key(&self) -> ModeKey {
+        ModeKey::new(&self.mode, &self.package)
+    }
+}
+
+/// Identifies a mode declaration, and the template rules in that mode. See XSLT 3.5.4.
+/// Named modes are shared by packages, but the unnamed mode of a package is private to that package,
+/// so it is identified by the name of the package. A stylesheet that is not a package has no name.
+/// Likewise a named mode of a used package that is hidden in the using package is private to the used package.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ModeKey {
+    Unnamed(Option<Rc<str>>),
+    Named(QualifiedName),
+    Private(Option<Rc<str>>, QualifiedName),
+}
+
+impl ModeKey {
+    /// The key for a mode used by the code of the given package. If the mode has no name then it is the unnamed mode of that package.
+    pub fn new(m: &Option<Rc<QualifiedName>>, package: &Option<Rc<str>>) -> Self {
+        match m {
+            Some(n) => ModeKey::Named((**n).clone()),
+            None => ModeKey::Unnamed(package.clone()),
+        }
+    }
+}
+
+/// The action taken when no template rule in the stylesheet matches an item. See XSLT 6.7.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum OnNoMatch {
+    #[default]
+    TextOnlyCopy,
+    ShallowCopy,
+    DeepCopy,
+    ShallowSkip,
+    DeepSkip,
+    Fail,
+}
+
+impl TryFrom<&str> for OnNoMatch {
+    type Error = String;
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s.trim() {
+            "text-only-copy" => Ok(OnNoMatch::TextOnlyCopy),
+            "shallow-copy" => Ok(OnNoMatch::ShallowCopy),
+            "deep-copy" => Ok(OnNoMatch::DeepCopy),
+            "shallow-skip" => Ok(OnNoMatch::ShallowSkip),
+            "deep-skip" => Ok(OnNoMatch::DeepSkip),
+            "fail" => Ok(OnNoMatch::Fail),
+            _ => Err(format!("unknown on-no-match value \"{}\"", s)),
+        }
+    }
+}
+
+/// Whether the items processed by a mode are typed. See XSLT 6.6.2.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Typed {
+    Yes,
+    No,
+    Strict,
+    Lax,
+    #[default]
+    Unspecified,
+}
+
+impl TryFrom<&str> for Typed {
+    type Error = String;
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s.trim() {
+            "yes" | "true" | "1" => Ok(Typed::Yes),
+            "no" | "false" | "0" => Ok(Typed::No),
+            "strict" => Ok(Typed::Strict),
+            "lax" => Ok(Typed::Lax),
+            "unspecified" => Ok(Typed::Unspecified),
+            _ => Err(format!("unknown typed value \"{}\"", s)),
+        }
+    }
+}
+
+/// The properties of a mode, as declared by xsl:mode. See XSLT 6.6.
+/// A mode that is used but not declared has the default properties.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mode {
+    pub on_no_match: OnNoMatch,
+    /// If true, then it is an error for more than one template rule to match an item.
+    /// Otherwise the last matching template in document order is used.
+    pub on_multiple_match_fail: bool,
+    pub warning_on_no_match: bool,
+    pub warning_on_multiple_match: bool,
+    /// Streaming is not supported, so a streamable mode is processed in the same way as any other mode.
+    pub streamable: bool,
+    /// There is no schema support, so every node is untyped.
+    pub typed: Typed,
 }
 
 /// Two templates are equal if they have the same priority, import precedence, and mode.
@@ -54,6 +151,9 @@ impl<N: Node> PartialOrd for Template<N> {
         Some(self.cmp(other))
     }
 }
+/// Template rules with a lower import level take precedence, then those with a higher priority.
+/// The template rules of a used package have a lower import level than those of the using package,
+/// so that a template rule in xsl:override takes precedence over them.
 impl<N: Node> Ord for Template<N> {
     fn cmp(&self, other: &Self) -> Ordering {
         self.priority.map_or_else(
@@ -66,11 +166,11 @@ impl<N: Node> Ord for Template<N> {
                 other.priority.map_or_else(
                     || Ordering::Less,
                     |t| {
-                        if s < t {
+                        self.import.len().cmp(&other.import.len()).then(if s < t {
                             Ordering::Greater
                         } else {
                             Ordering::Less
-                        }
+                        })
                     },
                 )
             },
@@ -89,6 +189,7 @@ impl<N: Node> Debug for Template<N> {
 }
 
 /// Apply templates to the select expression.
+/// The parameters are supplied to each template rule that is invoked.
 pub(crate) fn apply_templates<
     N: Node,
     F: FnMut(&str) -> Result<(), Error>,
@@ -100,6 +201,7 @@ pub(crate) fn apply_templates<
     s: &Transform<N>,
     m: &Option<Rc<QualifiedName>>,
     o: &Vec<(Order, Transform<N>)>, // sort keys
+    p: &HashMap<String, Sequence<N>>,
 ) -> Result<Sequence<N>, Error> {
     // s is the select expression. Evaluate it, and then iterate over its items.
     // Each iteration becomes an item in the result sequence.
@@ -137,6 +239,9 @@ pub(crate) fn apply_templates<
             .context(vec![i.clone()])
             .previous_context(Some(i.clone()))
             .current_templates(templates)
+            .current_mode(m.clone())
+            .template_params(p.clone())
+            .package(matching.package.clone())
             .build()
             .dispatch(stctxt, &matching.body)?;
         result.append(&mut u);
@@ -168,6 +273,8 @@ pub(crate) fn apply_imports<
     if !next.is_empty() {
         ContextBuilder::from(ctxt)
             .current_templates(next.clone())
+            .template_params(HashMap::new())
+            .package(next[0].package.clone())
             .build()
             .dispatch(stctxt, &next[0].body)
     } else {
@@ -188,9 +295,84 @@ pub(crate) fn next_match<
     if ctxt.current_templates.len() > 2 {
         ContextBuilder::from(ctxt)
             .current_templates(ctxt.current_templates.iter().skip(1).cloned().collect())
+            .template_params(HashMap::new())
+            .package(ctxt.current_templates[1].package.clone())
             .build()
             .dispatch(stctxt, &ctxt.current_templates[1].body)
     } else {
         Ok(vec![])
     }
 }
+
+/// Evaluate an xsl:apply-templates instruction with non-tunnel parameters. See XSLT 10.1.1.
+/// The parameters are only supplied to the template rules invoked by the instruction.
+pub(crate) fn with_parameters<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    params: &[(String, Transform<N>)],
+    body: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    let mut values = HashMap::new();
+    params.iter().try_for_each(|(name, t)| {
+        values.insert(name.clone(), ctxt.dispatch(stctxt, t)?);
+        Ok::<(), Error>(())
+    })?;
+    match body {
+        Transform::ApplyTemplates(s, m, o) => apply_templates(ctxt, stctxt, s, m, o, &values),
+        Transform::ApplyTemplatesCurrentMode(s, o) => {
+            apply_templates(ctxt, stctxt, s, &ctxt.current_mode, o, &values)
+        }
+        _ => ctxt.dispatch(stctxt, body),
+    }
+}
+
+/// Evaluate an xsl:apply-templates instruction, supplying the parameters of the template rule being evaluated.
+pub(crate) fn pass_parameters<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    body: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    match body {
+        Transform::ApplyTemplates(s, m, o) => {
+            apply_templates(ctxt, stctxt, s, m, o, &ctxt.template_params)
+        }
+        Transform::ApplyTemplatesCurrentMode(s, o) => apply_templates(
+            ctxt,
+            stctxt,
+            s,
+            &ctxt.current_mode,
+            o,
+            &ctxt.template_params,
+        ),
+        _ => ctxt.dispatch(stctxt, body),
+    }
+}
+
+/// The value of a parameter declared by a template rule.
+/// If the parameter was not supplied then its default value is used.
+pub(crate) fn template_parameter<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    name: &String,
+    dflt: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    match ctxt.template_params.get(name) {
+        Some(v) => Ok(v.clone()),
+        None => ctxt.dispatch(stctxt, dflt),
+    }
+}
@@ -0,0 +1,960 @@
+//! # Sequence types
+//! A [SequenceType] describes a [Sequence]: the type of its items and how many items may occur.
+//!
+//! Atomic types are represented by a [Value] of that type, for example xs:integer is represented by a Value::Integer.
+//! Use [atomic_type] to find the representative value for the name of an XML Schema built-in type.
+
+use chrono::{Datelike, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::Decimal;
+use std::fmt;
+use std::fmt::Formatter;
+use std::rc::Rc;
+use std::str::FromStr;
+use url::Url;
+
+use crate::item::{Item, Node, Sequence};
+use crate::parser::common::{is_namechar, is_namestartchar, is_ncnamechar, is_ncnamestartchar};
+use crate::qname::QualifiedName;
+use crate::transform::booleans::effective_boolean_value;
+use crate::transform::context::{Context, StaticContext};
+use crate::transform::maps::atomize_key;
+use crate::transform::{NodeTest, Transform};
+use crate::value::{
+    base64_decode, hex_decode, Duration, NegativeInteger, NonNegativeInteger, NonPositiveInteger,
+    NormalizedString, PositiveInteger, Value,
+};
+use crate::xdmerror::{Error, ErrorKind};
+
+/// The type of a sequence.
+#[derive(Clone, Debug)]
+pub enum SequenceType {
+    /// empty-sequence()
+    Empty,
+    /// A sequence of items, each of which matches the item type.
+    Items(ItemType, Occurrence),
+}
+
+/// The number of items permitted in a sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Occurrence {
+    /// No occurrence indicator
+    ExactlyOne,
+    /// ?
+    ZeroOrOne,
+    /// *
+    ZeroOrMore,
+    /// +
+    OneOrMore,
+}
+
+/// The type of an item.
+#[derive(Clone, Debug)]
+pub enum ItemType {
+    /// item()
+    Any,
+    /// A node kind test, such as element() or node()
+    Node(NodeTest),
+    /// An atomic type. The type is given by a value of that type.
+    Atomic(Value),
+    /// A function test. Function signatures are not checked.
+    Function,
+    /// A map test. The types of entries are not checked.
+    Map,
+    /// An array test. The types of members are not checked.
+    Array,
+}
+
+impl SequenceType {
+    /// Does the sequence match this type?
+    pub fn matches<N: Node>(&self, s: &Sequence<N>) -> bool {
+        match self {
+            SequenceType::Empty => s.is_empty(),
+            SequenceType::Items(t, o) => {
+                let count = match o {
+                    Occurrence::ExactlyOne => s.len() == 1,
+                    Occurrence::ZeroOrOne => s.len() <= 1,
+                    Occurrence::ZeroOrMore => true,
+                    Occurrence::OneOrMore => !s.is_empty(),
+                };
+                count && s.iter().all(|i| t.matches(i))
+            }
+        }
+    }
+}
+
+impl fmt::Display for SequenceType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SequenceType::Empty => write!(f, "empty-sequence()"),
+            SequenceType::Items(t, o) => write!(
+                f,
+                "{}{}",
+                t,
+                match o {
+                    Occurrence::ExactlyOne => "",
+                    Occurrence::ZeroOrOne => "?",
+                    Occurrence::ZeroOrMore => "*",
+                    Occurrence::OneOrMore => "+",
+                }
+            ),
+        }
+    }
+}
+
+impl ItemType {
+    /// Does the item match this type?
+    pub fn matches<N: Node>(&self, i: &Item<N>) -> bool {
+        match (self, i) {
+            (ItemType::Any, _) => true,
+            (ItemType::Node(nt), Item::Node(_)) => nt.matches(i),
+            (ItemType::Atomic(t), Item::Value(v)) => derives_from(v, t),
+            (ItemType::Function, Item::Function(_) | Item::Map(_) | Item::Array(_)) => true,
+            (ItemType::Map, Item::Map(_)) => true,
+            (ItemType::Array, Item::Array(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for ItemType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ItemType::Any => write!(f, "item()"),
+            ItemType::Node(nt) => write!(f, "{}", nt),
+            ItemType::Atomic(v) => write!(f, "xs:{}", atomic_type_name(v)),
+            ItemType::Function => write!(f, "function(*)"),
+            ItemType::Map => write!(f, "map(*)"),
+            ItemType::Array => write!(f, "array(*)"),
+        }
+    }
+}
+
+/// Find the representative value for an XML Schema built-in atomic type, given the local part of its name.
+pub fn atomic_type(name: &str) -> Option<Value> {
+    match name {
+        "anyAtomicType" => Some(Value::AnyAtomicType),
+        "untypedAtomic" => Some(Value::UntypedAtomic),
+        "numeric" => Some(Value::Numeric),
+        "string" => Some(Value::String(String::new())),
+        "normalizedString" => NormalizedString::try_from("")
+            .ok()
+            .map(Value::NormalizedString),
+        "token" => Some(Value::Token(String::new())),
+        "language" => Some(Value::Language(String::new())),
+        "NMTOKEN" => Some(Value::NMTOKEN(String::new())),
+        "Name" => Some(Value::Name(String::new())),
+        "NCName" => Some(Value::NCName(String::new())),
+        "ID" => Some(Value::ID(String::new())),
+        "IDREF" => Some(Value::IDREF(String::new())),
+        "ENTITY" => Some(Value::ENTITY(String::new())),
+        "boolean" => Some(Value::Boolean(false)),
+        "base64Binary" => Some(Value::Base64Binary(vec![])),
+        "hexBinary" => Some(Value::HexBinary(vec![])),
+        "anyURI" => Some(Value::AnyURI(String::new())),
+        "NOTATION" => Some(Value::NOTATION),
+        "decimal" => Some(Value::Decimal(Decimal::ZERO)),
+        "float" => Some(Value::Float(0.0)),
+        "double" => Some(Value::Double(0.0)),
+        "integer" => Some(Value::Integer(0)),
+        "nonPositiveInteger" => NonPositiveInteger::try_from(0)
+            .ok()
+            .map(Value::NonPositiveInteger),
+        "negativeInteger" => NegativeInteger::try_from(-1)
+            .ok()
+            .map(Value::NegativeInteger),
+        "long" => Some(Value::Long(0)),
+        "int" => Some(Value::Int(0)),
+        "short" => Some(Value::Short(0)),
+        "byte" => Some(Value::Byte(0)),
+        "nonNegativeInteger" => NonNegativeInteger::try_from(0)
+            .ok()
+            .map(Value::NonNegativeInteger),
+        "unsignedLong" => Some(Value::UnsignedLong(0)),
+        "unsignedInt" => Some(Value::UnsignedInt(0)),
+        "unsignedShort" => Some(Value::UnsignedShort(0)),
+        "unsignedByte" => Some(Value::UnsignedByte(0)),
+        "positiveInteger" => PositiveInteger::try_from(1)
+            .ok()
+            .map(Value::PositiveInteger),
+        "duration" => Some(Value::Duration(Duration::default())),
+        "yearMonthDuration" => Some(Value::YearMonthDuration(Duration::default())),
+        "dayTimeDuration" => Some(Value::DayTimeDuration(Duration::default())),
+        "dateTime" => Some(Value::DateTime(NaiveDateTime::default(), None)),
+        "dateTimeStamp" => Some(Value::DateTimeStamp(
+            NaiveDateTime::default(),
+            FixedOffset::east_opt(0).unwrap(),
+        )),
+        "date" => Some(Value::Date(NaiveDate::default(), None)),
+        "gYearMonth" => Some(Value::GYearMonth(1972, 1, None)),
+        "gYear" => Some(Value::GYear(1972, None)),
+        "gMonthDay" => Some(Value::GMonthDay(1, 1, None)),
+        "gMonth" => Some(Value::GMonth(1, None)),
+        "gDay" => Some(Value::GDay(1, None)),
+        "time" => Some(Value::Time(NaiveTime::default(), None)),
+        "QName" => Some(Value::QName(QualifiedName::new(None, None, ""))),
+        _ => None,
+    }
+}
+
+/// The local part of the name of the XML Schema type of a value.
+fn atomic_type_name(v: &Value) -> &'static str {
+    match v {
+        Value::AnyAtomicType => "anyAtomicType",
+        Value::UntypedAtomic => "untypedAtomic",
+        Value::Numeric => "numeric",
+        Value::String(_) => "string",
+        Value::NormalizedString(_) => "normalizedString",
+        Value::Token(_) => "token",
+        Value::Language(_) => "language",
+        Value::NMTOKEN(_) => "NMTOKEN",
+        Value::Name(_) => "Name",
+        Value::NCName(_) => "NCName",
+        Value::ID(_) => "ID",
+        Value::IDREF(_) => "IDREF",
+        Value::ENTITY(_) => "ENTITY",
+        Value::Boolean(_) => "boolean",
+        Value::Base64Binary(_) => "base64Binary",
+        Value::HexBinary(_) => "hexBinary",
+        Value::AnyURI(_) => "anyURI",
+        Value::NOTATION => "NOTATION",
+        Value::Decimal(_) => "decimal",
+        Value::Float(_) => "float",
+        Value::Double(_) => "double",
+        Value::Integer(_) => "integer",
+        Value::NonPositiveInteger(_) => "nonPositiveInteger",
+        Value::NegativeInteger(_) => "negativeInteger",
+        Value::Long(_) => "long",
+        Value::Int(_) => "int",
+        Value::Short(_) => "short",
+        Value::Byte(_) => "byte",
+        Value::NonNegativeInteger(_) => "nonNegativeInteger",
+        Value::UnsignedLong(_) => "unsignedLong",
+        Value::UnsignedInt(_) => "unsignedInt",
+        Value::UnsignedShort(_) => "unsignedShort",
+        Value::UnsignedByte(_) => "unsignedByte",
+        Value::PositiveInteger(_) => "positiveInteger",
+        Value::Duration(_) => "duration",
+        Value::YearMonthDuration(_) => "yearMonthDuration",
+        Value::DayTimeDuration(_) => "dayTimeDuration",
+        Value::DateTime(..) => "dateTime",
+        Value::DateTimeStamp(..) => "dateTimeStamp",
+        Value::Date(..) => "date",
+        Value::GYearMonth(..) => "gYearMonth",
+        Value::GYear(..) => "gYear",
+        Value::GMonthDay(..) => "gMonthDay",
+        Value::GMonth(..) => "gMonth",
+        Value::GDay(..) => "gDay",
+        Value::Time(..) => "time",
+        Value::QName(_) | Value::RQName(_) => "QName",
+        Value::AnyType => "anyType",
+        Value::Untyped => "untyped",
+        Value::AnySimpleType => "anySimpleType",
+        Value::IDREFS(_) => "IDREFS",
+        Value::NMTOKENS => "NMTOKENS",
+        Value::ENTITIES => "ENTITIES",
+    }
+}
+
+// The type from which a built-in type is derived by restriction.
+// Types are identified by Value::value_type.
+fn base_type(t: &str) -> Option<&'static str> {
+    match t {
+        "Integer" => Some("Decimal"),
+        "NonPositiveInteger" | "Long" | "NonNegativeInteger" => Some("Integer"),
+        "NegativeInteger" => Some("NonPositiveInteger"),
+        "Int" => Some("Long"),
+        "Short" => Some("Int"),
+        "Byte" => Some("Short"),
+        "UnsignedLong" | "PositiveInteger" => Some("NonNegativeInteger"),
+        "UnsignedInt" => Some("UnsignedLong"),
+        "UnsignedShort" => Some("UnsignedInt"),
+        "UnsignedByte" => Some("UnsignedShort"),
+        "NormalizedString" => Some("String"),
+        "Token" => Some("NormalizedString"),
+        "Language" | "NMTOKEN" | "Name" => Some("Token"),
+        "NCName" => Some("Name"),
+        "ID" | "IDREF" | "ENTITY" => Some("NCName"),
+        "DateTimeStamp" => Some("DateTime"),
+        "YearMonthDuration" | "DayTimeDuration" => Some("Duration"),
+        _ => None,
+    }
+}
+
+/// Is the type of the value the same as, or derived from, the type of the target value?
+pub fn derives_from(v: &Value, target: &Value) -> bool {
+    match target {
+        Value::AnyAtomicType => true,
+        Value::Numeric => v.is_numeric(),
+        _ => {
+            let goal = target.value_type();
+            let mut t = Some(v.value_type());
+            while let Some(u) = t {
+                if u == goal {
+                    return true;
+                }
+                t = base_type(u)
+            }
+            false
+        }
+    }
+}
+
+fn invalid_value(v: &Value, target: &Value) -> Error {
+    Error::new_with_code(
+        ErrorKind::Unknown,
+        format!(
+            "\"{}\" is not a valid xs:{}",
+            lexical(v),
+            atomic_type_name(target)
+        ),
+        Some(QualifiedName::new(None, None, "FORG0001")),
+    )
+}
+
+// The lexical form of a value.
+fn lexical(v: &Value) -> String {
+    match v {
+        Value::Boolean(b) => b.to_string(),
+        Value::Double(d) => double_lexical(*d),
+        Value::Float(f) => double_lexical(*f as f64),
+        _ => v.to_string(),
+    }
+}
+
+fn double_lexical(d: f64) -> String {
+    if d.is_nan() {
+        String::from("NaN")
+    } else if d.is_infinite() {
+        String::from(if d > 0.0 { "INF" } else { "-INF" })
+    } else {
+        d.to_string()
+    }
+}
+
+// Parse the lexical form of a double or float.
+fn parse_double(s: &str) -> Option<f64> {
+    match s {
+        "INF" | "+INF" => Some(f64::INFINITY),
+        "-INF" => Some(f64::NEG_INFINITY),
+        "NaN" => Some(f64::NAN),
+        _ if s.contains(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c))) => None,
+        _ => s.parse::<f64>().ok(),
+    }
+}
+
+// Convert a value to an integer, for casting to one of the integer types.
+fn integer_value(v: &Value, target: &Value) -> Result<i64, Error> {
+    let not_finite = || {
+        Error::new_with_code(
+            ErrorKind::Unknown,
+            "cannot cast NaN or infinity to an integer",
+            Some(QualifiedName::new(None, None, "FOCA0002")),
+        )
+    };
+    match v {
+        Value::Boolean(b) => Ok(i64::from(*b)),
+        Value::Decimal(d) => d.trunc().to_i64().ok_or_else(|| invalid_value(v, target)),
+        Value::Double(_) | Value::Float(_) => {
+            let d = v.to_double();
+            if d.is_finite() {
+                Ok(d.trunc() as i64)
+            } else {
+                Err(not_finite())
+            }
+        }
+        _ if v.is_numeric() => v.to_int(),
+        _ => lexical(v)
+            .trim()
+            .parse::<i64>()
+            .map_err(|_| invalid_value(v, target)),
+    }
+}
+
+// Split the optional timezone from the lexical form of a date/time value.
+// The timezone is either "Z" or an offset of the form +HH:MM or -HH:MM.
+// The colon distinguishes an offset from the "-MM-DD" at the end of a date.
+pub(crate) fn split_timezone(t: &str) -> Option<(&str, Option<FixedOffset>)> {
+    if let Some(u) = t.strip_suffix('Z') {
+        Some((u, FixedOffset::east_opt(0)))
+    } else if t.len() > 6
+        && t.is_char_boundary(t.len() - 6)
+        && t[t.len() - 6..].starts_with(['+', '-'])
+        && t.get(t.len() - 3..t.len() - 2) == Some(":")
+    {
+        let (u, z) = t.split_at(t.len() - 6);
+        let sign = if z.starts_with('-') { -1 } else { 1 };
+        let h = z[1..3].parse::<i32>().ok()?;
+        let m = z[4..6].parse::<i32>().ok()?;
+        if h > 14 || m > 59 || (h == 14 && m > 0) {
+            return None;
+        }
+        Some((u, Some(FixedOffset::east_opt(sign * (h * 3600 + m * 60))?)))
+    } else {
+        Some((t, None))
+    }
+}
+
+// Collapse whitespace: leading and trailing whitespace is removed and other sequences of whitespace are replaced by a single space.
+fn collapse(s: &str) -> String {
+    s.split([' ', '\t', '\n', '\r'])
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn is_name(s: &str) -> bool {
+    let mut it = s.chars();
+    it.next().is_some_and(|c| is_namestartchar(&c)) && it.all(|c| is_namechar(&c))
+}
+
+fn is_ncname(s: &str) -> bool {
+    let mut it = s.chars();
+    it.next().is_some_and(|c| is_ncnamestartchar(&c)) && it.all(|c| is_ncnamechar(&c))
+}
+
+// language ::= [a-zA-Z]{1,8}(-[a-zA-Z0-9]{1,8})*
+fn is_language(s: &str) -> bool {
+    s.split('-').enumerate().all(|(i, p)| {
+        (1..=8).contains(&p.len())
+            && p.chars()
+                .all(|c| c.is_ascii_alphabetic() || (i > 0 && c.is_ascii_digit()))
+    })
+}
+
+// Parse an unsigned number with exactly the given number of digits.
+fn digits(s: &str, n: usize) -> Option<u32> {
+    if s.len() == n && s.bytes().all(|c| c.is_ascii_digit()) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+// Parse a year, which has at least four digits, with an optional leading minus sign.
+// Leading zeroes are only allowed for a four digit year.
+fn parse_year(s: &str) -> Option<i32> {
+    let u = s.strip_prefix('-').unwrap_or(s);
+    if u.len() < 4 || !u.bytes().all(|c| c.is_ascii_digit()) || (u.len() > 4 && u.starts_with('0'))
+    {
+        return None;
+    }
+    // XML Schema 1.1 allows year zero, which is 1 BCE, but not negative zero
+    let y: i32 = u.parse().ok()?;
+    match (y, s.starts_with('-')) {
+        (0, true) => None,
+        (_, true) => Some(-y),
+        _ => Some(y),
+    }
+}
+
+// Parse the lexical form of one of the Gregorian types, i.e. a partial date.
+// The target value determines which lexical form is expected.
+fn parse_gregorian(t: &str, target: &Value) -> Option<Value> {
+    let (u, tz) = split_timezone(t)?;
+    let valid_month = |m: u32| (1..=12).contains(&m);
+    // The maximum day in a month, in a leap year
+    let valid_day = |m: u32, d: u32| NaiveDate::from_ymd_opt(2000, m, d).is_some();
+    match target {
+        Value::GYearMonth(..) => {
+            let (y, m) = u.rsplit_once('-')?;
+            let (y, m) = (parse_year(y)?, digits(m, 2)?);
+            valid_month(m).then_some(Value::GYearMonth(y, m, tz))
+        }
+        Value::GYear(..) => parse_year(u).map(|y| Value::GYear(y, tz)),
+        Value::GMonthDay(..) => {
+            let (m, d) = u.strip_prefix("--")?.split_once('-')?;
+            let (m, d) = (digits(m, 2)?, digits(d, 2)?);
+            (valid_month(m) && valid_day(m, d)).then_some(Value::GMonthDay(m, d, tz))
+        }
+        Value::GMonth(..) => {
+            let m = digits(u.strip_prefix("--")?, 2)?;
+            valid_month(m).then_some(Value::GMonth(m, tz))
+        }
+        Value::GDay(..) => {
+            let d = digits(u.strip_prefix("---")?, 2)?;
+            (1..=31).contains(&d).then_some(Value::GDay(d, tz))
+        }
+        _ => None,
+    }
+}
+
+/// Cast a value to the type of the target value.
+pub fn cast_value(v: &Value, target: &Value) -> Result<Value, Error> {
+    // A dateTimeStamp is cast in the same way as a dateTime
+    let stamp;
+    let v = match v {
+        Value::DateTimeStamp(dt, tz) => {
+            stamp = Value::DateTime(*dt, Some(*tz));
+            &stamp
+        }
+        _ => v,
+    };
+    let s = lexical(v);
+    let t = s.trim();
+    match target {
+        Value::String(_) => Ok(Value::String(s)),
+        Value::NormalizedString(_) => {
+            NormalizedString::try_from(s.replace(['\t', '\n', '\r'], " ").as_str())
+                .map(Value::NormalizedString)
+                .map_err(|_| invalid_value(v, target))
+        }
+        Value::Token(_) => Ok(Value::Token(collapse(&s))),
+        Value::Language(_) if is_language(t) => Ok(Value::Language(t.to_string())),
+        Value::NMTOKEN(_) if !t.is_empty() && t.chars().all(|c| is_namechar(&c)) => {
+            Ok(Value::NMTOKEN(t.to_string()))
+        }
+        Value::Name(_) if is_name(t) => Ok(Value::Name(t.to_string())),
+        Value::NCName(_) if is_ncname(t) => Ok(Value::NCName(t.to_string())),
+        Value::ID(_) if is_ncname(t) => Ok(Value::ID(t.to_string())),
+        Value::IDREF(_) if is_ncname(t) => Ok(Value::IDREF(t.to_string())),
+        Value::ENTITY(_) if is_ncname(t) => Ok(Value::ENTITY(t.to_string())),
+        Value::Language(_)
+        | Value::NMTOKEN(_)
+        | Value::Name(_)
+        | Value::NCName(_)
+        | Value::ID(_)
+        | Value::IDREF(_)
+        | Value::ENTITY(_) => Err(invalid_value(v, target)),
+        Value::AnyURI(_) => Ok(Value::AnyURI(collapse(&s))),
+        Value::Base64Binary(_) => match v {
+            Value::Base64Binary(b) | Value::HexBinary(b) => Ok(Value::Base64Binary(b.clone())),
+            _ => base64_decode(t)
+                .map(Value::Base64Binary)
+                .ok_or_else(|| invalid_value(v, target)),
+        },
+        Value::HexBinary(_) => match v {
+            Value::Base64Binary(b) | Value::HexBinary(b) => Ok(Value::HexBinary(b.clone())),
+            _ => hex_decode(t)
+                .map(Value::HexBinary)
+                .ok_or_else(|| invalid_value(v, target)),
+        },
+        Value::GYearMonth(..)
+        | Value::GYear(..)
+        | Value::GMonthDay(..)
+        | Value::GMonth(..)
+        | Value::GDay(..) => {
+            // Extract the components of a date or dateTime
+            let (date, tz) = match v {
+                Value::Date(d, tz) => (Some(*d), *tz),
+                Value::DateTime(dt, tz) => (Some(dt.date()), *tz),
+                _ => (None, None),
+            };
+            match (date, target) {
+                (Some(d), Value::GYearMonth(..)) => Ok(Value::GYearMonth(d.year(), d.month(), tz)),
+                (Some(d), Value::GYear(..)) => Ok(Value::GYear(d.year(), tz)),
+                (Some(d), Value::GMonthDay(..)) => Ok(Value::GMonthDay(d.month(), d.day(), tz)),
+                (Some(d), Value::GMonth(..)) => Ok(Value::GMonth(d.month(), tz)),
+                (Some(d), Value::GDay(..)) => Ok(Value::GDay(d.day(), tz)),
+                _ => parse_gregorian(t, target).ok_or_else(|| invalid_value(v, target)),
+            }
+        }
+        Value::DateTimeStamp(..) => {
+            match cast_value(v, &Value::DateTime(NaiveDateTime::default(), None))? {
+                Value::DateTime(dt, Some(tz)) => Ok(Value::DateTimeStamp(dt, tz)),
+                _ => Err(invalid_value(v, target)),
+            }
+        }
+        Value::Boolean(_) => match v {
+            Value::Boolean(b) => Ok(Value::Boolean(*b)),
+            _ if v.is_numeric() => {
+                let d = v.to_double();
+                Ok(Value::Boolean(d != 0.0 && !d.is_nan()))
+            }
+            _ => match t {
+                "true" | "1" => Ok(Value::Boolean(true)),
+                "false" | "0" => Ok(Value::Boolean(false)),
+                _ => Err(invalid_value(v, target)),
+            },
+        },
+        Value::Double(_) | Value::Float(_) => {
+            let d = match v {
+                Value::Boolean(b) => f64::from(u8::from(*b)),
+                _ if v.is_numeric() => v.to_double(),
+                _ => parse_double(t).ok_or_else(|| invalid_value(v, target))?,
+            };
+            if let Value::Float(_) = target {
+                Ok(Value::Float(d as f32))
+            } else {
+                Ok(Value::Double(d))
+            }
+        }
+        Value::Decimal(_) => match v {
+            Value::Decimal(d) => Ok(Value::Decimal(*d)),
+            Value::Boolean(b) => Ok(Value::Decimal(Decimal::from(u8::from(*b)))),
+            Value::Double(_) | Value::Float(_) => Decimal::from_f64(v.to_double())
+                .map(Value::Decimal)
+                .ok_or_else(|| {
+                    Error::new_with_code(
+                        ErrorKind::Unknown,
+                        "cannot cast NaN or infinity to xs:decimal",
+                        Some(QualifiedName::new(None, None, "FOCA0002")),
+                    )
+                }),
+            _ if v.is_numeric() => Ok(Value::Decimal(Decimal::from(v.to_int()?))),
+            _ => Decimal::from_str(t)
+                .map(Value::Decimal)
+                .map_err(|_| invalid_value(v, target)),
+        },
+        Value::Integer(_) => Ok(Value::Integer(integer_value(v, target)?)),
+        Value::Long(_) => Ok(Value::Long(integer_value(v, target)?)),
+        Value::Int(_) => i32::try_from(integer_value(v, target)?)
+            .map(Value::Int)
+            .map_err(|_| invalid_value(v, target)),
+        Value::Short(_) => i16::try_from(integer_value(v, target)?)
+            .map(Value::Short)
+            .map_err(|_| invalid_value(v, target)),
+        Value::Byte(_) => i8::try_from(integer_value(v, target)?)
+            .map(Value::Byte)
+            .map_err(|_| invalid_value(v, target)),
+        Value::UnsignedLong(_) => u64::try_from(integer_value(v, target)?)
+            .map(Value::UnsignedLong)
+            .map_err(|_| invalid_value(v, target)),
+        Value::UnsignedInt(_) => u32::try_from(integer_value(v, target)?)
+            .map(Value::UnsignedInt)
+            .map_err(|_| invalid_value(v, target)),
+        Value::UnsignedShort(_) => u16::try_from(integer_value(v, target)?)
+            .map(Value::UnsignedShort)
+            .map_err(|_| invalid_value(v, target)),
+        Value::UnsignedByte(_) => u8::try_from(integer_value(v, target)?)
+            .map(Value::UnsignedByte)
+            .map_err(|_| invalid_value(v, target)),
+        Value::NonPositiveInteger(_) => NonPositiveInteger::try_from(integer_value(v, target)?)
+            .map(Value::NonPositiveInteger)
+            .map_err(|_| invalid_value(v, target)),
+        Value::NegativeInteger(_) => NegativeInteger::try_from(integer_value(v, target)?)
+            .map(Value::NegativeInteger)
+            .map_err(|_| invalid_value(v, target)),
+        Value::NonNegativeInteger(_) => NonNegativeInteger::try_from(integer_value(v, target)?)
+            .map(Value::NonNegativeInteger)
+            .map_err(|_| invalid_value(v, target)),
+        Value::PositiveInteger(_) => PositiveInteger::try_from(integer_value(v, target)?)
+            .map(Value::PositiveInteger)
+            .map_err(|_| invalid_value(v, target)),
+        Value::Date(..) => match v {
+            Value::Date(d, tz) => Ok(Value::Date(*d, *tz)),
+            Value::DateTime(dt, tz) => Ok(Value::Date(dt.date(), *tz)),
+            Value::DateTimeStamp(dt, tz) => Ok(Value::Date(dt.date(), Some(*tz))),
+            _ => split_timezone(t)
+                .and_then(|(u, tz)| {
+                    NaiveDate::parse_from_str(u, "%Y-%m-%d")
+                        .ok()
+                        .map(|d| Value::Date(d, tz))
+                })
+                .ok_or_else(|| invalid_value(v, target)),
+        },
+        Value::DateTime(..) => match v {
+            Value::DateTime(dt, tz) => Ok(Value::DateTime(*dt, *tz)),
+            Value::Date(d, tz) => Ok(Value::DateTime(d.and_time(NaiveTime::MIN), *tz)),
+            _ => split_timezone(t)
+                .and_then(|(u, tz)| {
+                    NaiveDateTime::parse_from_str(u, "%Y-%m-%dT%H:%M:%S%.f")
+                        .ok()
+                        .map(|dt| Value::DateTime(dt, tz))
+                })
+                .ok_or_else(|| invalid_value(v, target)),
+        },
+        Value::Time(..) => match v {
+            Value::Time(t, tz) => Ok(Value::Time(*t, *tz)),
+            Value::DateTime(dt, tz) => Ok(Value::Time(dt.time(), *tz)),
+            _ => split_timezone(t)
+                .and_then(|(u, tz)| {
+                    NaiveTime::parse_from_str(u, "%H:%M:%S%.f")
+                        .ok()
+                        .map(|nt| Value::Time(nt, tz))
+                })
+                .ok_or_else(|| invalid_value(v, target)),
+        },
+        Value::Duration(_) => match v {
+            Value::Duration(d) | Value::YearMonthDuration(d) | Value::DayTimeDuration(d) => {
+                Ok(Value::Duration(*d))
+            }
+            _ => Ok(Value::Duration(Duration::parse(t)?.0)),
+        },
+        Value::YearMonthDuration(_) => match v {
+            Value::Duration(d) | Value::YearMonthDuration(d) | Value::DayTimeDuration(d) => {
+                Ok(Value::YearMonthDuration(Duration::from_months(d.months())))
+            }
+            _ => match Duration::parse(t)? {
+                (d, _, false) => Ok(Value::YearMonthDuration(d)),
+                _ => Err(invalid_value(v, target)),
+            },
+        },
+        Value::DayTimeDuration(_) => match v {
+            Value::Duration(d) | Value::YearMonthDuration(d) | Value::DayTimeDuration(d) => {
+                Ok(Value::DayTimeDuration(Duration::from_seconds(d.seconds())))
+            }
+            _ => match Duration::parse(t)? {
+                (d, false, _) => Ok(Value::DayTimeDuration(d)),
+                _ => Err(invalid_value(v, target)),
+            },
+        },
+        Value::AnyAtomicType | Value::Numeric | Value::NOTATION => Err(Error::new_with_code(
+            ErrorKind::StaticData,
+            format!(
+                "cannot cast to abstract type xs:{}",
+                atomic_type_name(target)
+            ),
+            Some(QualifiedName::new(None, None, "XPST0080")),
+        )),
+        _ => Err(Error::new(
+            ErrorKind::NotImplemented,
+            format!(
+                "casting to xs:{} is not supported",
+                atomic_type_name(target)
+            ),
+        )),
+    }
+}
+
+// Evaluate the operand of a cast expression. The result is None if the operand is the empty sequence.
+fn cast_operand<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    empty: bool,
+) -> Result<Option<Rc<Value>>, Error> {
+    let seq = ctxt.dispatch(stctxt, s)?;
+    match seq.len() {
+        0 if empty => Ok(None),
+        1 => atomize_key(&seq[0]).map(Some),
+        _ => Err(Error::new_with_code(
+            ErrorKind::TypeError,
+            "the operand of a cast must be a single atomic value",
+            Some(QualifiedName::new(None, None, "XPTY0004")),
+        )),
+    }
+}
+
+/// Apply the function conversion rules, so that a sequence matches the given type.
+/// Nodes are atomized and their untyped values are cast to the expected atomic type.
+/// Numeric values are promoted, and xs:anyURI values are promoted to xs:string.
+/// If the converted sequence does not match the type then the error has the given code.
+pub(crate) fn function_conversion<N: Node>(
+    s: Sequence<N>,
+    t: &SequenceType,
+    code: &str,
+) -> Result<Sequence<N>, Error> {
+    conversion(s, t, code, false)
+}
+
+/// Apply the function conversion rules, treating strings as untyped values.
+/// This is used for values supplied by the application, which has no way to give a string a type,
+/// and for sequence constructors, where literal text is compiled to strings rather than text nodes.
+pub(crate) fn untyped_conversion<N: Node>(
+    s: Sequence<N>,
+    t: &SequenceType,
+    code: &str,
+) -> Result<Sequence<N>, Error> {
+    conversion(s, t, code, true)
+}
+
+fn conversion<N: Node>(
+    s: Sequence<N>,
+    t: &SequenceType,
+    code: &str,
+    untyped_strings: bool,
+) -> Result<Sequence<N>, Error> {
+    if t.matches(&s) {
+        return Ok(s);
+    }
+    let converted = match t {
+        SequenceType::Items(ItemType::Atomic(target), _) => s
+            .iter()
+            .map(|i| {
+                let (v, untyped) = match i {
+                    Item::Node(n) => (Value::from(n.to_string()), true),
+                    Item::Value(v) => (
+                        (**v).clone(),
+                        untyped_strings && matches!(**v, Value::String(_)),
+                    ),
+                    _ => return Ok(i.clone()),
+                };
+                if derives_from(&v, target) {
+                    Ok(Item::Value(Rc::new(v)))
+                } else if untyped
+                    || (v.is_numeric()
+                        && (matches!(target, Value::Double(_))
+                            || (matches!(target, Value::Float(_))
+                                && !matches!(v, Value::Double(_)))))
+                    || (matches!(v, Value::AnyURI(_)) && matches!(target, Value::String(_)))
+                {
+                    cast_value(&v, target).map(|c| Item::Value(Rc::new(c)))
+                } else {
+                    Ok(Item::Value(Rc::new(v)))
+                }
+            })
+            .collect::<Result<Sequence<N>, Error>>(),
+        _ => Ok(s),
+    };
+    match converted {
+        Ok(c) if t.matches(&c) => Ok(c),
+        _ => Err(Error::new_with_code(
+            ErrorKind::TypeError,
+            format!("value does not match the required type {}", t),
+            Some(QualifiedName::new(None, None, code)),
+        )),
+    }
+}
+
+/// XPath cast as expression.
+pub(crate) fn cast_as<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    t: &Value,
+    empty: bool,
+) -> Result<Sequence<N>, Error> {
+    match cast_operand(ctxt, stctxt, s, empty)? {
+        Some(v) => Ok(vec![Item::Value(Rc::new(cast_value(&v, t)?))]),
+        None => Ok(vec![]),
+    }
+}
+
+/// XPath castable as expression.
+pub(crate) fn castable_as<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    t: &Value,
+    empty: bool,
+) -> Result<Sequence<N>, Error> {
+    let b = match cast_operand(ctxt, stctxt, s, empty) {
+        Ok(Some(v)) => cast_value(&v, t).is_ok(),
+        Ok(None) => true,
+        Err(_) => false,
+    };
+    Ok(vec![Item::Value(Rc::new(Value::Boolean(b)))])
+}
+
+/// XPath instance of expression.
+pub(crate) fn instance_of<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    t: &SequenceType,
+) -> Result<Sequence<N>, Error> {
+    let seq = ctxt.dispatch(stctxt, s)?;
+    Ok(vec![Item::Value(Rc::new(Value::Boolean(t.matches(&seq))))])
+}
+
+/// XPath treat as expression.
+pub(crate) fn treat_as<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    t: &SequenceType,
+) -> Result<Sequence<N>, Error> {
+    let seq = ctxt.dispatch(stctxt, s)?;
+    if t.matches(&seq) {
+        Ok(seq)
+    } else {
+        Err(Error::new_with_code(
+            ErrorKind::TypeError,
+            format!("sequence does not match type {}", t),
+            Some(QualifiedName::new(None, None, "XPDY0050")),
+        ))
+    }
+}
+
+/// Convert the value of a declaration, such as an xsl:variable or an xsl:param, to its declared type.
+/// The error code is used if the value cannot be converted.
+/// If the value is given by a sequence constructor then strings are untyped, since they stand for text nodes.
+pub(crate) fn convert<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    s: &Transform<N>,
+    t: &SequenceType,
+    code: &str,
+    untyped: bool,
+) -> Result<Sequence<N>, Error> {
+    conversion(ctxt.dispatch(stctxt, s)?, t, code, untyped)
+}
+
+/// Quantifier for a quantified expression.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quantifier {
+    /// some ... satisfies
+    Some,
+    /// every ... satisfies
+    Every,
+}
+
+/// XPath quantified expression.
+/// Each variable binding is iterated in turn, so that later bindings may refer to earlier variables.
+pub(crate) fn quantified<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    q: &Quantifier,
+    v: &[(String, Transform<N>)],
+    t: &Transform<N>,
+) -> Result<Sequence<N>, Error> {
+    Ok(vec![Item::Value(Rc::new(Value::Boolean(quantified_aux(
+        ctxt, stctxt, q, v, t,
+    )?)))])
+}
+
+fn quantified_aux<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    q: &Quantifier,
+    v: &[(String, Transform<N>)],
+    t: &Transform<N>,
+) -> Result<bool, Error> {
+    match v.split_first() {
+        None => effective_boolean_value(&ctxt.dispatch(stctxt, t)?),
+        Some(((name, e), rest)) => {
+            for i in ctxt.dispatch(stctxt, e)? {
+                let mut newctxt = ctxt.clone();
+                newctxt.var_push(name.clone(), vec![i]);
+                let b = quantified_aux(&newctxt, stctxt, q, rest, t)?;
+                match (q, b) {
+                    (Quantifier::Some, true) => return Ok(true),
+                    (Quantifier::Every, false) => return Ok(false),
+                    _ => {}
+                }
+            }
+            Ok(*q == Quantifier::Every)
+        }
+    }
+}
@@ -1,11 +1,50 @@
 //! Support for variables.
 
-use crate::item::{Node, Sequence};
-use crate::transform::context::{Context, ContextBuilder, StaticContext};
+use crate::item::{Item, Node, Sequence};
+use crate::qname::QualifiedName;
+use crate::transform::context::{
+    hidden_component, ComponentKey, Context, ContextBuilder, StaticContext,
+};
+use crate::transform::types::{function_conversion, untyped_conversion, SequenceType};
 use crate::transform::Transform;
 use crate::xdmerror::{Error, ErrorKind};
+use std::collections::HashMap;
+use std::rc::Rc;
 use url::Url;
 
+/// A stylesheet parameter, i.e. a global variable whose value may be supplied by the application.
+/// The value of the global variable declaration is the default value of the parameter.
+#[derive(Clone, Debug)]
+pub(crate) struct GlobalParameter {
+    pub(crate) name: QualifiedName,
+    pub(crate) required: bool,
+    pub(crate) as_type: Option<SequenceType>,
+}
+
+/// The values of global variables.
+/// A global variable is evaluated when it is first referenced, and its value is then retained for the rest of the transformation.
+#[derive(Clone, Debug)]
+pub(crate) struct GlobalValues<N: Node> {
+    // The global context item
+    context: Option<Item<N>>,
+    // A variable that is being evaluated has no value yet
+    values: HashMap<ComponentKey<String>, Option<Sequence<N>>>,
+}
+
+impl<N: Node> GlobalValues<N> {
+    pub(crate) fn new() -> Self {
+        GlobalValues {
+            context: None,
+            values: HashMap::new(),
+        }
+    }
+    /// Discard the values from a previous transformation, and set the global context item.
+    pub(crate) fn reset(&mut self, context: Option<Item<N>>) {
+        self.context = context;
+        self.values.clear()
+    }
+}
+
 /// Declare a variable in a new scope and then evaluate the given transformation.
 /// Returns the result of the transformation.
 pub fn declare_variable<
@@ -25,7 +64,18 @@ pub fn declare_variable<
         .build()
         .dispatch(stctxt, f)
 }
-pub fn reference_variable<N: Node>(ctxt: &Context<N>, name: &String) -> Result<Sequence<N>, Error> {
+/// Find the value of a variable.
+/// If there is no local variable in scope with the given name then it is a reference to a global variable.
+pub fn reference_variable<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    name: &String,
+) -> Result<Sequence<N>, Error> {
     match ctxt.vars.get(name) {
         Some(u) => match u.last() {
             Some(t) => Ok(t.clone()),
@@ -34,9 +84,90 @@ pub fn reference_variable<N: Node>(ctxt: &Context<N>, name: &String) -> Result<S
                 format!("variable \"{}\" is no longer in scope", name),
             )),
         },
-        None => Err(Error::new(
-            ErrorKind::Unknown,
-            format!("unknown variable \"{}\"", name),
-        )),
+        None => match ctxt.variable_key(name) {
+            Some(key) => match ctxt.globals.get(&key) {
+                Some((t, p)) => global_variable(ctxt, stctxt, &key, t, p),
+                None => Err(Error::new(
+                    ErrorKind::Unknown,
+                    format!("unknown variable \"{}\"", name),
+                )),
+            },
+            None => Err(hidden_component("variable", name, "XTSE3050")),
+        },
+    }
+}
+
+// Evaluate a global variable, unless it already has a value.
+// The variable is evaluated with the global context item and no local variables in scope.
+fn global_variable<
+    N: Node,
+    F: FnMut(&str) -> Result<(), Error>,
+    G: FnMut(&str) -> Result<N, Error>,
+    H: FnMut(&Url) -> Result<String, Error>,
+>(
+    ctxt: &Context<N>,
+    stctxt: &mut StaticContext<N, F, G, H>,
+    key: &ComponentKey<String>,
+    t: &Transform<N>,
+    package: &Option<Rc<str>>,
+) -> Result<Sequence<N>, Error> {
+    let name = match key {
+        ComponentKey::Visible(n) | ComponentKey::Private(_, n) => n,
+    };
+    let context = {
+        let mut gv = ctxt.global_values.borrow_mut();
+        match gv.values.get(key) {
+            Some(Some(v)) => return Ok(v.clone()),
+            Some(None) => {
+                return Err(Error::new_with_code(
+                    ErrorKind::DynamicAbsent,
+                    format!("circular definition of global variable \"{}\"", name),
+                    Some(QualifiedName::new(None, None, "XTDE0640")),
+                ))
+            }
+            None => {
+                gv.values.insert(key.clone(), None);
+            }
+        }
+        gv.context.clone()
+    };
+    let mut gctxt = ContextBuilder::from(ctxt)
+        .variables(HashMap::new())
+        .previous_context(None)
+        .package(package.clone());
+    if let Some(i) = context {
+        gctxt = gctxt.context(vec![i]).index(0)
     }
+    let result = match ctxt.global_params.get(key) {
+        Some(p) => match (ctxt.params.get(&p.name), &p.as_type) {
+            (Some(v), Some(st)) => untyped_conversion(v.clone(), st, "XTTE0590"),
+            (Some(v), None) => Ok(v.clone()),
+            (None, _) if p.required => Err(missing_parameter(&p.name)),
+            (None, st) => gctxt.build().dispatch(stctxt, t).and_then(|v| {
+                st.as_ref()
+                    .map_or(Ok(v.clone()), |st| function_conversion(v, st, "XTTE0590"))
+            }),
+        },
+        None => gctxt.build().dispatch(stctxt, t),
+    };
+    let mut gv = ctxt.global_values.borrow_mut();
+    match result {
+        Ok(v) => {
+            gv.values.insert(key.clone(), Some(v.clone()));
+            Ok(v)
+        }
+        Err(e) => {
+            gv.values.remove(key);
+            Err(e)
+        }
+    }
+}
+
+/// The error for a required stylesheet parameter that has not been supplied.
+pub(crate) fn missing_parameter(name: &QualifiedName) -> Error {
+    Error::new_with_code(
+        ErrorKind::DynamicAbsent,
+        format!("no value supplied for required parameter \"{}\"", name),
+        Some(QualifiedName::new(None, None, "XTDE0050")),
+    )
 }
@@ -44,7 +44,7 @@ assert_eq!(doc.to_xml(), "<Top-Level>content of the element</Top-Level>")
 */
 
 use crate::item::{Node as ItemNode, NodeType};
-use crate::output::OutputDefinition;
+use crate::output::{OutputDefinition, OutputMethod};
 use crate::qname;
 use crate::qname::QualifiedName;
 use crate::trees::smite;
@@ -76,7 +76,7 @@ enum NodeInner {
         RefCell<Vec<RNode>>, // children
         Rc<RefCell<BTreeMap<Option<Rc<Value>>, RNode>>>, // namespace declarations
     ),
-    Text(RefCell<Weak<Node>>, Rc<Value>),
+    Text(RefCell<Weak<Node>>, Rc<Value>, bool), // true if output escaping is disabled
     Attribute(RefCell<Weak<Node>>, Rc<QualifiedName>, Rc<Value>),
     Comment(RefCell<Weak<Node>>, Rc<Value>),
     ProcessingInstruction(RefCell<Weak<Node>>, Rc<QualifiedName>, Rc<Value>),
@@ -181,7 +181,7 @@ impl PartialEq for Node {
                     false
                 }
             }
-            (NodeInner::Text(_, v), NodeInner::Text(_, u)) => v == u,
+            (NodeInner::Text(_, v, _), NodeInner::Text(_, u, _)) => v == u,
             (NodeInner::Attribute(_, name, v), NodeInner::Attribute(_, o_name, o_v)) => {
                 if name == o_name {
                     v == o_v
@@ -210,7 +210,7 @@ impl ItemNode for RNode {
             NodeInner::Document(_, _, _) => NodeType::Document,
             NodeInner::Element(_, _, _, _, _) => NodeType::Element,
             NodeInner::Attribute(_, _, _) => NodeType::Attribute,
-            NodeInner::Text(_, _) => NodeType::Text,
+            NodeInner::Text(..) => NodeType::Text,
             NodeInner::Comment(_, _) => NodeType::Comment,
             NodeInner::ProcessingInstruction(_, _, _) => NodeType::ProcessingInstruction,
             NodeInner::Namespace(_, _, _) => NodeType::Namespace,
@@ -230,7 +230,7 @@ impl ItemNode for RNode {
     }
     fn value(&self) -> Rc<Value> {
         match &self.0 {
-            NodeInner::Text(_, v)
+            NodeInner::Text(_, v, _)
             | NodeInner::Comment(_, v)
             | NodeInner::ProcessingInstruction(_, _, v)
             | NodeInner::Attribute(_, _, v) => v.clone(),
@@ -252,17 +252,33 @@ impl ItemNode for RNode {
                 })
             }
             NodeInner::Attribute(_, _, v)
-            | NodeInner::Text(_, v)
+            | NodeInner::Text(_, v, _)
             | NodeInner::Comment(_, v)
             | NodeInner::ProcessingInstruction(_, _, v) => v.to_string(),
             NodeInner::Namespace(_, _, uri) => uri.to_string(),
         }
     }
     fn to_xml(&self) -> String {
-        to_xml_int(self, &OutputDefinition::new(), 0)
+        let mut od = OutputDefinition::new();
+        od.set_omit_xml_declaration(true);
+        to_xml_int(self, &od, 0)
     }
     fn to_xml_with_options(&self, od: &OutputDefinition) -> std::string::String {
-        to_xml_int(self, od, 0)
+        match od.get_method() {
+            OutputMethod::Text => od.map_characters(self.to_string().as_str()),
+            _ => {
+                let mut result = String::new();
+                if od.get_byte_order_mark() {
+                    result.push('\u{feff}')
+                }
+                if od.get_method() == OutputMethod::Html {
+                    result.push_str(to_html_int(self, od, 0).as_str())
+                } else {
+                    result.push_str(to_xml_int(self, od, 0).as_str())
+                }
+                result
+            }
+        }
     }
     fn is_same(&self, other: &Self) -> bool {
         Rc::ptr_eq(self, other)
@@ -361,10 +377,23 @@ impl ItemNode for RNode {
         let child = Rc::new(Node(NodeInner::Text(
             RefCell::new(Rc::downgrade(&self.owner_document())),
             v,
+            false,
         )));
         unattached(self, child.clone());
         Ok(child)
     }
+    fn new_unescaped_text(&self, v: Rc<Value>) -> Result<Self, Error> {
+        let child = Rc::new(Node(NodeInner::Text(
+            RefCell::new(Rc::downgrade(&self.owner_document())),
+            v,
+            true,
+        )));
+        unattached(self, child.clone());
+        Ok(child)
+    }
+    fn disables_output_escaping(&self) -> bool {
+        matches!(self.0, NodeInner::Text(_, _, true))
+    }
     fn new_attribute(&self, qn: Rc<QualifiedName>, v: Rc<Value>) -> Result<Self, Error> {
         //TODO if the attribute is xml:id then type needs to be set as ID, regardless of DTD.
         let att = Rc::new(Node(NodeInner::Attribute(
@@ -491,7 +520,7 @@ impl ItemNode for RNode {
                 }
             }
             NodeInner::Element(parent, _, _, _, _)
-            | NodeInner::Text(parent, _)
+            | NodeInner::Text(parent, _, _)
             | NodeInner::Comment(parent, _)
             | NodeInner::ProcessingInstruction(parent, _, _) => {
                 // Remove this node from the old parent's child list
@@ -607,7 +636,7 @@ impl ItemNode for RNode {
         // Now insert into parent's child list
         match &self.0 {
             NodeInner::Element(p, _, _, _, _)
-            | NodeInner::Text(p, _)
+            | NodeInner::Text(p, _, _)
             | NodeInner::Comment(p, _)
             | NodeInner::ProcessingInstruction(p, _, _) => {
                 let parent = Weak::upgrade(&p.borrow()).unwrap();
@@ -660,8 +689,8 @@ impl ItemNode for RNode {
                 qn.clone(),
                 v.clone(),
             )))),
-            NodeInner::Text(p, v) => {
-                let new = Rc::new(Node(NodeInner::Text(p.clone(), v.clone())));
+            NodeInner::Text(p, v, d) => {
+                let new = Rc::new(Node(NodeInner::Text(p.clone(), v.clone(), *d)));
                 unattached(&self.parent().unwrap(), new.clone());
                 Ok(new)
             }
@@ -731,7 +760,7 @@ impl ItemNode for RNode {
                 ErrorKind::TypeError,
                 "invalid node type".to_string(),
             )),
-            NodeInner::Text(_, v) => {
+            NodeInner::Text(_, v, _) => {
                 let d = self.owner_document();
                 let mut w = v.clone();
                 if let Value::String(s) = (*v.clone()).clone() {
@@ -854,7 +883,7 @@ impl Debug for Node {
             NodeInner::Attribute(_, qn, _) => {
                 write!(f, "attribute-type node \"{}\"", qn)
             }
-            NodeInner::Text(_, v) => write!(f, "text-type node \"{}\"", v),
+            NodeInner::Text(_, v, _) => write!(f, "text-type node \"{}\"", v),
             NodeInner::Comment(_, v) => write!(f, "comment-type node \"{}\"", v),
             NodeInner::ProcessingInstruction(_, qn, _) => {
                 write!(f, "PI-type node \"{}\"", qn)
@@ -910,7 +939,7 @@ fn make_parent(n: RNode, b: RNode) {
     match &n.0 {
         NodeInner::Element(p, _, _, _, _)
         | NodeInner::Attribute(p, _, _)
-        | NodeInner::Text(p, _)
+        | NodeInner::Text(p, _, _)
         | NodeInner::Comment(p, _)
         | NodeInner::Namespace(p, _, _)
         | NodeInner::ProcessingInstruction(p, _, _) => *p.borrow_mut() = Rc::downgrade(&b),
@@ -923,7 +952,7 @@ fn detach(n: RNode) {
     match &n.0 {
         NodeInner::Element(p, _, _, _, _)
         | NodeInner::Attribute(p, _, _)
-        | NodeInner::Text(p, _)
+        | NodeInner::Text(p, _, _)
         | NodeInner::Comment(p, _)
         | NodeInner::Namespace(p, _, _)
         | NodeInner::ProcessingInstruction(p, _, _) => {
@@ -982,7 +1011,7 @@ fn doc_order(n: &RNode) -> Vec<usize> {
             a
         }
         NodeInner::Element(p, _, _, _, _)
-        | NodeInner::Text(p, _)
+        | NodeInner::Text(p, _, _)
         | NodeInner::Comment(p, _)
         | NodeInner::ProcessingInstruction(p, _, _) => match Weak::upgrade(&p.borrow()) {
             Some(q) => {
@@ -1025,10 +1054,54 @@ fn find_index(parent: &RNode, child: &RNode) -> Result<usize, Error> {
 // "indent" is the current level of indentation.
 fn to_xml_int(node: &RNode, od: &OutputDefinition, indent: usize) -> String {
     match &node.0 {
-        NodeInner::Document(_, _, _) => node.child_iter().fold(String::new(), |mut result, c| {
-            result.push_str(to_xml_int(&c, od, indent + 2).as_str());
+        NodeInner::Document(_, _, _) => {
+            let mut result = String::new();
+            if !od.get_omit_xml_declaration()
+                && matches!(od.get_method(), OutputMethod::Xml | OutputMethod::Xhtml)
+            {
+                result.push_str("<?xml version=\"1.0\" encoding=\"");
+                result.push_str(od.get_encoding().as_str());
+                result.push('"');
+                if let Some(sa) = od.get_standalone() {
+                    result.push_str(if sa {
+                        " standalone=\"yes\""
+                    } else {
+                        " standalone=\"no\""
+                    })
+                }
+                result.push_str("?>");
+                if od.get_indent() {
+                    result.push('\n')
+                }
+            }
+            node.child_iter().for_each(|c| {
+                if c.node_type() == NodeType::Element {
+                    if let Some(sys) = od.get_doctype_system() {
+                        result.push_str("<!DOCTYPE ");
+                        result.push_str(c.name().to_string().as_str());
+                        if let Some(public) = od.get_doctype_public() {
+                            result.push_str(format!(" PUBLIC \"{}\"", public).as_str())
+                        } else {
+                            result.push_str(" SYSTEM")
+                        }
+                        result.push_str(format!(" \"{}\">", sys).as_str());
+                        if od.get_indent() {
+                            result.push('\n')
+                        }
+                    } else if od.get_method() == OutputMethod::Xhtml
+                        && od.get_html_version().is_some_and(|v| v >= 5.0)
+                        && c.name().localname_to_string() == "html"
+                    {
+                        result.push_str("<!DOCTYPE html>");
+                        if od.get_indent() {
+                            result.push('\n')
+                        }
+                    }
+                }
+                result.push_str(to_xml_int(&c, od, indent + 2).as_str())
+            });
             result
-        }),
+        }
         NodeInner::Element(_, qn, _, _, ns) => {
             let mut result = String::from("<");
             result.push_str(qn.to_string().as_str());
@@ -1045,14 +1118,41 @@ fn to_xml_int(node: &RNode, od: &OutputDefinition, indent: usize) -> String {
             // Attributes
             node.attribute_iter().for_each(|a| {
                 result.push_str(
-                    format!(" {}='{}'", a.name().to_string().as_str(), a.value()).as_str(),
+                    format!(
+                        " {}='{}'",
+                        a.name().to_string().as_str(),
+                        escape(a.value().to_string().as_str(), od, true)
+                    )
+                    .as_str(),
                 )
             });
+
+            // XHTML void elements use the minimised form, all other elements have an end tag.
+            if od.get_method() == OutputMethod::Xhtml
+                && is_html_element(qn)
+                && HTML_VOID_ELEMENTS.contains(&qn.localname_to_string().as_str())
+                && node.child_iter().next().is_none()
+            {
+                result.push_str(" />");
+                return result;
+            }
             result.push('>');
 
             // Content of the element.
             // If the indent option is enabled, then if no child is a text node then add spacing.
-            let do_indent: bool = od
+            // Indentation is not performed for the descendants of an element listed in suppress-indentation.
+            let suppress = od.get_indent() && od.get_suppress_indentation().contains(qn);
+            let unindented;
+            let child_od = if suppress {
+                let mut o = od.clone();
+                o.set_indent(false);
+                unindented = o;
+                &unindented
+            } else {
+                od
+            };
+            let cdata = od.get_cdata_section_elements().contains(qn);
+            let do_indent: bool = child_od
                 .get_indent()
                 .then(|| {
                     node.child_iter().fold(true, |mut acc, c| {
@@ -1069,7 +1169,13 @@ fn to_xml_int(node: &RNode, od: &OutputDefinition, indent: usize) -> String {
                     result.push('\n');
                     (0..indent).for_each(|_| result.push(' '))
                 }
-                result.push_str(to_xml_int(&c, od, indent + 2).as_str())
+                if cdata && c.node_type() == NodeType::Text && !c.disables_output_escaping() {
+                    result.push_str("<![CDATA[");
+                    result.push_str(c.to_string().replace("]]>", "]]]]><![CDATA[>").as_str());
+                    result.push_str("]]>")
+                } else {
+                    result.push_str(to_xml_int(&c, child_od, indent + 2).as_str())
+                }
             });
             if do_indent && indent > 1 {
                 result.push('\n');
@@ -1080,7 +1186,8 @@ fn to_xml_int(node: &RNode, od: &OutputDefinition, indent: usize) -> String {
             result.push('>');
             result
         }
-        NodeInner::Text(_, v) => v.to_string(),
+        NodeInner::Text(_, v, true) => v.to_string(),
+        NodeInner::Text(_, v, false) => escape(v.to_string().as_str(), od, false),
         NodeInner::Comment(_, v) => {
             let mut result = String::from("<!--");
             result.push_str(v.to_string().as_str());
@@ -1099,6 +1206,274 @@ fn to_xml_int(node: &RNode, od: &OutputDefinition, indent: usize) -> String {
     }
 }
 
+// Elements that have no content, and therefore no end tag, in HTML.
+const HTML_VOID_ELEMENTS: [&str; 16] = [
+    "area", "base", "basefont", "br", "col", "embed", "frame", "hr", "img", "input", "link",
+    "meta", "param", "source", "track", "wbr",
+];
+// Attributes whose presence alone gives their value in HTML.
+const HTML_BOOLEAN_ATTRIBUTES: [&str; 31] = [
+    "allowfullscreen",
+    "async",
+    "autofocus",
+    "autoplay",
+    "checked",
+    "compact",
+    "controls",
+    "declare",
+    "default",
+    "defer",
+    "disabled",
+    "formnovalidate",
+    "hidden",
+    "inert",
+    "ismap",
+    "itemscope",
+    "loop",
+    "multiple",
+    "muted",
+    "nohref",
+    "nomodule",
+    "noresize",
+    "noshade",
+    "novalidate",
+    "nowrap",
+    "open",
+    "playsinline",
+    "readonly",
+    "required",
+    "reversed",
+    "selected",
+];
+// Elements whose content is not escaped in HTML.
+const HTML_RAW_TEXT_ELEMENTS: [&str; 2] = ["script", "style"];
+// Attributes whose value is a URI.
+const HTML_URI_ATTRIBUTES: [&str; 13] = [
+    "action",
+    "background",
+    "cite",
+    "classid",
+    "codebase",
+    "data",
+    "formaction",
+    "href",
+    "longdesc",
+    "poster",
+    "profile",
+    "src",
+    "usemap",
+];
+
+// Escape the markup characters of text, or of an attribute value, after applying the character map.
+// Characters that are mapped are output as their replacement string, which is not escaped.
+fn escape(s: &str, od: &OutputDefinition, attribute: bool) -> String {
+    let cm = od.get_character_map();
+    s.chars().fold(String::new(), |mut acc, c| {
+        match (cm.get(&c), c) {
+            (Some(m), _) => acc.push_str(m),
+            (None, '&') => acc.push_str("&amp;"),
+            (None, '<') => acc.push_str("&lt;"),
+            (None, '>') if !attribute => acc.push_str("&gt;"),
+            (None, '\'') if attribute => acc.push_str("&apos;"),
+            (None, c) => acc.push(c),
+        }
+        acc
+    })
+}
+
+// Escape an attribute value for the HTML output method. Unlike XML, "<" is not escaped.
+fn escape_html_attribute(s: &str, od: &OutputDefinition) -> String {
+    let cm = od.get_character_map();
+    s.chars().fold(String::new(), |mut acc, c| {
+        match (cm.get(&c), c) {
+            (Some(m), _) => acc.push_str(m),
+            (None, '&') => acc.push_str("&amp;"),
+            (None, '\'') => acc.push_str("&apos;"),
+            (None, c) => acc.push(c),
+        }
+        acc
+    })
+}
+
+// Is this the name of an HTML element, i.e. is it in no namespace or the XHTML namespace?
+fn is_html_element(qn: &QualifiedName) -> bool {
+    qn.namespace_uri_to_string()
+        .map_or(true, |ns| ns == "http://www.w3.org/1999/xhtml")
+}
+
+// Escape the non-ASCII characters of a URI, as UTF-8 octets.
+fn escape_uri_attribute(v: &str) -> String {
+    v.chars().fold(String::new(), |mut acc, c| {
+        if c.is_ascii() {
+            acc.push(c)
+        } else {
+            let mut b = [0; 4];
+            c.encode_utf8(&mut b)
+                .bytes()
+                .for_each(|o| acc.push_str(format!("%{:02X}", o).as_str()))
+        }
+        acc
+    })
+}
+
+// This handles the HTML serialisation of the document.
+// The content of raw text elements is not escaped.
+// "indent" is the current level of indentation.
+fn to_html_int(node: &RNode, od: &OutputDefinition, indent: usize) -> String {
+    let html5 = od.get_html_version().map_or(true, |v| v >= 5.0);
+    match &node.0 {
+        NodeInner::Document(_, _, _) => {
+            let mut result = String::new();
+            let mut doctype = true;
+            node.child_iter().for_each(|c| {
+                if doctype && c.node_type() == NodeType::Element {
+                    doctype = false;
+                    match (od.get_doctype_public(), od.get_doctype_system()) {
+                        (None, None) => {
+                            if html5 && c.name().localname_to_string().eq_ignore_ascii_case("html")
+                            {
+                                result.push_str("<!DOCTYPE html>")
+                            }
+                        }
+                        (Some(public), None) => result
+                            .push_str(format!("<!DOCTYPE html PUBLIC \"{}\">", public).as_str()),
+                        (None, Some(sys)) => {
+                            result.push_str(format!("<!DOCTYPE html SYSTEM \"{}\">", sys).as_str())
+                        }
+                        (Some(public), Some(sys)) => result.push_str(
+                            format!("<!DOCTYPE html PUBLIC \"{}\" \"{}\">", public, sys).as_str(),
+                        ),
+                    }
+                    if od.get_indent() {
+                        result.push('\n')
+                    }
+                }
+                result.push_str(to_html_int(&c, od, indent + 2).as_str())
+            });
+            result
+        }
+        NodeInner::Element(_, qn, _, _, ns) => {
+            let html = is_html_element(qn);
+            let local = qn.localname_to_string().to_ascii_lowercase();
+            let mut result = String::from("<");
+            result.push_str(qn.to_string().as_str());
+
+            // Namespace declarations
+            ns.borrow().iter().for_each(|(pre, nsuri)| {
+                let pre_str = pre.as_ref().map_or_else(
+                    || format!(" xmlns='{}'", nsuri.to_string()),
+                    |p| format!(" xmlns:{}='{}'", p, nsuri.to_string()),
+                );
+                result.push_str(pre_str.as_str());
+            });
+
+            // Attributes.
+            // Boolean attributes whose value is the same as their name are minimised.
+            node.attribute_iter().for_each(|a| {
+                let name = a.name().to_string();
+                let value = a.value().to_string();
+                if html
+                    && a.name().namespace_uri().is_none()
+                    && HTML_BOOLEAN_ATTRIBUTES.contains(&name.to_ascii_lowercase().as_str())
+                    && value.eq_ignore_ascii_case(&name)
+                {
+                    result.push(' ');
+                    result.push_str(name.as_str())
+                } else if html
+                    && od.get_escape_uri_attributes()
+                    && HTML_URI_ATTRIBUTES.contains(&name.to_ascii_lowercase().as_str())
+                {
+                    result.push_str(
+                        format!(
+                            " {}='{}'",
+                            name,
+                            escape_uri_attribute(
+                                escape_html_attribute(value.as_str(), od).as_str()
+                            )
+                        )
+                        .as_str(),
+                    )
+                } else {
+                    result.push_str(
+                        format!(" {}='{}'", name, escape_html_attribute(value.as_str(), od))
+                            .as_str(),
+                    )
+                }
+            });
+            result.push('>');
+
+            // A void element has no end tag. HTML cannot represent any content it has, so that is not output.
+            if html && HTML_VOID_ELEMENTS.contains(&local.as_str()) {
+                return result;
+            }
+
+            // Content of the element.
+            // If the indent option is enabled, then if no child is a text node then add spacing.
+            let do_indent: bool = od.get_indent()
+                && !od.get_suppress_indentation().contains(qn)
+                && node.child_iter().all(|c| c.node_type() != NodeType::Text);
+            let raw = html && HTML_RAW_TEXT_ELEMENTS.contains(&local.as_str());
+
+            // The meta element giving the content type is the first child of the head element.
+            // Any existing meta element giving the content type is replaced.
+            let meta = html && local == "head" && od.get_include_content_type();
+            if meta {
+                if do_indent {
+                    result.push('\n');
+                    (0..indent).for_each(|_| result.push(' '))
+                }
+                if html5 {
+                    result.push_str(format!("<meta charset='{}'>", od.get_encoding()).as_str())
+                } else {
+                    result.push_str(
+                        format!(
+                            "<meta http-equiv='Content-Type' content='text/html; charset={}'>",
+                            od.get_encoding()
+                        )
+                        .as_str(),
+                    )
+                }
+            }
+
+            node.child_iter()
+                .filter(|c| {
+                    !(meta
+                        && c.node_type() == NodeType::Element
+                        && c.name().localname_to_string().eq_ignore_ascii_case("meta")
+                        && c.attribute_iter().any(|a| {
+                            let n = a.name().localname_to_string();
+                            n.eq_ignore_ascii_case("charset")
+                                || (n.eq_ignore_ascii_case("http-equiv")
+                                    && a.to_string().eq_ignore_ascii_case("content-type"))
+                        }))
+                })
+                .for_each(|c| {
+                    if do_indent {
+                        result.push('\n');
+                        (0..indent).for_each(|_| result.push(' '))
+                    }
+                    if raw && c.node_type() == NodeType::Text {
+                        result.push_str(c.to_string().as_str())
+                    } else {
+                        result.push_str(to_html_int(&c, od, indent + 2).as_str())
+                    }
+                });
+            if do_indent && indent > 1 {
+                result.push('\n');
+                (0..(indent - 2)).for_each(|_| result.push(' '))
+            }
+            result.push_str("</");
+            result.push_str(qn.to_string().as_str());
+            result.push('>');
+            result
+        }
+        NodeInner::ProcessingInstruction(_, qn, v) => {
+            format!("<?{} {}>", qn, v)
+        }
+        _ => to_xml_int(node, od, indent),
+    }
+}
+
 pub struct Children {
     v: Vec<RNode>,
     i: usize,
@@ -1146,7 +1521,7 @@ impl Iterator for Ancestors {
             NodeInner::Document(_, _, _) => None,
             NodeInner::Element(p, _, _, _, _)
             | NodeInner::Attribute(p, _, _)
-            | NodeInner::Text(p, _)
+            | NodeInner::Text(p, _, _)
             | NodeInner::Comment(p, _)
             | NodeInner::ProcessingInstruction(p, _, _)
             | NodeInner::Namespace(p, _, _) => Weak::upgrade(&p.borrow()),
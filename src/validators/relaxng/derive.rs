@@ -690,7 +690,7 @@ fn data_type_allows(dt: RNode, _params: Vec<Param>, _s: String) -> bool {
 fn datatype_equal((_d, s): (Rc<Value>, Rc<Value>), s1: String, s2: String) -> bool {
     match s.as_ref() {
         Value::String(_) => s1 == s2,
-        Value::Token => normalize_whitespace(s1) == normalize_whitespace(s2),
+        Value::Token(_) => normalize_whitespace(s1) == normalize_whitespace(s2),
         _ => false,
     }
     /*
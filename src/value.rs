@@ -4,7 +4,7 @@
 
 use crate::qname::QualifiedName;
 use crate::xdmerror::{Error, ErrorKind};
-use chrono::{DateTime, Local, NaiveDate};
+use chrono::{FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
 use core::fmt;
 use core::hash::{Hash, Hasher};
 use rust_decimal::prelude::ToPrimitive;
@@ -96,8 +96,11 @@ pub enum Value {
     AnyAtomicType,
     /// untyped atomic value
     UntypedAtomic,
-    Duration,
-    Time(DateTime<Local>), // Ignore the date part. Perhaps use Instant instead?
+    Duration(Duration),
+    YearMonthDuration(Duration),
+    DayTimeDuration(Duration),
+    /// A time of day, with an optional timezone.
+    Time(NaiveTime, Option<FixedOffset>),
     Decimal(Decimal),
     Float(f32),
     Double(f64),
@@ -114,41 +117,52 @@ pub enum Value {
     UnsignedShort(u16),
     UnsignedByte(u8),
     PositiveInteger(PositiveInteger),
-    DateTime(DateTime<Local>),
-    DateTimeStamp,
-    Date(NaiveDate),
-    // gYearMonth
-    // gYear
-    // gMonthDay
-    // gMonth
-    // gDay
+    /// A date and time, with an optional timezone.
+    DateTime(NaiveDateTime, Option<FixedOffset>),
+    /// A date and time, with a required timezone.
+    DateTimeStamp(NaiveDateTime, FixedOffset),
+    /// A date, with an optional timezone.
+    Date(NaiveDate, Option<FixedOffset>),
+    /// A year and month, with an optional timezone.
+    GYearMonth(i32, u32, Option<FixedOffset>),
+    /// A year, with an optional timezone.
+    GYear(i32, Option<FixedOffset>),
+    /// A month and day, with an optional timezone.
+    GMonthDay(u32, u32, Option<FixedOffset>),
+    /// A month, with an optional timezone.
+    GMonth(u32, Option<FixedOffset>),
+    /// A day of the month, with an optional timezone.
+    GDay(u32, Option<FixedOffset>),
     String(String),
     NormalizedString(NormalizedString),
     /// Like normalizedString, but without leading, trailing and consecutive whitespace
-    Token,
+    Token(String),
     /// language identifiers [a-zA-Z]{1,8}(-[a-zA-Z0-9]{1,8})*
-    Language,
+    Language(String),
     /// NameChar+
-    NMTOKEN,
+    NMTOKEN(String),
     /// NameStartChar NameChar+
-    Name,
+    Name(String),
     /// (Letter | '_') NCNameChar+ (i.e. a Name without the colon)
-    NCName,
+    NCName(String),
     /// Same format as NCName
     ID(String),
     /// Same format as NCName
     IDREF(String),
     /// Same format as NCName
-    ENTITY,
+    ENTITY(String),
     Boolean(bool),
-    //base64binary,
-    //hexBinary,
-    //anyURI,
+    /// Binary data, represented in base64 encoding
+    Base64Binary(Vec<u8>),
+    /// Binary data, represented in hexadecimal encoding
+    HexBinary(Vec<u8>),
+    AnyURI(String),
     /// Qualified Name
     QName(QualifiedName),
     /// Rc-shared Qualified Name
     RQName(Rc<QualifiedName>),
-    //NOTATION
+    /// A notation. This is an abstract type, so no value has this type.
+    NOTATION,
 }
 
 impl fmt::Display for Value {
@@ -172,9 +186,44 @@ impl fmt::Display for Value {
             Value::NonNegativeInteger(i) => i.0.to_string(),
             Value::PositiveInteger(i) => i.0.to_string(),
             Value::NegativeInteger(i) => i.0.to_string(),
-            Value::Time(t) => t.format("%H:%M:%S.%f").to_string(),
-            Value::DateTime(dt) => dt.format("%Y-%m-%dT%H:%M:%S%z").to_string(),
-            Value::Date(d) => d.format("%Y-%m-%d").to_string(),
+            Value::Time(t, tz) => format!(
+                "{}{}{}",
+                t.format("%H:%M:%S"),
+                fraction(t.nanosecond()),
+                tz.as_ref().map_or(String::new(), timezone)
+            ),
+            Value::DateTime(dt, tz) => format!(
+                "{}{}{}",
+                dt.format("%Y-%m-%dT%H:%M:%S"),
+                fraction(dt.nanosecond()),
+                tz.as_ref().map_or(String::new(), timezone)
+            ),
+            Value::Duration(d) | Value::DayTimeDuration(d) => d.to_string(),
+            Value::YearMonthDuration(d) if d.is_zero() => String::from("P0M"),
+            Value::YearMonthDuration(d) => d.to_string(),
+            Value::DateTimeStamp(dt, tz) => format!(
+                "{}{}{}",
+                dt.format("%Y-%m-%dT%H:%M:%S"),
+                fraction(dt.nanosecond()),
+                timezone(tz)
+            ),
+            Value::Date(d, tz) => format!("{}{}", d.format("%Y-%m-%d"), optional_timezone(tz)),
+            Value::GYearMonth(y, m, tz) => {
+                format!("{}-{:02}{}", year(*y), m, optional_timezone(tz))
+            }
+            Value::GYear(y, tz) => format!("{}{}", year(*y), optional_timezone(tz)),
+            Value::GMonthDay(m, d, tz) => format!("--{:02}-{:02}{}", m, d, optional_timezone(tz)),
+            Value::GMonth(m, tz) => format!("--{:02}{}", m, optional_timezone(tz)),
+            Value::GDay(d, tz) => format!("---{:02}{}", d, optional_timezone(tz)),
+            Value::Token(s)
+            | Value::Language(s)
+            | Value::NMTOKEN(s)
+            | Value::Name(s)
+            | Value::NCName(s)
+            | Value::ENTITY(s)
+            | Value::AnyURI(s) => s.to_string(),
+            Value::Base64Binary(b) => base64_encode(b),
+            Value::HexBinary(b) => b.iter().map(|c| format!("{:02X}", c)).collect(),
             Value::QName(q) => q.to_string(),
             Value::RQName(q) => q.to_string(),
             Value::ID(s) => s.to_string(),
@@ -186,6 +235,146 @@ impl fmt::Display for Value {
     }
 }
 
+// The fractional part of a number of seconds, without trailing zeroes.
+// If there is no fractional part the result is empty.
+fn fraction(nanos: u32) -> String {
+    if nanos == 0 {
+        String::new()
+    } else {
+        format!(".{:09}", nanos).trim_end_matches('0').to_string()
+    }
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encode binary data in the canonical base64 representation.
+pub(crate) fn base64_encode(b: &[u8]) -> String {
+    let mut result = String::with_capacity(b.len().div_ceil(3) * 4);
+    for chunk in b.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, c)| acc | (*c as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(BASE64_ALPHABET[((n >> (18 - 6 * i)) & 0x3F) as usize] as char)
+            } else {
+                result.push('=')
+            }
+        }
+    }
+    result
+}
+
+/// Decode the base64 representation of binary data. Whitespace is ignored.
+/// Returns None if the representation is not valid.
+pub(crate) fn base64_decode(s: &str) -> Option<Vec<u8>> {
+    let chars: Vec<u8> = s.bytes().filter(|c| !c.is_ascii_whitespace()).collect();
+    if chars.len() % 4 != 0 {
+        return None;
+    }
+    let mut result = Vec::with_capacity(chars.len() / 4 * 3);
+    for (q, quad) in chars.chunks(4).enumerate() {
+        let padding = quad.iter().rev().take_while(|c| **c == b'=').count();
+        // Padding may only occur at the end of the data
+        if padding > 2 || (padding > 0 && q != chars.len() / 4 - 1) {
+            return None;
+        }
+        let mut n = 0u32;
+        for (i, c) in quad[..4 - padding].iter().enumerate() {
+            let d = BASE64_ALPHABET.iter().position(|a| a == c)? as u32;
+            n |= d << (18 - 6 * i);
+        }
+        result.extend(n.to_be_bytes()[1..4 - padding].iter());
+    }
+    Some(result)
+}
+
+/// Decode the hexadecimal representation of binary data.
+/// Returns None if the representation is not valid.
+pub(crate) fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 || !s.is_ascii() {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+// The lexical representation of a year, which has at least four digits.
+fn year(y: i32) -> String {
+    if y < 0 {
+        format!("-{:04}", -y)
+    } else {
+        format!("{:04}", y)
+    }
+}
+
+fn optional_timezone(tz: &Option<FixedOffset>) -> String {
+    tz.as_ref().map_or(String::new(), timezone)
+}
+
+/// The lexical representation of a timezone. UTC is represented as "Z".
+pub(crate) fn timezone(o: &FixedOffset) -> String {
+    let secs = o.local_minus_utc();
+    if secs == 0 {
+        String::from("Z")
+    } else {
+        format!(
+            "{}{:02}:{:02}",
+            if secs < 0 { '-' } else { '+' },
+            secs.abs() / 3600,
+            (secs.abs() % 3600) / 60
+        )
+    }
+}
+
+/// The point in time represented by a dateTime, as a UTC date and time.
+/// A value without a timezone is taken to be in the given implicit timezone.
+pub(crate) fn instant(
+    dt: &NaiveDateTime,
+    tz: &Option<FixedOffset>,
+    implicit: &FixedOffset,
+) -> NaiveDateTime {
+    *dt - chrono::TimeDelta::seconds(tz.unwrap_or(*implicit).local_minus_utc() as i64)
+}
+
+/// An xs:time value is compared, and subtracted, as a dateTime on the reference date 1972-12-31 (XPath F&O 10.4).
+pub(crate) fn time_on_reference_date(
+    t: &NaiveTime,
+    tz: &Option<FixedOffset>,
+    implicit: &FixedOffset,
+) -> NaiveDateTime {
+    instant(
+        &NaiveDate::from_ymd_opt(1972, 12, 31).unwrap().and_time(*t),
+        tz,
+        implicit,
+    )
+}
+
+// Values are compared without a dynamic context, so a value without a timezone is taken to be in UTC.
+// The comparison operators first give such values the implicit timezone of the dynamic context.
+fn utc() -> FixedOffset {
+    FixedOffset::east_opt(0).unwrap()
+}
+
+// Does the ordering of two values satisfy the comparison operator?
+fn ordering_satisfies(o: Ordering, op: Operator) -> Result<bool, Error> {
+    match op {
+        Operator::Equal => Ok(o == Ordering::Equal),
+        Operator::NotEqual => Ok(o != Ordering::Equal),
+        Operator::LessThan => Ok(o == Ordering::Less),
+        Operator::LessThanEqual => Ok(o != Ordering::Greater),
+        Operator::GreaterThan => Ok(o == Ordering::Greater),
+        Operator::GreaterThanEqual => Ok(o != Ordering::Less),
+        Operator::Is | Operator::Before | Operator::After => {
+            Err(Error::new(ErrorKind::TypeError, String::from("type error")))
+        }
+    }
+}
+
 impl Hash for Value {
     fn hash<H: Hasher>(&self, state: &mut H) {
         format!("{:?}", self).hash(state)
@@ -203,6 +392,15 @@ impl Value {
                 !t.is_empty()
             }
             Value::NormalizedString(s) => !s.0.is_empty(),
+            Value::Token(s)
+            | Value::Language(s)
+            | Value::NMTOKEN(s)
+            | Value::Name(s)
+            | Value::NCName(s)
+            | Value::ID(s)
+            | Value::IDREF(s)
+            | Value::ENTITY(s)
+            | Value::AnyURI(s) => !s.is_empty(),
             Value::Double(n) => *n != 0.0,
             Value::Integer(i) => *i != 0,
             Value::Int(i) => *i != 0,
@@ -231,9 +429,77 @@ impl Value {
             Value::Integer(i) => (*i) as f64,
             Value::Int(i) => (*i) as f64,
             Value::Double(d) => *d,
+            Value::Float(f) => (*f) as f64,
+            Value::Decimal(d) => d.to_f64().unwrap_or(f64::NAN),
+            Value::Long(l) => (*l) as f64,
+            Value::Short(s) => (*s) as f64,
+            Value::Byte(b) => (*b) as f64,
+            Value::UnsignedLong(l) => (*l) as f64,
+            Value::UnsignedInt(i) => (*i) as f64,
+            Value::UnsignedShort(s) => (*s) as f64,
+            Value::UnsignedByte(b) => (*b) as f64,
+            Value::NonPositiveInteger(i) => i.0 as f64,
+            Value::NegativeInteger(i) => i.0 as f64,
+            Value::NonNegativeInteger(i) => i.0 as f64,
+            Value::PositiveInteger(i) => i.0 as f64,
             _ => f64::NAN,
         }
     }
+    /// Is this value of a numeric type?
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            Value::Decimal(_)
+                | Value::Float(_)
+                | Value::Double(_)
+                | Value::Integer(_)
+                | Value::NonPositiveInteger(_)
+                | Value::NegativeInteger(_)
+                | Value::Long(_)
+                | Value::Int(_)
+                | Value::Short(_)
+                | Value::Byte(_)
+                | Value::NonNegativeInteger(_)
+                | Value::UnsignedLong(_)
+                | Value::UnsignedInt(_)
+                | Value::UnsignedShort(_)
+                | Value::UnsignedByte(_)
+                | Value::PositiveInteger(_)
+        )
+    }
+    /// Serialise the value as JSON.
+    /// Numbers and booleans are written as JSON numbers and booleans, all other values as a JSON string.
+    pub fn to_json(&self) -> String {
+        match self {
+            Value::Boolean(b) => b.to_string(),
+            Value::Double(d) if !d.is_finite() => String::from("null"),
+            Value::Float(f) if !f.is_finite() => String::from("null"),
+            _ if self.is_numeric() => self.to_string(),
+            _ => json_string(self.to_string().as_str()),
+        }
+    }
+    // The date, time and timezone of a dateTime or dateTimeStamp value.
+    fn date_time_parts(&self) -> Option<(NaiveDateTime, Option<FixedOffset>)> {
+        match self {
+            Value::DateTime(dt, tz) => Some((*dt, *tz)),
+            Value::DateTimeStamp(dt, tz) => Some((*dt, Some(*tz))),
+            _ => None,
+        }
+    }
+    // The starting instant of a Gregorian date value (XML Schema 1.1 Part 2, D.2.1).
+    // Missing components are taken from the reference dateTime 1972-12-31T00:00:00.
+    fn g_instant(&self) -> Option<NaiveDateTime> {
+        let (y, m, d, tz) = match self {
+            Value::GYearMonth(y, m, tz) => (*y, *m, 1, tz),
+            Value::GYear(y, tz) => (*y, 1, 1, tz),
+            Value::GMonthDay(m, d, tz) => (1972, *m, *d, tz),
+            Value::GMonth(m, tz) => (1972, *m, 1, tz),
+            Value::GDay(d, tz) => (1972, 12, *d, tz),
+            _ => return None,
+        };
+        NaiveDate::from_ymd_opt(y, m, d)
+            .map(|date| instant(&date.and_time(NaiveTime::MIN), tz, &utc()))
+    }
     pub fn value_type(&self) -> &'static str {
         match &self {
             Value::AnyType => "AnyType",
@@ -245,8 +511,10 @@ impl Value {
             Value::Numeric => "Numeric",
             Value::AnyAtomicType => "AnyAtomicType",
             Value::UntypedAtomic => "UntypedAtomic",
-            Value::Duration => "Duration",
-            Value::Time(_) => "Time",
+            Value::Duration(_) => "Duration",
+            Value::YearMonthDuration(_) => "YearMonthDuration",
+            Value::DayTimeDuration(_) => "DayTimeDuration",
+            Value::Time(..) => "Time",
             Value::Decimal(_) => "Decimal",
             Value::Float(_) => "Float",
             Value::Double(_) => "Double",
@@ -263,22 +531,31 @@ impl Value {
             Value::UnsignedShort(_) => "UnsignedShort",
             Value::UnsignedByte(_) => "UnsignedByte",
             Value::PositiveInteger(_) => "PositiveInteger",
-            Value::DateTime(_) => "DateTime",
-            Value::DateTimeStamp => "DateTimeStamp",
-            Value::Date(_) => "Date",
+            Value::DateTime(..) => "DateTime",
+            Value::DateTimeStamp(..) => "DateTimeStamp",
+            Value::Date(..) => "Date",
+            Value::GYearMonth(..) => "GYearMonth",
+            Value::GYear(..) => "GYear",
+            Value::GMonthDay(..) => "GMonthDay",
+            Value::GMonth(..) => "GMonth",
+            Value::GDay(..) => "GDay",
             Value::String(_) => "String",
             Value::NormalizedString(_) => "NormalizedString",
-            Value::Token => "Token",
-            Value::Language => "Language",
-            Value::NMTOKEN => "NMTOKEN",
-            Value::Name => "Name",
-            Value::NCName => "NCName",
+            Value::Token(_) => "Token",
+            Value::Language(_) => "Language",
+            Value::NMTOKEN(_) => "NMTOKEN",
+            Value::Name(_) => "Name",
+            Value::NCName(_) => "NCName",
             Value::ID(_) => "ID",
             Value::IDREF(_) => "IDREF",
-            Value::ENTITY => "ENTITY",
+            Value::ENTITY(_) => "ENTITY",
             Value::Boolean(_) => "boolean",
+            Value::Base64Binary(_) => "Base64Binary",
+            Value::HexBinary(_) => "HexBinary",
+            Value::AnyURI(_) => "AnyURI",
             Value::QName(_) => "QName",
             Value::RQName(_) => "QName",
+            Value::NOTATION => "NOTATION",
         }
     }
     pub fn compare(&self, other: &Value, op: Operator) -> Result<bool, Error> {
@@ -339,7 +616,16 @@ impl Value {
                     }
                 }
             }
-            Value::String(i) => {
+            Value::String(i)
+            | Value::Token(i)
+            | Value::Language(i)
+            | Value::NMTOKEN(i)
+            | Value::Name(i)
+            | Value::NCName(i)
+            | Value::ID(i)
+            | Value::IDREF(i)
+            | Value::ENTITY(i)
+            | Value::AnyURI(i) => {
                 let c = other.to_string();
                 match op {
                     Operator::Equal => Ok(*i == c),
@@ -367,6 +653,76 @@ impl Value {
                 (Operator::NotEqual, Value::RQName(r)) => Ok(**q != **r),
                 _ => Err(Error::new(ErrorKind::TypeError, String::from("type error"))),
             },
+            Value::DateTime(..) | Value::DateTimeStamp(..) => {
+                match (self.date_time_parts(), other.date_time_parts()) {
+                    (Some((d, s)), Some((e, t))) => ordering_satisfies(
+                        instant(&d, &s, &utc()).cmp(&instant(&e, &t, &utc())),
+                        op,
+                    ),
+                    _ => Err(Error::new(ErrorKind::TypeError, String::from("type error"))),
+                }
+            }
+            Value::GYearMonth(..)
+            | Value::GYear(..)
+            | Value::GMonthDay(..)
+            | Value::GMonth(..)
+            | Value::GDay(..)
+            | Value::Base64Binary(_)
+            | Value::HexBinary(_) => match op {
+                // These types have equality, but no ordering
+                Operator::Equal => Ok(self == other),
+                Operator::NotEqual => Ok(self != other),
+                _ => Err(Error::new_with_code(
+                    ErrorKind::TypeError,
+                    format!("values of type {} cannot be ordered", self.value_type()),
+                    Some(QualifiedName::new(None, None, "XPTY0004")),
+                )),
+            },
+            Value::Date(d, s) => match other {
+                Value::Date(e, t) => ordering_satisfies(
+                    instant(&d.and_time(NaiveTime::MIN), s, &utc()).cmp(&instant(
+                        &e.and_time(NaiveTime::MIN),
+                        t,
+                        &utc(),
+                    )),
+                    op,
+                ),
+                _ => Err(Error::new(ErrorKind::TypeError, String::from("type error"))),
+            },
+            Value::Time(t, s) => match other {
+                Value::Time(u, v) => ordering_satisfies(
+                    time_on_reference_date(t, s, &utc()).cmp(&time_on_reference_date(u, v, &utc())),
+                    op,
+                ),
+                _ => Err(Error::new(ErrorKind::TypeError, String::from("type error"))),
+            },
+            Value::Duration(d) | Value::YearMonthDuration(d) | Value::DayTimeDuration(d) => {
+                match (self, other, op) {
+                    (
+                        _,
+                        Value::Duration(e) | Value::YearMonthDuration(e) | Value::DayTimeDuration(e),
+                        Operator::Equal | Operator::NotEqual,
+                    ) => ordering_satisfies(
+                        if d == e {
+                            Ordering::Equal
+                        } else {
+                            Ordering::Less
+                        },
+                        op,
+                    ),
+                    (Value::YearMonthDuration(_), Value::YearMonthDuration(e), _) => {
+                        ordering_satisfies(d.months().cmp(&e.months()), op)
+                    }
+                    (Value::DayTimeDuration(_), Value::DayTimeDuration(e), _) => {
+                        ordering_satisfies(d.seconds().cmp(&e.seconds()), op)
+                    }
+                    _ => Err(Error::new_with_code(
+                        ErrorKind::TypeError,
+                        "durations can only be ordered if they are both xs:yearMonthDuration or both xs:dayTimeDuration",
+                        Some(QualifiedName::new(None, None, "XPTY0004")),
+                    )),
+                }
+            }
             _ => Result::Err(Error::new(
                 ErrorKind::Unknown,
                 format!(
@@ -398,6 +754,54 @@ impl PartialEq for Value {
                 Value::Double(e) => d == e,
                 _ => false, // type error? coerce to integer?
             },
+            Value::DateTime(..) | Value::DateTimeStamp(..) => {
+                match (self.date_time_parts(), other.date_time_parts()) {
+                    (Some((d, s)), Some((e, t))) => {
+                        instant(&d, &s, &utc()) == instant(&e, &t, &utc())
+                    }
+                    _ => false,
+                }
+            }
+            Value::Token(s)
+            | Value::Language(s)
+            | Value::NMTOKEN(s)
+            | Value::Name(s)
+            | Value::NCName(s)
+            | Value::ID(s)
+            | Value::IDREF(s)
+            | Value::ENTITY(s)
+            | Value::AnyURI(s) => s.eq(&other.to_string()),
+            Value::GYearMonth(..)
+            | Value::GYear(..)
+            | Value::GMonthDay(..)
+            | Value::GMonth(..)
+            | Value::GDay(..) => match (self.g_instant(), other.g_instant()) {
+                (Some(a), Some(b)) => self.value_type() == other.value_type() && a == b,
+                _ => false,
+            },
+            Value::Base64Binary(a) => matches!(other, Value::Base64Binary(b) if a == b),
+            Value::HexBinary(a) => matches!(other, Value::HexBinary(b) if a == b),
+            Value::Date(d, s) => match other {
+                Value::Date(e, t) => {
+                    instant(&d.and_time(NaiveTime::MIN), s, &utc())
+                        == instant(&e.and_time(NaiveTime::MIN), t, &utc())
+                }
+                _ => false,
+            },
+            Value::Time(t, s) => match other {
+                Value::Time(u, v) => {
+                    time_on_reference_date(t, s, &utc()) == time_on_reference_date(u, v, &utc())
+                }
+                _ => false,
+            },
+            Value::Duration(d) | Value::YearMonthDuration(d) | Value::DayTimeDuration(d) => {
+                match other {
+                    Value::Duration(e)
+                    | Value::YearMonthDuration(e)
+                    | Value::DayTimeDuration(e) => d == e,
+                    _ => false,
+                }
+            }
             _ => false, // not yet implemented
         }
     }
@@ -541,6 +945,24 @@ impl From<Rc<QualifiedName>> for Value {
     }
 }
 
+/// Quote and escape a string for JSON output.
+pub fn json_string(s: &str) -> String {
+    let mut r = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => r.push_str("\\\""),
+            '\\' => r.push_str("\\\\"),
+            '\n' => r.push_str("\\n"),
+            '\r' => r.push_str("\\r"),
+            '\t' => r.push_str("\\t"),
+            c if (c as u32) < 0x20 => r.push_str(format!("\\u{:04x}", c as u32).as_str()),
+            c => r.push(c),
+        }
+    }
+    r.push('"');
+    r
+}
+
 #[derive(Clone, Debug, Hash)]
 pub struct NonPositiveInteger(i64);
 impl TryFrom<i64> for NonPositiveInteger {
@@ -647,6 +1069,246 @@ impl fmt::Display for NormalizedString {
     }
 }
 
+/// An XML Schema duration.
+/// A duration has a months component and a seconds component, which must not have opposite signs.
+/// The same type is used for xs:duration, xs:yearMonthDuration and xs:dayTimeDuration.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Duration {
+    months: i64,
+    seconds: Decimal,
+}
+
+impl Duration {
+    /// Create a duration from a number of months and a number of seconds.
+    pub fn new(months: i64, seconds: Decimal) -> Result<Self, Error> {
+        if (months < 0 && seconds.is_sign_positive() && !seconds.is_zero())
+            || (months > 0 && seconds.is_sign_negative() && !seconds.is_zero())
+        {
+            Err(Error::new_with_code(
+                ErrorKind::Unknown,
+                "the months and seconds of a duration must have the same sign",
+                Some(QualifiedName::new(None, None, "FORG0001")),
+            ))
+        } else {
+            Ok(Duration {
+                months,
+                seconds: seconds.normalize(),
+            })
+        }
+    }
+    /// Create a duration of a number of months.
+    pub fn from_months(months: i64) -> Self {
+        Duration {
+            months,
+            seconds: Decimal::ZERO,
+        }
+    }
+    /// Create a duration of a number of seconds.
+    pub fn from_seconds(seconds: Decimal) -> Self {
+        Duration {
+            months: 0,
+            seconds: seconds.normalize(),
+        }
+    }
+    /// The total number of months in the duration.
+    pub fn months(&self) -> i64 {
+        self.months
+    }
+    /// The total number of seconds in the duration, excluding the months.
+    pub fn seconds(&self) -> Decimal {
+        self.seconds
+    }
+    /// Is the duration negative?
+    pub fn is_negative(&self) -> bool {
+        self.months < 0 || (self.seconds.is_sign_negative() && !self.seconds.is_zero())
+    }
+    /// Is the duration of zero length?
+    pub fn is_zero(&self) -> bool {
+        self.months == 0 && self.seconds.is_zero()
+    }
+    /// The years component.
+    pub fn year_part(&self) -> i64 {
+        self.months / 12
+    }
+    /// The months component, i.e. excluding whole years.
+    pub fn month_part(&self) -> i64 {
+        self.months % 12
+    }
+    /// The days component.
+    pub fn day_part(&self) -> i64 {
+        (self.seconds / Decimal::from(86400))
+            .trunc()
+            .to_i64()
+            .unwrap_or(0)
+    }
+    /// The hours component, i.e. excluding whole days.
+    pub fn hour_part(&self) -> i64 {
+        ((self.seconds % Decimal::from(86400)) / Decimal::from(3600))
+            .trunc()
+            .to_i64()
+            .unwrap_or(0)
+    }
+    /// The minutes component, i.e. excluding whole hours.
+    pub fn minute_part(&self) -> i64 {
+        ((self.seconds % Decimal::from(3600)) / Decimal::from(60))
+            .trunc()
+            .to_i64()
+            .unwrap_or(0)
+    }
+    /// The seconds component, i.e. excluding whole minutes.
+    pub fn second_part(&self) -> Decimal {
+        (self.seconds % Decimal::from(60)).normalize()
+    }
+    /// Negate the duration.
+    pub fn negate(&self) -> Self {
+        Duration {
+            months: -self.months,
+            seconds: -self.seconds,
+        }
+    }
+    /// Parse the lexical form of a duration, such as "P1Y2M3DT4H5M6.7S".
+    /// As well as the duration, the result indicates whether the lexical form had year or month components,
+    /// and whether it had day or time components.
+    pub fn parse(s: &str) -> Result<(Self, bool, bool), Error> {
+        let invalid = || {
+            Error::new_with_code(
+                ErrorKind::Unknown,
+                format!("\"{}\" is not a valid duration", s),
+                Some(QualifiedName::new(None, None, "FORG0001")),
+            )
+        };
+        let t = s.trim();
+        let (negative, t) = match t.strip_prefix('-') {
+            Some(u) => (true, u),
+            None => (false, t),
+        };
+        let t = t.strip_prefix('P').ok_or_else(invalid)?;
+        let (date, time) = match t.split_once('T') {
+            Some((d, u)) if !u.is_empty() => (d, Some(u)),
+            Some(_) => return Err(invalid()),
+            None => (t, None),
+        };
+        let dc = duration_components(date, &['Y', 'M', 'D']).ok_or_else(invalid)?;
+        let tc = match time {
+            Some(u) => duration_components(u, &['H', 'M', 'S']).ok_or_else(invalid)?,
+            None => vec![],
+        };
+        if dc.is_empty() && tc.is_empty() {
+            return Err(invalid());
+        }
+        let overflow = || {
+            Error::new_with_code(
+                ErrorKind::Unknown,
+                format!("duration \"{}\" is too large", s),
+                Some(QualifiedName::new(None, None, "FODT0002")),
+            )
+        };
+        let mut months: i64 = 0;
+        let mut seconds = Decimal::ZERO;
+        let mut ym = false;
+        let mut dt = false;
+        for (d, n) in dc {
+            let i = n.trunc().to_i64().ok_or_else(overflow)?;
+            match d {
+                'Y' => {
+                    ym = true;
+                    months = i
+                        .checked_mul(12)
+                        .and_then(|m| months.checked_add(m))
+                        .ok_or_else(overflow)?
+                }
+                'M' => {
+                    ym = true;
+                    months = months.checked_add(i).ok_or_else(overflow)?
+                }
+                _ => {
+                    dt = true;
+                    seconds = n
+                        .checked_mul(Decimal::from(86400))
+                        .and_then(|t| seconds.checked_add(t))
+                        .ok_or_else(overflow)?
+                }
+            }
+        }
+        for (d, n) in tc {
+            dt = true;
+            let t = match d {
+                'H' => n.checked_mul(Decimal::from(3600)),
+                'M' => n.checked_mul(Decimal::from(60)),
+                _ => Some(n),
+            };
+            seconds = t
+                .and_then(|t| seconds.checked_add(t))
+                .ok_or_else(overflow)?
+        }
+        let d = Duration::new(months, seconds)?;
+        Ok((if negative { d.negate() } else { d }, ym, dt))
+    }
+}
+
+// Parse the components of a duration. Each component is a number followed by a designator.
+// The designators must appear in the given order, and only seconds may have a fractional part.
+fn duration_components(s: &str, designators: &[char]) -> Option<Vec<(char, Decimal)>> {
+    let mut result = vec![];
+    let mut rest = s;
+    let mut next = 0;
+    while !rest.is_empty() {
+        let end = rest.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+        let (number, tail) = rest.split_at(end);
+        let d = tail.chars().next()?;
+        let pos = designators[next..].iter().position(|e| *e == d)? + next;
+        if number.is_empty()
+            || number.starts_with('.')
+            || number.ends_with('.')
+            || (number.contains('.') && d != 'S')
+        {
+            return None;
+        }
+        result.push((d, Decimal::from_str_exact(number).ok()?));
+        next = pos + 1;
+        rest = &tail[1..];
+    }
+    Some(result)
+}
+
+impl fmt::Display for Duration {
+    /// The canonical representation of the duration.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("PT0S");
+        }
+        let d = if self.is_negative() {
+            f.write_str("-")?;
+            self.negate()
+        } else {
+            *self
+        };
+        f.write_str("P")?;
+        if d.year_part() != 0 {
+            write!(f, "{}Y", d.year_part())?
+        }
+        if d.month_part() != 0 {
+            write!(f, "{}M", d.month_part())?
+        }
+        if d.day_part() != 0 {
+            write!(f, "{}D", d.day_part())?
+        }
+        if d.hour_part() != 0 || d.minute_part() != 0 || !d.second_part().is_zero() {
+            f.write_str("T")?;
+            if d.hour_part() != 0 {
+                write!(f, "{}H", d.hour_part())?
+            }
+            if d.minute_part() != 0 {
+                write!(f, "{}M", d.minute_part())?
+            }
+            if !d.second_part().is_zero() {
+                write!(f, "{}S", d.second_part())?
+            }
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -664,6 +1326,16 @@ mod tests {
         assert_eq!(Value::from(dec!(001.23)).to_string(), "1.23");
     }
 
+    #[test]
+    fn to_json_string() {
+        assert_eq!(Value::from("a \"b\"\n").to_json(), "\"a \\\"b\\\"\\n\"");
+    }
+    #[test]
+    fn to_json_number() {
+        assert_eq!(Value::from(42_i64).to_json(), "42");
+        assert_eq!(Value::from(true).to_json(), "true");
+    }
+
     #[test]
     fn normalizedstring_valid_empty() {
         assert_eq!(
@@ -961,4 +1633,46 @@ mod tests {
     fn op_after() {
         assert_eq!(Operator::After.to_string(), ">>")
     }
+
+    // Durations
+
+    #[test]
+    fn duration_parse() {
+        let (d, ym, dt) = Duration::parse("-P1Y2M3DT4H5M6.5S").expect("unable to parse duration");
+        assert!(ym && dt);
+        assert_eq!(d.months(), -14);
+        assert_eq!(d.day_part(), -3);
+        assert_eq!(d.second_part(), Decimal::new(-65, 1));
+        assert_eq!(d.to_string(), "-P1Y2M3DT4H5M6.5S")
+    }
+    #[test]
+    fn base64_roundtrip() {
+        for b in [&b""[..], b"H", b"He", b"Hel", b"Hello"] {
+            assert_eq!(base64_decode(&base64_encode(b)).unwrap(), b)
+        }
+        assert_eq!(base64_encode(b"Hello"), "SGVsbG8=");
+        assert!(base64_decode("SG=sbG8=").is_none());
+        assert!(base64_decode("SGVsbG8").is_none())
+    }
+    #[test]
+    fn hex_decode_invalid() {
+        assert_eq!(hex_decode("0aFF").unwrap(), vec![10, 255]);
+        assert!(hex_decode("0aF").is_none());
+        assert!(hex_decode("0g").is_none())
+    }
+    #[test]
+    fn duration_invalid() {
+        assert!(Duration::parse("P").is_err());
+        assert!(Duration::parse("P1D2Y").is_err());
+        assert!(Duration::parse("PT").is_err());
+        assert!(Duration::parse("P1YT").is_err())
+    }
+    #[test]
+    fn duration_overflow() {
+        let e = Duration::parse("P800000000000000000Y").expect_err("expected overflow");
+        assert_eq!(e.code.unwrap().to_string(), "FODT0002");
+        let e = Duration::parse("P700000000000000000Y700000000000000000M")
+            .expect_err("expected overflow");
+        assert_eq!(e.code.unwrap().to_string(), "FODT0002")
+    }
 }
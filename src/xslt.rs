@@ -6,6 +6,9 @@ Once the stylesheet has been compiled, it may then be evaluated with an appropri
 
 NB. This module, by default, does not resolve include or import statements. See the xrust-net crate for a helper module to do that.
 
+A stylesheet that uses other packages is compiled with [package_from_document], which is given a closure to find each used package.
+A compiled [Package] may be used by any number of stylesheets, and may create any number of transformation [Context]s.
+
 ```rust
 use std::rc::Rc;
 use xrust::xdmerror::{Error, ErrorKind};
@@ -67,22 +70,27 @@ let seq = ctxt.evaluate(&mut static_context)
 assert_eq!(seq.to_xml(), "<html><head><title>XSLT in Rust</title></head><body><p>A simple document.</p></body></html>")
  */
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
 use std::rc::Rc;
 
 use crate::item::{Item, Node, NodeType, Sequence};
 use crate::output::*;
 use crate::parser::avt::parse as parse_avt;
-use crate::parser::xpath::parse;
+use crate::parser::xpath::{parse, parse_sequence_type};
 use crate::pattern::{Branch, Pattern};
 use crate::qname::*;
+use crate::transform::accumulators::{Accumulator, AccumulatorRule, Phase};
 use crate::transform::callable::{ActualParameters, Callable, FormalParameters};
-use crate::transform::context::{Context, ContextBuilder};
-use crate::transform::numbers::{Level, Numbering};
-use crate::transform::template::Template;
+use crate::transform::context::{ComponentKey, Context, ContextBuilder, Hidden};
+use crate::transform::numbers::{DecimalFormat, LetterValue, Level, NumberFormat, Numbering};
+use crate::transform::strings::CODEPOINT_COLLATION;
+use crate::transform::template::{Mode, ModeKey, OnNoMatch, Template, Typed};
+use crate::transform::types::SequenceType;
+use crate::transform::variables::GlobalParameter;
 use crate::transform::{
-    in_scope_namespaces, Axis, Grouping, KindTest, NameTest, NodeMatch, NodeTest, Order, Transform,
-    WildcardOrName,
+    in_scope_namespaces, variable_name, Axis, Grouping, KindTest, MergeSource, NameTest, NodeMatch,
+    NodeTest, Order, Transform, WildcardOrName,
 };
 use crate::value::*;
 use crate::xdmerror::*;
@@ -114,12 +122,145 @@ pub trait XSLT: Node {
     //    }
 }
 
+/// The visibility of a component of a package. See XSLT 3.5.3.
+/// A hidden component is used by the package that declares it, but cannot be referenced or overridden by a using package.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Visibility {
+    Public,
+    Private,
+    Final,
+    Abstract,
+    Hidden,
+}
+
+impl TryFrom<&str> for Visibility {
+    type Error = Error;
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s.trim() {
+            "public" => Ok(Visibility::Public),
+            "private" => Ok(Visibility::Private),
+            "final" => Ok(Visibility::Final),
+            "abstract" => Ok(Visibility::Abstract),
+            "hidden" => Ok(Visibility::Hidden),
+            _ => Err(Error::new_with_code(
+                ErrorKind::StaticAbsent,
+                format!("invalid visibility \"{}\"", s),
+                Some(QualifiedName::new(None, None, "XTSE0020")),
+            )),
+        }
+    }
+}
+
+/// A component of a package that may be used by another package.
+/// Named templates are identified by their name as written, and global variables by their expanded name,
+/// in the same way as when they are invoked.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Component {
+    Template(QualifiedName),
+    Function(QualifiedName),
+    Variable(String),
+    AttributeSet(QualifiedName),
+    Mode(QualifiedName),
+}
+
+impl Component {
+    // The kind of component, as given by the component attribute of xsl:expose and xsl:accept
+    fn kind(&self) -> &'static str {
+        match self {
+            Component::Template(_) => "template",
+            Component::Function(_) => "function",
+            Component::Variable(_) => "variable",
+            Component::AttributeSet(_) => "attribute-set",
+            Component::Mode(_) => "mode",
+        }
+    }
+}
+
+impl std::fmt::Display for Component {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Component::Template(n)
+            | Component::Function(n)
+            | Component::AttributeSet(n)
+            | Component::Mode(n) => write!(f, "{} \"{}\"", self.kind(), n),
+            Component::Variable(n) => write!(f, "variable \"{}\"", n),
+        }
+    }
+}
+
+/// A compiled XSLT package. See XSLT 3.5.
+///
+/// A package is compiled once. It may then be used by any number of other packages (see [package_from_document]),
+/// and used to create any number of transformation [Context]s.
+///
+/// The visible components of a used package share a single namespace with the components of the using package,
+/// so the using package may not declare a component with the same name as one of them, other than by overriding it.
+/// The private components of a used package, and those it hides from the using package, are private to the used package,
+/// so the using package, and any other package it uses, may have components with the same names.
+/// Keys, accumulators and decimal formats are not components, so each package has its own, and they may have the same names as those of the using package.
+#[derive(Clone, Debug)]
+pub struct Package<N: Node> {
+    name: Option<String>,
+    version: String,
+    context: Context<N>,
+    attribute_sets: HashMap<QualifiedName, Vec<Transform<N>>>,
+    visibility: HashMap<Component, Visibility>,
+}
+
+impl<N: Node> Package<N> {
+    /// The name of the package. A stylesheet that is not an xsl:package has no name.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+    /// The version of the package, given by the package-version attribute. The default is "1".
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+    /// The visibility of a component of the package. If the package has no such component, then the result is None.
+    pub fn visibility(&self, c: &Component) -> Option<Visibility> {
+        self.visibility.get(c).copied()
+    }
+    /// Create a transformation context, using this package as the top-level package.
+    /// Each call returns a new context, so the package is not recompiled for each transformation.
+    pub fn context(&self) -> Result<Context<N>, Error> {
+        if let Some(c) = self
+            .visibility
+            .iter()
+            .find_map(|(c, v)| (*v == Visibility::Abstract).then_some(c))
+        {
+            return Err(Error::new_with_code(
+                ErrorKind::StaticAbsent,
+                format!("abstract {} has not been overridden", c),
+                Some(QualifiedName::new(None, None, "XTSE3080")),
+            ));
+        }
+        let c = &self.context;
+        Ok(Context {
+            templates: c.templates.clone(),
+            modes: c.modes.clone(),
+            default_mode: c.default_mode.clone(),
+            package: c.package.clone(),
+            hidden: c.hidden.clone(),
+            callables: c.callables.clone(),
+            globals: c.globals.clone(),
+            global_params: c.global_params.clone(),
+            keys: c.keys.clone(),
+            accumulators: c.accumulators.clone(),
+            decimal_formats: c.decimal_formats.clone(),
+            od: c.od.clone(),
+            ..Context::new()
+        })
+    }
+}
+
 /// Compiles a [Node] into a transformation [Context].
 /// NB. Due to whitespace stripping, this is destructive of the stylesheet.
 /// The argument f is a closure that parses a string to a [Node].
 /// The argument g is a closure that resolves a URL to a string.
 /// These are used for include and import modules.
 /// They are not included in this module since some environments, in particular Wasm, do not have I/O facilities.
+///
+/// The stylesheet may not use other packages. See [package_from_document].
 pub fn from_document<N: Node, F, G>(
     styledoc: N,
     base: Option<Url>,
@@ -129,16 +270,45 @@ pub fn from_document<N: Node, F, G>(
 where
     F: Fn(&str) -> Result<N, Error>,
     G: Fn(&Url) -> Result<String, Error>,
+{
+    package_from_document(styledoc, base, f, g, |name: &str, _: &str| {
+        Err(Error::new_with_code(
+            ErrorKind::StaticAbsent,
+            format!("unable to find package \"{}\"", name),
+            Some(QualifiedName::new(None, None, "XTSE3000")),
+        ))
+    })?
+    .context()
+}
+
+/// Compiles a [Node] into a [Package].
+/// The [Node] may be an xsl:package, or a stylesheet.
+/// The arguments f and g are the same as for [from_document].
+/// The argument p is a closure that finds the package for an xsl:use-package declaration.
+/// Its arguments are the name of the package and the package-version attribute ("*" if it is absent).
+/// Since packages are compiled separately, the same compiled package may be returned for any number of using packages.
+pub fn package_from_document<N: Node, F, G, P>(
+    styledoc: N,
+    base: Option<Url>,
+    f: F,
+    g: G,
+    p: P,
+) -> Result<Package<N>, Error>
+where
+    F: Fn(&str) -> Result<N, Error>,
+    G: Fn(&Url) -> Result<String, Error>,
+    P: Fn(&str, &str) -> Result<Rc<Package<N>>, Error>,
 {
     // Check that this is a valid XSLT stylesheet
-    // There must be a single element as a child of the root node, and it must be named xsl:stylesheet or xsl:transform
+    // There must be a single element as a child of the root node, and it must be named xsl:stylesheet, xsl:transform or xsl:package
     let mut rnit = styledoc.child_iter();
     let stylenode = match rnit.next() {
         Some(root) => {
             // TODO: intern strings so that comparison is fast
             if !(root.name().namespace_uri_to_string() == Some(XSLTNS.to_string())
                 && (root.name().localname_to_string() == "stylesheet"
-                    || root.name().localname_to_string() == "transform"))
+                    || root.name().localname_to_string() == "transform"
+                    || root.name().localname_to_string() == "package"))
             {
                 return Result::Err(Error::new(
                     ErrorKind::TypeError,
@@ -164,6 +334,14 @@ where
 
     // TODO: check version attribute
 
+    // The name of the package. A stylesheet that is not an xsl:package has no name.
+    let package_name = stylenode
+        .get_attribute(&QualifiedName::new(None, None, "name"))
+        .to_string();
+    let package: Option<Rc<str>> = (is_xsl(&stylenode, "package")
+        && !package_name.trim().is_empty())
+    .then(|| Rc::from(package_name.trim()));
+
     // Strip whitespace from the stylesheet
     strip_whitespace(
         styledoc.clone(),
@@ -181,21 +359,13 @@ where
     )?;
 
     // Setup the serialization of the primary result document
-    let mut od = OutputDefinition::new();
-    if let Some(c) = stylenode.child_iter().find(|c| {
-        !(c.is_element()
-            && c.name().namespace_uri_to_string() == Some(XSLTNS.to_string())
-            && c.name().localname_to_string() == "output")
-    }) {
-        let b: bool = matches!(
-            c.get_attribute(&QualifiedName::new(None, None, "indent"))
-                .to_string()
-                .as_str(),
-            "yes" | "true" | "1"
-        );
-
-        od.set_indent(b);
-    };
+    let od = stylenode
+        .child_iter()
+        .find(|c| is_xsl(c, "output"))
+        .map_or_else(
+            || Ok(OutputDefinition::new()),
+            |c| output_definition(&c, OutputDefinition::new()),
+        )?;
 
     // Iterate over children, looking for includes
     // * resolve href
@@ -306,18 +476,81 @@ where
             Ok::<(), Error>(())
         })?;
 
+    // Iterate over children, looking for used packages
+    // * find the package
+    // * determine the visibility of its components in this package
+    // * collect the declarations that override its components
+    let mut used: Vec<(Rc<Package<N>>, HashMap<Component, Visibility>)> = vec![];
+    let mut overrides: Vec<N> = vec![];
+    stylenode
+        .child_iter()
+        .filter(|c| is_xsl(c, "use-package"))
+        .try_for_each(|c| {
+            let name = c
+                .get_attribute(&QualifiedName::new(None, None, "name"))
+                .to_string();
+            if name.trim().is_empty() {
+                return Err(Error::new_with_code(
+                    ErrorKind::StaticAbsent,
+                    "xsl:use-package must have a name attribute",
+                    Some(QualifiedName::new(None, None, "XTSE0010")),
+                ));
+            }
+            let version = c
+                .get_attribute(&QualifiedName::new(None, None, "package-version"))
+                .to_string();
+            let pkg = p(
+                name.trim(),
+                if version.trim().is_empty() {
+                    "*"
+                } else {
+                    version.trim()
+                },
+            )?;
+            if pkg.name() != Some(name.trim()) {
+                return Err(Error::new_with_code(
+                    ErrorKind::StaticAbsent,
+                    format!("unable to find package \"{}\"", name.trim()),
+                    Some(QualifiedName::new(None, None, "XTSE3000")),
+                ));
+            }
+            let accepted = accepted_visibility(&c, &pkg)?;
+            c.child_iter()
+                .filter(|o| is_xsl(o, "override"))
+                .for_each(|o| overrides.extend(o.child_iter().filter(|d| d.is_element())));
+            used.push((pkg, accepted));
+            Ok(())
+        })?;
+    // The top-level declarations, including those that override the components of used packages
+    let declarations: Vec<N> = stylenode
+        .child_iter()
+        .chain(overrides.iter().cloned())
+        .collect();
+
     // Find named attribute sets
 
     // Store for named attribute sets
-    let mut attr_sets: HashMap<QualifiedName, Vec<Transform<N>>> = HashMap::new();
+    // The attribute sets of used packages may be used, unless they are hidden
+    let mut attr_sets: HashMap<QualifiedName, Vec<Transform<N>>> = used
+        .iter()
+        .flat_map(|(pkg, accepted)| {
+            pkg.attribute_sets.iter().filter(|(n, _)| {
+                accepted
+                    .get(&Component::AttributeSet((*n).clone()))
+                    .is_some_and(|v| *v != Visibility::Hidden)
+            })
+        })
+        .map(|(n, a)| (n.clone(), a.clone()))
+        .collect();
 
-    stylenode
-        .child_iter()
+    declarations
+        .iter()
         .filter(|c| {
             c.is_element()
                 && c.name().namespace_uri_to_string() == Some(XSLTNS.to_string())
                 && c.name().localname_to_string() == "attribute-set"
         })
+        .cloned()
         .try_for_each(|c| {
             let name = c.get_attribute(&QualifiedName::new(None, None, "name"));
             let eqname = QualifiedName::try_from((name.to_string().as_str(), c.clone()))?;
@@ -344,13 +577,74 @@ where
             Ok(())
         })?;
 
+    // Iterate over the children, looking for mode declarations.
+    // Every mode that is used by a template rule or by xsl:apply-templates is implicitly declared, as is the unnamed mode.
+    let mut modes: HashMap<Option<Rc<QualifiedName>>, Mode> = HashMap::new();
+    stylenode
+        .child_iter()
+        .filter(|c| is_xsl(c, "mode"))
+        .try_for_each(|c| {
+            let name = c.get_attribute(&QualifiedName::new(None, None, "name"));
+            let qn = if name.to_string().is_empty() {
+                None
+            } else {
+                Some(Rc::new(QualifiedName::try_from((
+                    name.to_string().as_str(),
+                    c.clone(),
+                ))?))
+            };
+            let m = mode(&c)?;
+            match modes.get(&qn) {
+                Some(e) if *e != m => Err(Error::new_with_code(
+                    ErrorKind::StaticAbsent,
+                    format!("conflicting declarations of mode \"{}\"", name),
+                    Some(QualifiedName::new(None, None, "XTSE0545")),
+                )),
+                _ => {
+                    modes.insert(qn, m);
+                    Ok(())
+                }
+            }
+        })?;
+    modes.entry(None).or_default();
+    modes.entry(default_mode(&stylenode)?).or_default();
+    stylenode
+        .descend_iter()
+        .filter(|c| is_xsl(c, "template") || is_xsl(c, "apply-templates"))
+        .try_for_each(|c| {
+            let m = c.get_attribute(&QualifiedName::new(None, None, "mode"));
+            if m.to_string().trim().is_empty() {
+                modes.entry(default_mode(&c)?).or_default();
+            }
+            m.to_string()
+                .split_whitespace()
+                .filter(|t| *t != "#all" && *t != "#current")
+                .try_for_each(|t| {
+                    modes.entry(mode_name(&c, t)?).or_default();
+                    Ok::<(), Error>(())
+                })
+        })?;
+    // The named modes of used packages are declared by those packages, along with their built-in templates,
+    // unless they are hidden in this package
+    used.iter().for_each(|(pkg, accepted)| {
+        pkg.context.modes.keys().for_each(|k| {
+            if let ModeKey::Named(m) = k {
+                if accepted.get(&Component::Mode(m.clone())) != Some(&Visibility::Hidden) {
+                    modes.remove(&Some(Rc::new(m.clone())));
+                }
+            }
+        })
+    });
+    // Named modes are components of the package, but the unnamed mode is not
+    let mode_names: Vec<QualifiedName> = modes.keys().flatten().map(|m| (**m).clone()).collect();
+
     // Iterate over children, looking for templates
     // * compile match pattern
     // * compile content into sequence constructor
     // * register template in dynamic context
     let mut templates: Vec<Template<N>> = vec![];
-    stylenode
-        .child_iter()
+    declarations
+        .iter()
         .filter(|c| {
             c.is_element()
                 && c.name().namespace_uri_to_string() == Some(XSLTNS.to_string())
@@ -361,6 +655,7 @@ where
                 .to_string()
                 .is_empty()
         })
+        .cloned()
         .try_for_each(|c| {
             let m = c.get_attribute(&QualifiedName::new(None, None, "match"));
             let pat = Pattern::try_from(m.to_string()).map_err(|e| {
@@ -379,12 +674,25 @@ where
             if let Pattern::Selection(Branch::Error(e)) = pat {
                 return Err(e.clone());
             }
-            let mut body = vec![];
-            let mode = c.get_attribute_node(&QualifiedName::new(None, None, "mode"));
-            c.child_iter().try_for_each(|d| {
-                body.push(to_transform(d, &attr_sets)?);
+            // A template rule may apply in several modes
+            let m = c.get_attribute(&QualifiedName::new(None, None, "mode"));
+            let mut template_modes = vec![];
+            if m.to_string().trim().is_empty() {
+                template_modes.push(default_mode(&c)?)
+            }
+            m.to_string().split_whitespace().try_for_each(|t| {
+                if t == "#all" {
+                    template_modes.extend(modes.keys().cloned())
+                } else {
+                    template_modes.push(mode_name(&c, t)?)
+                }
                 Ok::<(), Error>(())
             })?;
+            let body = Transform::SequenceItems(sequence_constructor(c.child_iter(), &attr_sets)?);
+            let body = match sequence_type(&c)? {
+                Some(t) => Transform::Convert(Box::new(body), t, String::from("XTTE0505"), true),
+                None => body,
+            };
             //sc.static_analysis(&mut pat);
             //sc.static_analysis(&mut body);
             // Determine the priority of the template
@@ -435,19 +743,16 @@ where
             if im.to_string() != "" {
                 import = im.to_int()? as usize
             }
-            templates.push(Template::new(
-                pat,
-                Transform::SequenceItems(body),
-                Some(prio),
-                vec![import],
-                None,
-                mode.map(|n| {
-                    Rc::new(
-                        QualifiedName::try_from((n.to_string().as_str(), n))
-                            .expect("unable to resolve qualified name"),
-                    )
-                }), // TODO: don't panic
-            ));
+            template_modes.into_iter().for_each(|tm| {
+                templates.push(Template::new(
+                    pat.clone(),
+                    body.clone(),
+                    Some(prio),
+                    vec![import],
+                    None,
+                    tm,
+                ))
+            });
             Ok::<(), Error>(())
         })?;
 
@@ -470,60 +775,317 @@ where
             Ok(())
         })?;
 
-    let mut newctxt = ContextBuilder::new()
-        // Define the builtin templates
-        // See XSLT 6.7. This implements text-only-copy.
-        // TODO: Support deep-copy, shallow-copy, deep-skin, shallow-skip and fail
-        // This matches "/" and processes the root element
-        .template(Template::new(
-            Pattern::try_from("/")?,
-            Transform::ApplyTemplates(
-                Box::new(Transform::Step(NodeMatch::new(
-                    Axis::Child,
-                    NodeTest::Kind(KindTest::Any),
-                ))),
-                None,
-                vec![],
+    // Iterate over the children, looking for decimal format declarations.
+    let mut decimal_formats: HashMap<Option<QualifiedName>, DecimalFormat> = HashMap::new();
+    stylenode
+        .child_iter()
+        .filter(|c| is_xsl(c, "decimal-format"))
+        .try_for_each(|c| {
+            let name = c.get_attribute(&QualifiedName::new(None, None, "name"));
+            let qn = if name.to_string().is_empty() {
+                None
+            } else {
+                Some(QualifiedName::try_from((
+                    name.to_string().as_str(),
+                    c.clone(),
+                ))?)
+            };
+            let df = decimal_format(&c)?;
+            match decimal_formats.get(&qn) {
+                Some(e) if *e != df => Err(Error::new_with_code(
+                    ErrorKind::StaticAbsent,
+                    format!("conflicting declarations of decimal format \"{}\"", name),
+                    Some(QualifiedName::new(None, None, "XTSE1290")),
+                )),
+                _ => {
+                    decimal_formats.insert(qn, df);
+                    Ok(())
+                }
+            }
+        })?;
+
+    // Iterate over the children, looking for accumulator declarations.
+    let mut accumulators: HashMap<QualifiedName, Accumulator<N>> = HashMap::new();
+    stylenode
+        .child_iter()
+        .filter(|c| is_xsl(c, "accumulator"))
+        .try_for_each(|c| {
+            let name = c.get_attribute(&QualifiedName::new(None, None, "name"));
+            if name.to_string().is_empty() {
+                return Err(Error::new_with_code(
+                    ErrorKind::StaticAbsent,
+                    "xsl:accumulator must have a name attribute",
+                    Some(QualifiedName::new(None, None, "XTSE0010")),
+                ));
+            }
+            let qn = QualifiedName::try_from((name.to_string().as_str(), c.clone()))?;
+            if accumulators
+                .insert(qn, accumulator(&c, &attr_sets)?)
+                .is_some()
+            {
+                return Err(Error::new_with_code(
+                    ErrorKind::StaticAbsent,
+                    format!("duplicate declaration of accumulator \"{}\"", name),
+                    Some(QualifiedName::new(None, None, "XTSE3350")),
+                ));
+            }
+            Ok(())
+        })?;
+
+    // Iterate over the children, looking for global variable and stylesheet parameter declarations.
+    // These are evaluated lazily, so the order of declaration does not matter.
+    let mut globals = HashMap::new();
+    let mut global_params = HashMap::new();
+    declarations
+        .iter()
+        .filter(|c| is_xsl(*c, "variable") || is_xsl(*c, "param"))
+        .cloned()
+        .try_for_each(|c| {
+            let (name, value) = variable_declaration(&c, &attr_sets)?;
+            if c.name().localname_to_string() == "param" {
+                let required = matches!(
+                    c.get_attribute(&QualifiedName::new(None, None, "required"))
+                        .to_string()
+                        .trim(),
+                    "yes" | "true" | "1"
+                );
+                if required
+                    && (c.child_iter().next().is_some()
+                        || !c
+                            .get_attribute(&QualifiedName::new(None, None, "select"))
+                            .to_string()
+                            .is_empty())
+                {
+                    return Err(Error::new_with_code(
+                        ErrorKind::StaticAbsent,
+                        format!("required parameter \"{}\" has a default value", name),
+                        Some(QualifiedName::new(None, None, "XTSE0010")),
+                    ));
+                }
+                let as_type = c.get_attribute(&QualifiedName::new(None, None, "as"));
+                global_params.insert(
+                    ComponentKey::Visible(name.clone()),
+                    GlobalParameter {
+                        name: QualifiedName::try_from((
+                            c.get_attribute(&QualifiedName::new(None, None, "name"))
+                                .to_string()
+                                .as_str(),
+                            c.clone(),
+                        ))?,
+                        required,
+                        as_type: if as_type.to_string().is_empty() {
+                            None
+                        } else {
+                            Some(parse_sequence_type(&as_type.to_string(), Some(c.clone()))?)
+                        },
+                    },
+                );
+            }
+            if globals
+                .insert(name.clone(), (value, package.clone()))
+                .is_some()
+            {
+                return Err(Error::new_with_code(
+                    ErrorKind::StaticAbsent,
+                    format!("duplicate declaration of global variable \"{}\"", name),
+                    Some(QualifiedName::new(None, None, "XTSE0630")),
+                ));
+            }
+            Ok(())
+        })?;
+
+    // The components of used packages, unless they are overridden.
+    // Those that are visible share a single namespace with the components of this package, so a component with the same name is an error.
+    // Those that are hidden are private to the used package: the code of that package may reference them, but the code of this package may not.
+    // Keys, accumulators and decimal formats are not components, so each package has its own, keyed by the name of the package.
+    // Template rules in the unnamed mode of a used package are in the unnamed mode of that package.
+    // Template rules of a used package have a lower import level, so that those in xsl:override take precedence.
+    let overridden: Vec<Component> = declared_components(&overrides)?
+        .into_iter()
+        .map(|(c, _)| c)
+        .collect();
+    let conflict = |kind: &str, name: &dyn std::fmt::Display| {
+        Error::new_with_code(
+            ErrorKind::StaticAbsent,
+            format!(
+                "{} \"{}\" conflicts with a declaration of a used package",
+                kind, name
             ),
-            None,
-            vec![0],
-            None,
-            None,
-        ))
-        // This matches "*" and applies templates to all children
-        .template(Template::new(
-            Pattern::try_from("child::*")?,
-            Transform::ApplyTemplates(
-                Box::new(Transform::Step(NodeMatch::new(
-                    Axis::Child,
-                    NodeTest::Kind(KindTest::Any),
-                ))),
-                None,
-                vec![],
+            Some(QualifiedName::new(None, None, "XTSE3050")),
+        )
+    };
+    // A hidden component does not hide a component of the same name that the code of this package can see
+    let visible: Vec<Component> = declared_components(&declarations)?
+        .into_iter()
+        .map(|(c, _)| c)
+        .chain(mode_names.iter().cloned().map(Component::Mode))
+        .chain(used.iter().flat_map(|(_, accepted)| {
+            accepted
+                .iter()
+                .filter(|(_, v)| **v != Visibility::Hidden)
+                .map(|(c, _)| c.clone())
+        }))
+        .collect();
+    let mut hidden: HashMap<Option<Rc<str>>, Hidden> = HashMap::new();
+    let mut own_hidden = Hidden::default();
+    let mut package_keys = vec![];
+    let mut package_accumulators = vec![];
+    let mut package_decimal_formats = vec![];
+    let mut package_templates = vec![];
+    let mut package_modes = vec![];
+    let mut package_callables = vec![];
+    let mut private_globals = vec![];
+    used.iter().try_for_each(|(pkg, accepted)| {
+        let is_hidden = |c: Component| accepted.get(&c) == Some(&Visibility::Hidden);
+        let mut pkg_hidden = (*pkg.context.hidden).clone();
+        pkg.context.globals.iter().try_for_each(|(k, v)| {
+            let key = match k {
+                ComponentKey::Visible(n)
+                    if overridden.contains(&Component::Variable(n.clone())) =>
+                {
+                    return Ok(());
+                }
+                ComponentKey::Visible(n) if is_hidden(Component::Variable(n.clone())) => {
+                    make_private(n, &pkg.context.package, &mut pkg_hidden, |h| {
+                        (&mut h.private_variables, &h.variables)
+                    })
+                }
+                ComponentKey::Visible(n) => {
+                    if globals.insert(n.clone(), v.clone()).is_some() {
+                        return Err(conflict("variable", n));
+                    }
+                    k.clone()
+                }
+                ComponentKey::Private(_, _) => k.clone(),
+            };
+            if let Some(gp) = pkg.context.global_params.get(k) {
+                global_params.insert(key.clone(), gp.clone());
+            }
+            if let ComponentKey::Private(_, _) = key {
+                private_globals.push((key, v.clone()));
+            }
+            Ok(())
+        })?;
+        pkg.context.callables.iter().for_each(|(k, c)| {
+            let key = match k {
+                ComponentKey::Visible(n)
+                    if is_hidden(match c.parameters {
+                        FormalParameters::Named(_) => Component::Template(n.clone()),
+                        _ => Component::Function(n.clone()),
+                    }) =>
+                {
+                    make_private(n, &pkg.context.package, &mut pkg_hidden, |h| {
+                        (&mut h.private_callables, &h.callables)
+                    })
+                }
+                _ => k.clone(),
+            };
+            package_callables.push((key, c.clone()));
+        });
+        pkg.context.modes.iter().for_each(|(k, m)| {
+            let key = match k {
+                ModeKey::Named(n) if is_hidden(Component::Mode(n.clone())) => {
+                    match make_private(n, &pkg.context.package, &mut pkg_hidden, |h| {
+                        (&mut h.private_modes, &h.modes)
+                    }) {
+                        ComponentKey::Private(p, n) => ModeKey::Private(p, n),
+                        ComponentKey::Visible(n) => ModeKey::Named(n),
+                    }
+                }
+                _ => k.clone(),
+            };
+            package_modes.push((key, m.clone()));
+        });
+        package_keys.extend(pkg.context.keys.clone());
+        package_accumulators.extend(pkg.context.accumulators.clone());
+        package_decimal_formats.extend(pkg.context.decimal_formats.clone());
+        accepted
+            .iter()
+            .filter(|(c, v)| **v == Visibility::Hidden && !visible.contains(c))
+            .for_each(|(c, _)| match c {
+                Component::Template(n) | Component::Function(n) => {
+                    own_hidden.callables.insert(n.clone());
+                }
+                Component::Variable(n) => {
+                    own_hidden.variables.insert(n.clone());
+                }
+                Component::Mode(n) => {
+                    own_hidden.modes.insert(n.clone());
+                }
+                // Attribute sets are expanded when the package is compiled
+                Component::AttributeSet(_) => {}
+            });
+        hidden.extend(pkg_hidden);
+        package_templates.extend(pkg.context.templates.iter().map(|t| Template {
+            import: [t.import.as_slice(), &[0]].concat(),
+            ..(**t).clone()
+        }));
+        Ok(())
+    })?;
+    hidden.insert(package.clone(), own_hidden);
+
+    // Define the builtin templates for each mode. See XSLT 6.7.
+    // The template rules of this package, including the built-in templates, are evaluated as code of this package.
+    let mut newctxt = modes
+        .into_iter()
+        .try_fold(
+            accumulators.into_iter().fold(
+                decimal_formats.into_iter().fold(
+                    ContextBuilder::new().package(package.clone()),
+                    |b, (n, df)| b.decimal_format(n, df),
+                ),
+                |b, (n, a)| b.accumulator(n, a),
             ),
-            None,
-            vec![0],
-            None,
-            None,
-        ))
-        // This matches "text()" and copies content
-        .template(Template::new(
-            Pattern::try_from("child::text()")?,
-            Transform::ContextItem,
-            None,
-            vec![0],
-            None,
-            None,
-        ))
-        .template_all(templates)
+            |b, (name, m)| {
+                Ok::<ContextBuilder<N>, Error>(
+                    b.template_all(
+                        builtin_templates(&name, &m)?
+                            .into_iter()
+                            .map(|t| Template {
+                                package: package.clone(),
+                                ..t
+                            })
+                            .collect(),
+                    )
+                    .mode(ModeKey::new(&name, &package), m),
+                )
+            },
+        )?
+        .default_mode(default_mode(&stylenode)?)
+        .template_all(
+            templates
+                .into_iter()
+                .map(|t| Template {
+                    package: package.clone(),
+                    ..t
+                })
+                .collect(),
+        )
+        .template_all(package_templates)
+        .global_variables(globals)
+        .global_parameters(global_params)
         .output_definition(od)
+        .package(package.clone())
+        .hidden(hidden)
         .build();
+    package_modes.into_iter().for_each(|(n, m)| {
+        newctxt.modes.insert(n, m);
+    });
+    newctxt.globals.extend(private_globals);
+    // Named templates and functions that are overridden are replaced below
+    newctxt.callables.extend(package_callables);
+    newctxt.accumulators.extend(package_accumulators);
+    newctxt.decimal_formats.extend(package_decimal_formats);
+    package_keys.into_iter().for_each(|(name, defs)| {
+        newctxt.key_values.insert(name.clone(), HashMap::new());
+        newctxt.keys.insert(name, defs);
+    });
     keys.iter()
         .for_each(|(name, m, u)| newctxt.declare_key(name.to_string(), m.clone(), u.clone()));
 
     // Add named templates
-    stylenode
-        .child_iter()
+    declarations
+        .iter()
         .filter(|c| {
             c.is_element()
                 && c.name().namespace_uri_to_string() == Some(XSLTNS.to_string())
@@ -534,79 +1096,73 @@ where
                 .to_string()
                 .is_empty()
         })
+        .cloned()
         .try_for_each(|c| {
             let name = c.get_attribute(&QualifiedName::new(None, None, "name"));
             // xsl:param for formal parameters
             // TODO: validate that xsl:param elements come first in the child list
             // TODO: validate that xsl:param elements have unique name attributes
-            let mut params: Vec<(QualifiedName, Option<Transform<N>>)> = Vec::new();
-            c.child_iter()
-                .filter(|c| {
-                    c.is_element()
-                        && c.name().namespace_uri_to_string() == Some(XSLTNS.to_string())
-                        && c.name().localname_to_string() == "param"
-                })
-                .try_for_each(|c| {
-                    let p_name = c.get_attribute(&QualifiedName::new(None, None, "name"));
-                    if p_name.to_string().is_empty() {
-                        Err(Error::new(
-                            ErrorKind::StaticAbsent,
-                            "name attribute is missing",
-                        ))
+            let is_param = |c: &N| is_xsl(c, "param");
+            // Tunnel parameters are declared in the template body
+            let mut params: Vec<(QualifiedName, Option<Transform<N>>, Option<SequenceType>)> =
+                Vec::new();
+            c.child_iter().filter(|c| is_param(c)).try_for_each(|c| {
+                if is_tunnel(&c)? {
+                    return Ok(());
+                }
+                let p_name = c.get_attribute(&QualifiedName::new(None, None, "name"));
+                if p_name.to_string().is_empty() {
+                    Err(Error::new(
+                        ErrorKind::StaticAbsent,
+                        "name attribute is missing",
+                    ))
+                } else {
+                    let sel = c.get_attribute(&QualifiedName::new(None, None, "select"));
+                    if sel.to_string().is_empty() {
+                        // xsl:param content is the sequence constructor
+                        let body = sequence_constructor(c.child_iter(), &attr_sets)?;
+                        params.push((
+                            QualifiedName::try_from((p_name.to_string().as_str(), c.clone()))?,
+                            Some(Transform::SequenceItems(body)),
+                            sequence_type(&c)?,
+                        ));
+                        Ok(())
                     } else {
-                        let sel = c.get_attribute(&QualifiedName::new(None, None, "select"));
-                        if sel.to_string().is_empty() {
-                            // xsl:param content is the sequence constructor
-                            let mut body = vec![];
-                            c.child_iter().try_for_each(|d| {
-                                body.push(to_transform(d, &attr_sets)?);
-                                Ok(())
-                            })?;
-                            params.push((
-                                QualifiedName::new(None, None, p_name.to_string()),
-                                Some(Transform::SequenceItems(body)),
-                            ));
-                            Ok(())
-                        } else {
-                            // select attribute value is an expression
-                            params.push((
-                                QualifiedName::new(None, None, p_name.to_string()),
-                                Some(parse::<N>(&sel.to_string(), Some(c.clone()))?),
-                            ));
-                            Ok(())
-                        }
+                        // select attribute value is an expression
+                        params.push((
+                            QualifiedName::try_from((p_name.to_string().as_str(), c.clone()))?,
+                            Some(parse::<N>(&sel.to_string(), Some(c.clone()))?),
+                            sequence_type(&c)?,
+                        ));
+                        Ok(())
                     }
-                })?;
+                }
+            })?;
             // Content is the template body
-            let mut body = vec![];
-            c.child_iter()
-                .filter(|c| {
-                    !(c.is_element()
-                        && c.name().namespace_uri_to_string() == Some(XSLTNS.to_string())
-                        && c.name().localname_to_string() == "param")
-                })
-                .try_for_each(|d| {
-                    body.push(to_transform(d, &attr_sets)?);
-                    Ok::<(), Error>(())
-                })?;
+            let body = sequence_constructor(
+                c.child_iter()
+                    .filter(|c| !is_param(c) || is_tunnel(c).unwrap_or(false)),
+                &attr_sets,
+            )?;
             newctxt.callable_push(
                 QualifiedName::new(None, None, name.to_string()),
-                Callable::new(
-                    Transform::SequenceItems(body),
-                    FormalParameters::Named(params),
-                ),
+                Callable {
+                    package: package.clone(),
+                    ..Callable::new_with_type(
+                        Transform::SequenceItems(body),
+                        FormalParameters::Named(params),
+                        sequence_type(&c)?,
+                    )
+                },
             );
             Ok(())
         })?;
 
     // Add functions
-    stylenode
-        .child_iter()
-        .filter(|c| {
-            c.is_element()
-                && c.name().namespace_uri_to_string() == Some(XSLTNS.to_string())
-                && c.name().localname_to_string() == "function"
-        })
+    declarations
+        .iter()
+        .filter(|c| is_xsl(*c, "function"))
+        .cloned()
         .try_for_each(|c| {
             let name = c.get_attribute(&QualifiedName::new(None, None, "name"));
             // Name must have a namespace. See XSLT 10.3.1.
@@ -621,13 +1177,9 @@ where
             // xsl:param for formal parameters
             // TODO: validate that xsl:param elements come first in the child list
             // TODO: validate that xsl:param elements have unique name attributes
-            let mut params: Vec<QualifiedName> = Vec::new();
+            let mut params: Vec<(QualifiedName, Option<SequenceType>)> = Vec::new();
             c.child_iter()
-                .filter(|c| {
-                    c.is_element()
-                        && c.name().namespace_uri_to_string() == Some(XSLTNS.to_string())
-                        && c.name().localname_to_string() == "param"
-                })
+                .filter(|c| is_xsl(c, "param"))
                 .try_for_each(|c| {
                     let p_name = c.get_attribute(&QualifiedName::new(None, None, "name"));
                     if p_name.to_string().is_empty() {
@@ -637,36 +1189,112 @@ where
                         ))
                     } else {
                         // TODO: validate that xsl:param elements do not specify a default value. See XSLT 10.3.2.
-                        params.push(QualifiedName::new(None, None, p_name.to_string()));
+                        params.push((
+                            QualifiedName::try_from((p_name.to_string().as_str(), c.clone()))?,
+                            sequence_type(&c)?,
+                        ));
                         Ok(())
                     }
                 })?;
             // Content is the function body
-            let mut body = vec![];
-            c.child_iter()
-                .filter(|c| {
-                    !(c.is_element()
-                        && c.name().namespace_uri_to_string() == Some(XSLTNS.to_string())
-                        && c.name().localname_to_string() == "param")
-                })
-                .try_for_each(|d| {
-                    body.push(to_transform(d, &attr_sets)?);
-                    Ok::<(), Error>(())
-                })?;
+            let body =
+                sequence_constructor(c.child_iter().filter(|c| !is_xsl(c, "param")), &attr_sets)?;
             newctxt.callable_push(
                 eqname,
-                Callable::new(
-                    Transform::SequenceItems(body),
-                    FormalParameters::Positional(params),
-                ),
+                Callable {
+                    package: package.clone(),
+                    ..Callable::new_with_type(
+                        Transform::SequenceItems(body),
+                        FormalParameters::Positional(params),
+                        sequence_type(&c)?,
+                    )
+                },
             );
             Ok(())
         })?;
 
-    Ok(newctxt)
-}
-
-/// Compile a node in a template to a sequence [Combinator]
+    // Determine the visibility of each component of this package, and check that the declarations
+    // of this package are consistent with the components of used packages. See XSLT 3.5.
+    let mut declared = declared_components(&declarations)?;
+    mode_names.into_iter().for_each(|m| {
+        let c = Component::Mode(m);
+        if !declared.iter().any(|(d, _)| *d == c)
+            && !used
+                .iter()
+                .any(|(_, accepted)| accepted.get(&c).is_some_and(|v| *v != Visibility::Hidden))
+        {
+            declared.push((c, stylenode.clone()))
+        }
+    });
+    // Hidden components are private to the used package, so they do not conflict with any other component
+    let mut visibility: HashMap<Component, Visibility> = HashMap::new();
+    used.iter().try_for_each(|(_, accepted)| {
+        accepted.iter().try_for_each(|(c, v)| {
+            match visibility.get(c) {
+                Some(w) if *w != Visibility::Hidden && *v != Visibility::Hidden => {
+                    return Err(Error::new_with_code(
+                        ErrorKind::StaticAbsent,
+                        format!("{} is provided by more than one used package", c),
+                        Some(QualifiedName::new(None, None, "XTSE3050")),
+                    ));
+                }
+                Some(w) if *w != Visibility::Hidden => {}
+                _ => {
+                    visibility.insert(c.clone(), *v);
+                }
+            }
+            Ok(())
+        })
+    })?;
+    declared.iter().try_for_each(|(c, d)| {
+        let is_override = d.parent().is_some_and(|o| is_xsl(&o, "override"));
+        // An override refers to the component of the used package, whatever its accepted visibility
+        match (
+            used.iter().find_map(|(pkg, accepted)| {
+                pkg.visibility(c)
+                    .filter(|_| is_override || accepted.get(c) != Some(&Visibility::Hidden))
+            }),
+            is_override,
+        ) {
+            (Some(_), false) => Err(Error::new_with_code(
+                ErrorKind::StaticAbsent,
+                format!("{} conflicts with a component of a used package", c),
+                Some(QualifiedName::new(None, None, "XTSE3050")),
+            )),
+            (None, true) => Err(Error::new_with_code(
+                ErrorKind::StaticAbsent,
+                format!("{} does not override a component of a used package", c),
+                Some(QualifiedName::new(None, None, "XTSE3058")),
+            )),
+            (Some(v), true) if v != Visibility::Public && v != Visibility::Abstract => {
+                Err(Error::new_with_code(
+                    ErrorKind::StaticAbsent,
+                    format!("{} cannot be overridden", c),
+                    Some(QualifiedName::new(None, None, "XTSE3060")),
+                ))
+            }
+            _ => Ok(()),
+        }
+    })?;
+    visibility.extend(exposed_visibility(&stylenode, &declared)?);
+
+    let package_version = stylenode
+        .get_attribute(&QualifiedName::new(None, None, "package-version"))
+        .to_string();
+    Ok(Package {
+        name: package.map(|p| p.to_string()),
+        version: if package_version.trim().is_empty() {
+            String::from("1")
+        } else {
+            package_version.trim().to_string()
+        },
+        context: newctxt,
+        attribute_sets: attr_sets,
+        visibility,
+    })
+}
+
+/// Compile a node in a template to a sequence [Combinator]
 fn to_transform<N: Node>(
     n: N,
     attr_sets: &HashMap<QualifiedName, Vec<Transform<N>>>,
@@ -689,36 +1317,14 @@ fn to_transform<N: Node>(
                         None,
                         "disable-output-escaping".to_string(),
                     ));
-                    if !doe.to_string().is_empty() {
-                        match &doe.to_string()[..] {
-                            "yes" => Ok(Transform::Literal(Item::Value(Rc::new(Value::String(
-                                n.to_string(),
-                            ))))),
-                            "no" => {
-                                let text = n
-                                    .to_string()
-                                    .replace('&', "&amp;")
-                                    .replace('>', "&gt;")
-                                    .replace('<', "&lt;")
-                                    .replace('\'', "&apos;")
-                                    .replace('\"', "&quot;");
-                                Ok(Transform::Literal(Item::Value(Rc::new(Value::from(text)))))
-                            }
-                            _ => Err(Error::new(
-                                ErrorKind::TypeError,
-                                "disable-output-escaping only accepts values yes or no."
-                                    .to_string(),
-                            )),
-                        }
-                    } else {
-                        let text = n
-                            .to_string()
-                            .replace('&', "&amp;")
-                            .replace('>', "&gt;")
-                            .replace('<', "&lt;")
-                            .replace('\'', "&apos;")
-                            .replace('\"', "&quot;");
-                        Ok(Transform::Literal(Item::Value(Rc::new(Value::from(text)))))
+                    let text = Transform::Literal(Item::Value(Rc::new(Value::from(n.to_string()))));
+                    match doe.to_string().as_str() {
+                        "yes" => Ok(Transform::LiteralText(Box::new(text), true)),
+                        "no" | "" => Ok(text),
+                        _ => Err(Error::new(
+                            ErrorKind::TypeError,
+                            "disable-output-escaping only accepts values yes or no.".to_string(),
+                        )),
                     }
                 }
                 (Some(XSLTNS), "value-of") => {
@@ -754,35 +1360,32 @@ fn to_transform<N: Node>(
                 }
                 (Some(XSLTNS), "apply-templates") => {
                     let sel = n.get_attribute(&QualifiedName::new(None, None, "select"));
-                    let m = n.get_attribute_node(&QualifiedName::new(None, None, "mode"));
+                    let m = n.get_attribute(&QualifiedName::new(None, None, "mode"));
                     let sort_keys = get_sort_keys(&n)?;
-                    if !sel.to_string().is_empty() {
-                        Ok(Transform::ApplyTemplates(
-                            Box::new(parse::<N>(&sel.to_string(), Some(n.clone()))?),
-                            m.map(|s| {
-                                Rc::new(
-                                    QualifiedName::try_from((s.to_string().as_str(), n))
-                                        .expect("unable to resolve qualified name"),
-                                )
-                            }),
-                            sort_keys,
-                        )) // TODO: don't panic
+                    let (p, tp) = with_params(&n, attr_sets)?;
+                    // If there is no select attribute, then default is "child::node()"
+                    let select = Box::new(if sel.to_string().is_empty() {
+                        Transform::Step(NodeMatch::new(Axis::Child, NodeTest::Kind(KindTest::Any)))
                     } else {
-                        // If there is no select attribute, then default is "child::node()"
-                        Ok(Transform::ApplyTemplates(
-                            Box::new(Transform::Step(NodeMatch::new(
-                                Axis::Child,
-                                NodeTest::Kind(KindTest::Any),
-                            ))),
-                            m.map(|s| {
-                                Rc::new(
-                                    QualifiedName::try_from((s.to_string().as_str(), n))
-                                        .expect("unable to resolve qualified name"),
-                                )
-                            }),
-                            sort_keys,
-                        )) // TODO: don't panic
-                    }
+                        parse::<N>(&sel.to_string(), Some(n.clone()))?
+                    });
+                    let apply = match m.to_string().trim() {
+                        "#current" => Transform::ApplyTemplatesCurrentMode(select, sort_keys),
+                        "" => Transform::ApplyTemplates(select, default_mode(&n)?, sort_keys),
+                        t => Transform::ApplyTemplates(select, mode_name(&n, t)?, sort_keys),
+                    };
+                    // Non-tunnel parameters are passed to the template rules that are invoked
+                    let apply = if p.is_empty() {
+                        apply
+                    } else {
+                        Transform::WithParameters(
+                            p.into_iter()
+                                .map(|(qn, t)| (variable_name(&qn), t))
+                                .collect(),
+                            Box::new(apply),
+                        )
+                    };
+                    Ok(with_tunnel(apply, tp))
                 }
                 (Some(XSLTNS), "apply-imports") => Ok(Transform::ApplyImports),
                 (Some(XSLTNS), "sequence") => {
@@ -802,12 +1405,9 @@ fn to_transform<N: Node>(
                         Ok(Transform::Switch(
                             vec![(
                                 parse::<N>(&t.to_string(), Some(n.clone()))?,
-                                Transform::SequenceItems(n.child_iter().try_fold(
-                                    vec![],
-                                    |mut body, e| {
-                                        body.push(to_transform(e, attr_sets)?);
-                                        Ok(body)
-                                    },
+                                Transform::SequenceItems(sequence_constructor(
+                                    n.child_iter(),
+                                    attr_sets,
                                 )?),
                             )],
                             Box::new(Transform::Empty),
@@ -838,14 +1438,7 @@ fn to_transform<N: Node>(
                                                     clauses.push((
                                                         parse::<N>(&t.to_string(), Some(n.clone()))?,
                                                         Transform::SequenceItems(
-                                                            m.child_iter()
-                                                                .try_fold(
-                                                                    vec![],
-                                                                    |mut body, e| {
-                                                                        body.push(to_transform(e, attr_sets)?);
-                                                                        Ok(body)
-                                                                    },
-                                                                )?
+                                                            sequence_constructor(m.child_iter(), attr_sets)?
                                                         )
                                                     ));
                                                 } else {
@@ -857,14 +1450,7 @@ fn to_transform<N: Node>(
                                         }
                                         (Some(XSLTNS), "otherwise") => {
                                             if !clauses.is_empty() {
-                                                otherwise = Some(Transform::SequenceItems(m.child_iter()
-                                                    .try_fold(
-                                                        vec![],
-                                                        |mut o, e| {
-                                                            o.push(to_transform(e, attr_sets)?);
-                                                            Ok(o)
-                                                        },
-                                                    )?));
+                                                otherwise = Some(Transform::SequenceItems(sequence_constructor(m.child_iter(), attr_sets)?));
                                             } else {
                                                 status.replace(Error::new(ErrorKind::TypeError, "invalid content in choose element: no when elements".to_string()));
                                             }
@@ -901,12 +1487,9 @@ fn to_transform<N: Node>(
                         Ok(Transform::ForEach(
                             None,
                             Box::new(parse::<N>(&s.to_string(), Some(n.clone()))?),
-                            Box::new(Transform::SequenceItems(n.child_iter().try_fold(
-                                vec![],
-                                |mut body, e| {
-                                    body.push(to_transform(e, attr_sets)?);
-                                    Ok(body)
-                                },
+                            Box::new(Transform::SequenceItems(sequence_constructor(
+                                n.child_iter(),
+                                attr_sets,
                             )?)),
                             get_sort_keys(&n)?,
                         ))
@@ -938,24 +1521,18 @@ fn to_transform<N: Node>(
                             (by, "", "", "") => Ok(Transform::ForEach(
                                 Some(Grouping::By(vec![parse::<N>(by, Some(n.clone()))?])),
                                 Box::new(parse::<N>(&s.to_string(), Some(n.clone()))?),
-                                Box::new(Transform::SequenceItems(n.child_iter().try_fold(
-                                    vec![],
-                                    |mut body, e| {
-                                        body.push(to_transform(e, attr_sets)?);
-                                        Ok(body)
-                                    },
+                                Box::new(Transform::SequenceItems(sequence_constructor(
+                                    n.child_iter(),
+                                    attr_sets,
                                 )?)),
                                 ord,
                             )),
                             ("", adj, "", "") => Ok(Transform::ForEach(
                                 Some(Grouping::Adjacent(vec![parse::<N>(adj, Some(n.clone()))?])),
                                 Box::new(parse::<N>(&s.to_string(), Some(n.clone()))?),
-                                Box::new(Transform::SequenceItems(n.child_iter().try_fold(
-                                    vec![],
-                                    |mut body, e| {
-                                        body.push(to_transform(e, attr_sets)?);
-                                        Ok(body)
-                                    },
+                                Box::new(Transform::SequenceItems(sequence_constructor(
+                                    n.child_iter(),
+                                    attr_sets,
                                 )?)),
                                 ord,
                             )),
@@ -975,10 +1552,7 @@ fn to_transform<N: Node>(
                 (Some(XSLTNS), "copy") => {
                     // TODO: handle select attribute
                     let mut content: Vec<Transform<N>> =
-                        n.child_iter().try_fold(vec![], |mut body, e| {
-                            body.push(to_transform(e, attr_sets)?);
-                            Ok(body)
-                        })?;
+                        sequence_constructor(n.child_iter(), attr_sets)?;
                     // Process @xsl:use-attribute-sets
                     let use_atts = n.get_attribute(&QualifiedName::new(
                         Some(XSLTNS.to_string()),
@@ -1021,53 +1595,16 @@ fn to_transform<N: Node>(
                 (Some(XSLTNS), "call-template") => {
                     let name = n.get_attribute(&QualifiedName::new(None, None, "name"));
                     if !name.to_string().is_empty() {
-                        // Iterate over the xsl:with-param elements to get the actual parameters
+                        // The xsl:with-param elements are the actual parameters
                         // TODO: validate that the children are only xsl:with-param elements
-                        let mut ap = vec![];
-                        n.child_iter()
-                            .filter(|c| {
-                                c.is_element()
-                                    && c.name().namespace_uri_to_string()
-                                        == Some(XSLTNS.to_string())
-                                    && c.name().localname_to_string() == "with-param"
-                            })
-                            .try_for_each(|c| {
-                                let wp_name =
-                                    c.get_attribute(&QualifiedName::new(None, None, "name"));
-                                if !wp_name.to_string().is_empty() {
-                                    let sel =
-                                        c.get_attribute(&QualifiedName::new(None, None, "select"));
-                                    if sel.to_string().is_empty() {
-                                        // xsl:with-param content is the sequence constructor
-                                        let mut body = vec![];
-                                        c.child_iter().try_for_each(|d| {
-                                            body.push(to_transform(d, attr_sets)?);
-                                            Ok(())
-                                        })?;
-                                        ap.push((
-                                            QualifiedName::new_from_values(None, None, wp_name),
-                                            Transform::SequenceItems(body),
-                                        ));
-                                        Ok(())
-                                    } else {
-                                        // select attribute value is an expression
-                                        ap.push((
-                                            QualifiedName::new_from_values(None, None, wp_name),
-                                            parse::<N>(&sel.to_string(), Some(n.clone()))?,
-                                        ));
-                                        Ok(())
-                                    }
-                                } else {
-                                    Err(Error::new(
-                                        ErrorKind::StaticAbsent,
-                                        "missing name attribute",
-                                    ))
-                                }
-                            })?;
-                        Ok(Transform::Invoke(
-                            Rc::new(QualifiedName::new_from_values(None, None, name)),
-                            ActualParameters::Named(ap),
-                            in_scope_namespaces(Some(n)),
+                        let (ap, tp) = with_params(&n, attr_sets)?;
+                        Ok(with_tunnel(
+                            Transform::Invoke(
+                                Rc::new(QualifiedName::new_from_values(None, None, name)),
+                                ActualParameters::Named(ap),
+                                in_scope_namespaces(Some(n)),
+                            ),
+                            tp,
                         ))
                     } else {
                         Err(Error::new(
@@ -1081,10 +1618,7 @@ fn to_transform<N: Node>(
                     if m.to_string().is_empty() {
                         return Err(Error::new(ErrorKind::TypeError, "missing name attribute"));
                     }
-                    let mut content = n.child_iter().try_fold(vec![], |mut body, e| {
-                        body.push(to_transform(e, attr_sets)?);
-                        Ok(body)
-                    })?;
+                    let mut content = sequence_constructor(n.child_iter(), attr_sets)?;
                     // Process @xsl:use-attribute-sets
                     let use_atts = n.get_attribute(&QualifiedName::new(
                         Some(XSLTNS.to_string()),
@@ -1118,12 +1652,9 @@ fn to_transform<N: Node>(
                     if !m.to_string().is_empty() {
                         Ok(Transform::LiteralAttribute(
                             Rc::new(QualifiedName::new_from_values(None, None, m)),
-                            Box::new(Transform::SequenceItems(n.child_iter().try_fold(
-                                vec![],
-                                |mut body, e| {
-                                    body.push(to_transform(e, attr_sets)?);
-                                    Ok(body)
-                                },
+                            Box::new(Transform::SequenceItems(sequence_constructor(
+                                n.child_iter(),
+                                attr_sets,
                             )?)),
                         ))
                     } else {
@@ -1131,10 +1662,7 @@ fn to_transform<N: Node>(
                     }
                 }
                 (Some(XSLTNS), "comment") => Ok(Transform::LiteralComment(Box::new(
-                    Transform::SequenceItems(n.child_iter().try_fold(vec![], |mut body, e| {
-                        body.push(to_transform(e, attr_sets)?);
-                        Ok(body)
-                    })?),
+                    Transform::SequenceItems(sequence_constructor(n.child_iter(), attr_sets)?),
                 ))),
                 (Some(XSLTNS), "processing-instruction") => {
                     let m = n.get_attribute(&QualifiedName::new(None, None, "name"));
@@ -1146,24 +1674,18 @@ fn to_transform<N: Node>(
                     }
                     Ok(Transform::LiteralProcessingInstruction(
                         Box::new(parse_avt(m.to_string().as_str(), Some(n.clone()))?),
-                        Box::new(Transform::SequenceItems(n.child_iter().try_fold(
-                            vec![],
-                            |mut body, e| {
-                                body.push(to_transform(e, attr_sets)?);
-                                Ok(body)
-                            },
+                        Box::new(Transform::SequenceItems(sequence_constructor(
+                            n.child_iter(),
+                            attr_sets,
                         )?)),
                     ))
                 }
                 (Some(XSLTNS), "message") => {
                     let t = n.get_attribute(&QualifiedName::new(None, None, "terminate"));
                     Ok(Transform::Message(
-                        Box::new(Transform::SequenceItems(n.child_iter().try_fold(
-                            vec![],
-                            |mut body, e| {
-                                body.push(to_transform(e, attr_sets)?);
-                                Ok(body)
-                            },
+                        Box::new(Transform::SequenceItems(sequence_constructor(
+                            n.child_iter(),
+                            attr_sets,
                         )?)),
                         None,
                         Box::new(Transform::Empty),
@@ -1177,20 +1699,37 @@ fn to_transform<N: Node>(
                 (Some(XSLTNS), "number") => {
                     let value = n.get_attribute(&QualifiedName::new(None, None, "value"));
                     let sel = n.get_attribute(&QualifiedName::new(None, None, "select"));
-                    let level = n.get_attribute(&QualifiedName::new(None, None, "level"));
-                    if level.to_string() != "" && level.to_string() != "single" {
-                        return Err(Error::new(
-                            ErrorKind::NotImplemented,
-                            "only single level numbering is supported",
-                        ));
-                    }
+                    let level = match n
+                        .get_attribute(&QualifiedName::new(None, None, "level"))
+                        .to_string()
+                        .trim()
+                    {
+                        "" | "single" => Level::Single,
+                        "multiple" => Level::Multiple,
+                        "any" => Level::Any,
+                        l => {
+                            return Err(Error::new_with_code(
+                                ErrorKind::StaticAbsent,
+                                format!("invalid level \"{}\"", l),
+                                Some(QualifiedName::new(None, None, "XTSE0020")),
+                            ))
+                        }
+                    };
                     let count = n.get_attribute(&QualifiedName::new(None, None, "count"));
                     let from = n.get_attribute(&QualifiedName::new(None, None, "from"));
                     let format = n.get_attribute(&QualifiedName::new(None, None, "format"));
-                    // TODO: lang, letter-value, ordinal, start-at, grouping-separator, grouping-size
+                    let format = Box::new(Transform::Literal(Item::Value(
+                        if format.to_string().is_empty() {
+                            Rc::new(Value::from("1"))
+                        } else {
+                            format
+                        },
+                    )));
+                    let nf = Box::new(number_format(&n)?);
+                    // TODO: ordinal, start-at
                     if value.to_string().is_empty() {
                         // Compute place marker
-                        Ok(Transform::FormatInteger(
+                        Ok(Transform::NumberToString(
                             Box::new(Transform::GenerateIntegers(
                                 Box::new(Transform::Empty), // start-at (TODO)
                                 Box::new(if sel.to_string().is_empty() {
@@ -1199,7 +1738,7 @@ fn to_transform<N: Node>(
                                     parse::<N>(&sel.to_string(), Some(n.clone()))?
                                 }), // select
                                 Box::new(Numbering::new(
-                                    Level::Single, // TODO: parse level attribute value
+                                    level,
                                     if count.to_string().is_empty() {
                                         None
                                     } else {
@@ -1212,31 +1751,260 @@ fn to_transform<N: Node>(
                                     },
                                 )),
                             )),
-                            Box::new(Transform::Literal(Item::Value(
-                                if format.to_string().is_empty() {
-                                    Rc::new(Value::from("1"))
-                                } else {
-                                    format
-                                },
-                            ))),
+                            format,
+                            nf,
                         ))
                     } else {
                         // Place marker is supplied
-                        Ok(Transform::FormatInteger(
+                        Ok(Transform::NumberToString(
                             Box::new(parse::<N>(&value.to_string(), Some(n.clone()))?),
-                            Box::new(Transform::Literal(Item::Value(
-                                if format.to_string().is_empty() {
-                                    Rc::new(Value::from("1"))
+                            format,
+                            nf,
+                        ))
+                    }
+                }
+                (Some(XSLTNS), "iterate") => {
+                    let sel = n.get_attribute(&QualifiedName::new(None, None, "select"));
+                    if sel.to_string().is_empty() {
+                        return Err(Error::new_with_code(
+                            ErrorKind::StaticAbsent,
+                            "xsl:iterate must have a select attribute",
+                            Some(QualifiedName::new(None, None, "XTSE0010")),
+                        ));
+                    }
+                    let params = n
+                        .child_iter()
+                        .filter(|c| is_xsl(c, "param"))
+                        .map(|c| variable_declaration(&c, attr_sets))
+                        .collect::<Result<Vec<(String, Transform<N>)>, Error>>()?;
+                    let on_completion = match n.child_iter().find(|c| is_xsl(c, "on-completion")) {
+                        Some(c) => {
+                            let oc_sel = c.get_attribute(&QualifiedName::new(None, None, "select"));
+                            if oc_sel.to_string().is_empty() {
+                                Transform::SequenceItems(sequence_constructor(
+                                    c.child_iter(),
+                                    attr_sets,
+                                )?)
+                            } else {
+                                parse::<N>(&oc_sel.to_string(), Some(c.clone()))?
+                            }
+                        }
+                        None => Transform::Empty,
+                    };
+                    Ok(Transform::Iterate(
+                        Box::new(parse::<N>(&sel.to_string(), Some(n.clone()))?),
+                        params,
+                        Box::new(Transform::SequenceItems(sequence_constructor(
+                            n.child_iter()
+                                .filter(|c| !is_xsl(c, "param") && !is_xsl(c, "on-completion")),
+                            attr_sets,
+                        )?)),
+                        Box::new(on_completion),
+                    ))
+                }
+                (Some(XSLTNS), "break") => {
+                    enclosing_iterate(&n)?;
+                    let sel = n.get_attribute(&QualifiedName::new(None, None, "select"));
+                    Ok(Transform::Break(Box::new(if sel.to_string().is_empty() {
+                        Transform::SequenceItems(sequence_constructor(n.child_iter(), attr_sets)?)
+                    } else {
+                        parse::<N>(&sel.to_string(), Some(n.clone()))?
+                    })))
+                }
+                (Some(XSLTNS), "next-iteration") => {
+                    let it = enclosing_iterate(&n)?;
+                    let params = n
+                        .child_iter()
+                        .filter(|c| is_xsl(c, "with-param"))
+                        .map(|c| variable_declaration(&c, attr_sets))
+                        .collect::<Result<Vec<(String, Transform<N>)>, Error>>()?;
+                    // Each parameter must be declared by the xsl:iterate instruction
+                    let declared = it
+                        .child_iter()
+                        .filter(|c| is_xsl(c, "param"))
+                        .map(|c| {
+                            let name = c.get_attribute(&QualifiedName::new(None, None, "name"));
+                            Ok(variable_name(&QualifiedName::try_from((
+                                name.to_string().as_str(),
+                                c.clone(),
+                            ))?))
+                        })
+                        .collect::<Result<Vec<String>, Error>>()?;
+                    if let Some((name, _)) =
+                        params.iter().find(|(name, _)| !declared.contains(name))
+                    {
+                        return Err(Error::new_with_code(
+                            ErrorKind::StaticAbsent,
+                            format!("xsl:iterate has no parameter named \"{}\"", name),
+                            Some(QualifiedName::new(None, None, "XTSE3130")),
+                        ));
+                    }
+                    Ok(Transform::NextIteration(params))
+                }
+                (Some(XSLTNS), "analyze-string") => {
+                    let s = n.get_attribute(&QualifiedName::new(None, None, "select"));
+                    let r = n.get_attribute(&QualifiedName::new(None, None, "regex"));
+                    let f = n.get_attribute(&QualifiedName::new(None, None, "flags"));
+                    if s.to_string().is_empty() || r.to_string().is_empty() {
+                        return Err(Error::new_with_code(
+                            ErrorKind::StaticAbsent,
+                            "xsl:analyze-string must have select and regex attributes",
+                            Some(QualifiedName::new(None, None, "XTSE0010")),
+                        ));
+                    }
+                    let substring = |name: &str| -> Result<Option<Transform<N>>, Error> {
+                        n.child_iter()
+                            .find(|c| is_xsl(c, name))
+                            .map(|c| {
+                                Ok(Transform::SequenceItems(sequence_constructor(
+                                    c.child_iter(),
+                                    attr_sets,
+                                )?))
+                            })
+                            .transpose()
+                    };
+                    let matching = substring("matching-substring")?;
+                    let non_matching = substring("non-matching-substring")?;
+                    if matching.is_none() && non_matching.is_none() {
+                        return Err(Error::new_with_code(
+                            ErrorKind::StaticAbsent,
+                            "xsl:analyze-string must have xsl:matching-substring or xsl:non-matching-substring",
+                            Some(QualifiedName::new(None, None, "XTSE1130")),
+                        ));
+                    }
+                    Ok(Transform::AnalyzeStringInstruction(
+                        Box::new(parse::<N>(&s.to_string(), Some(n.clone()))?),
+                        Box::new(parse_avt(r.to_string().as_str(), Some(n.clone()))?),
+                        Box::new(if f.to_string().is_empty() {
+                            Transform::Literal(Item::Value(Rc::new(Value::from(""))))
+                        } else {
+                            parse_avt(f.to_string().as_str(), Some(n.clone()))?
+                        }),
+                        Box::new(matching.unwrap_or(Transform::Empty)),
+                        Box::new(non_matching.unwrap_or(Transform::Empty)),
+                    ))
+                }
+                (Some(XSLTNS), "merge") => {
+                    let sources = n
+                        .child_iter()
+                        .filter(|c| is_xsl(c, "merge-source"))
+                        .map(|c| merge_source(&c))
+                        .collect::<Result<Vec<MergeSource<N>>, Error>>()?;
+                    let actions: Vec<N> = n
+                        .child_iter()
+                        .filter(|c| is_xsl(c, "merge-action"))
+                        .collect();
+                    if sources.is_empty() || actions.len() != 1 {
+                        return Err(Error::new_with_code(
+                            ErrorKind::StaticAbsent,
+                            "xsl:merge must have at least one xsl:merge-source and one xsl:merge-action",
+                            Some(QualifiedName::new(None, None, "XTSE0010")),
+                        ));
+                    }
+                    if sources
+                        .iter()
+                        .any(|m| m.keys.len() != sources[0].keys.len())
+                    {
+                        return Err(Error::new_with_code(
+                            ErrorKind::StaticAbsent,
+                            "merge sources have different numbers of merge keys",
+                            Some(QualifiedName::new(None, None, "XTSE2200")),
+                        ));
+                    }
+                    // Corresponding merge keys must compare their values in the same way
+                    let key_attributes = |m: &N| -> Vec<Vec<String>> {
+                        m.child_iter()
+                            .filter(|k| is_xsl(k, "merge-key"))
+                            .map(|k| merge_key_attributes(&k))
+                            .collect()
+                    };
+                    let mut merge_sources = n.child_iter().filter(|c| is_xsl(c, "merge-source"));
+                    if let Some(first) = merge_sources.next().map(|m| key_attributes(&m)) {
+                        if merge_sources.any(|m| key_attributes(&m) != first) {
+                            return Err(Error::new_with_code(
+                                ErrorKind::StaticAbsent,
+                                "merge sources have incompatible merge keys",
+                                Some(QualifiedName::new(None, None, "XTDE2210")),
+                            ));
+                        }
+                    }
+                    if let Some(name) = sources.iter().enumerate().find_map(|(i, m)| {
+                        m.name.as_ref().filter(|name| {
+                            sources[..i].iter().any(|o| o.name.as_ref() == Some(name))
+                        })
+                    }) {
+                        return Err(Error::new_with_code(
+                            ErrorKind::StaticAbsent,
+                            format!("duplicate merge source name \"{}\"", name),
+                            Some(QualifiedName::new(None, None, "XTSE3190")),
+                        ));
+                    }
+                    Ok(Transform::Merge(
+                        sources,
+                        Box::new(Transform::SequenceItems(sequence_constructor(
+                            actions[0].child_iter(),
+                            attr_sets,
+                        )?)),
+                    ))
+                }
+                (Some(XSLTNS), "try") => {
+                    let is_catch = |c: &N| is_xsl(c, "catch");
+                    let catches = n
+                        .child_iter()
+                        .filter(is_catch)
+                        .map(|c| {
+                            let c_sel = c.get_attribute(&QualifiedName::new(None, None, "select"));
+                            Ok((
+                                error_name_tests(&c)?,
+                                if c_sel.to_string().is_empty() {
+                                    Transform::SequenceItems(sequence_constructor(
+                                        c.child_iter(),
+                                        attr_sets,
+                                    )?)
                                 } else {
-                                    format
+                                    parse::<N>(&c_sel.to_string(), Some(c.clone()))?
                                 },
-                            ))),
-                        ))
+                            ))
+                        })
+                        .collect::<Result<Vec<(Vec<NameTest>, Transform<N>)>, Error>>()?;
+                    if catches.is_empty() {
+                        return Err(Error::new_with_code(
+                            ErrorKind::StaticAbsent,
+                            "xsl:try must have at least one xsl:catch",
+                            Some(QualifiedName::new(None, None, "XTSE0010")),
+                        ));
                     }
+                    let sel = n.get_attribute(&QualifiedName::new(None, None, "select"));
+                    let rollback =
+                        n.get_attribute(&QualifiedName::new(None, None, "rollback-output"));
+                    Ok(Transform::Try(
+                        Box::new(if sel.to_string().is_empty() {
+                            Transform::SequenceItems(sequence_constructor(
+                                n.child_iter().filter(|c| !is_catch(c)),
+                                attr_sets,
+                            )?)
+                        } else {
+                            parse::<N>(&sel.to_string(), Some(n.clone()))?
+                        }),
+                        catches,
+                        rollback.to_string().is_empty() || yes_or_no(&rollback.to_string())?,
+                    ))
+                }
+                (Some(XSLTNS), "result-document") => {
+                    let href = n.get_attribute(&QualifiedName::new(None, None, "href"));
+                    Ok(Transform::ResultDocument(
+                        Box::new(parse_avt(href.to_string().as_str(), Some(n.clone()))?),
+                        Box::new(output_definition(&n, unnamed_output(&n)?)?),
+                        Box::new(Transform::SequenceItems(sequence_constructor(
+                            n.child_iter(),
+                            attr_sets,
+                        )?)),
+                    ))
+                }
+                // A declaration, processed by from_document
+                (Some(XSLTNS), "decimal-format") | (Some(XSLTNS), "accumulator") => {
+                    Ok(Transform::Empty)
                 }
-                (Some(XSLTNS), "decimal-format") => Ok(Transform::NotImplemented(String::from(
-                    "unsupported XSL element \"decimal-format\"",
-                ))),
                 (Some(XSLTNS), u) => Ok(Transform::NotImplemented(format!(
                     "unsupported XSL element \"{}\"",
                     u
@@ -1266,10 +2034,7 @@ fn to_transform<N: Node>(
                             content.push(to_transform(e, attr_sets)?);
                             Ok::<(), Error>(())
                         })?;
-                    n.child_iter().try_for_each(|e| {
-                        content.push(to_transform(e, attr_sets)?);
-                        Ok::<(), Error>(())
-                    })?;
+                    content.append(&mut sequence_constructor(n.child_iter(), attr_sets)?);
                     Ok(Transform::LiteralElement(
                         Rc::new(QualifiedName::new_from_values(
                             u.map(|v| Rc::new(Value::from(v))),
@@ -1305,6 +2070,1243 @@ fn to_transform<N: Node>(
     }
 }
 
+/// Compile a sequence constructor.
+/// A variable declared by an xsl:variable element is in scope for the following siblings of that element.
+/// The parameters of a template rule are declared in the same way.
+fn sequence_constructor<N: Node, I: Iterator<Item = N>>(
+    children: I,
+    attr_sets: &HashMap<QualifiedName, Vec<Transform<N>>>,
+) -> Result<Vec<Transform<N>>, Error> {
+    let nodes: Vec<N> = children.collect();
+    nodes.into_iter().rev().try_fold(vec![], |mut body, c| {
+        if is_xsl(&c, "variable") || is_xsl(&c, "param") {
+            let (name, value) = variable_declaration(&c, attr_sets)?;
+            Ok(vec![Transform::VariableDeclaration(
+                name,
+                Box::new(value),
+                Box::new(Transform::SequenceItems(body)),
+                in_scope_namespaces(Some(c)),
+            )])
+        } else {
+            body.insert(0, to_transform(c, attr_sets)?);
+            Ok(body)
+        }
+    })
+}
+
+/// Compile an xsl:variable or xsl:param element, returning the name and the value of the variable.
+/// If the variable has content, and no as attribute, then its value is a temporary tree.
+/// If there is an as attribute then the value is converted to that type.
+/// A tunnel parameter takes its value from the tunnel parameters of the template, if one was supplied.
+fn variable_declaration<N: Node>(
+    n: &N,
+    attr_sets: &HashMap<QualifiedName, Vec<Transform<N>>>,
+) -> Result<(String, Transform<N>), Error> {
+    let name = n.get_attribute(&QualifiedName::new(None, None, "name"));
+    if name.to_string().is_empty() {
+        return Err(Error::new(
+            ErrorKind::StaticAbsent,
+            "name attribute is missing",
+        ));
+    }
+    let qn = QualifiedName::try_from((name.to_string().as_str(), n.clone()))?;
+    let sel = n.get_attribute(&QualifiedName::new(None, None, "select"));
+    let as_type = n.get_attribute(&QualifiedName::new(None, None, "as"));
+    let has_content = n.child_iter().next().is_some();
+    let value = match (sel.to_string().is_empty(), has_content) {
+        (false, true) => {
+            return Err(Error::new_with_code(
+                ErrorKind::StaticAbsent,
+                format!(
+                    "variable \"{}\" has both a select attribute and content",
+                    name
+                ),
+                Some(QualifiedName::new(None, None, "XTSE0620")),
+            ))
+        }
+        (false, false) => parse::<N>(&sel.to_string(), Some(n.clone()))?,
+        (true, true) => {
+            let body = Transform::SequenceItems(sequence_constructor(n.child_iter(), attr_sets)?);
+            if as_type.to_string().is_empty() {
+                Transform::DocumentNode(Box::new(body))
+            } else {
+                body
+            }
+        }
+        (true, false) => {
+            if as_type.to_string().is_empty() {
+                Transform::Literal(Item::Value(Rc::new(Value::from(""))))
+            } else {
+                Transform::Empty
+            }
+        }
+    };
+    let name = variable_name(&qn);
+    let tunnel = n.name().localname_to_string() == "param" && is_tunnel(n)?;
+    let value = if tunnel {
+        Transform::TunnelParameter(name.clone(), Box::new(value))
+    } else if n.name().localname_to_string() == "param"
+        && n.parent().is_some_and(|t| is_xsl(&t, "template"))
+    {
+        Transform::TemplateParameter(name.clone(), Box::new(value))
+    } else {
+        value
+    };
+    let value = match sequence_type(n)? {
+        Some(t) => Transform::Convert(
+            Box::new(value),
+            t,
+            String::from(if tunnel { "XTTE0590" } else { "XTTE0570" }),
+            has_content,
+        ),
+        None => value,
+    };
+    Ok((name, value))
+}
+
+/// Compile an xsl:merge-source element.
+/// A for-each-source attribute gives the URIs of the documents that are the inputs.
+fn merge_source<N: Node>(n: &N) -> Result<MergeSource<N>, Error> {
+    let attr = |a: &str| {
+        let v = n
+            .get_attribute(&QualifiedName::new(None, None, a))
+            .to_string();
+        if v.is_empty() {
+            None
+        } else {
+            Some(v)
+        }
+    };
+    let select = attr("select").ok_or_else(|| {
+        Error::new_with_code(
+            ErrorKind::StaticAbsent,
+            "xsl:merge-source must have a select attribute",
+            Some(QualifiedName::new(None, None, "XTSE0010")),
+        )
+    })?;
+    let for_each =
+        match (attr("for-each-item"), attr("for-each-source")) {
+            (Some(_), Some(_)) => return Err(Error::new_with_code(
+                ErrorKind::StaticAbsent,
+                "xsl:merge-source cannot have both for-each-item and for-each-source attributes",
+                Some(QualifiedName::new(None, None, "XTSE3195")),
+            )),
+            (Some(i), None) => Some(parse::<N>(&i, Some(n.clone()))?),
+            (None, Some(s)) => Some(Transform::Document(
+                Box::new(parse::<N>(&s, Some(n.clone()))?),
+                None,
+            )),
+            (None, None) => None,
+        };
+    let keys = n
+        .child_iter()
+        .filter(|c| is_xsl(c, "merge-key"))
+        .map(|c| merge_key(&c))
+        .collect::<Result<Vec<(Order, Transform<N>)>, Error>>()?;
+    if keys.is_empty() {
+        return Err(Error::new_with_code(
+            ErrorKind::StaticAbsent,
+            "xsl:merge-source must have at least one xsl:merge-key",
+            Some(QualifiedName::new(None, None, "XTSE0010")),
+        ));
+    }
+    Ok(MergeSource::new(
+        attr("name"),
+        for_each,
+        parse::<N>(&select, Some(n.clone()))?,
+        match attr("sort-before-merge") {
+            Some(v) => yes_or_no(v.trim())?,
+            None => false,
+        },
+        keys,
+    ))
+}
+
+/// Compile an xsl:merge-key element.
+/// A data-type attribute converts the merge key value to a string or a number before it is compared.
+/// Only the Unicode codepoint collation is supported.
+fn merge_key<N: Node>(c: &N) -> Result<(Order, Transform<N>), Error> {
+    let attr = |a: &str| {
+        c.get_attribute(&QualifiedName::new(None, None, a))
+            .to_string()
+            .trim()
+            .to_string()
+    };
+    let order = match attr("order").as_str() {
+        "" | "ascending" => Order::Ascending,
+        "descending" => Order::Descending,
+        o => {
+            return Err(Error::new_with_code(
+                ErrorKind::StaticAbsent,
+                format!("invalid order \"{}\"", o),
+                Some(QualifiedName::new(None, None, "XTSE0020")),
+            ))
+        }
+    };
+    let collation = attr("collation");
+    if collation.is_empty() {
+        // The lang and case-order attributes select a collation, but only the codepoint collation is supported
+        if let Some(a) = ["lang", "case-order"].iter().find(|a| !attr(a).is_empty()) {
+            return Err(Error::new(
+                ErrorKind::NotImplemented,
+                format!("xsl:merge-key/@{} is not supported", a),
+            ));
+        }
+    } else if collation != CODEPOINT_COLLATION {
+        return Err(Error::new_with_code(
+            ErrorKind::StaticAbsent,
+            format!("collation \"{}\" is not supported", collation),
+            Some(QualifiedName::new(None, None, "XTDE1035")),
+        ));
+    }
+    let sel = attr("select");
+    let key = if sel.is_empty() {
+        Transform::ContextItem
+    } else {
+        parse::<N>(&sel, Some(c.clone()))?
+    };
+    let key = match attr("data-type").as_str() {
+        "" => key,
+        "text" => Transform::String(Box::new(key)),
+        "number" => Transform::Number(Box::new(key)),
+        d => {
+            return Err(Error::new_with_code(
+                ErrorKind::StaticAbsent,
+                format!("invalid data-type \"{}\"", d),
+                Some(QualifiedName::new(None, None, "XTSE0020")),
+            ))
+        }
+    };
+    Ok((order, key))
+}
+
+/// The effective values of the attributes of an xsl:merge-key element that determine the merge order.
+fn merge_key_attributes<N: Node>(c: &N) -> Vec<String> {
+    [
+        ("order", "ascending"),
+        ("data-type", ""),
+        ("collation", CODEPOINT_COLLATION),
+        ("lang", ""),
+        ("case-order", ""),
+    ]
+    .iter()
+    .map(|(a, d)| {
+        let v = c
+            .get_attribute(&QualifiedName::new(None, None, *a))
+            .to_string()
+            .trim()
+            .to_string();
+        if v.is_empty() {
+            d.to_string()
+        } else {
+            v
+        }
+    })
+    .collect()
+}
+
+/// Compile an xsl:accumulator declaration. The accumulator rules are kept in declaration order.
+fn accumulator<N: Node>(
+    n: &N,
+    attr_sets: &HashMap<QualifiedName, Vec<Transform<N>>>,
+) -> Result<Accumulator<N>, Error> {
+    let iv = n.get_attribute(&QualifiedName::new(None, None, "initial-value"));
+    if iv.to_string().is_empty() {
+        return Err(Error::new_with_code(
+            ErrorKind::StaticAbsent,
+            "xsl:accumulator must have an initial-value attribute",
+            Some(QualifiedName::new(None, None, "XTSE0010")),
+        ));
+    }
+    let rules = n
+        .child_iter()
+        .filter(|c| is_xsl(c, "accumulator-rule"))
+        .map(|c| {
+            let m = c.get_attribute(&QualifiedName::new(None, None, "match"));
+            let pat = Pattern::try_from(m.to_string()).map_err(|e| {
+                Error::new(
+                    e.kind,
+                    format!("Error parsing match pattern \"{}\": {}", m, e.message),
+                )
+            })?;
+            if pat.is_err() {
+                return Err(pat.get_err().unwrap());
+            }
+            let ph = c.get_attribute(&QualifiedName::new(None, None, "phase"));
+            let phase = if ph.to_string().is_empty() {
+                Phase::Start
+            } else {
+                Phase::try_from(ph.to_string().as_str()).map_err(|e| {
+                    Error::new_with_code(
+                        ErrorKind::StaticAbsent,
+                        e,
+                        Some(QualifiedName::new(None, None, "XTSE0020")),
+                    )
+                })?
+            };
+            let sel = c.get_attribute(&QualifiedName::new(None, None, "select"));
+            let select = if sel.to_string().is_empty() {
+                Transform::SequenceItems(sequence_constructor(c.child_iter(), attr_sets)?)
+            } else {
+                parse::<N>(&sel.to_string(), Some(c.clone()))?
+            };
+            Ok(AccumulatorRule::new(pat, phase, select))
+        })
+        .collect::<Result<Vec<AccumulatorRule<N>>, Error>>()?;
+    Ok(Accumulator::new(
+        parse::<N>(&iv.to_string(), Some(n.clone()))?,
+        sequence_type(n)?,
+        rules,
+    ))
+}
+
+/// Compile the as attribute of a declaration, if it has one.
+fn sequence_type<N: Node>(n: &N) -> Result<Option<SequenceType>, Error> {
+    let t = n.get_attribute(&QualifiedName::new(None, None, "as"));
+    if t.to_string().is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(parse_sequence_type(&t.to_string(), Some(n.clone()))?))
+    }
+}
+
+/// Whether an xsl:param or xsl:with-param element is a tunnel parameter.
+fn is_tunnel<N: Node>(n: &N) -> Result<bool, Error> {
+    let t = n.get_attribute(&QualifiedName::new(None, None, "tunnel"));
+    match t.to_string().trim() {
+        "" => Ok(false),
+        v => yes_or_no(v),
+    }
+}
+
+/// Compile the xsl:with-param children of an instruction.
+/// Returns the ordinary parameters and the tunnel parameters.
+#[allow(clippy::type_complexity)]
+fn with_params<N: Node>(
+    n: &N,
+    attr_sets: &HashMap<QualifiedName, Vec<Transform<N>>>,
+) -> Result<
+    (
+        Vec<(QualifiedName, Transform<N>)>,
+        Vec<(String, Transform<N>)>,
+    ),
+    Error,
+> {
+    let mut params = vec![];
+    let mut tunnel = vec![];
+    n.child_iter()
+        .filter(|c| is_xsl(c, "with-param"))
+        .try_for_each(|c| {
+            let wp_name = c.get_attribute(&QualifiedName::new(None, None, "name"));
+            if wp_name.to_string().is_empty() {
+                return Err(Error::new(
+                    ErrorKind::StaticAbsent,
+                    "missing name attribute",
+                ));
+            }
+            let sel = c.get_attribute(&QualifiedName::new(None, None, "select"));
+            let value = if sel.to_string().is_empty() {
+                // xsl:with-param content is the sequence constructor
+                Transform::SequenceItems(sequence_constructor(c.child_iter(), attr_sets)?)
+            } else {
+                // select attribute value is an expression
+                parse::<N>(&sel.to_string(), Some(c.clone()))?
+            };
+            let value = match sequence_type(&c)? {
+                Some(t) => Transform::Convert(
+                    Box::new(value),
+                    t,
+                    String::from("XTTE0570"),
+                    sel.to_string().is_empty(),
+                ),
+                None => value,
+            };
+            if is_tunnel(&c)? {
+                tunnel.push((
+                    variable_name(&QualifiedName::try_from((
+                        wp_name.to_string().as_str(),
+                        c.clone(),
+                    ))?),
+                    value,
+                ));
+            } else {
+                params.push((
+                    QualifiedName::try_from((wp_name.to_string().as_str(), c.clone()))?,
+                    value,
+                ));
+            }
+            Ok(())
+        })?;
+    Ok((params, tunnel))
+}
+
+/// Pass tunnel parameters to the templates invoked by an instruction.
+fn with_tunnel<N: Node>(t: Transform<N>, tunnel: Vec<(String, Transform<N>)>) -> Transform<N> {
+    if tunnel.is_empty() {
+        t
+    } else {
+        Transform::Tunnel(tunnel, Box::new(t))
+    }
+}
+
+/// Determine how a result document is to be serialised, from the attributes of an xsl:output or xsl:result-document element.
+/// Serialization parameters that are not specified are taken from the given definition.
+fn output_definition<N: Node>(n: &N, mut od: OutputDefinition) -> Result<OutputDefinition, Error> {
+    let attr = |a: &str| {
+        let v = n
+            .get_attribute(&QualifiedName::new(None, None, a))
+            .to_string();
+        if v.is_empty() {
+            None
+        } else {
+            Some(v)
+        }
+    };
+    let names = |v: String| {
+        v.split_whitespace()
+            .map(|s| QualifiedName::try_from((s, n.clone())))
+            .collect::<Result<Vec<QualifiedName>, Error>>()
+    };
+    if let Some(m) = attr("method") {
+        od.set_method(OutputMethod::try_from(m.as_str()).map_err(|e| {
+            Error::new_with_code(
+                ErrorKind::StaticAbsent,
+                e,
+                Some(QualifiedName::new(None, None, "XTSE1570")),
+            )
+        })?);
+        if od.get_method() == OutputMethod::Adaptive {
+            return Err(Error::new_with_code(
+                ErrorKind::NotImplemented,
+                "the adaptive output method is not supported",
+                Some(QualifiedName::new(None, None, "SEPM0016")),
+            ));
+        }
+    }
+    if let Some(v) = attr("indent") {
+        od.set_indent(yes_or_no(&v)?)
+    }
+    if let Some(v) = attr("omit-xml-declaration") {
+        od.set_omit_xml_declaration(yes_or_no(&v)?)
+    }
+    if let Some(v) = attr("standalone") {
+        if v.trim() == "omit" {
+            od.set_standalone(None)
+        } else {
+            od.set_standalone(Some(yes_or_no(&v)?))
+        }
+    }
+    if let Some(v) = attr("doctype-public") {
+        od.set_doctype_public(Some(v))
+    }
+    if let Some(v) = attr("doctype-system") {
+        od.set_doctype_system(Some(v))
+    }
+    if let Some(v) = attr("cdata-section-elements") {
+        let mut c = od.get_cdata_section_elements().clone();
+        c.append(&mut names(v)?);
+        od.set_cdata_section_elements(c)
+    }
+    if let Some(v) = attr("suppress-indentation") {
+        let mut c = od.get_suppress_indentation().clone();
+        c.append(&mut names(v)?);
+        od.set_suppress_indentation(c)
+    }
+    if let Some(v) = attr("encoding") {
+        od.set_encoding(v.trim().to_string())
+    }
+    if let Some(v) = attr("byte-order-mark") {
+        od.set_byte_order_mark(yes_or_no(&v)?)
+    }
+    if let Some(v) = attr("use-character-maps") {
+        let mut cm = od.get_character_map().clone();
+        for name in names(v)? {
+            cm.extend(character_map(n, &name, &mut vec![])?)
+        }
+        od.set_character_map(cm)
+    }
+    if let Some(v) = attr("item-separator") {
+        if v == "#absent" {
+            od.set_item_separator(None)
+        } else {
+            od.set_item_separator(Some(v))
+        }
+    }
+    if let Some(v) = attr("escape-uri-attributes") {
+        od.set_escape_uri_attributes(yes_or_no(&v)?)
+    }
+    if let Some(v) = attr("include-content-type") {
+        od.set_include_content_type(yes_or_no(&v)?)
+    }
+    if let Some(v) = attr("html-version") {
+        od.set_html_version(Some(v.trim().parse::<f64>().map_err(|_| {
+            Error::new_with_code(
+                ErrorKind::StaticAbsent,
+                format!("invalid html-version \"{}\"", v),
+                Some(QualifiedName::new(None, None, "XTSE0020")),
+            )
+        })?))
+    }
+    Ok(od)
+}
+
+/// The output definition given by the unnamed xsl:output declaration of the stylesheet that contains the given node.
+fn unnamed_output<N: Node>(n: &N) -> Result<OutputDefinition, Error> {
+    n.owner_document()
+        .child_iter()
+        .find(|c| c.is_element())
+        .and_then(|s| {
+            s.child_iter().find(|c| {
+                is_xsl(c, "output")
+                    && c.get_attribute(&QualifiedName::new(None, None, "name"))
+                        .to_string()
+                        .is_empty()
+            })
+        })
+        .map_or_else(
+            || Ok(OutputDefinition::new()),
+            |o| output_definition(&o, OutputDefinition::new()),
+        )
+}
+
+/// Find the named xsl:character-map in the stylesheet that contains the given node,
+/// and combine it with the character maps that it uses.
+/// The stack of character map names is used to detect circular references.
+fn character_map<N: Node>(
+    n: &N,
+    name: &QualifiedName,
+    stack: &mut Vec<QualifiedName>,
+) -> Result<HashMap<char, String>, Error> {
+    if stack.contains(name) {
+        return Err(Error::new_with_code(
+            ErrorKind::StaticAbsent,
+            format!("character map \"{}\" refers to itself", name),
+            Some(QualifiedName::new(None, None, "XTSE1600")),
+        ));
+    }
+    let cm = n
+        .owner_document()
+        .child_iter()
+        .find(|c| c.is_element())
+        .and_then(|s| {
+            s.child_iter().find(|c| {
+                is_xsl(c, "character-map")
+                    && QualifiedName::try_from((
+                        c.get_attribute(&QualifiedName::new(None, None, "name"))
+                            .to_string()
+                            .as_str(),
+                        c.clone(),
+                    ))
+                    .is_ok_and(|qn| qn == *name)
+            })
+        })
+        .ok_or_else(|| {
+            Error::new_with_code(
+                ErrorKind::StaticAbsent,
+                format!("no character map named \"{}\"", name),
+                Some(QualifiedName::new(None, None, "XTSE1590")),
+            )
+        })?;
+    stack.push(name.clone());
+    let mut result = HashMap::new();
+    for u in cm
+        .get_attribute(&QualifiedName::new(None, None, "use-character-maps"))
+        .to_string()
+        .split_whitespace()
+    {
+        result.extend(character_map(
+            n,
+            &QualifiedName::try_from((u, cm.clone()))?,
+            stack,
+        )?)
+    }
+    stack.pop();
+    for oc in cm.child_iter().filter(|c| is_xsl(c, "output-character")) {
+        let ch = oc
+            .get_attribute(&QualifiedName::new(None, None, "character"))
+            .to_string();
+        let mut chars = ch.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => {
+                result.insert(
+                    c,
+                    oc.get_attribute(&QualifiedName::new(None, None, "string"))
+                        .to_string(),
+                );
+            }
+            _ => {
+                return Err(Error::new_with_code(
+                    ErrorKind::StaticAbsent,
+                    format!(
+                        "output-character must be a single character, not \"{}\"",
+                        ch
+                    ),
+                    Some(QualifiedName::new(None, None, "XTSE0020")),
+                ))
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Create a decimal format from the attributes of an xsl:decimal-format declaration.
+fn decimal_format<N: Node>(n: &N) -> Result<DecimalFormat, Error> {
+    let mut df = DecimalFormat::default();
+    let attr = |a: &str| {
+        let v = n
+            .get_attribute(&QualifiedName::new(None, None, a))
+            .to_string();
+        if v.is_empty() {
+            None
+        } else {
+            Some(v)
+        }
+    };
+    let character = |a: &str, d: char| -> Result<char, Error> {
+        match attr(a) {
+            None => Ok(d),
+            Some(v) => {
+                let mut c = v.chars();
+                match (c.next(), c.next()) {
+                    (Some(ch), None) => Ok(ch),
+                    _ => Err(Error::new_with_code(
+                        ErrorKind::StaticAbsent,
+                        format!("{} must be a single character, not \"{}\"", a, v),
+                        Some(QualifiedName::new(None, None, "XTSE0020")),
+                    )),
+                }
+            }
+        }
+    };
+    df.decimal_separator = character("decimal-separator", df.decimal_separator)?;
+    df.grouping_separator = character("grouping-separator", df.grouping_separator)?;
+    df.exponent_separator = character("exponent-separator", df.exponent_separator)?;
+    df.minus_sign = character("minus-sign", df.minus_sign)?;
+    df.percent = character("percent", df.percent)?;
+    df.per_mille = character("per-mille", df.per_mille)?;
+    df.zero_digit = character("zero-digit", df.zero_digit)?;
+    df.digit = character("digit", df.digit)?;
+    df.pattern_separator = character("pattern-separator", df.pattern_separator)?;
+    if let Some(i) = attr("infinity") {
+        df.infinity = i
+    }
+    if let Some(nan) = attr("NaN") {
+        df.nan = nan
+    }
+    if !(df.zero_digit == '0' || (!df.zero_digit.is_ascii() && df.zero_digit.is_numeric())) {
+        return Err(Error::new_with_code(
+            ErrorKind::StaticAbsent,
+            format!("zero-digit \"{}\" is not a digit", df.zero_digit),
+            Some(QualifiedName::new(None, None, "XTSE1295")),
+        ));
+    }
+    // The characters used in a picture string must be distinct.
+    let zero = df.zero_digit as u32;
+    let pictures = [
+        df.decimal_separator,
+        df.grouping_separator,
+        df.exponent_separator,
+        df.percent,
+        df.per_mille,
+        df.digit,
+        df.pattern_separator,
+    ];
+    if pictures.iter().enumerate().any(|(i, c)| {
+        pictures[i + 1..].contains(c) || (*c as u32 >= zero && (*c as u32) < zero + 10)
+    }) {
+        return Err(Error::new_with_code(
+            ErrorKind::StaticAbsent,
+            String::from("decimal format picture characters are not distinct"),
+            Some(QualifiedName::new(None, None, "XTSE1300")),
+        ));
+    }
+    Ok(df)
+}
+
+// The name tests given by the errors attribute of xsl:catch. The default is to match any error.
+fn error_name_tests<N: Node>(n: &N) -> Result<Vec<NameTest>, Error> {
+    let errors = n
+        .get_attribute(&QualifiedName::new(None, None, "errors"))
+        .to_string();
+    let name = |s: &str| Some(WildcardOrName::Name(Rc::new(Value::from(s))));
+    errors
+        .split_whitespace()
+        .chain(if errors.trim().is_empty() {
+            Some("*")
+        } else {
+            None
+        })
+        .map(|t| {
+            if t == "*" {
+                Ok(NameTest::new(
+                    Some(WildcardOrName::Wildcard),
+                    None,
+                    Some(WildcardOrName::Wildcard),
+                ))
+            } else if let Some(l) = t.strip_prefix("*:") {
+                Ok(NameTest::new(Some(WildcardOrName::Wildcard), None, name(l)))
+            } else if let Some(u) = t.strip_prefix("Q{").and_then(|u| u.strip_suffix("}*")) {
+                Ok(NameTest::new(name(u), None, Some(WildcardOrName::Wildcard)))
+            } else if let Some(p) = t.strip_suffix(":*") {
+                match n
+                    .namespace_iter()
+                    .find(|ns| ns.name().localname_to_string() == p)
+                {
+                    Some(ns) => Ok(NameTest::new(
+                        Some(WildcardOrName::Name(ns.value())),
+                        Some(Rc::new(Value::from(p))),
+                        Some(WildcardOrName::Wildcard),
+                    )),
+                    None => Err(Error::new_with_code(
+                        ErrorKind::StaticAbsent,
+                        format!("namespace prefix \"{}\" is not declared", p),
+                        Some(QualifiedName::new(None, None, "XTSE0280")),
+                    )),
+                }
+            } else {
+                let qn = QualifiedName::try_from((t, n.clone()))?;
+                Ok(NameTest::new(
+                    qn.namespace_uri().map(WildcardOrName::Name),
+                    qn.prefix(),
+                    Some(WildcardOrName::Name(qn.localname())),
+                ))
+            }
+        })
+        .collect()
+}
+
+// Find the xsl:iterate instruction that contains an xsl:break or xsl:next-iteration instruction.
+fn enclosing_iterate<N: Node>(n: &N) -> Result<N, Error> {
+    n.ancestor_iter()
+        .find(|a| is_xsl(a, "iterate"))
+        .ok_or_else(|| {
+            Error::new_with_code(
+                ErrorKind::StaticAbsent,
+                format!(
+                    "xsl:{} is not within an xsl:iterate instruction",
+                    n.name().localname_to_string()
+                ),
+                Some(QualifiedName::new(None, None, "XTSE3120")),
+            )
+        })
+}
+
+// The properties of a mode declared by xsl:mode.
+fn mode<N: Node>(n: &N) -> Result<Mode, Error> {
+    let attr = |a: &str| {
+        n.get_attribute(&QualifiedName::new(None, None, a))
+            .to_string()
+    };
+    let invalid = |a: &str, e: String| {
+        Error::new_with_code(
+            ErrorKind::StaticAbsent,
+            format!("invalid {} attribute: {}", a, e),
+            Some(QualifiedName::new(None, None, "XTSE0020")),
+        )
+    };
+    let flag = |a: &str| -> Result<bool, Error> {
+        let v = attr(a);
+        if v.is_empty() {
+            Ok(false)
+        } else {
+            yes_or_no(&v)
+        }
+    };
+    let mut m = Mode::default();
+    let onm = attr("on-no-match");
+    if !onm.is_empty() {
+        m.on_no_match = OnNoMatch::try_from(onm.as_str()).map_err(|e| invalid("on-no-match", e))?;
+    }
+    m.on_multiple_match_fail = match attr("on-multiple-match").trim() {
+        "" | "use-last" => false,
+        "fail" => true,
+        v => {
+            return Err(invalid(
+                "on-multiple-match",
+                format!("unknown value \"{}\"", v),
+            ))
+        }
+    };
+    m.warning_on_no_match = flag("warning-on-no-match")?;
+    m.warning_on_multiple_match = flag("warning-on-multiple-match")?;
+    m.streamable = flag("streamable")?;
+    let typed = attr("typed");
+    if !typed.is_empty() {
+        m.typed = Typed::try_from(typed.as_str()).map_err(|e| invalid("typed", e))?;
+    }
+    Ok(m)
+}
+
+// The default mode, given by the [xsl:]default-mode attribute of the innermost element that has one.
+// See XSLT 3.7.2.
+fn default_mode<N: Node>(n: &N) -> Result<Option<Rc<QualifiedName>>, Error> {
+    let mut e = Some(n.clone());
+    while let Some(a) = e {
+        if a.is_element() {
+            let dm = if a.name().namespace_uri_to_string() == Some(XSLTNS.to_string()) {
+                a.get_attribute(&QualifiedName::new(None, None, "default-mode"))
+            } else {
+                a.get_attribute(&QualifiedName::new(
+                    Some(XSLTNS.to_string()),
+                    None,
+                    "default-mode",
+                ))
+            };
+            match dm.to_string().trim() {
+                "" => {}
+                "#unnamed" => return Ok(None),
+                m => return Ok(Some(Rc::new(QualifiedName::try_from((m, a.clone()))?))),
+            }
+        }
+        e = a.parent()
+    }
+    Ok(None)
+}
+
+// Resolve a mode given by the mode attribute of xsl:template or xsl:apply-templates.
+// The caller deals with #all and #current.
+fn mode_name<N: Node>(n: &N, m: &str) -> Result<Option<Rc<QualifiedName>>, Error> {
+    match m {
+        "#default" => default_mode(n),
+        "#unnamed" => Ok(None),
+        _ => Ok(Some(Rc::new(QualifiedName::try_from((m, n.clone()))?))),
+    }
+}
+
+fn is_xsl<N: Node>(n: &N, name: &str) -> bool {
+    n.is_element()
+        && n.name().namespace_uri_to_string() == Some(XSLTNS.to_string())
+        && n.name().localname_to_string() == name
+}
+
+// The named components declared by top-level elements, and the elements that declare them.
+fn declared_components<N: Node>(declarations: &[N]) -> Result<Vec<(Component, N)>, Error> {
+    declarations.iter().try_fold(vec![], |mut v, d| {
+        if !d.is_element() || d.name().namespace_uri_to_string() != Some(XSLTNS.to_string()) {
+            return Ok(v);
+        }
+        let name = d
+            .get_attribute(&QualifiedName::new(None, None, "name"))
+            .to_string();
+        if name.is_empty() {
+            return Ok(v);
+        }
+        let qn = || QualifiedName::try_from((name.as_str(), d.clone()));
+        let c = match d.name().localname_to_string().as_str() {
+            "template" => Component::Template(QualifiedName::new(None, None, name.clone())),
+            "function" => Component::Function(qn()?),
+            "variable" | "param" => Component::Variable(variable_name(&qn()?)),
+            "attribute-set" => Component::AttributeSet(qn()?),
+            "mode" => Component::Mode(qn()?),
+            _ => return Ok(v),
+        };
+        v.push((c, d.clone()));
+        Ok(v)
+    })
+}
+
+// The value of the visibility attribute of a declaration, if any.
+fn declared_visibility<N: Node>(n: &N) -> Result<Option<Visibility>, Error> {
+    let v = n
+        .get_attribute(&QualifiedName::new(None, None, "visibility"))
+        .to_string();
+    if v.trim().is_empty() {
+        return Ok(None);
+    }
+    match Visibility::try_from(v.as_str())? {
+        Visibility::Hidden => Err(Error::new_with_code(
+            ErrorKind::StaticAbsent,
+            "a component cannot be declared to be hidden",
+            Some(QualifiedName::new(None, None, "XTSE0020")),
+        )),
+        vis => Ok(Some(vis)),
+    }
+}
+
+// The components selected by the component and names attributes of an xsl:expose or xsl:accept element,
+// with the priority of the name test that selects each of them.
+// An explicit name has a higher priority than a namespace wildcard, which has a higher priority than "*".
+// It is an error, with the given code, if an explicit name does not select any component.
+fn selected_components<N: Node>(
+    n: &N,
+    components: &[Component],
+    code: &str,
+) -> Result<Vec<(Component, u8)>, Error> {
+    let kind = n
+        .get_attribute(&QualifiedName::new(None, None, "component"))
+        .to_string();
+    let kind = kind.trim();
+    if !matches!(
+        kind,
+        "template" | "function" | "attribute-set" | "variable" | "mode" | "*"
+    ) {
+        return Err(Error::new_with_code(
+            ErrorKind::StaticAbsent,
+            format!("invalid component \"{}\"", kind),
+            Some(QualifiedName::new(None, None, "XTSE0020")),
+        ));
+    }
+    n.get_attribute(&QualifiedName::new(None, None, "names"))
+        .to_string()
+        .split_whitespace()
+        .try_fold(vec![], |mut v, token| {
+            let mut found = false;
+            for c in components
+                .iter()
+                .filter(|c| kind == "*" || c.kind() == kind)
+            {
+                if let Some(p) = name_test(n, token, c)? {
+                    v.push((c.clone(), p));
+                    found = true
+                }
+            }
+            if !found && !token.ends_with('*') {
+                return Err(Error::new_with_code(
+                    ErrorKind::StaticAbsent,
+                    format!("no {} named \"{}\"", kind, token),
+                    Some(QualifiedName::new(None, None, code)),
+                ));
+            }
+            Ok(v)
+        })
+}
+
+// Match a token in the names attribute of an xsl:expose or xsl:accept element against the name of a component.
+// Functions are identified by name only, so the arity of a function is not significant.
+fn name_test<N: Node>(n: &N, token: &str, c: &Component) -> Result<Option<u8>, Error> {
+    let qn = match c {
+        Component::Template(q)
+        | Component::Function(q)
+        | Component::AttributeSet(q)
+        | Component::Mode(q) => q.clone(),
+        Component::Variable(v) => QualifiedName::new(None, None, v.clone()),
+    };
+    if token == "*" {
+        return Ok(Some(0));
+    }
+    if let Some(ns) = token.strip_prefix("Q{").and_then(|t| t.strip_suffix("}*")) {
+        return Ok((qn.namespace_uri_to_string().as_deref() == Some(ns)).then_some(1));
+    }
+    if let Some(prefix) = token.strip_suffix(":*") {
+        let ns = n
+            .namespace_iter()
+            .find(|ns| ns.name().localname_to_string() == prefix)
+            .map(|ns| ns.value().to_string())
+            .ok_or_else(|| {
+                Error::new_with_code(
+                    ErrorKind::StaticAbsent,
+                    format!("no namespace matching prefix \"{}\"", prefix),
+                    Some(QualifiedName::new(None, None, "XTSE0280")),
+                )
+            })?;
+        return Ok((qn.namespace_uri_to_string() == Some(ns)).then_some(1));
+    }
+    let name = token.split('#').next().unwrap_or(token);
+    let matched = match c {
+        Component::Template(_) => {
+            name == qn.to_string() || QualifiedName::try_from((name, n.clone()))? == qn
+        }
+        Component::Variable(v) => variable_name(&QualifiedName::try_from((name, n.clone()))?) == *v,
+        _ => QualifiedName::try_from((name, n.clone()))? == qn,
+    };
+    Ok(matched.then_some(2))
+}
+
+// The visibility of the components declared by a package. See XSLT 3.5.2.
+// A visibility attribute on the declaration takes precedence.
+// Otherwise the xsl:expose element with the highest priority name test that selects the component determines its visibility,
+// and if there is no such element then the component is private.
+fn exposed_visibility<N: Node>(
+    stylenode: &N,
+    declared: &[(Component, N)],
+) -> Result<HashMap<Component, Visibility>, Error> {
+    let components: Vec<Component> = declared.iter().map(|(c, _)| c.clone()).collect();
+    let mut result = HashMap::new();
+    declared.iter().try_for_each(|(c, d)| {
+        result.insert(
+            c.clone(),
+            declared_visibility(d)?.unwrap_or(Visibility::Private),
+        );
+        Ok::<(), Error>(())
+    })?;
+    let mut exposed: HashMap<Component, (u8, Visibility)> = HashMap::new();
+    stylenode
+        .child_iter()
+        .filter(|c| is_xsl(c, "expose"))
+        .try_for_each(|e| {
+            let v = Visibility::try_from(
+                e.get_attribute(&QualifiedName::new(None, None, "visibility"))
+                    .to_string()
+                    .as_str(),
+            )?;
+            match v {
+                Visibility::Hidden => {
+                    return Err(Error::new_with_code(
+                        ErrorKind::StaticAbsent,
+                        "a component cannot be exposed as hidden",
+                        Some(QualifiedName::new(None, None, "XTSE0020")),
+                    ))
+                }
+                Visibility::Abstract => {
+                    return Err(Error::new_with_code(
+                        ErrorKind::StaticAbsent,
+                        "a component cannot be exposed as abstract",
+                        Some(QualifiedName::new(None, None, "XTSE3025")),
+                    ))
+                }
+                _ => {}
+            }
+            selected_components(&e, &components, "XTSE3020")?
+                .into_iter()
+                .for_each(|(c, p)| {
+                    if exposed.get(&c).map_or(true, |(q, _)| p >= *q) {
+                        exposed.insert(c, (p, v));
+                    }
+                });
+            Ok(())
+        })?;
+    declared.iter().try_for_each(|(c, d)| {
+        if declared_visibility(d)?.is_none() {
+            if let Some((_, v)) = exposed.get(c) {
+                result.insert(c.clone(), *v);
+            }
+        }
+        Ok::<(), Error>(())
+    })?;
+    Ok(result)
+}
+
+// Make a component of a used package that is hidden in the using package private to the used package.
+// The code of the used package, and of the packages that it uses, that references the component by name
+// then references the private component, unless that name refers to another component.
+fn make_private<T: Clone + Eq + Hash>(
+    name: &T,
+    package: &Option<Rc<str>>,
+    hidden: &mut HashMap<Option<Rc<str>>, Hidden>,
+    names: fn(&mut Hidden) -> (&mut HashMap<T, Option<Rc<str>>>, &HashSet<T>),
+) -> ComponentKey<T> {
+    hidden.values_mut().for_each(|h| {
+        let (private, hidden) = names(h);
+        if !hidden.contains(name) {
+            private
+                .entry(name.clone())
+                .or_insert_with(|| package.clone());
+        }
+    });
+    ComponentKey::Private(package.clone(), name.clone())
+}
+
+// The visibility of the components of a used package in the using package. See XSLT 3.5.3.2.
+// By default, public and final components become private, abstract components remain abstract, and private components are hidden.
+// The xsl:accept element with the highest priority name test that selects a component may change its visibility,
+// but cannot make it more visible than it is in the used package.
+fn accepted_visibility<N: Node>(
+    u: &N,
+    pkg: &Package<N>,
+) -> Result<HashMap<Component, Visibility>, Error> {
+    let components: Vec<Component> = pkg.visibility.keys().cloned().collect();
+    let mut accepted: HashMap<Component, (u8, Visibility)> = HashMap::new();
+    u.child_iter()
+        .filter(|c| is_xsl(c, "accept"))
+        .try_for_each(|a| {
+            let v = Visibility::try_from(
+                a.get_attribute(&QualifiedName::new(None, None, "visibility"))
+                    .to_string()
+                    .as_str(),
+            )?;
+            selected_components(&a, &components, "XTSE3030")?
+                .into_iter()
+                .try_for_each(|(c, p)| {
+                    let compatible = match pkg.visibility[&c] {
+                        Visibility::Public => v != Visibility::Abstract,
+                        Visibility::Final => matches!(
+                            v,
+                            Visibility::Private | Visibility::Final | Visibility::Hidden
+                        ),
+                        Visibility::Abstract => {
+                            matches!(v, Visibility::Abstract | Visibility::Hidden)
+                        }
+                        Visibility::Private | Visibility::Hidden => false,
+                    };
+                    if !compatible {
+                        // A wildcard only selects the components that may be accepted
+                        return if p == 2 {
+                            Err(Error::new_with_code(
+                                ErrorKind::StaticAbsent,
+                                format!("{} cannot be accepted as {:?}", c, v),
+                                Some(QualifiedName::new(None, None, "XTSE3040")),
+                            ))
+                        } else {
+                            Ok(())
+                        };
+                    }
+                    if accepted.get(&c).map_or(true, |(q, _)| p >= *q) {
+                        accepted.insert(c, (p, v));
+                    }
+                    Ok(())
+                })
+        })?;
+    Ok(pkg
+        .visibility
+        .iter()
+        .map(|(c, v)| {
+            (
+                c.clone(),
+                accepted.get(c).map_or(
+                    match v {
+                        Visibility::Public | Visibility::Final => Visibility::Private,
+                        Visibility::Abstract => Visibility::Abstract,
+                        Visibility::Private | Visibility::Hidden => Visibility::Hidden,
+                    },
+                    |(_, a)| *a,
+                ),
+            )
+        })
+        .collect())
+}
+
+// The built-in template rules for a mode. See XSLT 6.7.
+// Built-in templates have no priority, so they only match when no template rule in the stylesheet does.
+// The result of a transformation is a sequence of nodes, not a document, so a document node is never copied.
+// For the fail action there are no built-in templates; the error is raised when no template rule matches.
+fn builtin_templates<N: Node>(
+    name: &Option<Rc<QualifiedName>>,
+    m: &Mode,
+) -> Result<Vec<Template<N>>, Error> {
+    // Built-in template rules pass their parameters on to the template rules they invoke
+    let apply = |s: Transform<N>| {
+        Transform::PassParameters(Box::new(Transform::ApplyTemplates(
+            Box::new(s),
+            name.clone(),
+            vec![],
+        )))
+    };
+    let children = || Transform::Step(NodeMatch::new(Axis::Child, NodeTest::Kind(KindTest::Any)));
+    let attributes_and_children = || -> Result<Transform<N>, Error> {
+        Ok(Transform::SequenceItems(vec![
+            apply(parse::<N>("attribute::*", None)?),
+            apply(children()),
+        ]))
+    };
+    let copy = || Transform::Copy(Box::new(Transform::ContextItem), Box::new(Transform::Empty));
+    // The actions for document nodes, elements, text nodes, attributes, and other nodes (comments and processing instructions)
+    let (document, element, text, attribute, other) = match m.on_no_match {
+        OnNoMatch::TextOnlyCopy => (
+            apply(children()),
+            apply(children()),
+            Transform::ContextItem,
+            Transform::LiteralText(Box::new(Transform::ContextItem), false),
+            Transform::Empty,
+        ),
+        OnNoMatch::ShallowCopy => (
+            apply(children()),
+            Transform::Copy(
+                Box::new(Transform::ContextItem),
+                Box::new(attributes_and_children()?),
+            ),
+            copy(),
+            copy(),
+            copy(),
+        ),
+        OnNoMatch::DeepCopy => (
+            Transform::DeepCopy(Box::new(children())),
+            Transform::DeepCopy(Box::new(Transform::ContextItem)),
+            Transform::DeepCopy(Box::new(Transform::ContextItem)),
+            Transform::DeepCopy(Box::new(Transform::ContextItem)),
+            Transform::DeepCopy(Box::new(Transform::ContextItem)),
+        ),
+        OnNoMatch::ShallowSkip => (
+            attributes_and_children()?,
+            attributes_and_children()?,
+            Transform::Empty,
+            Transform::Empty,
+            Transform::Empty,
+        ),
+        OnNoMatch::DeepSkip => (
+            apply(children()),
+            Transform::Empty,
+            Transform::Empty,
+            Transform::Empty,
+            Transform::Empty,
+        ),
+        OnNoMatch::Fail => return Ok(vec![]),
+    };
+    let builtin = |p: &str, body: Transform<N>| -> Result<Template<N>, Error> {
+        Ok(Template::new(
+            Pattern::try_from(p)?,
+            body,
+            None,
+            vec![0],
+            None,
+            name.clone(),
+        ))
+    };
+    Ok(vec![
+        builtin("/", document)?,
+        builtin("child::*", element)?,
+        builtin("child::text()", text)?,
+        builtin("attribute::*", attribute)?,
+        builtin("child::comment()", other.clone())?,
+        builtin("child::processing-instruction()", other)?,
+    ])
+}
+
+// The formatting attributes of an xsl:number instruction.
+// These are static values; attribute value templates are not supported.
+fn number_format<N: Node>(n: &N) -> Result<NumberFormat, Error> {
+    let attr = |a: &str| {
+        let v = n
+            .get_attribute(&QualifiedName::new(None, None, a))
+            .to_string();
+        if v.is_empty() {
+            None
+        } else {
+            Some(v)
+        }
+    };
+    let grouping_size = match attr("grouping-size") {
+        None => None,
+        Some(g) => Some(g.trim().parse::<usize>().map_err(|_| {
+            Error::new_with_code(
+                ErrorKind::StaticAbsent,
+                format!("invalid grouping-size \"{}\"", g),
+                Some(QualifiedName::new(None, None, "XTSE0020")),
+            )
+        })?),
+    };
+    let letter_value = match attr("letter-value").as_deref().map(str::trim) {
+        None => None,
+        Some("alphabetic") => Some(LetterValue::Alphabetic),
+        Some("traditional") => Some(LetterValue::Traditional),
+        Some(l) => {
+            return Err(Error::new_with_code(
+                ErrorKind::StaticAbsent,
+                format!("invalid letter-value \"{}\"", l),
+                Some(QualifiedName::new(None, None, "XTSE0020")),
+            ))
+        }
+    };
+    Ok(NumberFormat {
+        grouping_separator: attr("grouping-separator"),
+        grouping_size,
+        lang: attr("lang"),
+        letter_value,
+    })
+}
+
+/// Interpret the value of a boolean serialization attribute.
+fn yes_or_no(v: &str) -> Result<bool, Error> {
+    match v.trim() {
+        "yes" | "true" | "1" => Ok(true),
+        "no" | "false" | "0" => Ok(false),
+        _ => Err(Error::new_with_code(
+            ErrorKind::StaticAbsent,
+            format!("invalid boolean value \"{}\"", v),
+            Some(QualifiedName::new(None, None, "XTSE0020")),
+        )),
+    }
+}
+
 fn get_sort_keys<N: Node>(n: &N) -> Result<Vec<(Order, Transform<N>)>, Error> {
     let mut result = vec![];
     let mut nit = n.child_iter();
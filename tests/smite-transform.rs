@@ -776,6 +776,22 @@ fn tr_current_time() {
         .expect("test failed")
 }
 #[test]
+fn tr_current_date_time_fixed() {
+    transformgeneric::generic_tr_current_date_time_fixed::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn tr_implicit_timezone() {
+    transformgeneric::generic_tr_implicit_timezone::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
 fn tr_format_date_time() {
     transformgeneric::generic_tr_format_date_time::<RNode, _, _>(
         smite::make_empty_doc,
@@ -120,11 +120,110 @@ fn xpath_cast() {
         .expect("test failed")
 }
 #[test]
+fn xpath_instanceof_occurrence() {
+    xpathgeneric::generic_instanceof_occurrence::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_instanceof_atomic() {
+    xpathgeneric::generic_instanceof_atomic::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_instanceof_node() {
+    xpathgeneric::generic_instanceof_node::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_instanceof_function() {
+    xpathgeneric::generic_instanceof_function::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_treat_error() {
+    xpathgeneric::generic_treat_error::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_castable_invalid() {
+    xpathgeneric::generic_castable_invalid::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_castable_empty() {
+    xpathgeneric::generic_castable_empty::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_cast_double() {
+    xpathgeneric::generic_cast_double::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_cast_invalid() {
+    xpathgeneric::generic_cast_invalid::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_cast_empty() {
+    xpathgeneric::generic_cast_empty::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_quantified_some() {
+    xpathgeneric::generic_quantified_some::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_quantified_every() {
+    xpathgeneric::generic_quantified_every::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_quantified_bindings() {
+    xpathgeneric::generic_quantified_bindings::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_inline_function_types() {
+    xpathgeneric::generic_inline_function_types::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_inline_function_type_error() {
+    xpathgeneric::generic_inline_function_type_error::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
 fn xpath_arrow() {
     xpathgeneric::generic_arrow::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
         .expect("test failed")
 }
 #[test]
+fn xpath_arrow_count() {
+    xpathgeneric::generic_arrow_count::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_arrow_dynamic() {
+    xpathgeneric::generic_arrow_dynamic::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_arrow_partial() {
+    xpathgeneric::generic_arrow_partial::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
 fn xpath_unary() {
     xpathgeneric::generic_unary::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
         .expect("test failed")
@@ -135,6 +234,31 @@ fn xpath_simplemap() {
         .expect("test failed")
 }
 #[test]
+fn xpath_simplemap_context() {
+    xpathgeneric::generic_simplemap_context::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_simplemap_nodes() {
+    xpathgeneric::generic_simplemap_nodes::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_simplemap_chain() {
+    xpathgeneric::generic_simplemap_chain::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_simplemap_not_equal() {
+    xpathgeneric::generic_simplemap_not_equal::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_unary_lookup() {
+    xpathgeneric::generic_unary_lookup::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
 fn xpath_int() {
     xpathgeneric::generic_int::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
         .expect("test failed")
@@ -504,6 +628,78 @@ fn xpath_format_number_1() {
         .expect("test failed")
 }
 #[test]
+fn xpath_format_number_grouping() {
+    xpathgeneric::generic_format_number_grouping::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_format_number_sub_pictures() {
+    xpathgeneric::generic_format_number_sub_pictures::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_format_number_rounding() {
+    xpathgeneric::generic_format_number_rounding::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_format_number_percent() {
+    xpathgeneric::generic_format_number_percent::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_format_number_exponent() {
+    xpathgeneric::generic_format_number_exponent::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_format_number_special() {
+    xpathgeneric::generic_format_number_special::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_format_number_invalid_picture() {
+    xpathgeneric::generic_format_number_invalid_picture::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_format_number_optional_digit() {
+    xpathgeneric::generic_format_number_optional_digit::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_format_number_unknown_format() {
+    xpathgeneric::generic_format_number_unknown_format::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
 fn xpath_document_1() {
     xpathgeneric::generic_document_1::<RNode, _, _, _>(
         smite::make_empty_doc,
@@ -512,3 +708,945 @@ fn xpath_document_1() {
     )
     .expect("test failed")
 }
+#[test]
+fn xpath_map_constructor() {
+    xpathgeneric::generic_map_constructor::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_map_lookup_wildcard() {
+    xpathgeneric::generic_map_lookup_wildcard::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_map_lookup_numeric_key() {
+    xpathgeneric::generic_map_lookup_numeric_key::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_map_size() {
+    xpathgeneric::generic_map_size::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_map_put_get() {
+    xpathgeneric::generic_map_put_get::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_map_remove_contains() {
+    xpathgeneric::generic_map_remove_contains::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_map_remove_lookup() {
+    xpathgeneric::generic_map_remove_lookup::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_map_merge() {
+    xpathgeneric::generic_map_merge::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_map_find() {
+    xpathgeneric::generic_map_find::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_array_square() {
+    xpathgeneric::generic_array_square::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_array_curly() {
+    xpathgeneric::generic_array_curly::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_array_sequence_lookup() {
+    xpathgeneric::generic_array_sequence_lookup::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_array_subarray() {
+    xpathgeneric::generic_array_subarray::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_array_insert_remove() {
+    xpathgeneric::generic_array_insert_remove::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_array_reverse_join() {
+    xpathgeneric::generic_array_reverse_join::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_array_flatten() {
+    xpathgeneric::generic_array_flatten::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_array_bounds() {
+    xpathgeneric::generic_array_bounds::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_array_key_type() {
+    xpathgeneric::generic_array_key_type::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_map_key_types() {
+    xpathgeneric::generic_map_key_types::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_map_array_no_value() {
+    xpathgeneric::generic_map_array_no_value::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_map_to_json() {
+    xpathgeneric::generic_map_to_json::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+
+#[test]
+fn xpath_inline_function() {
+    xpathgeneric::generic_inline_function::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+
+#[test]
+fn xpath_function_closure() {
+    xpathgeneric::generic_function_closure::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+
+#[test]
+fn xpath_named_function_ref() {
+    xpathgeneric::generic_named_function_ref::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+
+#[test]
+fn xpath_fncall_eqname() {
+    xpathgeneric::generic_fncall_eqname::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+
+#[test]
+fn xpath_named_function_ref_variadic() {
+    xpathgeneric::generic_named_function_ref_variadic::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xpath_partial_application() {
+    xpathgeneric::generic_partial_application::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+
+#[test]
+fn xpath_partial_application_dynamic() {
+    xpathgeneric::generic_partial_application_dynamic::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xpath_fn_for_each() {
+    xpathgeneric::generic_fn_for_each::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+
+#[test]
+fn xpath_fn_filter() {
+    xpathgeneric::generic_fn_filter::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+
+#[test]
+fn xpath_fold_left() {
+    xpathgeneric::generic_fold_left::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+
+#[test]
+fn xpath_fold_right() {
+    xpathgeneric::generic_fold_right::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+
+#[test]
+fn xpath_fn_sort() {
+    xpathgeneric::generic_fn_sort::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+
+#[test]
+fn xpath_fn_sort_key() {
+    xpathgeneric::generic_fn_sort_key::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+
+#[test]
+fn xpath_map_for_each() {
+    xpathgeneric::generic_map_for_each::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+
+#[test]
+fn xpath_array_higher_order() {
+    xpathgeneric::generic_array_higher_order::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+
+#[test]
+fn xpath_array_sort() {
+    xpathgeneric::generic_array_sort::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+
+#[test]
+fn xpath_map_array_as_function() {
+    xpathgeneric::generic_map_array_as_function::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xpath_function_arity() {
+    xpathgeneric::generic_function_arity::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_node_is() {
+    xpathgeneric::generic_node_is::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_node_is_not() {
+    xpathgeneric::generic_node_is_not::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_node_before() {
+    xpathgeneric::generic_node_before::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_node_after() {
+    xpathgeneric::generic_node_after::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_node_after_ancestor() {
+    xpathgeneric::generic_node_after_ancestor::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_node_comparison_empty() {
+    xpathgeneric::generic_node_comparison_empty::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_node_comparison_not_singleton() {
+    xpathgeneric::generic_node_comparison_not_singleton::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xpath_fncall_uppercase() {
+    xpathgeneric::generic_fncall_uppercase::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_lowercase() {
+    xpathgeneric::generic_fncall_lowercase::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_stringjoin() {
+    xpathgeneric::generic_fncall_stringjoin::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_stringjoin_nosep() {
+    xpathgeneric::generic_fncall_stringjoin_nosep::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_stringlength() {
+    xpathgeneric::generic_fncall_stringlength::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_tokenize_whitespace() {
+    xpathgeneric::generic_fncall_tokenize_whitespace::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_tokenize_pattern() {
+    xpathgeneric::generic_fncall_tokenize_pattern::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_tokenize_arrow() {
+    xpathgeneric::generic_fncall_tokenize_arrow::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_matches() {
+    xpathgeneric::generic_fncall_matches::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_matches_flags() {
+    xpathgeneric::generic_fncall_matches_flags::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_matches_literal() {
+    xpathgeneric::generic_fncall_matches_literal::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_matches_name_escape() {
+    xpathgeneric::generic_fncall_matches_name_escape::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_replace() {
+    xpathgeneric::generic_fncall_replace::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_replace_flags() {
+    xpathgeneric::generic_fncall_replace_flags::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_codepointstostring() {
+    xpathgeneric::generic_fncall_codepointstostring::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_stringtocodepoints() {
+    xpathgeneric::generic_fncall_stringtocodepoints::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_compare() {
+    xpathgeneric::generic_fncall_compare::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_compare_empty() {
+    xpathgeneric::generic_fncall_compare_empty::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_normalizeunicode() {
+    xpathgeneric::generic_fncall_normalizeunicode::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_normalizeunicode_nfd() {
+    xpathgeneric::generic_fncall_normalizeunicode_nfd::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_encodeforuri() {
+    xpathgeneric::generic_fncall_encodeforuri::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_escapehtmluri() {
+    xpathgeneric::generic_fncall_escapehtmluri::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_iritouri() {
+    xpathgeneric::generic_fncall_iritouri::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_matches_badflag() {
+    xpathgeneric::generic_fncall_matches_badflag::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_matches_badregex() {
+    xpathgeneric::generic_fncall_matches_badregex::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_tokenize_emptymatch() {
+    xpathgeneric::generic_fncall_tokenize_emptymatch::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_replace_badreplacement() {
+    xpathgeneric::generic_fncall_replace_badreplacement::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_compare_collation() {
+    xpathgeneric::generic_fncall_compare_collation::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_normalizeunicode_badform() {
+    xpathgeneric::generic_fncall_normalizeunicode_badform::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_analyzestring() {
+    xpathgeneric::generic_fncall_analyzestring::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+
+#[test]
+fn xpath_fncall_analyzestring_groups() {
+    xpathgeneric::generic_fncall_analyzestring_groups::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_backreference() {
+    xpathgeneric::generic_fncall_backreference::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+
+#[test]
+fn xpath_fncall_empty() {
+    xpathgeneric::generic_fncall_empty::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_exists() {
+    xpathgeneric::generic_fncall_exists::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_distinctvalues() {
+    xpathgeneric::generic_fncall_distinctvalues::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_indexof() {
+    xpathgeneric::generic_fncall_indexof::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_insertbefore() {
+    xpathgeneric::generic_fncall_insertbefore::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_insertbefore_end() {
+    xpathgeneric::generic_fncall_insertbefore_end::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_remove() {
+    xpathgeneric::generic_fncall_remove::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_remove_outofrange() {
+    xpathgeneric::generic_fncall_remove_outofrange::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_reverse() {
+    xpathgeneric::generic_fncall_reverse::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_subsequence() {
+    xpathgeneric::generic_fncall_subsequence::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_subsequence_round() {
+    xpathgeneric::generic_fncall_subsequence_round::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_unordered() {
+    xpathgeneric::generic_fncall_unordered::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_head() {
+    xpathgeneric::generic_fncall_head::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_tail() {
+    xpathgeneric::generic_fncall_tail::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_exactlyone() {
+    xpathgeneric::generic_fncall_exactlyone::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_deepequal() {
+    xpathgeneric::generic_fncall_deepequal::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_deepequal_neg() {
+    xpathgeneric::generic_fncall_deepequal_neg::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_deepequal_nodes() {
+    xpathgeneric::generic_fncall_deepequal_nodes::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_deepequal_nodes_neg() {
+    xpathgeneric::generic_fncall_deepequal_nodes_neg::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_innermost() {
+    xpathgeneric::generic_fncall_innermost::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_outermost() {
+    xpathgeneric::generic_fncall_outermost::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_root() {
+    xpathgeneric::generic_fncall_root::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_path() {
+    xpathgeneric::generic_fncall_path::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_path_attribute() {
+    xpathgeneric::generic_fncall_path_attribute::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_haschildren() {
+    xpathgeneric::generic_fncall_haschildren::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_namespaceuri() {
+    xpathgeneric::generic_fncall_namespaceuri::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_id() {
+    xpathgeneric::generic_fncall_id::<RNode, _, _>(smite::make_empty_doc, smite::make_id_doc)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_idref() {
+    xpathgeneric::generic_fncall_idref::<RNode, _, _>(smite::make_empty_doc, smite::make_id_doc)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_lang() {
+    xpathgeneric::generic_fncall_lang::<RNode, _, _>(smite::make_empty_doc, smite::make_id_doc)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_lang_arg() {
+    xpathgeneric::generic_fncall_lang_arg::<RNode, _, _>(smite::make_empty_doc, smite::make_id_doc)
+        .expect("test failed")
+}
+#[test]
+fn xpath_fncall_zeroorone_error() {
+    xpathgeneric::generic_fncall_zeroorone_error::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_oneormore_error() {
+    xpathgeneric::generic_fncall_oneormore_error::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_exactlyone_error() {
+    xpathgeneric::generic_fncall_exactlyone_error::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fncall_deepequal_function() {
+    xpathgeneric::generic_fncall_deepequal_function::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_duration_canonical() {
+    xpathgeneric::generic_duration_canonical::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_duration_zero() {
+    xpathgeneric::generic_duration_zero::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_duration_compare() {
+    xpathgeneric::generic_duration_compare::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_datetime_subtract() {
+    xpathgeneric::generic_datetime_subtract::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_date_add_duration() {
+    xpathgeneric::generic_date_add_duration::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_date_timezone() {
+    xpathgeneric::generic_date_timezone::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_date_implicit_timezone() {
+    xpathgeneric::generic_date_implicit_timezone::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_datetime_add_daytime() {
+    xpathgeneric::generic_datetime_add_daytime::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_duration_multiply() {
+    xpathgeneric::generic_duration_multiply::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_duration_divide() {
+    xpathgeneric::generic_duration_divide::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_year_from_date() {
+    xpathgeneric::generic_year_from_date::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_hours_from_duration() {
+    xpathgeneric::generic_hours_from_duration::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_seconds_from_datetime() {
+    xpathgeneric::generic_seconds_from_datetime::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_timezone_from_datetime() {
+    xpathgeneric::generic_timezone_from_datetime::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_adjust_datetime() {
+    xpathgeneric::generic_adjust_datetime::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_duration_invalid() {
+    xpathgeneric::generic_duration_invalid::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_duration_overflow() {
+    xpathgeneric::generic_duration_overflow::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_adjust_datetime_invalid() {
+    xpathgeneric::generic_adjust_datetime_invalid::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_datetime_add_datetime() {
+    xpathgeneric::generic_datetime_add_datetime::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_datetime_keeps_timezone() {
+    xpathgeneric::generic_datetime_keeps_timezone::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_datetime_no_timezone() {
+    xpathgeneric::generic_datetime_no_timezone::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_datetime_compare_timezones() {
+    xpathgeneric::generic_datetime_compare_timezones::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_timezone_from_datetime_absent() {
+    xpathgeneric::generic_timezone_from_datetime_absent::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_adjust_datetime_remove() {
+    xpathgeneric::generic_adjust_datetime_remove::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_adjust_time() {
+    xpathgeneric::generic_adjust_time::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_format_datetime_timezone() {
+    xpathgeneric::generic_format_datetime_timezone::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_constructor_ncname() {
+    xpathgeneric::generic_constructor_ncname::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_constructor_token() {
+    xpathgeneric::generic_constructor_token::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_constructor_language() {
+    xpathgeneric::generic_constructor_language::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_constructor_derived() {
+    xpathgeneric::generic_constructor_derived::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_constructor_empty() {
+    xpathgeneric::generic_constructor_empty::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_gyear_timezone() {
+    xpathgeneric::generic_gyear_timezone::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_gyear_from_date() {
+    xpathgeneric::generic_gyear_from_date::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_gday() {
+    xpathgeneric::generic_gday::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_gyearmonth_equal() {
+    xpathgeneric::generic_gyearmonth_equal::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_gmonth_castable() {
+    xpathgeneric::generic_gmonth_castable::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_base64_from_hex() {
+    xpathgeneric::generic_base64_from_hex::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_hex_from_base64() {
+    xpathgeneric::generic_hex_from_base64::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_anyuri() {
+    xpathgeneric::generic_anyuri::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_constructor_ncname_invalid() {
+    xpathgeneric::generic_constructor_ncname_invalid::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_gmonthday_invalid() {
+    xpathgeneric::generic_gmonthday_invalid::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_base64_invalid() {
+    xpathgeneric::generic_base64_invalid::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
+#[test]
+fn xpath_datetimestamp_no_timezone() {
+    xpathgeneric::generic_datetimestamp_no_timezone::<RNode, _, _>(
+        smite::make_empty_doc,
+        smite::make_sd,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xpath_fn_error() {
+    xpathgeneric::generic_fn_error::<RNode, _, _>(smite::make_empty_doc, smite::make_sd)
+        .expect("test failed")
+}
@@ -166,6 +166,24 @@ fn xslt_callable_posn_1() {
     .expect("test failed")
 }
 #[test]
+fn xslt_function_namespace() {
+    xsltgeneric::generic_function_namespace(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_callable_function_item() {
+    xsltgeneric::generic_callable_function_item(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
 #[should_panic]
 fn xslt_include() {
     xsltgeneric::generic_include(
@@ -274,3 +292,680 @@ fn xslt_issue_126() {
     )
     .expect("test failed")
 }
+#[test]
+fn xslt_variable_local() {
+    xsltgeneric::generic_variable_local(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_variable_scope() {
+    xsltgeneric::generic_variable_scope(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_variable_as() {
+    xsltgeneric::generic_variable_as(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_variable_global() {
+    xsltgeneric::generic_variable_global(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_variable_global_circular() {
+    xsltgeneric::generic_variable_global_circular(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_variable_select_and_content() {
+    xsltgeneric::generic_variable_select_and_content(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_variable_global_duplicate() {
+    xsltgeneric::generic_variable_global_duplicate(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_variable_global_once() {
+    xsltgeneric::generic_variable_global_once(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_param_supplied() {
+    xsltgeneric::generic_param_supplied(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_param_default() {
+    xsltgeneric::generic_param_default(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_param_required() {
+    xsltgeneric::generic_param_required(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_param_type() {
+    xsltgeneric::generic_param_type(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_result_document() {
+    xsltgeneric::generic_result_document(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_result_document_duplicate() {
+    xsltgeneric::generic_result_document_duplicate(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_output_serialization() {
+    xsltgeneric::generic_output_serialization(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_output_unknown_method() {
+    xsltgeneric::generic_output_unknown_method(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_output_character_map_circular() {
+    xsltgeneric::generic_output_character_map_circular(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_output_html() {
+    xsltgeneric::generic_output_html(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_output_escaping() {
+    xsltgeneric::generic_output_escaping(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_output_xhtml() {
+    xsltgeneric::generic_output_xhtml(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_output_adaptive() {
+    xsltgeneric::generic_output_adaptive(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_decimal_format() {
+    xsltgeneric::generic_decimal_format(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_decimal_format_conflict() {
+    xsltgeneric::generic_decimal_format_conflict(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_number_multiple() {
+    xsltgeneric::generic_number_multiple(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_number_any() {
+    xsltgeneric::generic_number_any(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_number_from_unmatched() {
+    xsltgeneric::generic_number_from_unmatched(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_number_format() {
+    xsltgeneric::generic_number_format(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_mode_shallow_copy() {
+    xsltgeneric::generic_mode_shallow_copy(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_mode_skip() {
+    xsltgeneric::generic_mode_skip(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_mode_current() {
+    xsltgeneric::generic_mode_current(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_mode_fail() {
+    xsltgeneric::generic_mode_fail(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_mode_multiple_match() {
+    xsltgeneric::generic_mode_multiple_match(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_mode_warning() {
+    xsltgeneric::generic_mode_warning(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_iterate_total() {
+    xsltgeneric::generic_iterate_total(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_iterate_break() {
+    xsltgeneric::generic_iterate_break(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_iterate_long() {
+    xsltgeneric::generic_iterate_long(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_iterate_prefixed_param() {
+    xsltgeneric::generic_iterate_prefixed_param(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_iterate_unknown_param() {
+    xsltgeneric::generic_iterate_unknown_param(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_break_outside_iterate() {
+    xsltgeneric::generic_break_outside_iterate(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_try_catch() {
+    xsltgeneric::generic_try_catch(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_try_catch_variables() {
+    xsltgeneric::generic_try_catch_variables(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_try_uncaught() {
+    xsltgeneric::generic_try_uncaught(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_try_rollback() {
+    xsltgeneric::generic_try_rollback(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xslt_apply_templates_params() {
+    xsltgeneric::generic_apply_templates_params(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_builtin_template_params() {
+    xsltgeneric::generic_builtin_template_params(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_prefixed_params() {
+    xsltgeneric::generic_prefixed_params(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_tunnel_params() {
+    xsltgeneric::generic_tunnel_params(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xslt_typed_params() {
+    xsltgeneric::generic_typed_params(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xslt_variable_type_error() {
+    xsltgeneric::generic_variable_type_error(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xslt_param_string_type_error() {
+    xsltgeneric::generic_param_string_type_error(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xslt_function_result_type_error() {
+    xsltgeneric::generic_function_result_type_error(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xslt_accumulator() {
+    xsltgeneric::generic_accumulator(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xslt_accumulator_unknown() {
+    xsltgeneric::generic_accumulator_unknown(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xslt_merge() {
+    xsltgeneric::generic_merge(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xslt_merge_sort_before_merge() {
+    xsltgeneric::generic_merge_sort_before_merge(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xslt_merge_unsorted() {
+    xsltgeneric::generic_merge_unsorted(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_merge_data_type() {
+    xsltgeneric::generic_merge_data_type(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_merge_incompatible_keys() {
+    xsltgeneric::generic_merge_incompatible_keys(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xslt_analyze_string() {
+    xsltgeneric::generic_analyze_string(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xslt_analyze_string_quantifier() {
+    xsltgeneric::generic_analyze_string_quantifier(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_analyze_string_flags() {
+    xsltgeneric::generic_analyze_string_flags(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xslt_analyze_string_no_substring() {
+    xsltgeneric::generic_analyze_string_no_substring(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xslt_package() {
+    xsltgeneric::generic_package(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xslt_package_reuse() {
+    xsltgeneric::generic_package_reuse(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xslt_package_override() {
+    xsltgeneric::generic_package_override(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xslt_package_conflict() {
+    xsltgeneric::generic_package_conflict(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xslt_package_hidden() {
+    xsltgeneric::generic_package_hidden(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xslt_package_override_final() {
+    xsltgeneric::generic_package_override_final(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+
+#[test]
+fn xslt_package_abstract() {
+    xsltgeneric::generic_package_abstract(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_package_private_names() {
+    xsltgeneric::generic_package_private_names(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_package_private_names_used() {
+    xsltgeneric::generic_package_private_names_used(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_package_local_declarations() {
+    xsltgeneric::generic_package_local_declarations(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
+#[test]
+fn xslt_package_override_rules() {
+    xsltgeneric::generic_package_override_rules(
+        smite::make_from_str,
+        smite::make_from_str_with_ns,
+        smite::make_sd_cooked,
+    )
+    .expect("test failed")
+}
@@ -64,3 +64,11 @@ pub fn make_from_str_with_ns(s: &str) -> Result<(RNode, Rc<NamespaceMap>), Error
     let r = parse_with_ns(doc.clone(), s, None)?;
     Ok(r)
 }
+
+#[allow(dead_code)]
+pub fn make_id_doc() -> Item<RNode> {
+    Item::Node(
+        make_from_str("<!DOCTYPE doc [<!ATTLIST e id ID #IMPLIED ref IDREFS #IMPLIED>]><doc xml:lang='en-GB'><e id='e1'/><e id='e2' ref='e1 e3'/><e id='e3' xml:lang='fr'><f/></e></doc>")
+            .expect("unable to parse XML"),
+    )
+}
@@ -1,6 +1,6 @@
 //! Tests for transform module defined generically
 
-use chrono::{Datelike, Local, Timelike};
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDate, Timelike};
 use std::rc::Rc;
 use xrust::item::{Item, Node, SequenceTrait};
 use xrust::namespace::NamespaceMap;
@@ -5029,7 +5029,7 @@ where
     assert_eq!(seq.len(), 1);
     match &seq[0] {
         Item::Value(v) => match **v {
-            Value::DateTime(dt) => {
+            Value::DateTime(dt, _) => {
                 assert_eq!(dt.year(), Local::now().year());
                 assert_eq!(dt.month(), Local::now().month());
                 assert_eq!(dt.day(), Local::now().day());
@@ -5062,7 +5062,7 @@ where
     assert_eq!(seq.len(), 1);
     match &seq[0] {
         Item::Value(v) => match **v {
-            Value::Date(dt) => {
+            Value::Date(dt, _) => {
                 assert_eq!(dt.year(), Local::now().year());
                 assert_eq!(dt.month(), Local::now().month());
                 assert_eq!(dt.day(), Local::now().day());
@@ -5092,7 +5092,7 @@ where
     assert_eq!(seq.len(), 1);
     match &seq[0] {
         Item::Value(v) => match **v {
-            Value::Time(dt) => {
+            Value::Time(dt, _) => {
                 assert_eq!(dt.hour(), Local::now().hour());
                 assert_eq!(dt.minute(), Local::now().minute());
                 assert_eq!(dt.second(), Local::now().second()); // It is possible for this to fail if the elapsed time to execute the function call and the test falls across a second quantum
@@ -5104,6 +5104,83 @@ where
     }
 }
 
+pub fn generic_tr_current_date_time_fixed<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    // XPath == format-dateTime(current-dateTime(), "[H]:[m] [D]/[M]/[Y]")
+    // The current dateTime and implicit timezone are fixed, so the result is reproducible
+    let x = Transform::FormatDateTime(
+        Box::new(Transform::CurrentDateTime),
+        Box::new(Transform::Literal(Item::<N>::Value(Rc::new(Value::from(
+            "[H]:[m] [D]/[M]/[Y]",
+        ))))),
+        None,
+        None,
+        None,
+    );
+    let mut stctxt = StaticContextBuilder::new()
+        .message(|_| Ok(()))
+        .fetcher(|_| Err(Error::new(ErrorKind::NotImplemented, "not implemented")))
+        .parser(|_| Err(Error::new(ErrorKind::NotImplemented, "not implemented")))
+        .build();
+    let ctxt = ContextBuilder::new()
+        .implicit_timezone(FixedOffset::east_opt(10 * 3600).unwrap())
+        .current_date_time(DateTime::parse_from_rfc3339("2024-02-29T12:00:00Z").unwrap())
+        .build();
+    let seq = ctxt.dispatch(&mut stctxt, &x).expect("evaluation failed");
+    assert_eq!(seq.to_string(), "22:00 29/02/2024");
+    let seq = ctxt
+        .dispatch(&mut stctxt, &Transform::CurrentDateTime)
+        .expect("evaluation failed");
+    assert_eq!(seq.to_string(), "2024-02-29T22:00:00+10:00");
+    Ok(())
+}
+
+pub fn generic_tr_implicit_timezone<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    // A dateTime without a timezone is compared using the implicit timezone
+    let local = NaiveDate::from_ymd_opt(2024, 1, 1)
+        .unwrap()
+        .and_hms_opt(10, 0, 0)
+        .unwrap();
+    let utc = NaiveDate::from_ymd_opt(2024, 1, 1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap();
+    let x = Transform::ValueComparison(
+        Operator::Equal,
+        Box::new(Transform::Literal(Item::<N>::Value(Rc::new(
+            Value::DateTime(local, None),
+        )))),
+        Box::new(Transform::Literal(Item::<N>::Value(Rc::new(
+            Value::DateTime(utc, FixedOffset::east_opt(0)),
+        )))),
+    );
+    let mut stctxt = StaticContextBuilder::new()
+        .message(|_| Ok(()))
+        .fetcher(|_| Err(Error::new(ErrorKind::NotImplemented, "not implemented")))
+        .parser(|_| Err(Error::new(ErrorKind::NotImplemented, "not implemented")))
+        .build();
+    let seq = ContextBuilder::new()
+        .implicit_timezone(FixedOffset::east_opt(10 * 3600).unwrap())
+        .build()
+        .dispatch(&mut stctxt, &x)
+        .expect("evaluation failed");
+    assert!(seq.to_bool());
+    let seq = ContextBuilder::new()
+        .implicit_timezone(FixedOffset::east_opt(0).unwrap())
+        .build()
+        .dispatch(&mut stctxt, &x)
+        .expect("evaluation failed");
+    assert!(!seq.to_bool());
+    Ok(())
+}
+
 pub fn generic_tr_format_date_time<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
 where
     G: Fn() -> N,
@@ -5208,6 +5285,7 @@ where
             "#.##",
         ))))),
         None,
+        Rc::new(NamespaceMap::new()),
     );
     let mut stctxt = StaticContextBuilder::new()
         .message(|_| Ok(()))
@@ -5331,6 +5409,7 @@ where
                 FormalParameters::Named(vec![(
                     QualifiedName::new(None, None, String::from("param1")),
                     None,
+                    None,
                 )]),
             ),
         )
@@ -5381,10 +5460,9 @@ where
                         Rc::new(NamespaceMap::new()),
                     ),
                 ]),
-                FormalParameters::Positional(vec![QualifiedName::new(
-                    None,
+                FormalParameters::Positional(vec![(
+                    QualifiedName::new(None, None, String::from("param1")),
                     None,
-                    String::from("param1"),
                 )]),
             ),
         )
@@ -1345,6 +1345,126 @@ where
     Ok(())
 }
 
+pub fn generic_format_number_grouping<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("format-number(1234567.891, '#,##0.00')")?;
+    assert_eq!(s.to_string(), "1,234,567.89");
+    let s: Sequence<N> = no_src_no_result("format-number(1234567.8, '#,##,##0.#')")?;
+    assert_eq!(s.to_string(), "12,34,567.8");
+    Ok(())
+}
+pub fn generic_format_number_sub_pictures<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("format-number(0 - 1234.5, '#,##0.00;(#,##0.00)')")?;
+    assert_eq!(s.to_string(), "(1,234.50)");
+    let s: Sequence<N> = no_src_no_result("format-number(0 - 3, '0')")?;
+    assert_eq!(s.to_string(), "-3");
+    Ok(())
+}
+pub fn generic_format_number_rounding<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("format-number(0.5, '#.##')")?;
+    assert_eq!(s.to_string(), ".5");
+    let s: Sequence<N> = no_src_no_result("format-number(2.5, '0')")?;
+    assert_eq!(s.to_string(), "2");
+    let s: Sequence<N> = no_src_no_result("format-number(3.5, '0')")?;
+    assert_eq!(s.to_string(), "4");
+    let s: Sequence<N> = no_src_no_result("format-number(9.999, '0.00')")?;
+    assert_eq!(s.to_string(), "10.00");
+    Ok(())
+}
+pub fn generic_format_number_percent<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("format-number(0.25, '#%')")?;
+    assert_eq!(s.to_string(), "25%");
+    let s: Sequence<N> = no_src_no_result("format-number(0.1234, '0.0‰')")?;
+    assert_eq!(s.to_string(), "123.4‰");
+    Ok(())
+}
+pub fn generic_format_number_exponent<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("format-number(1234.5678, '0.00e00')")?;
+    assert_eq!(s.to_string(), "1.23e03");
+    let s: Sequence<N> = no_src_no_result("format-number(0.000123, '0.0e0')")?;
+    assert_eq!(s.to_string(), "1.2e-4");
+    Ok(())
+}
+pub fn generic_format_number_special<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("format-number(xs:double('INF'), '#,##0')")?;
+    assert_eq!(s.to_string(), "Infinity");
+    let s: Sequence<N> = no_src_no_result("format-number(xs:double('-INF'), '#')")?;
+    assert_eq!(s.to_string(), "-Infinity");
+    let s: Sequence<N> = no_src_no_result("format-number(xs:double('NaN'), '#')")?;
+    assert_eq!(s.to_string(), "NaN");
+    Ok(())
+}
+pub fn generic_format_number_invalid_picture<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("format-number(1, '#.#.#')") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "FODF1310");
+            Ok(())
+        }
+    }
+}
+pub fn generic_format_number_optional_digit<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("format-number(1, '0#')") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "FODF1310");
+            Ok(())
+        }
+    }
+}
+pub fn generic_format_number_unknown_format<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("format-number(1, '#', 'nothing')") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "FODF1280");
+            Ok(())
+        }
+    }
+}
 pub fn generic_fncall_user_defined<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
 where
     G: Fn() -> N,
@@ -1653,92 +1773,2323 @@ where
     Ok(())
 }
 
-fn unimplemented_rig<N: Node, G, H>(
-    e: impl AsRef<str>,
-    make_empty_doc: G,
-    make_doc: H,
-) -> Result<(), Error>
+// Maps and arrays
+
+pub fn generic_map_constructor<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
 where
     G: Fn() -> N,
     H: Fn() -> Item<N>,
 {
-    match dispatch_rig(e, make_empty_doc, make_doc) {
-        Err(e) => {
-            if e.kind == ErrorKind::NotImplemented {
-                Ok(())
-            } else {
-                Err(Error::new(ErrorKind::Unknown, "unexpected error code"))
+    let s: Sequence<N> = no_src_no_result("map{'a': 1, 'b': (2, 3)}?b")?;
+    assert_eq!(s.len(), 2);
+    assert_eq!(s.to_string(), "23");
+    Ok(())
+}
+pub fn generic_map_lookup_wildcard<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("map{'a': 1, 'b': 2}?*")?;
+    assert_eq!(s.len(), 2);
+    assert_eq!(s.to_string(), "12");
+    Ok(())
+}
+pub fn generic_map_lookup_numeric_key<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("map{1: 'one', 2.0: 'two'}?2")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "two");
+    Ok(())
+}
+pub fn generic_map_size<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("map:size(map{'a': 1, 'b': 2})")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "2");
+    Ok(())
+}
+pub fn generic_map_put_get<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("map:get(map:put(map{'a': 1}, 'b', 2), 'b')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "2");
+    Ok(())
+}
+pub fn generic_map_remove_contains<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result(
+        "if (map:contains(map:remove(map{'a': 1, 'b': 2}, 'a'), 'a')) then 'yes' else 'no'",
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "no");
+    Ok(())
+}
+pub fn generic_map_remove_lookup<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result(
+        "let $m := map:remove(map{'a': 1, 'b': 2, 3: 'c'}, 'b') return (map:keys($m), $m?a, $m?(3.0e0))",
+    )?;
+    assert_eq!(s.to_string(), "a31c");
+    Ok(())
+}
+pub fn generic_map_merge<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result(
+        "map:merge((map{'a': 1}, map{'a': 2, 'b': 3}), map{'duplicates': 'combine'})?a",
+    )?;
+    assert_eq!(s.len(), 2);
+    assert_eq!(s.to_string(), "12");
+    Ok(())
+}
+pub fn generic_map_find<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("array:size(map:find([map{'a': 1}, map{'b': map{'a': 2}}], 'a'))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "2");
+    Ok(())
+}
+pub fn generic_array_square<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("[1, (2, 3), 4]?2")?;
+    assert_eq!(s.len(), 2);
+    assert_eq!(s.to_string(), "23");
+    Ok(())
+}
+pub fn generic_array_curly<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("array:size(array{1, (2, 3), 4})")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "4");
+    Ok(())
+}
+pub fn generic_array_sequence_lookup<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("([1, 2], [3, 4])?2")?;
+    assert_eq!(s.len(), 2);
+    assert_eq!(s.to_string(), "24");
+    Ok(())
+}
+pub fn generic_array_subarray<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("array:subarray(['a', 'b', 'c', 'd'], 2, 2)?*")?;
+    assert_eq!(s.len(), 2);
+    assert_eq!(s.to_string(), "bc");
+    Ok(())
+}
+pub fn generic_array_insert_remove<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("array:remove(array:insert-before(['a', 'c'], 2, 'b'), 1)?*")?;
+    assert_eq!(s.len(), 2);
+    assert_eq!(s.to_string(), "bc");
+    Ok(())
+}
+pub fn generic_array_reverse_join<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("array:reverse(array:join((['a'], ['b', 'c'])))?*")?;
+    assert_eq!(s.len(), 3);
+    assert_eq!(s.to_string(), "cba");
+    Ok(())
+}
+pub fn generic_array_flatten<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("array:flatten([1, [2, [3]], 4])")?;
+    assert_eq!(s.len(), 4);
+    assert_eq!(s.to_string(), "1234");
+    Ok(())
+}
+pub fn generic_array_bounds<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    // A position that is too large for an xs:integer is also out of bounds
+    for e in ["[1, 2]?3", "[1]?99999999999999999999"] {
+        match no_src_no_result::<N>(e) {
+            Err(e) => assert_eq!(e.code.unwrap().to_string(), "FOAY0001"),
+            Ok(s) => {
+                return Err(Error::new(
+                    ErrorKind::Unknown,
+                    format!("expected error, got \"{}\"", s.to_string()),
+                ))
             }
         }
-        _ => Err(Error::new(ErrorKind::Unknown, "expected to fail")),
     }
+    Ok(())
 }
-
-pub fn generic_union<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+pub fn generic_array_key_type<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
 where
     G: Fn() -> N,
     H: Fn() -> Item<N>,
 {
-    dispatch_rig("'a' | 'b'", make_empty_doc, make_doc).map_or(Ok(()), |v| {
-        Err(Error::new(
-            ErrorKind::TypeError,
-            format!("expected type error, got \"{}\"", v.to_string()),
-        ))
-    })
+    // Array positions must be integers; strings are not converted
+    for e in ["[10, 20, 30]?(\"2\")", "[10, 20, 30]?(1.5)"] {
+        match no_src_no_result::<N>(e) {
+            Err(e) => assert_eq!(e.code.unwrap().to_string(), "XPTY0004"),
+            Ok(s) => {
+                return Err(Error::new(
+                    ErrorKind::Unknown,
+                    format!("expected error, got \"{}\"", s.to_string()),
+                ))
+            }
+        }
+    }
+    Ok(())
 }
-pub fn generic_intersectexcept<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+pub fn generic_map_key_types<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
 where
     G: Fn() -> N,
     H: Fn() -> Item<N>,
 {
-    unimplemented_rig("'a' intersect 'b' except 'c'", make_empty_doc, make_doc)
+    // Keys of different type families are distinct, even when their string values are equal
+    let s: Sequence<N> = no_src_no_result(
+        "map:size(map:merge((map{xs:date('2024-01-01'): 1}, map{'2024-01-01': 2})))",
+    )?;
+    assert_eq!(s.to_string(), "2");
+    // Decimals that differ only beyond double precision are distinct keys
+    let s: Sequence<N> =
+        no_src_no_result("map:size(map:merge((map{0.1: 1}, map{0.1000000000000000000001: 2})))")?;
+    assert_eq!(s.to_string(), "2");
+    let s: Sequence<N> = no_src_no_result("map{1: 'one'}?(1.0e0)")?;
+    assert_eq!(s.to_string(), "one");
+    Ok(())
+}
+pub fn generic_map_array_no_value<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    for (e, code) in [
+        ("boolean(map{})", "FORG0006"),
+        ("if ([1]) then 1 else 2", "FORG0006"),
+        ("not((1, 2))", "FORG0006"),
+        ("string(map{'a': 1})", "FOTY0014"),
+        ("string([1])", "FOTY0014"),
+    ] {
+        match no_src_no_result::<N>(e) {
+            Err(e) => assert_eq!(e.code.unwrap().to_string(), code),
+            Ok(s) => {
+                return Err(Error::new(
+                    ErrorKind::Unknown,
+                    format!("expected error, got \"{}\"", s.to_string()),
+                ))
+            }
+        }
+    }
+    Ok(())
 }
-pub fn generic_instanceof<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+pub fn generic_map_to_json<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
 where
     G: Fn() -> N,
     H: Fn() -> Item<N>,
 {
-    unimplemented_rig("'a' instance of empty-sequence()", make_empty_doc, make_doc)
+    let s: Sequence<N> =
+        no_src_no_result("map{'a': 1, 'b': [true(), 'x \"y\"', ()], 'c': (1, 2)}")?;
+    assert_eq!(
+        s.to_json(),
+        r#"{"a":1,"b":[true,"x \"y\"",null],"c":[1,2]}"#
+    );
+    Ok(())
 }
-pub fn generic_treat<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+
+// Function items
+pub fn generic_inline_function<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
 where
     G: Fn() -> N,
     H: Fn() -> Item<N>,
 {
-    unimplemented_rig("'a' treat as empty-sequence()", make_empty_doc, make_doc)
+    let s: Sequence<N> =
+        no_src_no_result("let $f := function($a, $b) { $a + $b } return $f(1, 2)")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "3");
+    Ok(())
 }
-pub fn generic_castable<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+pub fn generic_function_closure<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
 where
     G: Fn() -> N,
     H: Fn() -> Item<N>,
 {
-    unimplemented_rig("'a' castable as type?", make_empty_doc, make_doc)
+    let s: Sequence<N> =
+        no_src_no_result("let $x := 10, $f := function($a) { $a + $x } return $f(1)")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "11");
+    Ok(())
 }
-pub fn generic_cast<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+pub fn generic_named_function_ref<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
 where
     G: Fn() -> N,
     H: Fn() -> Item<N>,
 {
-    unimplemented_rig("'a' cast as type?", make_empty_doc, make_doc)
+    let s: Sequence<N> = no_src_no_result("fn:count#1((1, 2, 3))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "3");
+    Ok(())
 }
-pub fn generic_arrow<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+pub fn generic_fncall_eqname<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
 where
     G: Fn() -> N,
     H: Fn() -> Item<N>,
 {
-    unimplemented_rig("'a' => spec()", make_empty_doc, make_doc)
+    let s: Sequence<N> = no_src_no_result(
+        "(Q{http://www.w3.org/2005/xpath-functions}upper-case('abc'), map:size(Q{http://www.w3.org/2005/xpath-functions/map}merge((map{1: 2}, map{3: 4}))), Q{http://www.w3.org/2005/xpath-functions}count#1((1, 2, 3)))",
+    )?;
+    assert_eq!(s.len(), 3);
+    assert_eq!(s.to_string(), "ABC23");
+    Ok(())
 }
-pub fn generic_unary<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+pub fn generic_named_function_ref_variadic<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
 where
     G: Fn() -> N,
     H: Fn() -> Item<N>,
 {
-    unimplemented_rig("+'a'", make_empty_doc, make_doc)
+    let s: Sequence<N> = no_src_no_result("let $f := concat#3 return $f('a', 'b', 'c')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "abc");
+    Ok(())
 }
-pub fn generic_simplemap<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+pub fn generic_partial_application<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
 where
     G: Fn() -> N,
     H: Fn() -> Item<N>,
 {
-    unimplemented_rig("'a'!'b'", make_empty_doc, make_doc)
+    let s: Sequence<N> =
+        no_src_no_result("let $f := concat('a', ?, 'c') return ($f('b'), $f('x'))")?;
+    assert_eq!(s.len(), 2);
+    assert_eq!(s.to_string(), "abcaxc");
+    Ok(())
+}
+pub fn generic_partial_application_dynamic<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("let $f := function($a, $b) { $a - $b }, $g := $f(?, 1) return $g(10)")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "9");
+    Ok(())
+}
+pub fn generic_fn_for_each<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("for-each((1, 2, 3), function($x) { $x * 2 })")?;
+    assert_eq!(s.len(), 3);
+    assert_eq!(s.to_string(), "246");
+    Ok(())
+}
+pub fn generic_fn_filter<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("filter((1, 2, 3, 4), function($x) { $x > 2 })")?;
+    assert_eq!(s.len(), 2);
+    assert_eq!(s.to_string(), "34");
+    Ok(())
+}
+pub fn generic_fold_left<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("fold-left((1, 2, 3), 0, function($a, $b) { $a + $b })")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "6");
+    Ok(())
+}
+pub fn generic_fold_right<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("fold-right((1, 2, 3), (), function($a, $b) { ($b, $a) })")?;
+    assert_eq!(s.len(), 3);
+    assert_eq!(s.to_string(), "321");
+    Ok(())
+}
+pub fn generic_fn_sort<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("sort((3, 1, 2))")?;
+    assert_eq!(s.len(), 3);
+    assert_eq!(s.to_string(), "123");
+    Ok(())
+}
+pub fn generic_fn_sort_key<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("sort((3, 1, 2), (), function($x) { 0 - $x })")?;
+    assert_eq!(s.len(), 3);
+    assert_eq!(s.to_string(), "321");
+    Ok(())
+}
+pub fn generic_map_for_each<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("map:for-each(map{'a': 1, 'b': 2}, function($k, $v) { concat($k, $v) })")?;
+    assert_eq!(s.len(), 2);
+    assert_eq!(s.to_string(), "a1b2");
+    Ok(())
+}
+pub fn generic_array_higher_order<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("array:fold-left(array:filter(array:for-each([1, 2, 3, 4], function($m) { $m * 10 }), function($m) { $m > 15 }), 0, function($a, $m) { $a + $m })")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "90");
+    Ok(())
+}
+pub fn generic_array_sort<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("array:flatten(array:sort(['b', 'c', 'a']))")?;
+    assert_eq!(s.len(), 3);
+    assert_eq!(s.to_string(), "abc");
+    Ok(())
+}
+pub fn generic_map_array_as_function<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("(map{'a': 1}('a'), [4, 5](2))")?;
+    assert_eq!(s.len(), 2);
+    assert_eq!(s.to_string(), "15");
+    Ok(())
+}
+pub fn generic_function_arity<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let r: Result<Sequence<N>, Error> = no_src_no_result("function($a) { $a }(1, 2)");
+    match r {
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XPTY0004");
+            Ok(())
+        }
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+    }
+}
+
+pub fn generic_node_is<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig(
+        "if (descendant::b[@id = 'b3'] is a/b/a[@id = 'a2']/b[@id = 'b3']) then 'yes' else 'no'",
+        make_empty_doc,
+        make_doc,
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_node_is_not<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig(
+        "if (a/b[@id = 'b1'] is a/b[@id = 'b6']) then 'yes' else 'no'",
+        make_empty_doc,
+        make_doc,
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "no");
+    Ok(())
+}
+pub fn generic_node_before<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig(
+        "if (descendant::b[@id = 'b3'] << descendant::a[@id = 'a3']) then 'yes' else 'no'",
+        make_empty_doc,
+        make_doc,
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_node_after<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig(
+        "if (descendant::b[@id = 'b3'] >> descendant::a[@id = 'a3']) then 'yes' else 'no'",
+        make_empty_doc,
+        make_doc,
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "no");
+    Ok(())
+}
+pub fn generic_node_after_ancestor<N: Node, G, H>(
+    make_empty_doc: G,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig(
+        "if (descendant::b[@id = 'b3'] >> a) then 'yes' else 'no'",
+        make_empty_doc,
+        make_doc,
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_node_comparison_empty<N: Node, G, H>(
+    make_empty_doc: G,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig("a/b[@id = 'b1'] is ()", make_empty_doc, make_doc)?;
+    assert_eq!(s.len(), 0);
+    assert_eq!(s.to_string(), "");
+    Ok(())
+}
+pub fn generic_node_comparison_not_singleton<N: Node, G, H>(
+    make_empty_doc: G,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match dispatch_rig("a/b is a/b[@id = 'b1']", make_empty_doc, make_doc) {
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XPTY0004");
+            Ok(())
+        }
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+    }
+}
+
+fn unimplemented_rig<N: Node, G, H>(
+    e: impl AsRef<str>,
+    make_empty_doc: G,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match dispatch_rig(e, make_empty_doc, make_doc) {
+        Err(e) => {
+            if e.kind == ErrorKind::NotImplemented {
+                Ok(())
+            } else {
+                Err(Error::new(ErrorKind::Unknown, "unexpected error code"))
+            }
+        }
+        _ => Err(Error::new(ErrorKind::Unknown, "expected to fail")),
+    }
+}
+
+pub fn generic_union<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    dispatch_rig("'a' | 'b'", make_empty_doc, make_doc).map_or(Ok(()), |v| {
+        Err(Error::new(
+            ErrorKind::TypeError,
+            format!("expected type error, got \"{}\"", v.to_string()),
+        ))
+    })
+}
+pub fn generic_intersectexcept<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    unimplemented_rig("'a' intersect 'b' except 'c'", make_empty_doc, make_doc)
+}
+pub fn generic_instanceof<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("if ('a' instance of empty-sequence()) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "no");
+    Ok(())
+}
+pub fn generic_instanceof_occurrence<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("if ((1, 2) instance of xs:integer+) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_instanceof_atomic<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("if ((1, 'a') instance of xs:integer*) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "no");
+    Ok(())
+}
+pub fn generic_instanceof_node<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig(
+        "if (. instance of document-node()) then 'yes' else 'no'",
+        make_empty_doc,
+        make_doc,
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_instanceof_function<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("if (function($a) { $a } instance of function(*)) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_treat<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("'a' treat as xs:string")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "a");
+    Ok(())
+}
+pub fn generic_treat_error<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let r: Result<Sequence<N>, Error> = no_src_no_result("'a' treat as empty-sequence()");
+    match r {
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XPDY0050");
+            Ok(())
+        }
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+    }
+}
+pub fn generic_castable<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("if ('2024-01-01' castable as xs:date) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_castable_invalid<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("if ('2024-13-01' castable as xs:date) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "no");
+    Ok(())
+}
+pub fn generic_castable_empty<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("if (() castable as xs:integer?) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_cast<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("('5' cast as xs:integer) + 1")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "6");
+    Ok(())
+}
+pub fn generic_cast_double<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("('1.5' cast as xs:double) * 2")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "3");
+    Ok(())
+}
+pub fn generic_cast_invalid<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let r: Result<Sequence<N>, Error> = no_src_no_result("'a' cast as xs:integer");
+    match r {
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "FORG0001");
+            Ok(())
+        }
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+    }
+}
+pub fn generic_cast_empty<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let r: Result<Sequence<N>, Error> = no_src_no_result("() cast as xs:integer");
+    match r {
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XPTY0004");
+            Ok(())
+        }
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+    }
+}
+pub fn generic_quantified_some<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("if (some $x in (1, 2, 3) satisfies $x gt 2) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_quantified_every<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("if (every $x in (1, 2, 3) satisfies $x gt 2) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "no");
+    Ok(())
+}
+pub fn generic_quantified_bindings<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result(
+        "if (every $x in (1, 2), $y in ($x, 3) satisfies $y ge $x) then 'yes' else 'no'",
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_inline_function_types<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("function($a as xs:integer) as xs:double { $a + 1 }(1)")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "2");
+    Ok(())
+}
+pub fn generic_inline_function_type_error<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    // The argument is promoted to the declared type
+    let s: Sequence<N> = no_src_no_result(
+        "function($a as xs:double) { if ($a instance of xs:double) then 'yes' else 'no' }(1)",
+    )?;
+    assert_eq!(s.to_string(), "yes");
+    for e in [
+        "function($a as xs:integer) { $a }('one')",
+        "function($a) as xs:integer { $a }('one')",
+        // A string is not untyped, so it is not cast to the declared type
+        "function($a as xs:integer) { $a }('5')",
+        "function($a) as xs:integer { $a }('5')",
+    ] {
+        match no_src_no_result::<N>(e) {
+            Err(e) => assert_eq!(e.code.unwrap().to_string(), "XPTY0004"),
+            Ok(s) => {
+                return Err(Error::new(
+                    ErrorKind::Unknown,
+                    format!("expected error, got \"{}\"", s.to_string()),
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+pub fn generic_arrow<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("'x' => concat('y') => concat('z')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "xyz");
+    Ok(())
+}
+pub fn generic_arrow_count<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("('a', 'b', 'c') => count()")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "3");
+    Ok(())
+}
+pub fn generic_arrow_dynamic<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("let $f := function($a, $b) { $a + $b } return 1 => $f(2)")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "3");
+    Ok(())
+}
+pub fn generic_arrow_partial<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("(1 => (function($a, $b) { $a - $b })(?))(3)")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "-2");
+    Ok(())
+}
+pub fn generic_unary<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    unimplemented_rig("+'a'", make_empty_doc, make_doc)
+}
+pub fn generic_simplemap<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("'a'!'b'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "b");
+    Ok(())
+}
+pub fn generic_simplemap_context<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("(1, 2, 3) ! (. * 2)")?;
+    assert_eq!(s.len(), 3);
+    assert_eq!(s.to_string(), "246");
+    Ok(())
+}
+pub fn generic_simplemap_nodes<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig("a/b ! string(@id)", make_empty_doc, make_doc)?;
+    assert_eq!(s.len(), 2);
+    assert_eq!(s.to_string(), "b1b6");
+    Ok(())
+}
+pub fn generic_simplemap_chain<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("('a', 'b') ! concat(., '-') ! concat(., '+')")?;
+    assert_eq!(s.len(), 2);
+    assert_eq!(s.to_string(), "a-+b-+");
+    Ok(())
+}
+pub fn generic_simplemap_not_equal<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("if (1!=2) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_unary_lookup<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("([1, 2], [3, 4]) ! ?2")?;
+    assert_eq!(s.len(), 2);
+    assert_eq!(s.to_string(), "24");
+    Ok(())
+}
+
+pub fn generic_fncall_uppercase<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("upper-case('abc')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "ABC");
+    Ok(())
+}
+pub fn generic_fncall_lowercase<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("lower-case('ABC')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "abc");
+    Ok(())
+}
+pub fn generic_fncall_stringjoin<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string-join(('a', 'b', 'c'), '-')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "a-b-c");
+    Ok(())
+}
+pub fn generic_fncall_stringjoin_nosep<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string-join(('a', 'b', 'c'))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "abc");
+    Ok(())
+}
+pub fn generic_fncall_stringlength<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string-length('abcé')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "4");
+    Ok(())
+}
+pub fn generic_fncall_tokenize_whitespace<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("count(tokenize(' a  b c '))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "3");
+    Ok(())
+}
+pub fn generic_fncall_tokenize_pattern<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string-join(tokenize('a1b22c', '\\d+'), ',')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "a,b,c");
+    Ok(())
+}
+pub fn generic_fncall_tokenize_arrow<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("'a,b,c' => tokenize(',') => count()")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "3");
+    Ok(())
+}
+pub fn generic_fncall_matches<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("if (matches('abracadabra', '^a.*a$')) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_fncall_matches_flags<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("if (matches('HELLO world', 'hello', 'i')) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_fncall_matches_literal<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("if (matches('abc', 'a.c', 'q')) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "no");
+    Ok(())
+}
+pub fn generic_fncall_matches_name_escape<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("if (matches('a-b', '^\\i\\c*$')) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_fncall_replace<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("replace('abracadabra', 'a(.)', 'a$1$1')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "abbraccaddabbra");
+    Ok(())
+}
+pub fn generic_fncall_replace_flags<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("replace('Hello', 'h', 'J', 'i')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "Jello");
+    Ok(())
+}
+pub fn generic_fncall_codepointstostring<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("codepoints-to-string((72, 105))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "Hi");
+    Ok(())
+}
+pub fn generic_fncall_stringtocodepoints<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("string-join(string-to-codepoints('Hi') ! string(.), ' ')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "72 105");
+    Ok(())
+}
+pub fn generic_fncall_compare<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("compare('abc', 'abd')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "-1");
+    Ok(())
+}
+pub fn generic_fncall_compare_empty<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("compare((), 'abd')")?;
+    assert_eq!(s.len(), 0);
+    assert_eq!(s.to_string(), "");
+    Ok(())
+}
+pub fn generic_fncall_normalizeunicode<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("string-length(normalize-unicode(codepoints-to-string((101, 769))))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "1");
+    Ok(())
+}
+pub fn generic_fncall_normalizeunicode_nfd<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string-length(normalize-unicode('é', 'NFD'))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "2");
+    Ok(())
+}
+pub fn generic_fncall_encodeforuri<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("encode-for-uri('100% a/b~é')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "100%25%20a%2Fb~%C3%A9");
+    Ok(())
+}
+pub fn generic_fncall_escapehtmluri<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("escape-html-uri('http://a/b cé')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "http://a/b c%C3%A9");
+    Ok(())
+}
+pub fn generic_fncall_iritouri<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("iri-to-uri('http://a/b c<é>')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "http://a/b%20c%3C%C3%A9%3E");
+    Ok(())
+}
+pub fn generic_fncall_matches_badflag<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("matches('a', 'a', 'z')") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "FORX0001");
+            Ok(())
+        }
+    }
+}
+pub fn generic_fncall_matches_badregex<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("matches('a', '(?i)a')") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "FORX0002");
+            Ok(())
+        }
+    }
+}
+pub fn generic_fncall_tokenize_emptymatch<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("tokenize('abc', 'x*')") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "FORX0003");
+            Ok(())
+        }
+    }
+}
+pub fn generic_fncall_replace_badreplacement<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("replace('abc', 'b', '$x')") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "FORX0004");
+            Ok(())
+        }
+    }
+}
+pub fn generic_fncall_compare_collation<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("compare('a', 'b', 'http://example.org/collation')") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "FOCH0002");
+            Ok(())
+        }
+    }
+}
+pub fn generic_fncall_normalizeunicode_badform<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("normalize-unicode('a', 'XYZ')") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "FOCH0003");
+            Ok(())
+        }
+    }
+}
+pub fn generic_fncall_analyzestring<N: Node, G, H>(
+    make_empty_doc: G,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig(
+        "analyze-string('ab12c', '([a-z])(\\d+)')",
+        make_empty_doc,
+        make_doc,
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(
+        s[0].to_xml(),
+        "<fn:analyze-string-result xmlns:fn='http://www.w3.org/2005/xpath-functions'><fn:non-match>a</fn:non-match><fn:match><fn:group nr='1'>b</fn:group><fn:group nr='2'>12</fn:group></fn:match><fn:non-match>c</fn:non-match></fn:analyze-string-result>"
+    );
+    Ok(())
+}
+
+pub fn generic_fncall_analyzestring_groups<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    // No result document is needed. An empty group at the end of a match follows the previous group.
+    let s: Sequence<N> = no_src_no_result("analyze-string('a', '(a)()')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(
+        s[0].to_xml(),
+        "<fn:analyze-string-result xmlns:fn='http://www.w3.org/2005/xpath-functions'><fn:match><fn:group nr='1'>a</fn:group><fn:group nr='2'></fn:group></fn:match></fn:analyze-string-result>"
+    );
+    let s: Sequence<N> = no_src_no_result("analyze-string('ab', '(a())(b)')")?;
+    assert_eq!(
+        s[0].to_xml(),
+        "<fn:analyze-string-result xmlns:fn='http://www.w3.org/2005/xpath-functions'><fn:match><fn:group nr='1'>a<fn:group nr='2'></fn:group></fn:group><fn:group nr='3'>b</fn:group></fn:match></fn:analyze-string-result>"
+    );
+    Ok(())
+}
+pub fn generic_fncall_backreference<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("matches('abab', '^(ab)\\1$')")?;
+    assert_eq!(s.to_string(), "true");
+    let s: Sequence<N> = no_src_no_result("replace('aabc', '(.)\\1', '[$1]')")?;
+    assert_eq!(s.to_string(), "[a]bc");
+    let s: Sequence<N> = no_src_no_result("matches('abc', '\\p{IsBasicLatin}+')")?;
+    assert_eq!(s.to_string(), "true");
+    Ok(())
+}
+
+pub fn generic_fncall_empty<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("if (empty(())) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_fncall_exists<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("if (exists(('a', 'b'))) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_fncall_distinctvalues<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result(
+        "string-join(distinct-values((1, 2, 1, 'a', 'a', 2.0)) ! string(.), ',')",
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "1,2,a");
+    Ok(())
+}
+pub fn generic_fncall_indexof<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("string-join(index-of((10, 20, 30, 20), 20) ! string(.), ',')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "2,4");
+    Ok(())
+}
+pub fn generic_fncall_insertbefore<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string-join(insert-before(('a', 'b'), 2, 'x'), ',')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "a,x,b");
+    Ok(())
+}
+pub fn generic_fncall_insertbefore_end<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string-join(insert-before(('a', 'b'), 5, 'x'), ',')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "a,b,x");
+    Ok(())
+}
+pub fn generic_fncall_remove<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string-join(remove(('a', 'b', 'c'), 2), ',')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "a,c");
+    Ok(())
+}
+pub fn generic_fncall_remove_outofrange<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string-join(remove(('a', 'b', 'c'), 0), ',')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "a,b,c");
+    Ok(())
+}
+pub fn generic_fncall_reverse<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string-join(reverse(('a', 'b', 'c')), ',')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "c,b,a");
+    Ok(())
+}
+pub fn generic_fncall_subsequence<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("string-join(subsequence(('a', 'b', 'c', 'd', 'e'), 2, 3), ',')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "b,c,d");
+    Ok(())
+}
+pub fn generic_fncall_subsequence_round<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("string-join(subsequence(('a', 'b', 'c', 'd', 'e'), 1.5), ',')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "b,c,d,e");
+    Ok(())
+}
+pub fn generic_fncall_unordered<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("count(unordered(('a', 'b', 'c')))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "3");
+    Ok(())
+}
+pub fn generic_fncall_head<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("head(('a', 'b', 'c'))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "a");
+    Ok(())
+}
+pub fn generic_fncall_tail<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string-join(tail(('a', 'b', 'c')), ',')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "b,c");
+    Ok(())
+}
+pub fn generic_fncall_exactlyone<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("exactly-one('a')")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "a");
+    Ok(())
+}
+pub fn generic_fncall_deepequal<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("if (deep-equal((1, 'a', [1, 2], map{'k': 'v'}), (1, 'a', [1, 2], map{'k': 'v'}))) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_fncall_deepequal_neg<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("if (deep-equal((1, [1, 2]), (1, [1, 3]))) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "no");
+    Ok(())
+}
+pub fn generic_fncall_deepequal_nodes<N: Node, G, H>(
+    make_empty_doc: G,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig(
+        "if (deep-equal(//b[@id = 'b2'], //b[@id = 'b2'])) then 'yes' else 'no'",
+        make_empty_doc,
+        make_doc,
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_fncall_deepequal_nodes_neg<N: Node, G, H>(
+    make_empty_doc: G,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig(
+        "if (deep-equal(//b[@id = 'b2'], //b[@id = 'b3'])) then 'yes' else 'no'",
+        make_empty_doc,
+        make_doc,
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "no");
+    Ok(())
+}
+pub fn generic_fncall_innermost<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig(
+        "string-join(innermost(//a) ! string(@id), ',')",
+        make_empty_doc,
+        make_doc,
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "a2,a3,a4,a5");
+    Ok(())
+}
+pub fn generic_fncall_outermost<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig(
+        "string-join(outermost(//b) ! string(@id), ',')",
+        make_empty_doc,
+        make_doc,
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "b1,b6");
+    Ok(())
+}
+pub fn generic_fncall_root<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig("count(root(//b[@id = 'b2'])/a)", make_empty_doc, make_doc)?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "1");
+    Ok(())
+}
+pub fn generic_fncall_path<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig("path(//b[@id = 'b3'])", make_empty_doc, make_doc)?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "/Q{}a[1]/Q{}b[1]/Q{}a[1]/Q{}b[2]");
+    Ok(())
+}
+pub fn generic_fncall_path_attribute<N: Node, G, H>(
+    make_empty_doc: G,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig("path(//b[@id = 'b3']/@id)", make_empty_doc, make_doc)?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "/Q{}a[1]/Q{}b[1]/Q{}a[1]/Q{}b[2]/@id");
+    Ok(())
+}
+pub fn generic_fncall_haschildren<N: Node, G, H>(
+    make_empty_doc: G,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig(
+        "string-join((//b[@id = 'b1'], //b[@id = 'b2']) ! (if (has-children()) then 'yes' else 'no'), ',')",
+        make_empty_doc,
+        make_doc,
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes,no");
+    Ok(())
+}
+pub fn generic_fncall_namespaceuri<N: Node, G, H>(
+    make_empty_doc: G,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig("namespace-uri(//a[@id = 'a1'])", make_empty_doc, make_doc)?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "");
+    Ok(())
+}
+pub fn generic_fncall_id<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig(
+        "string-join(id('e3 e1') ! string(@id), ',')",
+        make_empty_doc,
+        make_doc,
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "e1,e3");
+    Ok(())
+}
+pub fn generic_fncall_idref<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig("count(idref('e3'))", make_empty_doc, make_doc)?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "1");
+    Ok(())
+}
+pub fn generic_fncall_lang<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig(
+        "string-join(//e ! (if (lang('en')) then 'yes' else 'no'), ',')",
+        make_empty_doc,
+        make_doc,
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes,yes,no");
+    Ok(())
+}
+pub fn generic_fncall_lang_arg<N: Node, G, H>(make_empty_doc: G, make_doc: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = dispatch_rig(
+        "if (lang('fr', //f)) then 'yes' else 'no'",
+        make_empty_doc,
+        make_doc,
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_fncall_zeroorone_error<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("zero-or-one((1, 2))") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "FORG0003");
+            Ok(())
+        }
+    }
+}
+pub fn generic_fncall_oneormore_error<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("one-or-more(())") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "FORG0004");
+            Ok(())
+        }
+    }
+}
+pub fn generic_fncall_exactlyone_error<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("exactly-one(())") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "FORG0005");
+            Ok(())
+        }
+    }
+}
+pub fn generic_fncall_deepequal_function<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("deep-equal(true#0, true#0)") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "FOTY0015");
+            Ok(())
+        }
+    }
+}
+pub fn generic_duration_canonical<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string('P1Y14M3DT25H' cast as xs:duration)")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "P2Y2M4DT1H");
+    Ok(())
+}
+pub fn generic_duration_zero<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string('P0Y' cast as xs:yearMonthDuration)")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "P0M");
+    Ok(())
+}
+pub fn generic_duration_compare<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("if (('P1Y' cast as xs:yearMonthDuration) eq ('P12M' cast as xs:yearMonthDuration)) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_datetime_subtract<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string(('2024-03-01T12:00:00Z' cast as xs:dateTime) - ('2024-02-28T10:30:00Z' cast as xs:dateTime))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "P2DT1H30M");
+    Ok(())
+}
+pub fn generic_date_add_duration<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result(
+        "string(('2024-01-31' cast as xs:date) + ('P1M' cast as xs:yearMonthDuration))",
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "2024-02-29");
+    Ok(())
+}
+pub fn generic_date_timezone<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result(
+        "(string('2024-01-01Z' cast as xs:date), string('2024-01-01+05:00' cast as xs:date), string(('2024-01-01T23:00:00-03:30' cast as xs:dateTime) cast as xs:date), string(timezone-from-date('2024-01-01-05:00' cast as xs:date)))",
+    )?;
+    assert_eq!(s.len(), 4);
+    assert_eq!(s[0].to_string(), "2024-01-01Z");
+    assert_eq!(s[1].to_string(), "2024-01-01+05:00");
+    assert_eq!(s[2].to_string(), "2024-01-01-03:30");
+    assert_eq!(s[3].to_string(), "-PT5H");
+    let s: Sequence<N> = no_src_no_result(
+        "string(adjust-date-to-timezone('2024-01-01+10:00' cast as xs:date, 'PT0S' cast as xs:dayTimeDuration))",
+    )?;
+    assert_eq!(s.to_string(), "2023-12-31Z");
+    Ok(())
+}
+pub fn generic_date_implicit_timezone<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    // A date without a timezone is in the implicit timezone
+    let mut stctxt = StaticContextBuilder::new()
+        .message(|_| Ok(()))
+        .fetcher(|_| Err(Error::new(ErrorKind::NotImplemented, "not implemented")))
+        .parser(|_| Err(Error::new(ErrorKind::NotImplemented, "not implemented")))
+        .build();
+    let s: Sequence<N> = ContextBuilder::new()
+        .implicit_timezone(chrono::FixedOffset::east_opt(10 * 3600).unwrap())
+        .build()
+        .dispatch(
+            &mut stctxt,
+            &parse(
+                "(string(('2024-01-02' cast as xs:date) - ('2024-01-01Z' cast as xs:date)), if (('2024-01-02' cast as xs:date) eq ('2024-01-01-14:00' cast as xs:date)) then 'yes' else 'no')",
+                None,
+            )?,
+        )?;
+    assert_eq!(s.len(), 2);
+    assert_eq!(s[0].to_string(), "PT14H");
+    assert_eq!(s[1].to_string(), "yes");
+    Ok(())
+}
+pub fn generic_datetime_add_daytime<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string(('2024-12-31T23:00:00Z' cast as xs:dateTime) + ('PT2H' cast as xs:dayTimeDuration))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "2025-01-01T01:00:00Z");
+    Ok(())
+}
+pub fn generic_duration_multiply<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string(('PT1H30M' cast as xs:dayTimeDuration) * 2)")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "PT3H");
+    Ok(())
+}
+pub fn generic_duration_divide<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result(
+        "('P1Y' cast as xs:yearMonthDuration) div ('P3M' cast as xs:yearMonthDuration)",
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "4");
+    Ok(())
+}
+pub fn generic_year_from_date<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("year-from-date('2024-05-17' cast as xs:date)")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "2024");
+    Ok(())
+}
+pub fn generic_hours_from_duration<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("hours-from-duration('P1DT25H' cast as xs:dayTimeDuration)")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "1");
+    Ok(())
+}
+pub fn generic_seconds_from_datetime<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("seconds-from-dateTime('2024-05-17T10:20:30.5Z' cast as xs:dateTime)")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "30.5");
+    Ok(())
+}
+pub fn generic_timezone_from_datetime<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result(
+        "string(timezone-from-dateTime('2024-05-17T10:20:30-05:00' cast as xs:dateTime))",
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "-PT5H");
+    Ok(())
+}
+pub fn generic_adjust_datetime<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string(adjust-dateTime-to-timezone('2024-05-17T10:00:00Z' cast as xs:dateTime, 'PT10H' cast as xs:dayTimeDuration))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "2024-05-17T20:00:00+10:00");
+    Ok(())
+}
+pub fn generic_duration_invalid<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("'P1Y2D' cast as xs:yearMonthDuration") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "FORG0001");
+            Ok(())
+        }
+    }
+}
+pub fn generic_duration_overflow<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    for e in [
+        "'P800000000000000000Y' cast as xs:duration",
+        "('P700000000000000000Y' cast as xs:yearMonthDuration) + ('P700000000000000000Y' cast as xs:yearMonthDuration)",
+        "('-P700000000000000000Y' cast as xs:yearMonthDuration) - ('P700000000000000000Y' cast as xs:yearMonthDuration)",
+    ] {
+        match no_src_no_result::<N>(e) {
+            Ok(s) => {
+                return Err(Error::new(
+                    ErrorKind::Unknown,
+                    format!("expected error, got \"{}\"", s.to_string()),
+                ))
+            }
+            Err(e) => assert_eq!(e.code.unwrap().to_string(), "FODT0002"),
+        }
+    }
+    Ok(())
+}
+pub fn generic_adjust_datetime_invalid<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("adjust-dateTime-to-timezone('2024-05-17T10:00:00Z' cast as xs:dateTime, 'PT15H' cast as xs:dayTimeDuration)") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "FODT0003");
+            Ok(())
+        }
+    }
+}
+pub fn generic_datetime_add_datetime<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("('2024-05-17T10:00:00Z' cast as xs:dateTime) + ('2024-05-17T10:00:00Z' cast as xs:dateTime)") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XPTY0004");
+            Ok(())
+        }
+    }
+}
+pub fn generic_datetime_keeps_timezone<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("string('2024-01-01T10:00:00+05:00' cast as xs:dateTime)")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "2024-01-01T10:00:00+05:00");
+    Ok(())
+}
+pub fn generic_datetime_no_timezone<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string('2024-01-01T10:00:00' cast as xs:dateTime)")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "2024-01-01T10:00:00");
+    Ok(())
+}
+pub fn generic_datetime_compare_timezones<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("if (('2024-01-01T10:00:00+05:00' cast as xs:dateTime) eq ('2024-01-01T05:00:00Z' cast as xs:dateTime)) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_timezone_from_datetime_absent<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result(
+        "count(timezone-from-dateTime('2024-01-01T10:00:00' cast as xs:dateTime))",
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "0");
+    Ok(())
+}
+pub fn generic_adjust_datetime_remove<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result(
+        "string(adjust-dateTime-to-timezone('2024-05-17T10:00:00+02:00' cast as xs:dateTime, ()))",
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "2024-05-17T10:00:00");
+    Ok(())
+}
+pub fn generic_adjust_time<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string(adjust-time-to-timezone('10:00:00-05:00' cast as xs:time, 'PT0S' cast as xs:dayTimeDuration))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "15:00:00Z");
+    Ok(())
+}
+pub fn generic_format_datetime_timezone<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result(
+        "format-dateTime('2024-01-01T10:00:00+05:00' cast as xs:dateTime, '[H]:[m]')",
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "10:00");
+    Ok(())
+}
+pub fn generic_constructor_ncname<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string(xs:NCName(' abc '))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "abc");
+    Ok(())
+}
+pub fn generic_constructor_token<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string(xs:token('  a   b  '))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "a b");
+    Ok(())
+}
+pub fn generic_constructor_language<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("if (xs:language('en-AU') instance of xs:language) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_constructor_derived<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("if (xs:NCName('a') instance of xs:Name) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_constructor_empty<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("count(xs:integer(()))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "0");
+    Ok(())
+}
+pub fn generic_gyear_timezone<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string(xs:gYear('2024+10:00'))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "2024+10:00");
+    Ok(())
+}
+pub fn generic_gyear_from_date<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string(xs:gYear(xs:date('2024-05-17')))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "2024");
+    Ok(())
+}
+pub fn generic_gday<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string(xs:gDay('---05Z'))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "---05Z");
+    Ok(())
+}
+pub fn generic_gyearmonth_equal<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result(
+        "if (xs:gYearMonth('2024-05') eq xs:gYearMonth('2024-05')) then 'yes' else 'no'",
+    )?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "yes");
+    Ok(())
+}
+pub fn generic_gmonth_castable<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> =
+        no_src_no_result("if ('--13' castable as xs:gMonth) then 'yes' else 'no'")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "no");
+    Ok(())
+}
+pub fn generic_base64_from_hex<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string(xs:base64Binary(xs:hexBinary('48656c6c6f')))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "SGVsbG8=");
+    Ok(())
+}
+pub fn generic_hex_from_base64<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string(xs:hexBinary(xs:base64Binary('SGVs bG8=')))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "48656C6C6F");
+    Ok(())
+}
+pub fn generic_anyuri<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    let s: Sequence<N> = no_src_no_result("string(xs:anyURI(' http://example.com/ '))")?;
+    assert_eq!(s.len(), 1);
+    assert_eq!(s.to_string(), "http://example.com/");
+    Ok(())
+}
+pub fn generic_constructor_ncname_invalid<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("xs:NCName('a:b')") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "FORG0001");
+            Ok(())
+        }
+    }
+}
+pub fn generic_gmonthday_invalid<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("xs:gMonthDay('--02-30')") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "FORG0001");
+            Ok(())
+        }
+    }
+}
+pub fn generic_base64_invalid<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("xs:base64Binary('abc')") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "FORG0001");
+            Ok(())
+        }
+    }
+}
+pub fn generic_datetimestamp_no_timezone<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("xs:dateTimeStamp('2024-01-01T00:00:00')") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "FORG0001");
+            Ok(())
+        }
+    }
+}
+pub fn generic_fn_error<N: Node, G, H>(_: G, _: H) -> Result<(), Error>
+where
+    G: Fn() -> N,
+    H: Fn() -> Item<N>,
+{
+    match no_src_no_result::<N>("error()") {
+        Ok(s) => {
+            return Err(Error::new(
+                ErrorKind::Unknown,
+                format!("expected error, got \"{}\"", s.to_string()),
+            ))
+        }
+        Err(e) => assert_eq!(e.code.unwrap().to_string(), "FOER0000"),
+    }
+    match no_src_no_result::<N>("error(QName('http://example.org/', 'ex:oops'), 'it failed')") {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.message, "it failed");
+            let code = e.code.unwrap();
+            assert_eq!(code.localname_to_string(), "oops");
+            assert_eq!(
+                code.namespace_uri_to_string(),
+                Some(String::from("http://example.org/"))
+            );
+            Ok(())
+        }
+    }
 }
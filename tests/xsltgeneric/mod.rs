@@ -5,9 +5,11 @@ use std::rc::Rc;
 use url::Url;
 use xrust::item::{Item, Node, Sequence, SequenceTrait};
 use xrust::namespace::NamespaceMap;
-use xrust::transform::context::StaticContextBuilder;
+use xrust::qname::QualifiedName;
+use xrust::transform::context::{ResultDocument, StaticContextBuilder};
+use xrust::value::Value;
 use xrust::xdmerror::{Error, ErrorKind};
-use xrust::xslt::from_document;
+use xrust::xslt::{from_document, package_from_document, Component, Package, Visibility};
 
 fn test_rig<N: Node, G, H, J>(
     src: impl AsRef<str>,
@@ -44,6 +46,71 @@ where
     ctxt.evaluate(&mut stctxt)
 }
 
+// Compile a package that does not use any other package.
+fn make_package<N: Node, G>(
+    pkg: impl AsRef<str>,
+    parse_from_str: G,
+) -> Result<Rc<Package<N>>, Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+{
+    let pkgdoc = parse_from_str(pkg.as_ref())?;
+    Ok(Rc::new(package_from_document(
+        pkgdoc,
+        None,
+        |s| parse_from_str(s),
+        |_| Ok(String::new()),
+        |name: &str, _: &str| {
+            Err(Error::new(
+                ErrorKind::Unknown,
+                format!("no package named \"{}\"", name),
+            ))
+        },
+    )?))
+}
+
+// Transform a source document with a stylesheet that uses packages.
+fn test_package_rig<N: Node, G, H, J>(
+    src: impl AsRef<str>,
+    pkgs: &[Rc<Package<N>>],
+    style: impl AsRef<str>,
+    parse_from_str: G,
+    _parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<Sequence<N>, Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let srcdoc = parse_from_str(src.as_ref())?;
+    let styledoc = parse_from_str(style.as_ref())?;
+    let mut stctxt = StaticContextBuilder::new()
+        .message(|_| Ok(()))
+        .fetcher(|_| Err(Error::new(ErrorKind::NotImplemented, "not implemented")))
+        .parser(|_| Err(Error::new(ErrorKind::NotImplemented, "not implemented")))
+        .build();
+    let mut ctxt = package_from_document(
+        styledoc,
+        None,
+        |s| parse_from_str(s),
+        |_| Ok(String::new()),
+        |name: &str, _: &str| -> Result<Rc<Package<N>>, Error> {
+            pkgs.iter()
+                .find(|pkg| pkg.name() == Some(name))
+                .cloned()
+                .ok_or_else(|| {
+                    Error::new(ErrorKind::Unknown, format!("no package named \"{}\"", name))
+                })
+        },
+    )?
+    .context()?;
+    ctxt.context(vec![Item::Node(srcdoc.clone())], 0);
+    ctxt.result_document(make_doc()?);
+    ctxt.populate_key_values(&mut stctxt, srcdoc.clone())?;
+    ctxt.evaluate(&mut stctxt)
+}
+
 fn test_msg_rig<N: Node, G, H, J>(
     src: impl AsRef<str>,
     style: impl AsRef<str>,
@@ -74,6 +141,55 @@ where
     Ok((seq, msgs))
 }
 
+fn test_params_rig<N: Node, G, H>(
+    src: impl AsRef<str>,
+    style: impl AsRef<str>,
+    params: Vec<(QualifiedName, Sequence<N>)>,
+    parse_from_str: G,
+    make_doc: H,
+) -> Result<Sequence<N>, Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+{
+    let srcdoc = parse_from_str(src.as_ref())?;
+    let styledoc = parse_from_str(style.as_ref())?;
+    let mut stctxt = StaticContextBuilder::new()
+        .message(|_| Ok(()))
+        .fetcher(|_| Err(Error::new(ErrorKind::NotImplemented, "not implemented")))
+        .parser(|_| Err(Error::new(ErrorKind::NotImplemented, "not implemented")))
+        .build();
+    let mut ctxt = from_document(styledoc, None, |s| parse_from_str(s), |_| Ok(String::new()))?;
+    ctxt.context(vec![Item::Node(srcdoc.clone())], 0);
+    ctxt.result_document(make_doc()?);
+    params.into_iter().for_each(|(qn, v)| ctxt.parameter(qn, v));
+    ctxt.evaluate(&mut stctxt)
+}
+
+fn test_result_documents_rig<N: Node, G, H>(
+    src: impl AsRef<str>,
+    style: impl AsRef<str>,
+    parse_from_str: G,
+    make_doc: H,
+) -> Result<(Sequence<N>, Vec<ResultDocument<N>>), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+{
+    let srcdoc = parse_from_str(src.as_ref())?;
+    let styledoc = parse_from_str(style.as_ref())?;
+    let mut stctxt = StaticContextBuilder::new()
+        .message(|_| Ok(()))
+        .fetcher(|_| Err(Error::new(ErrorKind::NotImplemented, "not implemented")))
+        .parser(|_| Err(Error::new(ErrorKind::NotImplemented, "not implemented")))
+        .build();
+    let mut ctxt = from_document(styledoc, None, |s| parse_from_str(s), |_| Ok(String::new()))?;
+    ctxt.context(vec![Item::Node(srcdoc.clone())], 0);
+    ctxt.result_document(make_doc()?);
+    let seq = ctxt.evaluate(&mut stctxt)?;
+    Ok((seq, ctxt.result_documents()))
+}
+
 pub fn generic_literal_text<N: Node, G, H, J>(
     parse_from_str: G,
     parse_from_str_with_ns: J,
@@ -170,14 +286,16 @@ where
         parse_from_str_with_ns,
         make_doc,
     )?;
-    if result.to_string() == "special &lt; less than" {
+    // The text node holds the string value, which is escaped when it is serialised
+    assert_eq!(result.to_string(), "special < less than");
+    if result.to_xml() == "special &lt; less than" {
         Ok(())
     } else {
         Err(Error::new(
             ErrorKind::Unknown,
             format!(
                 "got result \"{}\", expected \"special &lt; less than\"",
-                result.to_string()
+                result.to_xml()
             ),
         ))
     }
@@ -202,14 +320,16 @@ where
         parse_from_str_with_ns,
         make_doc,
     )?;
-    if result.to_string() == "special < less than" {
+    // The text node itself records that it is not escaped; there is no marker node
+    assert_eq!(result.len(), 1);
+    if result.to_xml() == "special < less than" {
         Ok(())
     } else {
         Err(Error::new(
             ErrorKind::Unknown,
             format!(
                 "got result \"{}\", expected \"special < less than\"",
-                result.to_string()
+                result.to_xml()
             ),
         ))
     }
@@ -821,6 +941,79 @@ where
     }
 }
 
+pub fn generic_function_namespace<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    // Built-in functions are found by namespace URI, whatever prefix is used
+    let result = test_rig(
+        "<Test/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:f='http://www.w3.org/2005/xpath-functions' xmlns:map='http://example.org/'>
+  <xsl:template match='/'>
+    <xsl:sequence select='f:upper-case("abc")'/>
+    <xsl:text>|</xsl:text>
+    <xsl:sequence select='map:size(1)'/>
+    <xsl:text>|</xsl:text>
+    <xsl:sequence select='map:size#1(1)'/>
+  </xsl:template>
+  <xsl:function name='map:size'>
+    <xsl:param name='m'/>
+    <xsl:text>mine</xsl:text>
+  </xsl:function>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "ABC|mine|mine");
+    Ok(())
+}
+
+pub fn generic_callable_function_item<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<Test><one>blue</one><two>yellow</two><three>green</three><four>blue</four></Test>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:eg='http://example.org/'>
+  <xsl:template match='/'><xsl:apply-templates/></xsl:template>
+  <xsl:template match='child::Test'>
+    <xsl:sequence select='for-each(child::*, eg:my_func#1)'/>
+  </xsl:template>
+  <xsl:function name='eg:my_func'>
+    <xsl:param name='my_param'/>
+    <xsl:sequence select='concat("[", $my_param, "]")'/>
+  </xsl:function>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    if result.to_string() == "[blue][yellow][green][blue]" {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ErrorKind::Unknown,
+            format!(
+                "got result \"{}\", expected \"[blue][yellow][green][blue]\"",
+                result.to_string()
+            ),
+        ))
+    }
+}
+
 pub fn generic_include<N: Node, G, H, J>(
     parse_from_str: G,
     _parse_from_str_with_ns: J,
@@ -1353,3 +1546,2550 @@ where
     );
     Ok(())
 }
+pub fn generic_variable_local<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<Test><one>blue</one><two>yellow</two><three>green</three><four>blue</four></Test>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'><xsl:apply-templates/></xsl:template>
+  <xsl:template match='child::Test'>
+    <xsl:variable name='n' select='count(child::*)'/>
+    <xsl:variable name='t'><a>1</a><a>2</a></xsl:variable>
+    <xsl:variable name='e'/>
+    <xsl:sequence select='$n'/>
+    <xsl:text>-</xsl:text>
+    <xsl:sequence select='count($t/child::a)'/>
+    <xsl:text>-</xsl:text>
+    <xsl:sequence select='string($t)'/>
+    <xsl:text>-</xsl:text>
+    <xsl:sequence select='string-length($e)'/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "4-2-12-0");
+    Ok(())
+}
+pub fn generic_variable_scope<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<Test><one>blue</one><two>yellow</two><three>green</three><four>blue</four></Test>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'><xsl:apply-templates/></xsl:template>
+  <xsl:template match='child::Test'>
+    <xsl:variable name='v' select='"outer"'/>
+    <xsl:for-each select='child::*'>
+      <xsl:variable name='v' select='string(.)'/>
+      <xsl:if test='$v eq "blue"'><xsl:variable name='w' select='name(.)'/><xsl:sequence select='$w'/></xsl:if>
+    </xsl:for-each>
+    <xsl:sequence select='$v'/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "onefourouter");
+    Ok(())
+}
+pub fn generic_variable_as<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<Test><one>blue</one><two>yellow</two><three>green</three><four>blue</four></Test>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:xs='http://www.w3.org/2001/XMLSchema'>
+  <xsl:template match='/'><xsl:apply-templates/></xsl:template>
+  <xsl:template match='child::Test'>
+    <xsl:variable name='s' as='xs:string*'><xsl:sequence select='"a"'/><xsl:sequence select='"b"'/></xsl:variable>
+    <xsl:variable name='e' as='xs:string*'/>
+    <xsl:sequence select='count($s)'/>
+    <xsl:sequence select='count($e)'/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "20");
+    Ok(())
+}
+pub fn generic_variable_global<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<Test><one>blue</one><two>yellow</two><three>green</three><four>blue</four></Test>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:eg='http://example.org/'>
+  <xsl:variable name='total' select='$count * 2'/>
+  <xsl:variable name='count' select='count(child::Test/child::*)'/>
+  <xsl:variable name='unused' select='$unused'/>
+  <xsl:template match='/'><xsl:apply-templates/></xsl:template>
+  <xsl:template match='child::Test'>
+    <xsl:sequence select='$total'/>
+    <xsl:text>-</xsl:text>
+    <xsl:sequence select='eg:count()'/>
+    <xsl:text>-</xsl:text>
+    <xsl:variable name='count' select='1'/>
+    <xsl:sequence select='$count'/>
+  </xsl:template>
+  <xsl:function name='eg:count'><xsl:sequence select='$count'/></xsl:function>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "8-4-1");
+    Ok(())
+}
+pub fn generic_variable_global_circular<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    match test_rig(
+        "<Test><one>blue</one><two>yellow</two><three>green</three><four>blue</four></Test>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:variable name='a' select='$b'/>
+  <xsl:variable name='b' select='$a'/>
+  <xsl:template match='/'><xsl:sequence select='$a'/></xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    ) {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTDE0640");
+            Ok(())
+        }
+    }
+}
+pub fn generic_variable_select_and_content<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    match test_rig(
+        "<Test><one>blue</one><two>yellow</two><three>green</three><four>blue</four></Test>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'><xsl:variable name='a' select='1'>2</xsl:variable><xsl:sequence select='$a'/></xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    ) {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTSE0620");
+            Ok(())
+        }
+    }
+}
+pub fn generic_variable_global_duplicate<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    match test_rig(
+        "<Test><one>blue</one><two>yellow</two><three>green</three><four>blue</four></Test>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:variable name='a' select='1'/>
+  <xsl:variable name='a' select='2'/>
+  <xsl:template match='/'><xsl:sequence select='$a'/></xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    ) {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTSE0630");
+            Ok(())
+        }
+    }
+}
+pub fn generic_variable_global_once<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let (result, msgs) = test_msg_rig(
+        "<Test/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:variable name='g'><xsl:message>evaluated</xsl:message>value</xsl:variable>
+  <xsl:template match='/'><xsl:sequence select='string($g)'/><xsl:apply-templates/></xsl:template>
+  <xsl:template match='child::Test'><xsl:sequence select='string($g)'/></xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "valuevalue");
+    assert_eq!(msgs, vec!["evaluated"]);
+    Ok(())
+}
+pub fn generic_param_supplied<N: Node, G, H, J>(
+    parse_from_str: G,
+    _parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_params_rig(
+        "<Test/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:xs='http://www.w3.org/2001/XMLSchema' xmlns:eg='http://example.org/'>
+  <xsl:param name='locale' select='"en"'/>
+  <xsl:param name='count' as='xs:integer' required='yes'/>
+  <xsl:param name='eg:flag' as='xs:boolean'>false</xsl:param>
+  <xsl:template match='/'>
+    <xsl:sequence select='$locale'/>
+    <xsl:text>-</xsl:text>
+    <xsl:sequence select='if ($count instance of xs:integer) then $count else "wrong"'/>
+    <xsl:text>-</xsl:text>
+    <xsl:sequence select='if ($eg:flag) then "on" else "off"'/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        vec![
+            (
+                QualifiedName::new(None, None, "locale"),
+                vec![Item::Value(Rc::new(Value::from("fr")))],
+            ),
+            (
+                QualifiedName::new(None, None, "count"),
+                vec![Item::Value(Rc::new(Value::from("41")))],
+            ),
+            (
+                QualifiedName::new(Some(String::from("http://example.org/")), None, "flag"),
+                vec![Item::Value(Rc::new(Value::from(true)))],
+            ),
+        ],
+        parse_from_str,
+        make_doc,
+    );
+    assert_eq!(result?.to_string(), "fr-41-on");
+    Ok(())
+}
+pub fn generic_param_default<N: Node, G, H, J>(
+    parse_from_str: G,
+    _parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_params_rig(
+        "<Test/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:xs='http://www.w3.org/2001/XMLSchema' xmlns:eg='http://example.org/'>
+  <xsl:param name='locale' select='"en"'/>
+  <xsl:param name='count' as='xs:integer' required='yes'/>
+  <xsl:param name='eg:flag' as='xs:boolean'>false</xsl:param>
+  <xsl:template match='/'>
+    <xsl:sequence select='$locale'/>
+    <xsl:text>-</xsl:text>
+    <xsl:sequence select='if ($count instance of xs:integer) then $count else "wrong"'/>
+    <xsl:text>-</xsl:text>
+    <xsl:sequence select='if ($eg:flag) then "on" else "off"'/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        vec![(
+            QualifiedName::new(None, None, "count"),
+            vec![Item::Value(Rc::new(Value::from(1)))],
+        )],
+        parse_from_str,
+        make_doc,
+    );
+    assert_eq!(result?.to_string(), "en-1-off");
+    Ok(())
+}
+pub fn generic_param_required<N: Node, G, H, J>(
+    parse_from_str: G,
+    _parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_params_rig(
+        "<Test/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:xs='http://www.w3.org/2001/XMLSchema' xmlns:eg='http://example.org/'>
+  <xsl:param name='locale' select='"en"'/>
+  <xsl:param name='count' as='xs:integer' required='yes'/>
+  <xsl:param name='eg:flag' as='xs:boolean'>false</xsl:param>
+  <xsl:template match='/'>
+    <xsl:sequence select='$locale'/>
+    <xsl:text>-</xsl:text>
+    <xsl:sequence select='if ($count instance of xs:integer) then $count else "wrong"'/>
+    <xsl:text>-</xsl:text>
+    <xsl:sequence select='if ($eg:flag) then "on" else "off"'/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        vec![],
+        parse_from_str,
+        make_doc,
+    );
+    match result {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTDE0050");
+            Ok(())
+        }
+    }
+}
+pub fn generic_param_type<N: Node, G, H, J>(
+    parse_from_str: G,
+    _parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_params_rig(
+        "<Test/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:xs='http://www.w3.org/2001/XMLSchema' xmlns:eg='http://example.org/'>
+  <xsl:param name='locale' select='"en"'/>
+  <xsl:param name='count' as='xs:integer' required='yes'/>
+  <xsl:param name='eg:flag' as='xs:boolean'>false</xsl:param>
+  <xsl:template match='/'>
+    <xsl:sequence select='$locale'/>
+    <xsl:text>-</xsl:text>
+    <xsl:sequence select='if ($count instance of xs:integer) then $count else "wrong"'/>
+    <xsl:text>-</xsl:text>
+    <xsl:sequence select='if ($eg:flag) then "on" else "off"'/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        vec![(
+            QualifiedName::new(None, None, "count"),
+            vec![Item::Value(Rc::new(Value::from("many")))],
+        )],
+        parse_from_str,
+        make_doc,
+    );
+    match result {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTTE0590");
+            Ok(())
+        }
+    }
+}
+pub fn generic_result_document<N: Node, G, H, J>(
+    parse_from_str: G,
+    _parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let (result, docs) = test_result_documents_rig(
+        "<Book><Chapter id='c1'>One</Chapter><Chapter id='c2'>Two</Chapter></Book>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'>
+    <xsl:for-each select='child::Book/child::Chapter'>
+      <xsl:result-document href='{@id}.html' indent='yes'>
+        <html><xsl:value-of select='.'/></html>
+      </xsl:result-document>
+    </xsl:for-each>
+    <xsl:result-document href=''><xsl:text>primary</xsl:text></xsl:result-document>
+    <xsl:text>-</xsl:text>
+    <xsl:sequence select='count(child::Book/child::Chapter)'/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "primary-2");
+    assert_eq!(docs.len(), 2);
+    assert_eq!(docs[0].href, "c1.html");
+    assert_eq!(docs[0].document.to_xml(), "<html>One</html>");
+    assert!(docs[0].output.get_indent());
+    assert_eq!(docs[1].href, "c2.html");
+    assert_eq!(docs[1].document.to_xml(), "<html>Two</html>");
+    Ok(())
+}
+pub fn generic_result_document_duplicate<N: Node, G, H, J>(
+    parse_from_str: G,
+    _parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    match test_result_documents_rig(
+        "<Book><Chapter id='c1'>One</Chapter><Chapter id='c2'>Two</Chapter></Book>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'>
+    <xsl:for-each select='child::Book/child::Chapter'>
+      <xsl:result-document href='chapter.html'><xsl:value-of select='.'/></xsl:result-document>
+    </xsl:for-each>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        make_doc,
+    ) {
+        Ok((s, _)) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTDE1490");
+            Ok(())
+        }
+    }
+}
+pub fn generic_decimal_format<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<Invoice><Total>1234567.891</Total></Invoice>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:fmt='http://example.org/formats'>
+  <xsl:decimal-format name='fmt:eu' decimal-separator=',' grouping-separator='.'/>
+  <xsl:decimal-format NaN='n/a' infinity='lots'/>
+  <xsl:template match='/'>
+    <xsl:value-of select="format-number(number(child::Invoice/child::Total), '#.##0,00', 'fmt:eu')"/>
+    <xsl:text>|</xsl:text>
+    <xsl:value-of xmlns:f='http://example.org/formats' select="format-number(12345.6, '#.##0,00', 'f:eu')"/>
+    <xsl:text>|</xsl:text>
+    <xsl:value-of select="format-number(12345.6, '#.##0,00', 'Q{http://example.org/formats}eu')"/>
+    <xsl:text>|</xsl:text>
+    <xsl:value-of select="format-number(number('none'), '#')"/>
+    <xsl:text>|</xsl:text>
+    <xsl:value-of select="format-number(xs:double('INF'), '#')"/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(
+        result.to_string(),
+        "1.234.567,89|12.345,60|12.345,60|n/a|lots"
+    );
+    Ok(())
+}
+pub fn generic_decimal_format_conflict<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    match test_rig(
+        "<Doc/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:decimal-format name='eu' decimal-separator=',' grouping-separator='.'/>
+  <xsl:decimal-format name='eu' decimal-separator=',' grouping-separator='_'/>
+  <xsl:template match='/'>nothing</xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    ) {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTSE1290");
+            Ok(())
+        }
+    }
+}
+pub fn generic_output_serialization<N: Node, G, H, J>(
+    parse_from_str: G,
+    _parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let (result, docs) = test_result_documents_rig(
+        "<Doc><code>a &lt; b</code><p>© 2024</p></Doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:output method='xml' standalone='yes' doctype-system='out.dtd' cdata-section-elements='code' use-character-maps='copy'/>
+  <xsl:character-map name='copy'>
+    <xsl:output-character character='©' string='(c)'/>
+  </xsl:character-map>
+  <xsl:template match='/'>
+    <xsl:result-document href='out.xml'>
+      <out><xsl:copy-of select='child::Doc/child::*'/></out>
+    </xsl:result-document>
+    <xsl:result-document href='out.txt' method='text' item-separator='|'>
+      <xsl:copy-of select='child::Doc/child::p'/>
+    </xsl:result-document>
+    <xsl:sequence select='(1, 2, 3)'/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        make_doc,
+    )?;
+    assert_eq!(docs.len(), 2);
+    assert_eq!(
+        docs[0].document.to_xml_with_options(&docs[0].output),
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><!DOCTYPE out SYSTEM "out.dtd"><out><code><![CDATA[a < b]]></code><p>(c) 2024</p></out>"#
+    );
+    assert_eq!(
+        docs[1].document.to_xml_with_options(&docs[1].output),
+        "(c) 2024"
+    );
+    assert_eq!(result.to_xml_with_options(&docs[1].output), "1|2|3");
+    Ok(())
+}
+pub fn generic_output_html<N: Node, G, H, J>(
+    parse_from_str: G,
+    _parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let (_, docs) = test_result_documents_rig(
+        "<Doc><title>Caf&#233;</title></Doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:output method='html'/>
+  <xsl:template match='/'>
+    <xsl:result-document href='page.html'>
+      <html>
+        <head><meta charset='ISO-8859-1'/><title><xsl:value-of select='child::Doc/child::title'/></title></head>
+        <body><br/><input type='checkbox' checked='checked'/><input name='value' value='value'/><br>text</br><a href='café.html?q=&lt;' title='a &lt; b'>menu</a><script>if (a &lt; b) {}</script></body>
+      </html>
+    </xsl:result-document>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        make_doc,
+    )?;
+    assert_eq!(docs.len(), 1);
+    assert_eq!(
+        docs[0].document.to_xml_with_options(&docs[0].output),
+        "<!DOCTYPE html><html><head><meta charset='UTF-8'><title>Café</title></head><body><br><input checked type='checkbox'><input name='value' value='value'><br><a href='caf%C3%A9.html?q=<' title='a < b'>menu</a><script>if (a < b) {}</script></body></html>"
+    );
+    Ok(())
+}
+pub fn generic_output_escaping<N: Node, G, H, J>(
+    parse_from_str: G,
+    _parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    // Constructed text and source text are both escaped once, and only once, by the serializers
+    let (_, docs) = test_result_documents_rig(
+        "<Doc><code>a &lt; b &amp; c</code><js>x &amp;lt; y</js></Doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'>
+    <xsl:result-document href='out.xml' cdata-section-elements='code'>
+      <out><code><xsl:value-of select='child::Doc/child::code'/></code><p title="it's"><xsl:value-of select='child::Doc/child::code'/><xsl:text>&gt;</xsl:text><xsl:text disable-output-escaping='yes'>&lt;br/&gt;</xsl:text></p></out>
+    </xsl:result-document>
+    <xsl:result-document href='out.html' method='html'>
+      <html><body><script><xsl:value-of select='child::Doc/child::js'/></script><p><xsl:value-of select='child::Doc/child::js'/></p></body></html>
+    </xsl:result-document>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        make_doc,
+    )?;
+    assert_eq!(docs.len(), 2);
+    assert_eq!(
+        docs[0].document.to_xml_with_options(&docs[0].output),
+        r#"<?xml version="1.0" encoding="UTF-8"?><out><code><![CDATA[a < b & c]]></code><p title='it&apos;s'>a &lt; b &amp; c&gt;<br/></p></out>"#
+    );
+    assert_eq!(
+        docs[1].document.to_xml_with_options(&docs[1].output),
+        "<!DOCTYPE html><html><body><script>x &lt; y</script><p>x &amp;lt; y</p></body></html>"
+    );
+    Ok(())
+}
+pub fn generic_output_unknown_method<N: Node, G, H, J>(
+    parse_from_str: G,
+    _parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    match test_result_documents_rig(
+        "<Doc/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:output method='pdf'/>
+  <xsl:template match='/'>nothing</xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        make_doc,
+    ) {
+        Ok((s, _)) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTSE1570");
+            Ok(())
+        }
+    }
+}
+pub fn generic_output_xhtml<N: Node, G, H, J>(
+    parse_from_str: G,
+    _parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let (result, docs) = test_result_documents_rig(
+        "<Doc/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:output method='xhtml' html-version='5' omit-xml-declaration='yes' byte-order-mark='yes'/>
+  <xsl:template match='/'>
+    <xsl:result-document href='page.xhtml'>
+      <html><body><br/><p/></body></html>
+    </xsl:result-document>
+    <br/><hr/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        make_doc,
+    )?;
+    assert_eq!(docs.len(), 1);
+    assert_eq!(
+        docs[0].document.to_xml_with_options(&docs[0].output),
+        "\u{feff}<!DOCTYPE html><html><body><br /><p></p></body></html>"
+    );
+    assert_eq!(
+        result.to_xml_with_options(&docs[0].output),
+        "\u{feff}<br /><hr />"
+    );
+    Ok(())
+}
+pub fn generic_output_adaptive<N: Node, G, H, J>(
+    parse_from_str: G,
+    _parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    match test_result_documents_rig(
+        "<Doc/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:output method='adaptive'/>
+  <xsl:template match='/'>nothing</xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        make_doc,
+    ) {
+        Ok((s, _)) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "SEPM0016");
+            Ok(())
+        }
+    }
+}
+pub fn generic_output_character_map_circular<N: Node, G, H, J>(
+    parse_from_str: G,
+    _parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    match test_result_documents_rig(
+        "<Doc/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:output use-character-maps='a'/>
+  <xsl:character-map name='a' use-character-maps='b'/>
+  <xsl:character-map name='b' use-character-maps='a'/>
+  <xsl:template match='/'>nothing</xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        make_doc,
+    ) {
+        Ok((s, _)) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTSE1600");
+            Ok(())
+        }
+    }
+}
+pub fn generic_number_multiple<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc><chapter><section><title/></section><section><title/><section><title/></section></section></chapter><chapter><section><title/></section></chapter></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'><xsl:apply-templates select='descendant::title'/></xsl:template>
+  <xsl:template match='title'><xsl:number level='multiple' count='chapter|section' format='1.a.i'/><xsl:text>|</xsl:text></xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "1.a|1.b|1.b.i|2.a|");
+    Ok(())
+}
+pub fn generic_number_any<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc><chapter><p>text<note/></p><note/></chapter><chapter><note/><p><note/></p></chapter></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'><xsl:apply-templates select='descendant::note'/></xsl:template>
+  <xsl:template match='note'><xsl:number level='any' from='chapter' format='(1)'/><xsl:number level='any'/></xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "(1)1(2)2(1)3(2)4");
+    Ok(())
+}
+pub fn generic_number_from_unmatched<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    // No ancestor matches the from pattern, so there is nothing to number
+    let result = test_rig(
+        "<doc><chapter><section><title/></section></chapter></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'><xsl:apply-templates select='descendant::title'/></xsl:template>
+  <xsl:template match='title'>
+    <xsl:text>[</xsl:text>
+    <xsl:number level='single' count='section' from='appendix'/>
+    <xsl:text>|</xsl:text>
+    <xsl:number level='multiple' count='chapter|section' from='appendix'/>
+    <xsl:text>|</xsl:text>
+    <xsl:number level='any' count='section' from='appendix'/>
+    <xsl:text>|</xsl:text>
+    <xsl:number level='multiple' count='chapter|section'/>
+    <xsl:text>]</xsl:text>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "[|||1.1]");
+    Ok(())
+}
+pub fn generic_number_format<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'>
+    <xsl:number value='1234567' grouping-separator='_' grouping-size='3'/>
+    <xsl:text>|</xsl:text>
+    <xsl:number value='28' format='a'/>
+    <xsl:text>|</xsl:text>
+    <xsl:number value='3' format='i' letter-value='alphabetic'/>
+    <xsl:text>|</xsl:text>
+    <xsl:number value='(1, 2, 3)' format='[A-1]'/>
+    <xsl:text>|</xsl:text>
+    <xsl:number value='21' format='Ww' lang='en'/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "1_234_567|ab|k|[A-2-3]|Twenty One");
+    Ok(())
+}
+pub fn generic_mode_shallow_copy<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc><keep a='1'>text<!--note--></keep><drop>gone</drop></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:mode on-no-match='shallow-copy'/>
+  <xsl:template match='drop'/>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(
+        result.to_xml(),
+        "<doc><keep a='1'>text<!--note--></keep></doc>"
+    );
+    Ok(())
+}
+pub fn generic_mode_skip<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc><p>one</p><div><p>two</p></div>text</doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:mode name='deep' on-no-match='deep-skip'/>
+  <xsl:mode name='shallow' on-no-match='shallow-skip'/>
+  <xsl:template match='/'>
+    <xsl:apply-templates select='child::doc/child::node()' mode='deep'/>
+    <xsl:text>|</xsl:text>
+    <xsl:apply-templates select='child::doc/child::node()' mode='shallow'/>
+  </xsl:template>
+  <xsl:template match='p' mode='deep shallow'>[<xsl:value-of select='.'/>]</xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "[one]|[one][two]");
+    Ok(())
+}
+pub fn generic_mode_current<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc><b><i>x</i>y</b><i>z</i></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform' default-mode='m'>
+  <xsl:mode name='m' on-no-match='shallow-copy'/>
+  <xsl:mode name='copy' on-no-match='deep-copy'/>
+  <xsl:template match='b'><B><xsl:apply-templates mode='#current'/></B></xsl:template>
+  <xsl:template match='i'>I</xsl:template>
+  <xsl:template match='i' mode='#unnamed'>unnamed</xsl:template>
+  <xsl:template match='doc' mode='copy'><xsl:apply-templates mode='#unnamed'/></xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_xml(), "<doc><B>Iy</B>I</doc>");
+    Ok(())
+}
+pub fn generic_mode_fail<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    match test_rig(
+        "<doc><p>one</p></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:mode on-no-match='fail'/>
+  <xsl:template match='/'><xsl:apply-templates/></xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    ) {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTDE0555");
+            Ok(())
+        }
+    }
+}
+pub fn generic_mode_multiple_match<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    match test_rig(
+        "<doc><p>one</p></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:mode on-multiple-match='fail'/>
+  <xsl:template match='p'>first</xsl:template>
+  <xsl:template match='p'>second</xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    ) {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTDE0540");
+            Ok(())
+        }
+    }
+}
+pub fn generic_mode_warning<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let (result, msgs) = test_msg_rig(
+        "<doc><p>one</p><q>two</q></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:mode on-no-match='deep-skip' warning-on-no-match='yes'/>
+  <xsl:template match='/'><xsl:apply-templates select='child::doc/child::*'/></xsl:template>
+  <xsl:template match='p'>found</xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "found");
+    assert_eq!(msgs.len(), 1);
+    Ok(())
+}
+pub fn generic_iterate_total<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc><n>1</n><n>2</n><n>3</n><n>4</n></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'>
+    <xsl:iterate select='child::doc/child::n'>
+      <xsl:param name='total' select='0'/>
+      <xsl:on-completion><xsl:text>total </xsl:text><xsl:value-of select='$total'/></xsl:on-completion>
+      <xsl:variable name='t' select='$total + number(.)'/>
+      <xsl:value-of select='$t'/>
+      <xsl:text>,</xsl:text>
+      <xsl:next-iteration>
+        <xsl:with-param name='total' select='$t'/>
+      </xsl:next-iteration>
+    </xsl:iterate>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "1,3,6,10,total 10");
+    Ok(())
+}
+pub fn generic_iterate_break<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc><n>1</n><n>2</n><n>3</n><n>4</n></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'>
+    <xsl:iterate select='child::doc/child::n'>
+      <xsl:on-completion>done</xsl:on-completion>
+      <xsl:choose>
+        <xsl:when test='number(.) gt 2'><xsl:break select="'stop'"/></xsl:when>
+        <xsl:otherwise><xsl:value-of select='.'/><xsl:text>,</xsl:text></xsl:otherwise>
+      </xsl:choose>
+    </xsl:iterate>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "1,2,stop");
+    Ok(())
+}
+pub fn generic_iterate_long<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'>
+    <xsl:iterate select='1 to 5000'>
+      <xsl:param name='count' select='0'/>
+      <xsl:on-completion select='$count'/>
+      <xsl:next-iteration>
+        <xsl:with-param name='count' select='$count + 1'/>
+      </xsl:next-iteration>
+    </xsl:iterate>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "5000");
+    Ok(())
+}
+pub fn generic_iterate_prefixed_param<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc><n>1</n><n>2</n><n>3</n><n>4</n></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:p='urn:test:p'>
+  <xsl:template match='/'>
+    <xsl:iterate select='child::doc/child::n'>
+      <xsl:param name='p:total' select='0'/>
+      <xsl:on-completion select='$p:total'/>
+      <xsl:next-iteration>
+        <xsl:with-param name='p:total' select='$p:total + number(.)'/>
+      </xsl:next-iteration>
+    </xsl:iterate>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "10");
+    Ok(())
+}
+pub fn generic_iterate_unknown_param<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    match test_rig(
+        "<doc><n>1</n><n>2</n><n>3</n><n>4</n></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'>
+    <xsl:iterate select='child::doc/child::n'>
+      <xsl:param name='total' select='0'/>
+      <xsl:next-iteration>
+        <xsl:with-param name='sum' select='1'/>
+      </xsl:next-iteration>
+    </xsl:iterate>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    ) {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTSE3130");
+            Ok(())
+        }
+    }
+}
+pub fn generic_break_outside_iterate<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    match test_rig(
+        "<doc><n>1</n><n>2</n><n>3</n><n>4</n></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'><xsl:break/></xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    ) {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTSE3120");
+            Ok(())
+        }
+    }
+}
+pub fn generic_try_catch<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:err='http://www.w3.org/2005/xqt-errors' xmlns:e='http://example.org/errors'>
+  <xsl:template match='/'>
+    <xsl:try>
+      <xsl:text>lost</xsl:text>
+      <xsl:sequence select="error(QName('http://example.org/errors', 'e:BAD'), 'went wrong', 42)"/>
+      <xsl:catch errors='err:FOER0000'>wrong catch</xsl:catch>
+      <xsl:catch errors='e:*'>[<xsl:value-of select='$err:code'/>|<xsl:value-of select='$err:description'/>|<xsl:value-of select='$err:value'/>]</xsl:catch>
+    </xsl:try>
+    <xsl:try select='error()'>
+      <xsl:catch errors='err:FOER0000' select='$err:description'/>
+    </xsl:try>
+    <xsl:try>
+      <xsl:message terminate='yes'>stop</xsl:message>
+      <xsl:catch>|recovered</xsl:catch>
+    </xsl:try>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(
+        result.to_string(),
+        "[e:BAD|went wrong|42]error raised by the error function|recovered"
+    );
+    Ok(())
+}
+pub fn generic_try_catch_variables<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:err='http://www.w3.org/2005/xqt-errors' xmlns:a='http://example.org/a' xmlns:b='http://example.org/b'>
+  <xsl:variable name='value' select='"mine"'/>
+  <xsl:variable name='a:x' select='"A"'/>
+  <xsl:variable name='b:x' select='"B"'/>
+  <xsl:template match='/'>
+    <xsl:variable name='code' select='"local"'/>
+    <xsl:try select="error(QName('http://example.org/errors', 'BAD'), 'oops', 7)">
+      <xsl:catch><xsl:value-of select="string-join(($value, $code, string($err:value), string($err:code), $a:x, $b:x), '|')"/></xsl:catch>
+    </xsl:try>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "mine|local|7|BAD|A|B");
+    Ok(())
+}
+pub fn generic_try_uncaught<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    match test_rig(
+        "<doc/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:err='http://www.w3.org/2005/xqt-errors'>
+  <xsl:template match='/'>
+    <xsl:try select="error(QName('http://example.org/errors', 'BAD'))">
+      <xsl:catch errors='err:FOER0000'>wrong catch</xsl:catch>
+    </xsl:try>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    ) {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "BAD");
+            Ok(())
+        }
+    }
+}
+pub fn generic_try_rollback<N: Node, G, H, J>(
+    parse_from_str: G,
+    _parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let (result, docs) = test_result_documents_rig(
+        "<doc/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'>
+    <xsl:try>
+      <xsl:result-document href='one.xml'><one/></xsl:result-document>
+      <xsl:sequence select='error()'/>
+      <xsl:catch>rolled back</xsl:catch>
+    </xsl:try>
+    <xsl:try rollback-output='no'>
+      <xsl:result-document href='two.xml'><two/></xsl:result-document>
+      <xsl:sequence select='error()'/>
+      <xsl:catch>|kept</xsl:catch>
+    </xsl:try>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "rolled back|kept");
+    assert_eq!(docs.len(), 1);
+    assert_eq!(docs[0].href, "two.xml");
+    Ok(())
+}
+pub fn generic_tunnel_params<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc><a><b/></a></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'>
+    <xsl:apply-templates select='child::doc'>
+      <xsl:with-param name='cfg' tunnel='yes' select="'deep'"/>
+    </xsl:apply-templates>
+    <xsl:apply-templates select='child::doc/child::a/child::b'/>
+  </xsl:template>
+  <xsl:template match='a'>
+    <xsl:param name='cfg' tunnel='yes' select="'none'"/>
+    <xsl:param name='plain' select="'default'"/>
+    <xsl:value-of select="concat('a:', $cfg, '/', $plain, ';')"/>
+    <xsl:call-template name='t'>
+      <xsl:with-param name='cfg' tunnel='yes' select="'override'"/>
+    </xsl:call-template>
+    <xsl:apply-templates/>
+  </xsl:template>
+  <xsl:template name='t'>
+    <xsl:param name='cfg' tunnel='yes'/>
+    <xsl:value-of select="concat('t:', $cfg, ';')"/>
+  </xsl:template>
+  <xsl:template match='b'>
+    <xsl:param name='cfg' tunnel='yes' select="'none'"/>
+    <xsl:value-of select="concat('b:', $cfg, ';')"/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(
+        result.to_string(),
+        "a:deep/default;t:override;b:deep;b:none;"
+    );
+    Ok(())
+}
+pub fn generic_apply_templates_params<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc><a><b/></a></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:eg='http://example.org/'>
+  <xsl:template match='/'>
+    <xsl:apply-templates select='child::doc/child::a'>
+      <xsl:with-param name='plain' select="'supplied'"/>
+      <xsl:with-param name='eg:other' select="'qualified'"/>
+    </xsl:apply-templates>
+  </xsl:template>
+  <xsl:template match='a'>
+    <xsl:param name='plain' select="'default'"/>
+    <xsl:param name='eg:other'/>
+    <xsl:value-of select="concat('a:', $plain, '/', $eg:other, ';')"/>
+    <xsl:apply-templates/>
+  </xsl:template>
+  <xsl:template match='b'>
+    <xsl:param name='plain' select="'default'"/>
+    <xsl:value-of select="concat('b:', $plain, ';')"/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "a:supplied/qualified;b:default;");
+    Ok(())
+}
+pub fn generic_builtin_template_params<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    // There is no template rule for doc or a, so the built-in template rule passes the parameter on
+    let result = test_rig(
+        "<doc><a><b/></a></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'>
+    <xsl:apply-templates select='child::doc'>
+      <xsl:with-param name='plain' select="'supplied'"/>
+    </xsl:apply-templates>
+  </xsl:template>
+  <xsl:template match='b'>
+    <xsl:param name='plain' select="'default'"/>
+    <xsl:value-of select="concat('b:', $plain, ';')"/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "b:supplied;");
+    Ok(())
+}
+pub fn generic_prefixed_params<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    // Parameters are bound by their expanded name, so a reference may use a different prefix
+    let result = test_rig(
+        "<doc/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'
+  xmlns:eg='http://example.org/' xmlns:p='urn:params'>
+  <xsl:function name='eg:f'>
+    <xsl:param name='p:x'/>
+    <xsl:sequence select='$q:x * 2' xmlns:q='urn:params'/>
+  </xsl:function>
+  <xsl:template name='t'>
+    <xsl:param name='p:y'/>
+    <xsl:sequence select='$p:y'/>
+  </xsl:template>
+  <xsl:template match='/'>
+    <xsl:sequence select='eg:f(21)'/>
+    <xsl:text>;</xsl:text>
+    <xsl:call-template name='t'>
+      <xsl:with-param name='p:y' select="'named'"/>
+    </xsl:call-template>
+    <xsl:text>;</xsl:text>
+    <xsl:sequence select='function($p:z) { $p:z + 1 }(2)'/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "42;named;3");
+    Ok(())
+}
+pub fn generic_typed_params<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc><n>41</n></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'
+  xmlns:xs='http://www.w3.org/2001/XMLSchema'
+  xmlns:f='http://example.org/functions'>
+  <xsl:template match='/'>
+    <xsl:variable name='v' as='xs:integer' select='child::doc/child::n'/>
+    <xsl:call-template name='t'>
+      <xsl:with-param name='n' select='child::doc/child::n'/>
+    </xsl:call-template>
+    <xsl:text>,</xsl:text>
+    <xsl:value-of select="if ($v instance of xs:integer) then 'integer' else 'other'"/>
+    <xsl:text>,</xsl:text>
+    <xsl:value-of select="if (f:half(3) instance of xs:double) then f:half(3) else 'other'"/>
+  </xsl:template>
+  <xsl:template name='t'>
+    <xsl:param name='n' as='xs:integer'/>
+    <xsl:value-of select='$n + 1'/>
+  </xsl:template>
+  <xsl:function name='f:half' as='xs:double'>
+    <xsl:param name='x' as='xs:double'/>
+    <xsl:sequence select='$x div 2'/>
+  </xsl:function>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "42,integer,1.5");
+    Ok(())
+}
+pub fn generic_variable_type_error<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    match test_rig(
+        "<doc/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'
+  xmlns:xs='http://www.w3.org/2001/XMLSchema'>
+  <xsl:template match='/'>
+    <xsl:variable name='v' as='xs:integer' select="'abc'"/>
+    <xsl:value-of select='$v'/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    ) {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTTE0570");
+            Ok(())
+        }
+    }
+}
+pub fn generic_param_string_type_error<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    match test_rig(
+        "<doc/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'
+  xmlns:xs='http://www.w3.org/2001/XMLSchema'>
+  <xsl:template match='/'>
+    <xsl:call-template name='t'/>
+  </xsl:template>
+  <xsl:template name='t'>
+    <xsl:param name='n' as='xs:integer' select="'5'"/>
+    <xsl:value-of select='$n'/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    ) {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTTE0570");
+            Ok(())
+        }
+    }
+}
+pub fn generic_function_result_type_error<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    match test_rig(
+        "<doc/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'
+  xmlns:xs='http://www.w3.org/2001/XMLSchema'
+  xmlns:f='http://example.org/functions'>
+  <xsl:template match='/'>
+    <xsl:value-of select='f:one()'/>
+  </xsl:template>
+  <xsl:function name='f:one' as='xs:integer'>
+    <xsl:sequence select="(1, 2)"/>
+  </xsl:function>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    ) {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTTE0780");
+            Ok(())
+        }
+    }
+}
+pub fn generic_accumulator<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc><sec><h>A</h><sec><h>B</h></sec></sec><sec><h>C</h></sec></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'
+  xmlns:xs='http://www.w3.org/2001/XMLSchema'>
+  <xsl:accumulator name='count' initial-value='0' as='xs:double'>
+    <xsl:accumulator-rule match='sec' select='$value + 1'/>
+  </xsl:accumulator>
+  <xsl:accumulator name='depth' initial-value='0'>
+    <xsl:accumulator-rule match='sec' phase='start' select='$value + 1'/>
+    <xsl:accumulator-rule match='sec' phase='end' select='$value - 1'/>
+  </xsl:accumulator>
+  <xsl:accumulator name='last-heading' initial-value="''">
+    <xsl:accumulator-rule match='h' phase='end' select='string(.)'/>
+  </xsl:accumulator>
+  <xsl:template match='/'>
+    <xsl:for-each select='descendant::sec'>
+      <xsl:value-of select="concat(accumulator-before('count'), ':', accumulator-before('depth'), ':', accumulator-before('last-heading'), ';')"/>
+    </xsl:for-each>
+    <xsl:value-of select="accumulator-after('last-heading')"/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "1:1:;2:2:A;3:1:B;C");
+    Ok(())
+}
+pub fn generic_accumulator_unknown<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    match test_rig(
+        "<doc/>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:accumulator name='count' initial-value='0'>
+    <xsl:accumulator-rule match='doc' select='$value + 1'/>
+  </xsl:accumulator>
+  <xsl:template match='/'>
+    <xsl:value-of select="accumulator-before('total')"/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    ) {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTDE3340");
+            Ok(())
+        }
+    }
+}
+pub fn generic_merge<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc><log1><t ts='1'>a</t><t ts='3'>c</t><t ts='10'>e</t></log1><log2><t ts='2'>b</t><t ts='3'>d</t></log2></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'>
+    <xsl:merge>
+      <xsl:merge-source name='one' select='child::doc/child::log1/child::t'>
+        <xsl:merge-key select='number(@ts)'/>
+      </xsl:merge-source>
+      <xsl:merge-source name='two' select='child::doc/child::log2/child::t'>
+        <xsl:merge-key select='number(@ts)'/>
+      </xsl:merge-source>
+      <xsl:merge-action>
+        <xsl:value-of select="concat(current-merge-key(), ':', string-join(current-merge-group(), ''), '(', count(current-merge-group('two')), ');')"/>
+      </xsl:merge-action>
+    </xsl:merge>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "1:a(0);2:b(1);3:cd(1);10:e(0);");
+    Ok(())
+}
+pub fn generic_merge_sort_before_merge<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc><g><v>2</v><v>5</v></g><g><v>4</v><v>1</v></g></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'>
+    <xsl:merge>
+      <xsl:merge-source for-each-item='child::doc/child::g' select='child::v' sort-before-merge='yes'>
+        <xsl:merge-key select='number(.)' order='descending'/>
+      </xsl:merge-source>
+      <xsl:merge-action><xsl:value-of select='.'/><xsl:text>,</xsl:text></xsl:merge-action>
+    </xsl:merge>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "5,4,2,1,");
+    Ok(())
+}
+pub fn generic_merge_unsorted<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    match test_rig(
+        "<doc><g><v>2</v><v>5</v></g><g><v>4</v><v>1</v></g></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'>
+    <xsl:merge>
+      <xsl:merge-source for-each-item='child::doc/child::g' select='child::v'>
+        <xsl:merge-key select='number(.)' order='descending'/>
+      </xsl:merge-source>
+      <xsl:merge-action><xsl:value-of select='.'/></xsl:merge-action>
+    </xsl:merge>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    ) {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTDE2220");
+            Ok(())
+        }
+    }
+}
+pub fn generic_merge_data_type<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    // As strings, '10' would sort before '9'
+    let result = test_rig(
+        "<doc><log1><t ts='2'>a</t><t ts='9' k='x'>b</t><t ts='10'>c</t></log1><log2><t ts='9' k='y'>d</t><t ts='10'>e</t></log2></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'>
+    <xsl:merge>
+      <xsl:merge-source select='child::doc/child::log1/child::t'>
+        <xsl:merge-key select='@ts' data-type='number'/>
+        <xsl:merge-key select='@k'/>
+      </xsl:merge-source>
+      <xsl:merge-source select='child::doc/child::log2/child::t'>
+        <xsl:merge-key select='@ts' data-type='number'/>
+        <xsl:merge-key select='@k'/>
+      </xsl:merge-source>
+      <xsl:merge-action>
+        <xsl:value-of select="concat(string-join(current-merge-key(), ''), ':', string-join(current-merge-group(), ''), ';')"/>
+      </xsl:merge-action>
+    </xsl:merge>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "2:a;9x:b;9y:d;10:ce;");
+    Ok(())
+}
+pub fn generic_merge_incompatible_keys<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    match test_rig(
+        "<doc><v>1</v><v>2</v></doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'>
+    <xsl:merge>
+      <xsl:merge-source select='child::doc/child::v'>
+        <xsl:merge-key select='.' data-type='number'/>
+      </xsl:merge-source>
+      <xsl:merge-source select='child::doc/child::v'>
+        <xsl:merge-key select='.' data-type='text'/>
+      </xsl:merge-source>
+      <xsl:merge-action><xsl:value-of select='.'/></xsl:merge-action>
+    </xsl:merge>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    ) {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTDE2210");
+            Ok(())
+        }
+    }
+}
+pub fn generic_analyze_string<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc>x=12, y=345;</doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'>
+    <xsl:analyze-string select='child::doc' regex='([a-z])=(\d+)'>
+      <xsl:matching-substring>
+        <xsl:value-of select="concat(regex-group(1), ':', regex-group(2), regex-group(3), '/', position(), ' ')"/>
+      </xsl:matching-substring>
+      <xsl:non-matching-substring>
+        <xsl:value-of select="concat('[', ., ']', last())"/>
+      </xsl:non-matching-substring>
+    </xsl:analyze-string>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "x:12/1 [, ]4y:345/3 [;]4");
+    Ok(())
+}
+pub fn generic_analyze_string_quantifier<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc>in 1999 and 2024, not 42</doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'>
+    <xsl:analyze-string select='child::doc' regex='\d{{4}}'>
+      <xsl:matching-substring>[<xsl:value-of select='.'/>]</xsl:matching-substring>
+    </xsl:analyze-string>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "[1999][2024]");
+    Ok(())
+}
+pub fn generic_analyze_string_flags<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let result = test_rig(
+        "<doc>12 ab-C 3</doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'>
+    <xsl:variable name='f' select='"i"'/>
+    <xsl:analyze-string select='child::doc' regex='\i\c*' flags='{$f}'>
+      <xsl:matching-substring>
+        <xsl:value-of select="concat('[', ., ']')"/>
+      </xsl:matching-substring>
+    </xsl:analyze-string>
+    <xsl:analyze-string select='child::doc' regex='[A-Z]+'>
+      <xsl:non-matching-substring>
+        <xsl:value-of select='.'/>
+      </xsl:non-matching-substring>
+    </xsl:analyze-string>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "[ab-C]12 ab- 3");
+    Ok(())
+}
+pub fn generic_analyze_string_no_substring<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    match test_rig(
+        "<doc>abc</doc>",
+        r#"<xsl:stylesheet xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:template match='/'>
+    <xsl:analyze-string select='child::doc' regex='b'/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    ) {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTSE1130");
+            Ok(())
+        }
+    }
+}
+
+const LIBRARY_PACKAGE: &str = r#"<xsl:package name='http://example.com/lib' package-version='1.2' version='3.0'
+  xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:lib='http://example.com/lib'>
+  <xsl:expose component='function' names='lib:*' visibility='public'/>
+  <xsl:variable name='prefix' select='"Hello, "'/>
+  <xsl:function name='lib:double'>
+    <xsl:param name='n'/>
+    <xsl:sequence select='lib:helper($n) * 2'/>
+  </xsl:function>
+  <xsl:function name='lib:helper' visibility='private'>
+    <xsl:param name='n'/>
+    <xsl:sequence select='$n + 1'/>
+  </xsl:function>
+  <xsl:template name='greet' visibility='public'>
+    <xsl:param name='who'/>
+    <xsl:value-of select='concat($prefix, $who)'/>
+  </xsl:template>
+  <xsl:template name='list' visibility='final'>
+    <xsl:apply-templates select='child::doc/child::item'/>
+  </xsl:template>
+  <xsl:template match='child::item'>
+    <xsl:value-of select="concat('[', ., ']')"/>
+  </xsl:template>
+  <xsl:mode name='lib:m' visibility='public'/>
+  <xsl:template match='child::item' mode='lib:m'>
+    <xsl:value-of select="concat('(', ., ')')"/>
+  </xsl:template>
+</xsl:package>"#;
+
+const OTHER_PACKAGE: &str = r#"<xsl:package name='http://example.com/other' version='3.0'
+  xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:lib='http://example.com/lib'>
+  <xsl:variable name='prefix' select='"Goodbye, "'/>
+  <xsl:function name='lib:triple' visibility='public'>
+    <xsl:param name='n'/>
+    <xsl:sequence select='lib:helper($n) * 3'/>
+  </xsl:function>
+  <xsl:function name='lib:helper'>
+    <xsl:param name='n'/>
+    <xsl:sequence select='$n - 1'/>
+  </xsl:function>
+  <xsl:template name='farewell' visibility='public'>
+    <xsl:param name='who'/>
+    <xsl:value-of select='concat($prefix, $who)'/>
+    <xsl:apply-templates select='child::doc/child::item' mode='m'/>
+  </xsl:template>
+  <xsl:template match='child::item' mode='m'>
+    <xsl:value-of select="concat('&lt;', ., '&gt;')"/>
+  </xsl:template>
+</xsl:package>"#;
+
+const PAGE_PACKAGE: &str = r#"<xsl:package name='http://example.com/page' version='3.0'
+  xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:pg='http://example.com/page'>
+  <xsl:template name='page' visibility='public'>
+    <xsl:call-template name='header'/>
+    <xsl:value-of select='pg:body()'/>
+  </xsl:template>
+  <xsl:template name='header' visibility='public'>
+    <xsl:value-of select='"default header;"'/>
+  </xsl:template>
+  <xsl:function name='pg:body' visibility='abstract'/>
+</xsl:package>"#;
+
+const LOCAL_PACKAGE: &str = r#"<xsl:package name='http://example.com/local' version='3.0'
+  xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:key name='id' match='child::item' use='child::text()'/>
+  <xsl:accumulator name='count' initial-value='0'>
+    <xsl:accumulator-rule match='item' select='$value + 1'/>
+  </xsl:accumulator>
+  <xsl:decimal-format decimal-separator=',' grouping-separator='.'/>
+  <xsl:template name='info' visibility='public'>
+    <xsl:value-of select="concat(count(key('id', 'a')), ':', format-number(1.5, '0,0'), ':', accumulator-after('count'))"/>
+  </xsl:template>
+</xsl:package>"#;
+
+pub fn generic_package<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let pkg = make_package(LIBRARY_PACKAGE, &parse_from_str)?;
+    let result = test_package_rig(
+        "<doc><item>a</item><item>b</item></doc>",
+        &[pkg.clone()],
+        r#"<xsl:stylesheet version='3.0' xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:lib='http://example.com/lib'>
+  <xsl:use-package name='http://example.com/lib' package-version='1.*'/>
+  <xsl:template match='/'>
+    <xsl:value-of select='lib:double(2)'/>
+    <xsl:call-template name='greet'>
+      <xsl:with-param name='who' select='"world"'/>
+    </xsl:call-template>
+    <xsl:call-template name='list'/>
+    <xsl:apply-templates select='child::doc/child::item' mode='lib:m'/>
+    <xsl:apply-templates select='child::doc/child::item'/>
+  </xsl:template>
+  <xsl:template match='child::item'>
+    <xsl:value-of select="concat('{', ., '}')"/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        &parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "6Hello, world[a][b](a)(b){a}{b}");
+    Ok(())
+}
+pub fn generic_package_reuse<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let pkg = make_package(LIBRARY_PACKAGE, &parse_from_str)?;
+    assert_eq!(pkg.name(), Some("http://example.com/lib"));
+    assert_eq!(pkg.version(), "1.2");
+    assert_eq!(
+        pkg.visibility(&Component::Function(QualifiedName::new(
+            Some(String::from("http://example.com/lib")),
+            None,
+            "double"
+        ))),
+        Some(Visibility::Public)
+    );
+    assert_eq!(
+        pkg.visibility(&Component::Function(QualifiedName::new(
+            Some(String::from("http://example.com/lib")),
+            None,
+            "helper"
+        ))),
+        Some(Visibility::Private)
+    );
+    assert_eq!(
+        pkg.visibility(&Component::Variable(String::from("prefix"))),
+        Some(Visibility::Private)
+    );
+    let first = test_package_rig(
+        "<doc/>",
+        &[pkg.clone()],
+        r#"<xsl:stylesheet version='3.0' xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:lib='http://example.com/lib'>
+  <xsl:use-package name='http://example.com/lib'/>
+  <xsl:template match='/'><xsl:value-of select='lib:double(10)'/></xsl:template>
+</xsl:stylesheet>"#,
+        &parse_from_str,
+        &parse_from_str_with_ns,
+        &make_doc,
+    )?;
+    assert_eq!(first.to_string(), "22");
+    let second = test_package_rig(
+        "<doc/>",
+        &[pkg.clone()],
+        r#"<xsl:stylesheet version='3.0' xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:use-package name='http://example.com/lib'/>
+  <xsl:template match='/'>
+    <xsl:call-template name='greet'>
+      <xsl:with-param name='who' select='"again"'/>
+    </xsl:call-template>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        &parse_from_str,
+        &parse_from_str_with_ns,
+        &make_doc,
+    )?;
+    assert_eq!(second.to_string(), "Hello, again");
+    Ok(())
+}
+pub fn generic_package_override<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let pkg = make_package(PAGE_PACKAGE, &parse_from_str)?;
+    let result = test_package_rig(
+        "<doc/>",
+        &[pkg.clone()],
+        r#"<xsl:stylesheet version='3.0' xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:pg='http://example.com/page'>
+  <xsl:use-package name='http://example.com/page'>
+    <xsl:accept component='template' names='page' visibility='public'/>
+    <xsl:override>
+      <xsl:template name='header'>
+        <xsl:value-of select='"my header;"'/>
+      </xsl:template>
+      <xsl:function name='pg:body'>
+        <xsl:sequence select='"my body"'/>
+      </xsl:function>
+    </xsl:override>
+  </xsl:use-package>
+  <xsl:template match='/'><xsl:call-template name='page'/></xsl:template>
+</xsl:stylesheet>"#,
+        &parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "my header;my body");
+    Ok(())
+}
+pub fn generic_package_conflict<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let pkg = make_package(LIBRARY_PACKAGE, &parse_from_str)?;
+    match test_package_rig(
+        "<doc/>",
+        &[pkg.clone()],
+        r#"<xsl:stylesheet version='3.0' xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:lib='http://example.com/lib'>
+  <xsl:use-package name='http://example.com/lib'/>
+  <xsl:function name='lib:double'>
+    <xsl:param name='n'/>
+    <xsl:sequence select='$n'/>
+  </xsl:function>
+  <xsl:template match='/'><xsl:value-of select='lib:double(1)'/></xsl:template>
+</xsl:stylesheet>"#,
+        &parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    ) {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTSE3050");
+            Ok(())
+        }
+    }
+}
+pub fn generic_package_hidden<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    // The private components of the package are used by its own code, but are hidden from the using stylesheet
+    let pkg = make_package(LIBRARY_PACKAGE, &parse_from_str)?;
+    [
+        ("lib:double(1)", "4"),
+        ("lib:helper(1)", "XPST0017"),
+        ("$prefix", "XTSE3050"),
+    ]
+    .iter()
+    .try_for_each(|(select, expected)| {
+        let result = test_package_rig(
+            "<doc/>",
+            &[pkg.clone()],
+            format!(
+                r#"<xsl:stylesheet version='3.0' xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:lib='http://example.com/lib'>
+  <xsl:use-package name='http://example.com/lib'/>
+  <xsl:template match='/'><xsl:value-of select='{}'/></xsl:template>
+</xsl:stylesheet>"#,
+                select
+            ),
+            &parse_from_str,
+            &parse_from_str_with_ns,
+            &make_doc,
+        );
+        match result {
+            Ok(s) => assert_eq!(s.to_string(), *expected),
+            Err(e) => assert_eq!(e.code.unwrap().to_string(), *expected),
+        }
+        Ok(())
+    })
+}
+pub fn generic_package_override_final<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let pkg = make_package(LIBRARY_PACKAGE, &parse_from_str)?;
+    match test_package_rig(
+        "<doc/>",
+        &[pkg.clone()],
+        r#"<xsl:stylesheet version='3.0' xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:use-package name='http://example.com/lib'>
+    <xsl:override>
+      <xsl:template name='list'>
+        <xsl:value-of select='"overridden"'/>
+      </xsl:template>
+    </xsl:override>
+  </xsl:use-package>
+  <xsl:template match='/'><xsl:call-template name='list'/></xsl:template>
+</xsl:stylesheet>"#,
+        &parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    ) {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTSE3060");
+            Ok(())
+        }
+    }
+}
+pub fn generic_package_abstract<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    let pkg = make_package(PAGE_PACKAGE, &parse_from_str)?;
+    match test_package_rig(
+        "<doc/>",
+        &[pkg.clone()],
+        r#"<xsl:stylesheet version='3.0' xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:use-package name='http://example.com/page'>
+    <xsl:accept component='template' names='page' visibility='public'/>
+  </xsl:use-package>
+  <xsl:template match='/'><xsl:call-template name='page'/></xsl:template>
+</xsl:stylesheet>"#,
+        &parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    ) {
+        Ok(s) => Err(Error::new(
+            ErrorKind::Unknown,
+            format!("expected error, got \"{}\"", s.to_string()),
+        )),
+        Err(e) => {
+            assert_eq!(e.code.unwrap().to_string(), "XTSE3080");
+            Ok(())
+        }
+    }
+}
+pub fn generic_package_private_names<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    // The private components of the package do not conflict with the components of the using stylesheet
+    let pkg = make_package(LIBRARY_PACKAGE, &parse_from_str)?;
+    let result = test_package_rig(
+        "<doc/>",
+        &[pkg.clone()],
+        r#"<xsl:stylesheet version='3.0' xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:lib='http://example.com/lib'>
+  <xsl:use-package name='http://example.com/lib'/>
+  <xsl:variable name='prefix' select='"Hi, "'/>
+  <xsl:function name='lib:helper'>
+    <xsl:param name='n'/>
+    <xsl:sequence select='$n * 10'/>
+  </xsl:function>
+  <xsl:template match='/'>
+    <xsl:value-of select='concat(lib:double(1), ";", lib:helper(1), ";", $prefix)'/>
+    <xsl:call-template name='greet'>
+      <xsl:with-param name='who' select='"world"'/>
+    </xsl:call-template>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        &parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "4;10;Hi, Hello, world");
+    Ok(())
+}
+pub fn generic_package_private_names_used<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    // Each used package has its own private components with the same names
+    let lib = make_package(LIBRARY_PACKAGE, &parse_from_str)?;
+    let other = make_package(OTHER_PACKAGE, &parse_from_str)?;
+    let result = test_package_rig(
+        "<doc><item>a</item></doc>",
+        &[lib, other],
+        r#"<xsl:stylesheet version='3.0' xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:lib='http://example.com/lib'>
+  <xsl:use-package name='http://example.com/lib'/>
+  <xsl:use-package name='http://example.com/other'/>
+  <xsl:template match='/'>
+    <xsl:value-of select='concat(lib:double(2), ";", lib:triple(2), ";")'/>
+    <xsl:call-template name='greet'>
+      <xsl:with-param name='who' select='"world;"'/>
+    </xsl:call-template>
+    <xsl:call-template name='farewell'>
+      <xsl:with-param name='who' select='"world;"'/>
+    </xsl:call-template>
+    <xsl:apply-templates select='child::doc/child::item' mode='m'/>
+  </xsl:template>
+  <xsl:template match='child::item' mode='m'>
+    <xsl:value-of select="concat('{', ., '}')"/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        &parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "6;3;Hello, world;Goodbye, world;<a>{a}");
+    Ok(())
+}
+pub fn generic_package_local_declarations<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    // Keys, accumulators and decimal formats with the same names are declared by both the package and the using stylesheet.
+    // The code of each uses its own.
+    let pkg = make_package(LOCAL_PACKAGE, &parse_from_str)?;
+    let result = test_package_rig(
+        "<doc><item>a</item><item>a</item><item>b</item></doc>",
+        &[pkg],
+        r#"<xsl:stylesheet version='3.0' xmlns:xsl='http://www.w3.org/1999/XSL/Transform'>
+  <xsl:use-package name='http://example.com/local'/>
+  <xsl:key name='id' match='child::doc' use="'a'"/>
+  <xsl:accumulator name='count' initial-value='100'>
+    <xsl:accumulator-rule match='item' select='$value'/>
+  </xsl:accumulator>
+  <xsl:template match='/'>
+    <xsl:value-of select="concat(count(key('id', 'a')), ':', format-number(1.5, '0.0'), ':', accumulator-after('count'), ';')"/>
+    <xsl:call-template name='info'/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        &parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "1:1.5:100;2:1,5:3");
+    Ok(())
+}
+pub fn generic_package_override_rules<N: Node, G, H, J>(
+    parse_from_str: G,
+    parse_from_str_with_ns: J,
+    make_doc: H,
+) -> Result<(), Error>
+where
+    G: Fn(&str) -> Result<N, Error>,
+    H: Fn() -> Result<N, Error>,
+    J: Fn(&str) -> Result<(N, Rc<NamespaceMap>), Error>,
+{
+    // A template rule in xsl:override takes precedence over those of the used package, whatever its priority
+    let pkg = make_package(LIBRARY_PACKAGE, &parse_from_str)?;
+    let result = test_package_rig(
+        "<doc><item>a</item><item>b</item></doc>",
+        &[pkg.clone()],
+        r#"<xsl:stylesheet version='3.0' xmlns:xsl='http://www.w3.org/1999/XSL/Transform' xmlns:lib='http://example.com/lib'>
+  <xsl:use-package name='http://example.com/lib'>
+    <xsl:override>
+      <xsl:template match='child::item' mode='lib:m' priority='-1'>
+        <xsl:value-of select="concat('&lt;', ., '&gt;')"/>
+      </xsl:template>
+    </xsl:override>
+  </xsl:use-package>
+  <xsl:template match='/'>
+    <xsl:apply-templates select='child::doc/child::item' mode='lib:m'/>
+  </xsl:template>
+</xsl:stylesheet>"#,
+        &parse_from_str,
+        parse_from_str_with_ns,
+        make_doc,
+    )?;
+    assert_eq!(result.to_string(), "<a><b>");
+    Ok(())
+}